@@ -1,24 +1,54 @@
+pub mod aliases;
+pub mod bibtex;
 pub mod callback;
+pub mod cases;
 pub mod counter;
+pub mod defaults;
+pub mod diagnostics;
+pub mod diff;
+pub mod frozen;
+pub mod git_dates;
+pub mod graph;
+pub mod hooks;
 pub mod html_parser;
+pub mod link_index;
+pub mod numbering;
+pub mod page_meta;
 pub mod parser;
+pub mod related;
+pub mod rename;
+pub mod schema;
 pub mod section;
 pub mod state;
+pub mod store;
 pub mod taxon;
+pub mod timings;
+pub mod translations;
 pub mod typst;
+pub mod variables;
 pub mod writer;
 
-use std::{collections::HashMap, fmt::Debug, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use parser::parse_markdown;
-use section::{HTMLContent, ShallowSection};
+use callback::Callback;
+use diagnostics::{Diagnostics, Severity};
+use hooks::PostHook;
+use parser::parse_markdown_source;
+use rayon::prelude::*;
+use section::{HTMLContent, Section, ShallowSection};
 use state::CompileState;
+use timings::{Phase, Timings};
 use typst::parse_typst;
 use walkdir::WalkDir;
 use writer::Writer;
 
 use crate::{
-    config::{self, verify_and_file_hash},
+    config::{self, verify_update_hash, Config},
     slug::{self, Ext},
 };
 
@@ -26,61 +56,482 @@ use crate::{
 #[derive(Debug)]
 pub enum CompileError {
     IO(Option<&'static str>, std::io::Error, String),
-    Syntax(Option<&'static str>, Box<dyn Debug>, String),
+    Syntax(Option<&'static str>, Box<dyn Debug + Send + Sync>, String),
+
+    /// The slug `.0` does not exist. `.1` is the slug of the page that requested it,
+    /// or `None` if it was requested by the compiler itself.
+    Missing(String, Option<String>),
+
+    /// The root page `.0` does not exist.
+    MissingRoot(String),
+
+    /// The source files `.1` and `.2` both produce the slug `.0`.
+    DuplicateSlug(String, String, String),
+
+    /// The source files `.1` and `.3` produce the slugs `.0` and `.2`, which differ only
+    /// in case.
+    CaseCollision(String, String, String, String),
+
+    /// The source file `.1` produces the slug `.0`, under the
+    /// [`config::reserved_prefix`] of the generated pages.
+    ReservedSlug(String, String),
+
+    /// The source file `.1` produces the slug `.0`, which has the character `.2`
+    /// not allowed in the output paths, see [`slug::rejected_char`].
+    InvalidSlug(String, String, char),
+
+    /// The slug `.0` is an alias of the page `.1`, but also the slug or an alias of `.2`.
+    AliasCollision(String, String, String),
+
+    /// The page `.1` embeds the non-existent slug `.0`.
+    /// The embed is skipped and compilation continues.
+    MissingEmbed(String, String),
 }
 
-pub fn compile_all(workspace_dir: &str) -> Result<(), CompileError> {
-    let mut state = CompileState::new();
-    let workspace = all_source_files(Path::new(workspace_dir)).unwrap();
+impl CompileError {
+    /// Whether compilation could continue past this error.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, CompileError::MissingEmbed(_, _))
+    }
+}
 
-    for (slug, ext) in &workspace.slug_exts {
-        let relative_path = format!("{}.{}", slug, ext);
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::Missing(slug, None) => {
+                write!(f, "Error: attempting to fetch a non-existent [{}].", slug)
+            }
+            CompileError::Missing(slug, Some(referrer)) => write!(
+                f,
+                "Error: [{}] attempting to fetch a non-existent [{}].",
+                referrer, slug
+            ),
+            CompileError::MissingEmbed(slug, referrer) => write!(
+                f,
+                "Warning: [{}] attempting to embed a non-existent [{}].",
+                referrer, slug
+            ),
+            CompileError::MissingRoot(slug) => {
+                write!(f, "Error: the root page [{}] does not exist.", slug)
+            }
+            CompileError::DuplicateSlug(slug, a, b) => write!(
+                f,
+                "Error: [{}] is produced by both `{}` and `{}`.",
+                slug, a, b
+            ),
+            CompileError::CaseCollision(slug, a, other, b) => write!(
+                f,
+                "Error: [{}] of `{}` and [{}] of `{}` differ only in case, which is the same file on a case-insensitive filesystem.",
+                slug, a, other, b
+            ),
+            CompileError::ReservedSlug(slug, path) => write!(
+                f,
+                "Error: [{}] of `{}` is under `{}`, which is reserved for the generated pages.",
+                slug,
+                path,
+                config::reserved_prefix()
+            ),
+            CompileError::InvalidSlug(slug, path, c) => write!(
+                f,
+                "Error: [{}] of `{}` has the character {:?}, which is not allowed in the output paths, rename the file.",
+                slug, path, c
+            ),
+            CompileError::AliasCollision(alias, slug, other) if alias == other => write!(
+                f,
+                "Error: [{}] is an alias of [{}], but also a page.",
+                alias, slug
+            ),
+            CompileError::AliasCollision(alias, slug, other) => write!(
+                f,
+                "Error: [{}] is an alias of both [{}] and [{}].",
+                alias, other, slug
+            ),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
 
-        let is_modified = verify_and_file_hash(&relative_path).map_err(|e| {
-            CompileError::IO(
-                Some(concat!(file!(), '#', line!())),
-                e,
-                relative_path.to_string(),
-            )
-        })?;
+/// Compile and write all pages in the workspace.
+/// The diagnostics collected during compilation are left in `state.diagnostics`.
+pub fn compile_all(workspace_dir: &str) -> Result<CompileState, CompileError> {
+    let config = config::lock_config().clone();
+    let mut forest = compile_tree_cached(read_sources(workspace_dir)?, &config)?;
+    // the callback and the metadata are final once compiled, before anything is rendered.
+    forest.state.reconcile_callback();
+    forest.state.reconcile_expired();
 
-        let entry_path_str = format!("{}.entry", relative_path);
-        let entry_path_buf = config::entry_path(&entry_path_str);
+    write_forest(&forest);
+    Ok(forest.state)
+}
 
-        let shallow = if !is_modified && entry_path_buf.exists() {
-            let serialized = std::fs::read_to_string(entry_path_buf).map_err(|e| {
-                let position = Some(concat!(file!(), '#', line!()));
-                CompileError::IO(position, e, entry_path_str)
-            })?;
+/// Compile all pages in the workspace like [`compile_all`] without writing any HTML.
+pub fn compile_state(workspace_dir: &str) -> Result<CompileState, CompileError> {
+    let config = config::lock_config().clone();
+    let forest = compile_tree_cached(read_sources(workspace_dir)?, &config)?;
+    Ok(forest.state)
+}
 
-            let shallow: ShallowSection = serde_json::from_str(&serialized).unwrap();
-            shallow
-        } else {
-            let shallow = match ext {
-                Ext::Markdown => parse_markdown(slug)?,
-                Ext::Typst => parse_typst(slug, workspace_dir)?,
-            };
-            let serialized = serde_json::to_string(&shallow).unwrap();
-            std::fs::write(entry_path_buf, serialized).map_err(|e| {
-                CompileError::IO(Some(concat!(file!(), '#', line!())), e, entry_path_str)
-            })?;
+/// Compile all pages in the workspace like [`compile_dry_run`] and compare their HTML with
+/// `old_output_dir`. Nothing is written, not even the cache: the pages whose source differs
+/// from the one last compiled are [`CompileState::modified`].
+pub fn diff_workspace(
+    workspace_dir: &str,
+    old_output_dir: &str,
+) -> Result<(Vec<diff::PageChange>, CompileState), CompileError> {
+    let sources = read_sources(workspace_dir)?;
+    let mut modified_paths = BTreeSet::new();
+    for (path, source) in &sources {
+        let relative_path = slug::pretty_path(path);
+        let hash_name = format!("{}.hash", config::source_cache_name(&relative_path));
+        let hash_path = Path::new(&config::hash_dir()).join(hash_name);
+        if config::is_hash_updated(source, hash_path).0 {
+            modified_paths.insert(relative_path);
+        }
+    }
 
-            shallow
-        };
+    let mut config = config::lock_config().clone();
+    config.dry_run = true;
+    let CompiledForest {
+        slugs,
+        source_paths,
+        mut state,
+    } = compile_tree(sources, &config)?;
+    state.modified = source_paths
+        .into_iter()
+        .filter(|(_, relative_path)| modified_paths.contains(relative_path))
+        .map(|(slug, _)| slug)
+        .collect();
+    let changes = diff::diff_output(&slugs, &state, old_output_dir)?;
+    Ok((changes, state))
+}
+
+/// Compile and write only the pages `slugs` and the pages they transitively embed.
+/// The metadata of all pages is still collected so that link titles resolve.
+pub fn compile_only(workspace_dir: &str, slugs: &[String]) -> Result<CompileState, CompileError> {
+    let config = config::lock_config().clone();
+    let (source_paths, mut state) =
+        parse_forest(read_sources(workspace_dir)?, &config, vec![], true)?;
+
+    state.compile_only(slugs);
+
+    let compiled_slugs = source_paths
+        .into_keys()
+        .chain(state.extra_slugs())
+        .filter(|slug| state.pages.get(slug).is_some())
+        .collect();
+    Writer::write_needed_slugs(&compiled_slugs, &state);
+    aliases::write_redirects(&state);
+    page_meta::write_page_meta(&state, &compiled_slugs);
+
+    Ok(state)
+}
+
+/// The result of [`compile_tree`], which is not written anywhere yet.
+#[derive(Debug)]
+pub struct CompiledForest {
+    /// The slugs of all pages, ordered.
+    pub slugs: Vec<String>,
+
+    /// The source file of each slug, relative to the workspace dir.
+    pub source_paths: BTreeMap<String, String>,
+
+    pub state: CompileState,
+}
+
+impl CompiledForest {
+    /// The forest of the compiled `state`, whose source files are `source_paths`.
+    fn new(source_paths: BTreeMap<String, String>, state: CompileState) -> CompiledForest {
+        let mut slugs: Vec<String> = source_paths
+            .keys()
+            .cloned()
+            .chain(state.extra_slugs())
+            .filter(|slug| !state.drafts.contains(slug))
+            .collect();
+        slugs.sort();
+        CompiledForest {
+            slugs,
+            source_paths,
+            state,
+        }
+    }
+
+    /// The compiled sections by slug.
+    pub fn compiled(&self) -> &BTreeMap<String, Arc<Section>> {
+        self.state.pages.compiled()
+    }
+
+    pub fn callback(&self) -> &Callback {
+        &self.state.callback
+    }
 
-        state.residued.insert(slug.to_string(), shallow);
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.state.diagnostics
     }
+}
+
+/// Compile `sources`, pairs of a path relative to `config.root_dir` and the content of that
+/// file, without reading the workspace or touching the cache.
+///
+/// Markdown sources are parsed from the given content. Typst sources, including inline Typst
+/// images, are still compiled by the `typst` CLI from the files on disk.
+pub fn compile_tree(
+    sources: impl IntoIterator<Item = (PathBuf, String)>,
+    config: &Config,
+) -> Result<CompiledForest, CompileError> {
+    compile_tree_with_hooks(sources, config, vec![])
+}
+
+/// [`compile_tree`], running `hooks` on each compiled section after those of the config.
+pub fn compile_tree_with_hooks(
+    sources: impl IntoIterator<Item = (PathBuf, String)>,
+    config: &Config,
+    hooks: Vec<PostHook>,
+) -> Result<CompiledForest, CompileError> {
+    let (source_paths, mut state) = parse_forest(sources, config, hooks, false)?;
+    state.compile_all();
+    Ok(CompiledForest::new(source_paths, state))
+}
 
+/// [`compile_tree`], but loading the source files and the compiled pages unchanged since
+/// the last compilation from the cache of the workspace, which is updated, as
+/// `kodama compile` does.
+pub fn compile_tree_cached(
+    sources: impl IntoIterator<Item = (PathBuf, String)>,
+    config: &Config,
+) -> Result<CompiledForest, CompileError> {
+    let (source_paths, mut state) = parse_forest(sources, config, vec![], true)?;
     state.compile_all();
+    Ok(CompiledForest::new(source_paths, state))
+}
+
+/// Parse `sources` into a new [`CompileState`] running `hooks`, ready to be compiled, along
+/// with the source file of each slug. With `cached`, the source files unchanged since the
+/// last compilation are loaded from the cache, and the others are [`CompileState::modified`].
+fn parse_forest(
+    sources: impl IntoIterator<Item = (PathBuf, String)>,
+    config: &Config,
+    hooks: Vec<PostHook>,
+    cached: bool,
+) -> Result<(BTreeMap<String, String>, CompileState), CompileError> {
+    config::mutex_set(&config::CONFIG, config.clone());
+
+    let mut state = CompileState::new();
+    state.section_cache = cached;
+    hooks.into_iter().for_each(|hook| state.add_post_hook(hook));
+    let sources: BTreeMap<String, String> = sources
+        .into_iter()
+        .map(|(path, source)| (slug::pretty_path(&path), source))
+        .collect();
+    let paths = sources.keys().map(PathBuf::from);
+    let mut workspace = Workspace::collect(Path::new(&config.root_dir), paths)?;
+    let diagnostics = std::mem::take(&mut workspace.diagnostics);
+    state.diagnostics.extend(diagnostics);
+    if cached {
+        verify_cache(&mut state)?;
+    }
+
+    let start = Timings::start(&state.timings);
+    let shallows: Vec<Result<(ShallowSection, bool), CompileError>> = workspace
+        .slug_exts
+        .par_iter()
+        .map(|(slug, ext)| {
+            let start = Timings::start(&state.timings);
+            let relative_path = &workspace.source_paths[slug];
+            let source = &sources[relative_path];
+            let shallow = parse_source(slug, ext, relative_path, source, cached);
+            Timings::stop(&state.timings, start, Phase::Parse, Some(slug));
+            shallow
+        })
+        .collect();
+    Timings::stop(&state.timings, start, Phase::Parse, None);
+    for ((slug, _), shallow) in workspace.slug_exts.iter().zip(shallows) {
+        let (shallow, is_modified) = shallow?;
+        if is_modified {
+            state.modified.insert(slug.to_string());
+        }
+        state.pages.insert_pending(slug.to_string(), shallow);
+    }
+    let source_paths = workspace.source_paths;
+
+    let root_slug = config::root_slug();
+    if !source_paths.contains_key(&root_slug) {
+        return Err(CompileError::MissingRoot(root_slug));
+    }
+    if root_slug != "index" && source_paths.contains_key("index") {
+        let message = format!("[index] is shadowed by the root page [{}].", root_slug);
+        state
+            .diagnostics
+            .report(Severity::Warning, Some("index"), None, message);
+    }
+
+    defaults::insert_defaults(&mut state, &source_paths, &config.root_dir);
+    if config::is_git_dates() {
+        git_dates::insert_updated(&mut state, &source_paths, &config.root_dir);
+    }
+    variables::insert_variables(&mut state);
+    schema::validate_metadata(&mut state, &source_paths);
+    import_bibtex(&mut state, cached)?;
+    aliases::insert_aliases(&mut state)?;
+    state.drifted_slugs = workspace.drifted;
+    frozen::redirect_drifted(&mut state);
+    cases::resolve_cases(&mut state);
+    translations::check_translations(&mut state);
+    Ok((source_paths, state))
+}
+
+/// Compile all pages in the workspace like [`compile_all`], without using the cache
+/// or writing anything, see [`config::is_dry_run`].
+pub fn compile_dry_run(workspace_dir: &str) -> Result<CompiledForest, CompileError> {
+    let mut config = config::lock_config().clone();
+    config.dry_run = true;
+    compile_tree(read_sources(workspace_dir)?, &config)
+}
+
+/// The content of each source file in `workspace_dir`, with its path relative to it.
+fn read_sources(workspace_dir: &str) -> Result<Vec<(PathBuf, String)>, CompileError> {
+    let root_dir = Path::new(workspace_dir);
+    let paths = source_paths(root_dir).map_err(|e| {
+        let position = Some(concat!(file!(), '#', line!()));
+        CompileError::IO(position, e, workspace_dir.to_string())
+    })?;
+
+    let mut sources = vec![];
+    for path in paths {
+        if slug::source_slug(&path).1.is_none() {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).map_err(|e| {
+            let position = Some(concat!(file!(), '#', line!()));
+            CompileError::IO(position, e, path.display().to_string())
+        })?;
+        let relative_path = path.strip_prefix(root_dir).unwrap_or(&path);
+        sources.push((relative_path.to_path_buf(), source));
+    }
+    Ok(sources)
+}
+
+/// Compile the markdown `sources`, pairs of a slug and its content, with `config` for the
+/// tests, which keep that config as long as they hold the returned guard.
+#[cfg(test)]
+pub fn test_forest(
+    sources: &[(&str, &str)],
+    config: Config,
+) -> (CompiledForest, std::sync::MutexGuard<'static, ()>) {
+    let guard = config::lock_test_config(config.clone());
+    let sources = sources
+        .iter()
+        .map(|(slug, source)| (PathBuf::from(format!("{}.md", slug)), source.to_string()));
+    let forest = compile_tree(sources, &config).unwrap();
+    (forest, guard)
+}
+
+/// Write the HTML of all pages of `forest` to the output directory.
+pub fn write_forest(forest: &CompiledForest) {
+    Writer::write_needed_slugs(&forest.slugs, &forest.state);
+    aliases::write_redirects(&forest.state);
+    link_index::write_link_index(&forest.state);
+    page_meta::write_page_meta(&forest.state, &forest.slugs);
+}
 
-    Writer::write_needed_slugs(
-        &workspace.slug_exts.into_iter().map(|x| x.0).collect(),
-        &state,
-    );
+/// Check that the cache was written by this version of kodama, warning if the pages were
+/// last compiled with another [`config::slug_mode`].
+fn verify_cache(state: &mut CompileState) -> Result<(), CompileError> {
+    config::verify_cache_version().map_err(|e| {
+        let position = Some(concat!(file!(), '#', line!()));
+        CompileError::IO(position, e, config::get_cache_dir())
+    })?;
+    let last_slug_mode = config::verify_update_slug_mode().map_err(|e| {
+        let position = Some(concat!(file!(), '#', line!()));
+        CompileError::IO(position, e, config::get_cache_dir())
+    })?;
+    if let Some(last_slug_mode) = last_slug_mode {
+        let message = format!(
+            "The pages were last compiled with `slug_mode = \"{}\"`, and now with `\"{}\"`, so the pages of `{}` written then may have other slugs than now. Remove it to drop them.",
+            last_slug_mode,
+            config::slug_mode().as_str(),
+            config::output_dir()
+        );
+        state
+            .diagnostics
+            .report(Severity::Warning, None, None, message);
+    }
+    Ok(())
+}
 
+/// Import the entries of [`config::bibtex`] as reference pages, after the source files so
+/// that these are kept on a slug collision. With `track`, the imported pages are modified
+/// if the file changed since the last compilation.
+fn import_bibtex(state: &mut CompileState, track: bool) -> Result<(), CompileError> {
+    let Some(bibtex) = config::bibtex() else {
+        return Ok(());
+    };
+    let full_path = config::join_path(&config::root_dir(), &bibtex);
+    let source = std::fs::read_to_string(&full_path)
+        .map_err(|e| CompileError::IO(Some(concat!(file!(), '#', line!())), e, full_path))?;
+
+    let slugs = state.import_bibtex(&source, &bibtex);
+    if track {
+        let is_modified = verify_update_hash(&bibtex, &source).map_err(|e| {
+            CompileError::IO(Some(concat!(file!(), '#', line!())), e, bibtex.to_string())
+        })?;
+        if is_modified {
+            state.modified.extend(slugs);
+        }
+    }
     Ok(())
 }
 
+/// Parse the `source` of the file `relative_path` of `slug`. With `cached`, it is loaded from
+/// the cache instead if unchanged, and whether it changed since the last compilation is also
+/// returned, which is never without `cached`.
+fn parse_source(
+    slug: &str,
+    ext: &Ext,
+    relative_path: &str,
+    source: &str,
+    cached: bool,
+) -> Result<(ShallowSection, bool), CompileError> {
+    let parse = || match ext {
+        Ext::Markdown => parse_markdown_source(slug, relative_path, source),
+        Ext::Typst => parse_typst(slug, relative_path, &config::root_dir()),
+    };
+    if !cached {
+        return Ok((parse()?, false));
+    }
+
+    let cache_name = config::source_cache_name(relative_path);
+    let is_modified = verify_update_hash(&cache_name, source).map_err(|e| {
+        CompileError::IO(
+            Some(concat!(file!(), '#', line!())),
+            e,
+            relative_path.to_string(),
+        )
+    })?;
+
+    let entry_path_str = format!("{}.entry", cache_name);
+    let entry_path_buf = config::entry_path(&entry_path_str);
+
+    let shallow = if !is_modified && !config::is_no_cache() && entry_path_buf.exists() {
+        let serialized = std::fs::read_to_string(entry_path_buf).map_err(|e| {
+            let position = Some(concat!(file!(), '#', line!()));
+            CompileError::IO(position, e, entry_path_str)
+        })?;
+
+        let shallow: ShallowSection = serde_json::from_str(&serialized).unwrap();
+        shallow
+    } else {
+        let shallow = parse()?;
+        let serialized = serde_json::to_string(&shallow).unwrap();
+        std::fs::write(entry_path_buf, serialized).map_err(|e| {
+            CompileError::IO(Some(concat!(file!(), '#', line!())), e, entry_path_str)
+        })?;
+
+        shallow
+    };
+
+    Ok((shallow, is_modified))
+}
+
 pub fn should_ignored_file(path: &Path) -> bool {
     let name = path.file_name().unwrap();
     name == "README.md"
@@ -92,37 +543,28 @@ pub fn should_ignored_dir(path: &Path) -> bool {
         .map_or(false, |s| s.starts_with('.') || s.starts_with('_'))
 }
 
-fn err_collide(path: &Path, ext: &Ext) -> std::io::Error {
-    std::io::Error::new(
-        std::io::ErrorKind::AlreadyExists,
-        format!(
-            "{} collides with another file with '.{}'",
-            path.display(),
-            ext
-        ),
-    )
-}
-
 /**
  * collect all source file paths in workspace dir
  */
-pub fn all_source_files(root_dir: &Path) -> Result<Workspace, std::io::Error> {
-    let mut slug_exts = HashMap::new();
-    let to_slug_ext = |p: &Path| {
-        let p = p.strip_prefix(root_dir).unwrap_or(p);
-        let (slug, ext) = slug::path_to_slug(p);
-        Some((slug, ext?))
-    };
+pub fn all_source_files(root_dir: &Path) -> Result<Workspace, CompileError> {
+    let source_paths = source_paths(root_dir).map_err(|e| {
+        let position = Some(concat!(file!(), '#', line!()));
+        CompileError::IO(position, e, root_dir.display().to_string())
+    })?;
+    let relative_paths = source_paths.iter().map(|path| {
+        let relative_path = path.strip_prefix(root_dir).unwrap_or(path);
+        relative_path.to_path_buf()
+    });
+    Workspace::collect(root_dir, relative_paths)
+}
+
+fn source_paths(root_dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut paths = vec![];
 
     for entry in std::fs::read_dir(root_dir)? {
         let path = entry?.path();
         if path.is_file() && !should_ignored_file(&path) {
-            let Some((slug, ext)) = to_slug_ext(&path) else {
-                continue;
-            };
-            if let Some(ext) = slug_exts.insert(slug, ext) {
-                return Err(err_collide(&path, &ext));
-            };
+            paths.push(path);
         } else if path.is_dir() && !should_ignored_dir(&path) {
             for entry in WalkDir::new(&path)
                 .follow_links(true)
@@ -134,21 +576,346 @@ pub fn all_source_files(root_dir: &Path) -> Result<Workspace, std::io::Error> {
             {
                 let path = entry?.into_path();
                 if path.is_file() {
-                    let Some((slug, ext)) = to_slug_ext(&path) else {
-                        continue;
-                    };
-                    if let Some(ext) = slug_exts.insert(slug, ext) {
-                        return Err(err_collide(&path, &ext));
-                    }
+                    paths.push(path);
                 }
             }
         }
     }
 
-    Ok(Workspace { slug_exts })
+    Ok(paths)
 }
 
 #[derive(Debug)]
 pub struct Workspace {
-    pub slug_exts: HashMap<String, Ext>,
+    /// Ordered by slug so that compilation and output are deterministic.
+    pub slug_exts: BTreeMap<String, Ext>,
+
+    /// The source file of each slug, relative to the workspace dir.
+    pub source_paths: BTreeMap<String, String>,
+
+    /// The frozen slugs other than those computed from their source files, each with the
+    /// computed one, see [`frozen::frozen_slug`].
+    pub drifted: BTreeMap<String, String>,
+
+    /// The warnings about the source files left out of the workspace, and about the
+    /// frozen slugs of no source file.
+    pub diagnostics: Diagnostics,
+}
+
+impl Workspace {
+    /// The workspace of the source files `paths`, relative to `root_dir`, taken in order
+    /// of path. A source file is left out with a warning if its slug is that of the metadata
+    /// of a page, and is an error if its slug is not allowed or is already that of another
+    /// source file, even in another case. With [`config::is_force`], the second file of
+    /// the same slug is left out with a warning instead.
+    fn collect(
+        root_dir: &Path,
+        paths: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<Workspace, CompileError> {
+        let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+        paths.sort();
+
+        let frozen = frozen::read_frozen_slugs(root_dir)?;
+        let mut workspace = Workspace {
+            slug_exts: BTreeMap::new(),
+            source_paths: BTreeMap::new(),
+            drifted: BTreeMap::new(),
+            diagnostics: Diagnostics::new(),
+        };
+        // the slug of each slug in lowercase.
+        let mut cases: BTreeMap<String, String> = BTreeMap::new();
+        let mut relative_paths = BTreeSet::new();
+        for path in paths {
+            let (slug, Some(ext)) = slug::source_slug(&path) else {
+                continue;
+            };
+            let relative_path = slug::pretty_path(&path);
+            relative_paths.insert(relative_path.to_string());
+            let slug = frozen::frozen_slug(&frozen, &relative_path, slug, &mut workspace.drifted);
+            if workspace.is_left_out(&slug, &relative_path, &cases)? {
+                continue;
+            }
+            cases.insert(slug.to_lowercase(), slug.to_string());
+            workspace.slug_exts.insert(slug.to_string(), ext);
+            workspace.source_paths.insert(slug, relative_path);
+        }
+
+        for (path, slug) in frozen::missing_sources(&frozen, &relative_paths) {
+            let message = format!(
+                "`{}` of `{}` does not exist, so its frozen slug [{}] is unused.",
+                path,
+                config::SLUGS_FILE_NAME,
+                slug
+            );
+            workspace
+                .diagnostics
+                .report(Severity::Warning, Some(slug), None, message);
+        }
+        Ok(workspace)
+    }
+
+    /// Whether the source file `relative_path` of `slug` is left out, see
+    /// [`Workspace::collect`], `cases` being the slugs so far by their lowercase.
+    fn is_left_out(
+        &mut self,
+        slug: &str,
+        relative_path: &str,
+        cases: &BTreeMap<String, String>,
+    ) -> Result<bool, CompileError> {
+        let (slug, relative_path) = (slug.to_string(), relative_path.to_string());
+        if slug::is_metadata_slug(&slug) {
+            let message = format!(
+                "`{}` is ignored, as [{}] is reserved for the metadata of [{}].",
+                relative_path,
+                slug,
+                slug::clip_metadata_badge(&slug)
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+            return Ok(true);
+        }
+        if let Some(c) = slug::rejected_char(&slug) {
+            return Err(CompileError::InvalidSlug(slug, relative_path, c));
+        }
+        if slug::is_reserved_slug(&slug) {
+            return Err(CompileError::ReservedSlug(slug, relative_path));
+        }
+
+        if let Some(existed) = self.source_paths.get(&slug) {
+            let existed = existed.to_string();
+            if !config::is_force() {
+                return Err(CompileError::DuplicateSlug(slug, existed, relative_path));
+            }
+            let message = format!(
+                "[{}] is produced by both `{}` and `{}`, ignoring the latter.",
+                slug, existed, relative_path
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+            return Ok(true);
+        }
+        if let Some(other) = cases.get(&slug.to_lowercase()) {
+            let (other, existed) = (other.to_string(), self.source_paths[other].to_string());
+            if !config::is_force() {
+                return Err(CompileError::CaseCollision(
+                    other,
+                    existed,
+                    slug,
+                    relative_path,
+                ));
+            }
+            let message = format!(
+                "[{}] of `{}` and [{}] of `{}` differ only in case, ignoring the latter.",
+                other, existed, slug, relative_path
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SlugCase;
+
+    /// A fresh directory `name` holding the `files`, pairs of a path and its content.
+    fn test_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kodama-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn source_files(dir: &Path, config: Config) -> Result<Workspace, CompileError> {
+        let _guard = config::lock_test_config(config);
+        all_source_files(dir)
+    }
+
+    const FOO_FILES: [(&str, &str); 2] = [("Foo.md", "# Upper\n"), ("foo.md", "# Lower\n")];
+
+    #[test]
+    fn duplicate_slugs_name_both_files() {
+        let dir = test_dir("duplicate-slug", &FOO_FILES);
+        let mut config = config::test_config(dir.to_str().unwrap());
+        config.slug_case = SlugCase::Lower;
+
+        match source_files(&dir, config) {
+            Err(CompileError::DuplicateSlug(slug, existed, path)) => {
+                assert_eq!(slug, "foo");
+                assert_eq!((existed.as_str(), path.as_str()), ("Foo.md", "foo.md"));
+            }
+            other => panic!("expected a duplicate slug, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn force_keeps_the_first_file() {
+        let dir = test_dir("force-duplicate", &FOO_FILES);
+        let mut config = config::test_config(dir.to_str().unwrap());
+        config.slug_case = SlugCase::Lower;
+        config.force = true;
+
+        let workspace = source_files(&dir, config).unwrap();
+        assert_eq!(workspace.source_paths.len(), 1);
+        assert_eq!(workspace.source_paths["foo"], "Foo.md");
+        let messages: Vec<&str> = workspace.diagnostics.iter().map(|d| &*d.message).collect();
+        assert_eq!(
+            messages,
+            ["[foo] is produced by both `Foo.md` and `foo.md`, ignoring the latter."]
+        );
+    }
+
+    /// The warnings of compiling `Foo.md` and `foo.md` with `--force` and `config`.
+    fn forced_warnings(mut config: Config) -> Vec<String> {
+        config.force = true;
+        let index = "---\ntitle: Index\n---\n\nIndex body.\n";
+        let sources = [("foo", "# Lower\n"), ("index", index), ("Foo", "# Upper\n")];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        assert_eq!(forest.compiled().len(), 2);
+        let kept = forest
+            .compiled()
+            .keys()
+            .find(|slug| *slug != "index")
+            .unwrap();
+        let html = writer::Writer::html_doc(&forest.compiled()[kept], &forest.state).0;
+        assert!(html.contains("Upper") && !html.contains("Lower"));
+        forest
+            .diagnostics()
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .map(|d| d.message.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn force_keeps_the_first_file_of_the_sources() {
+        let mut config = config::test_config("kodama-tests");
+        config.slug_case = SlugCase::Lower;
+        assert_eq!(
+            forced_warnings(config),
+            ["[foo] is produced by both `Foo.md` and `foo.md`, ignoring the latter."]
+        );
+
+        let config = config::test_config("kodama-tests");
+        assert_eq!(
+            forced_warnings(config),
+            ["[Foo] of `Foo.md` and [foo] of `foo.md` differ only in case, ignoring the latter."]
+        );
+    }
+
+    #[test]
+    fn slugs_differing_in_case_collide() {
+        let dir = test_dir("case-collision", &FOO_FILES);
+        let config = config::test_config(dir.to_str().unwrap());
+
+        match source_files(&dir, config) {
+            Err(CompileError::CaseCollision(slug, path, other, other_path)) => {
+                assert_eq!((slug, path), ("Foo".to_string(), "Foo.md".to_string()));
+                assert_eq!(
+                    (other, other_path),
+                    ("foo".to_string(), "foo.md".to_string())
+                );
+            }
+            other => panic!("expected a case collision, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unused_frozen_slugs_are_warnings() {
+        let files = [
+            ("index.md", "# Index\n"),
+            ("slugs.toml", "\"gone.md\" = \"gone\"\n"),
+        ];
+        let dir = test_dir("unused-frozen", &files);
+        let config = config::test_config(dir.to_str().unwrap());
+
+        let workspace = source_files(&dir, config).unwrap();
+        let diagnostic = workspace.diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.slug.as_deref(), Some("gone"));
+        assert_eq!(
+            diagnostic.message,
+            "`gone.md` of `slugs.toml` does not exist, so its frozen slug [gone] is unused."
+        );
+    }
+
+    #[test]
+    fn ignored_metadata_sources_are_warnings() {
+        let config = config::test_config("kodama-tests");
+        let index = "---\ntitle: Index\n---\n\nIndex body.\n";
+        let metadata_slug = slug::metadata_slug("index");
+        let sources = [("index", index), (metadata_slug.as_str(), "")];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        assert!(forest.diagnostics().has_warnings());
+        let diagnostic = forest.diagnostics().iter().next().unwrap();
+        assert_eq!(diagnostic.slug, Some(metadata_slug));
+        assert_eq!(
+            diagnostic.message,
+            "`index:metadata.md` is ignored, as [index:metadata] is reserved for the metadata of [index]."
+        );
+    }
+
+    #[test]
+    fn shadowed_index_is_a_warning_of_the_tree() {
+        let mut config = config::test_config("kodama-tests");
+        config.root_slug = "home".to_string();
+        let home = "---\ntitle: Home\n---\n\nHome body.\n";
+        let (forest, _guard) = test_forest(&[("home", home), ("index", "# Index\n")], config);
+
+        let diagnostic = forest.diagnostics().iter().next().unwrap();
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.slug.as_deref(), Some("index"));
+        assert_eq!(
+            diagnostic.message,
+            "[index] is shadowed by the root page [home]."
+        );
+    }
+
+    #[test]
+    fn unchanged_pages_are_loaded_from_the_cache() {
+        let dir = test_dir("section-cache", &[]);
+        let config = config::test_config(dir.to_str().unwrap());
+        let _guard = config::lock_test_config(config.clone());
+        let compile = |title: &str| {
+            let index = "---\ntitle: Index\n---\n\nSee [](/alpha.md).\n";
+            let alpha = format!("---\ntitle: {}\n---\n\nAlpha body.\n", title);
+            let sources = [("index.md", index.to_string()), ("alpha.md", alpha)];
+            let sources = sources.map(|(path, source)| (PathBuf::from(path), source));
+            let forest = compile_tree_cached(sources, &config).unwrap();
+            writer::Writer::html_doc(&forest.compiled()["index"], &forest.state).0
+        };
+
+        assert!(compile("Alpha").contains("See "));
+        let path = config::section_path("index");
+        let cached = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, cached.replace("See ", "Cached ")).unwrap();
+        assert!(compile("Alpha").contains("Cached "));
+
+        // the title of the linked page changed.
+        let html = compile("Beta");
+        assert!(html.contains("See ") && html.contains(">Beta<"));
+    }
+
+    #[test]
+    fn over_long_file_names_are_cached() {
+        let name = format!("{}.md", "a".repeat(250));
+        let index = "---\ntitle: Index\n---\n\nIndex body.\n";
+        let dir = test_dir("long-name", &[("index.md", index), (&name, "# Long\n")]);
+        let config = config::test_config(dir.to_str().unwrap());
+        let _guard = config::lock_test_config(config);
+
+        for _ in 0..2 {
+            let state = compile_state(dir.to_str().unwrap()).unwrap();
+            assert_eq!(state.pages.compiled().len(), 2);
+        }
+    }
 }
@@ -0,0 +1,131 @@
+//! The former slugs of the pages, written as their `aliases`: the links and embeds of an
+//! alias go to its page, and a redirect to that page is written at the alias.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    config,
+    entry::{split_list, MetaData, KEY_ALIASES},
+    html_flake, slug,
+};
+
+use super::{
+    diagnostics::Severity,
+    section::{HTMLContent, LazyContent},
+    state::CompileState,
+    CompileError,
+};
+
+/// Collect the `aliases` of the pending pages into [`CompileState::aliases`], then rewrite
+/// the links and embeds of an alias to its page, with a diagnostic for each of them.
+///
+/// An alias which is the slug of a page, or an alias of another page, is an error.
+pub fn insert_aliases(state: &mut CompileState) -> Result<(), CompileError> {
+    let mut aliases: BTreeMap<String, String> = BTreeMap::new();
+    for (slug, shallow) in state.pages.pending() {
+        let items = match shallow.metadata.get_list(KEY_ALIASES) {
+            Some(items) => items.to_vec(),
+            None => shallow
+                .metadata
+                .get_str(KEY_ALIASES)
+                .map_or(vec![], |s| split_list(s)),
+        };
+        for alias in items {
+            let alias = slug::to_slug(&alias);
+            if state.pages.contains(&alias) {
+                return Err(CompileError::AliasCollision(
+                    alias.clone(),
+                    slug.to_string(),
+                    alias,
+                ));
+            }
+            if let Some(other) = aliases.get(&alias) {
+                let other = other.to_string();
+                return Err(CompileError::AliasCollision(alias, slug.to_string(), other));
+            }
+            aliases.insert(alias, slug.to_string());
+        }
+    }
+    if aliases.is_empty() {
+        return Ok(());
+    }
+
+    let redirected = redirect_links(state, |alias| aliases.get(alias).cloned());
+    for (referrer, alias, kind) in redirected {
+        let message = format!(
+            "[{}] is an alias of [{}], update the {} to [{}].",
+            alias, aliases[&alias], kind, aliases[&alias]
+        );
+        let referrer = Some(referrer.as_str());
+        state
+            .diagnostics
+            .report(Severity::Info, Some(&alias), referrer, message);
+    }
+
+    state.aliases = aliases;
+    Ok(())
+}
+
+/// Rewrite the links and embeds of the pending pages to the slug `target` gives for their
+/// own, if any. Returns the page, the former slug and `"link"` or `"embed"` of each of them.
+pub fn redirect_links(
+    state: &mut CompileState,
+    target: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, String, &'static str)> {
+    let mut redirected = vec![];
+    for (slug, shallow) in state.pages.pending_mut() {
+        let metadata = shallow.metadata.0.values_mut();
+        for content in std::iter::once(&mut shallow.content).chain(metadata) {
+            let HTMLContent::Lazy(lazy_contents) = content else {
+                continue;
+            };
+            for lazy_content in lazy_contents {
+                let (former, kind) = match lazy_content {
+                    LazyContent::Local(local_link) => {
+                        let Some(canonical) = target(&local_link.slug) else {
+                            continue;
+                        };
+                        let former = std::mem::replace(&mut local_link.slug, canonical);
+                        (former, "link")
+                    }
+                    LazyContent::Embed(embed_content) => {
+                        let (url, anchor) = slug::split_anchor(&embed_content.url);
+                        let former = slug::to_slug(url);
+                        let Some(canonical) = target(&former) else {
+                            continue;
+                        };
+                        embed_content.url = match anchor {
+                            Some(anchor) => format!("/{}#{}", canonical, anchor),
+                            None => format!("/{}", canonical),
+                        };
+                        (former, "embed")
+                    }
+                    _ => continue,
+                };
+                redirected.push((slug.to_string(), former, kind));
+            }
+        }
+    }
+    redirected
+}
+
+/// Write a redirect to its page at the output file of each alias,
+/// from [`config::CUSTOM_REDIRECT_HTML`] if given. The drafts left out of the build
+/// get no redirects.
+pub fn write_redirects(state: &CompileState) {
+    for (alias, slug) in &state.aliases {
+        if state.drafts.contains(slug) {
+            continue;
+        }
+        let title = state
+            .get_metadata(slug)
+            .and_then(|metadata| metadata.page_title())
+            .map_or(slug.as_str(), String::as_str);
+        let html = html_flake::html_redirect(&config::full_html_url(slug), title);
+        let html_url = config::output_html_name(alias);
+        match std::fs::write(config::output_path(&html_url), html) {
+            Ok(()) => println!("Redirect: {} -> [{}]", html_url, slug),
+            Err(err) => eprintln!("{:?}", err),
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// An entry `@kind{key, name = value, ...}` of a BibTeX file.
+#[derive(Debug, Clone)]
+pub struct BibEntry {
+    /// Lowercase, such as `article`, `book`, `inproceedings` or `misc`.
+    pub kind: String,
+    pub key: String,
+
+    /// By lowercase name, the values being raw LaTeX without their outer delimiters.
+    pub fields: BTreeMap<String, String>,
+}
+
+impl BibEntry {
+    /// The field `name` as plain text, see [`latex_to_text`].
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.fields
+            .get(name)
+            .map(|value| latex_to_text(value))
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Where the entry is published: the journal of an article, the proceedings of
+    /// a paper or the publisher of a book.
+    pub fn container(&self) -> Option<String> {
+        CONTAINER_FIELDS.iter().find_map(|name| self.text(name))
+    }
+
+    /// The field holding [`BibEntry::container`] in an entry of type `kind`.
+    pub fn container_field(kind: &str) -> &'static str {
+        match kind {
+            "article" => "journal",
+            "inproceedings" | "incollection" | "inbook" | "conference" => "booktitle",
+            "book" => "publisher",
+            _ => "howpublished",
+        }
+    }
+}
+
+const CONTAINER_FIELDS: [&str; 4] = ["journal", "booktitle", "publisher", "howpublished"];
+
+/// The entries as a BibTeX file, in the given order and with their fields by name,
+/// so that the file only changes with the entries. The values are escaped.
+pub fn write_bibtex(entries: &[BibEntry]) -> String {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let fields: String = entry
+                .fields
+                .iter()
+                .map(|(name, value)| format!("  {} = {{{}}},\n", name, escape_latex(value)))
+                .collect();
+            format!("@{}{{{},\n{}}}\n", entry.kind, entry.key, fields)
+        })
+        .collect();
+    entries.join("\n")
+}
+
+/// `text` with the characters special to LaTeX escaped, so that it reads as written.
+pub fn escape_latex(text: &str) -> String {
+    let mut latex = String::new();
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                latex.push('\\');
+                latex.push(c);
+            }
+            '~' => latex.push_str("\\textasciitilde{}"),
+            '^' => latex.push_str("\\textasciicircum{}"),
+            '\\' => latex.push_str("\\textbackslash{}"),
+            c => latex.push(c),
+        }
+    }
+    latex
+}
+
+/// Parse the entries of a BibTeX file, expanding the names defined by `@string`
+/// and skipping `@comment` and `@preamble`.
+pub fn parse_bibtex(source: &str) -> Result<Vec<BibEntry>, String> {
+    let mut parser = BibParser {
+        chars: source.chars().collect(),
+        pos: 0,
+        strings: HashMap::new(),
+    };
+    let mut entries = vec![];
+    while parser.skip_to('@') {
+        parser.pos += 1;
+        let kind = parser.take_while(|c| c.is_alphanumeric()).to_lowercase();
+        parser.skip_whitespace();
+        let close = match parser.peek() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue, // an `@` outside of any entry.
+        };
+        parser.pos += 1;
+        if kind == "string" {
+            let name = parser.take_while(|c| c != '=' && c != close);
+            if parser.peek() == Some('=') {
+                parser.pos += 1;
+                let value = parser.value(close)?;
+                parser.strings.insert(name.trim().to_lowercase(), value);
+            }
+            parser.skip_group(close)?;
+            continue;
+        }
+        if ["comment", "preamble"].contains(&kind.as_str()) {
+            parser.skip_group(close)?;
+            continue;
+        }
+
+        let key = parser
+            .take_while(|c| c != ',' && c != close)
+            .trim()
+            .to_string();
+        let mut fields = BTreeMap::new();
+        loop {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => return Err(format!("the entry `{}` is not closed.", key)),
+                Some(c) if c == close => {
+                    parser.pos += 1;
+                    break;
+                }
+                Some(',') => {
+                    parser.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            let name = parser.take_while(|c| c != '=' && c != ',' && c != close);
+            if parser.peek() != Some('=') {
+                continue; // a trailing name without value.
+            }
+            parser.pos += 1;
+            let value = parser.value(close)?;
+            fields.insert(name.trim().to_lowercase(), value);
+        }
+        entries.push(BibEntry { kind, key, fields });
+    }
+    Ok(entries)
+}
+
+struct BibParser {
+    chars: Vec<char>,
+    pos: usize,
+
+    /// The values of the names defined by `@string`, by lowercase name.
+    strings: HashMap<String, String>,
+}
+
+impl BibParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Move to the next `c`, returning whether there is one.
+    fn skip_to(&mut self, c: char) -> bool {
+        while let Some(next) = self.peek() {
+            if next == c {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&predicate) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Skip to after the `close` of the group just opened, counting the braces inside.
+    fn skip_group(&mut self, close: char) -> Result<(), String> {
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        Err("a `@string`, `@comment` or `@preamble` is not closed.".to_string())
+    }
+
+    /// The parts of a value joined by `#`, each of them `{...}`, `"..."`, a number
+    /// or a name defined by `@string`.
+    fn value(&mut self, close: char) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.pos += 1;
+                    value.push_str(&self.delimited(|c, depth| c == '}' && depth == 0)?);
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    value.push_str(&self.delimited(|c, depth| c == '"' && depth == 0)?);
+                }
+                _ => {
+                    let bare = self.take_while(|c| c != ',' && c != '#' && c != close);
+                    let bare = bare.trim();
+                    match self.strings.get(&bare.to_lowercase()) {
+                        Some(string) => value.push_str(string),
+                        None => value.push_str(bare),
+                    }
+                }
+            }
+            self.skip_whitespace();
+            if self.peek() != Some('#') {
+                return Ok(value);
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// The text up to the closing delimiter, which `is_end` detects at brace depth 0.
+    /// The braces inside are kept.
+    fn delimited(&mut self, is_end: impl Fn(char, usize) -> bool) -> Result<String, String> {
+        let start = self.pos;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            if is_end(c, depth) {
+                let text = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                return Ok(text);
+            }
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                '\\' => self.pos += 1, // an escaped delimiter.
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Err("a field value is not closed.".to_string())
+    }
+}
+
+/// Plain text from LaTeX: accents such as `\'e` or `{\"o}` become the accented letters,
+/// `\ss`, `\o` and the like their letters, and the other commands and the braces are
+/// dropped, keeping their arguments.
+pub fn latex_to_text(latex: &str) -> String {
+    let chars: Vec<char> = latex.chars().collect();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '{' | '}' => {}
+            '~' => text.push(' '),
+            '-' if chars.get(i) == Some(&'-') => {
+                let dashes = 1 + chars[i..].iter().take_while(|c| **c == '-').count();
+                text.push(if dashes >= 3 { '—' } else { '–' });
+                i += dashes.min(3) - 1;
+            }
+            '\\' => {
+                let Some(&next) = chars.get(i) else {
+                    break;
+                };
+                let is_accent_command = ACCENT_COMMANDS.contains(next)
+                    && !chars.get(i + 1).is_some_and(|c| c.is_ascii_alphabetic());
+                if !next.is_ascii_alphabetic() || is_accent_command {
+                    let is_letter_accent = next.is_ascii_alphabetic();
+                    i += 1;
+                    if let Some(accent) = accent_mark(next) {
+                        // the accented letter, as `e`, `{e}` or `{\i}`.
+                        while chars.get(i) == Some(&' ') && is_letter_accent {
+                            i += 1;
+                        }
+                        let rest = &chars[i..];
+                        let (letter, len) = match rest {
+                            ['{', '\\', letter, '}', ..] => (Some(*letter), 4),
+                            ['{', letter, '}', ..] => (Some(*letter), 3),
+                            ['\\', letter, ..] => (Some(*letter), 2),
+                            [letter, ..] if *letter != '{' => (Some(*letter), 1),
+                            _ => (None, 0),
+                        };
+                        i += len;
+                        if let Some(letter) = letter {
+                            text.push_str(&accented(letter, accent));
+                        }
+                        continue;
+                    }
+                    text.push(next); // an escaped symbol such as `\&`.
+                    continue;
+                }
+                let name: String = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect();
+                i += name.len();
+                if let Some(letter) = special_letter(&name) {
+                    text.push_str(letter);
+                    // the space ending the command is not part of the text.
+                    if chars.get(i) == Some(&' ') {
+                        i += 1;
+                    }
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The commands written as a letter but accenting the following letter, as in `\c{c}`.
+const ACCENT_COMMANDS: &str = "cvuHkr";
+
+/// The combining mark of an accent command.
+fn accent_mark(command: char) -> Option<char> {
+    let mark = match command {
+        '`' => '\u{300}',
+        '\'' => '\u{301}',
+        '^' => '\u{302}',
+        '~' => '\u{303}',
+        '=' => '\u{304}',
+        'u' => '\u{306}',
+        '.' => '\u{307}',
+        '"' => '\u{308}',
+        'r' => '\u{30A}',
+        'H' => '\u{30B}',
+        'v' => '\u{30C}',
+        'c' => '\u{327}',
+        'k' => '\u{328}',
+        _ => return None,
+    };
+    Some(mark)
+}
+
+/// The precomposed accented letters, by combining mark, as pairs of the letter and
+/// the accented letter. Other letters keep the combining mark.
+const PRECOMPOSED: [(char, &str); 8] = [
+    ('\u{300}', "aàeèiìoòuùAÀEÈIÌOÒUÙ"),
+    ('\u{301}', "aáeéiíoóuúyýcćnńsśzźAÁEÉIÍOÓUÚYÝCĆNŃSŚZŹ"),
+    ('\u{302}', "aâeêiîoôuûAÂEÊIÎOÔUÛ"),
+    ('\u{303}', "aãnñoõAÃNÑOÕ"),
+    ('\u{308}', "aäeëiïoöuüyÿAÄEËIÏOÖUÜ"),
+    ('\u{30A}', "aåuůAÅUŮ"),
+    ('\u{30C}', "cčeěnňrřsšzžCČEĚNŇRŘSŠZŽ"),
+    ('\u{327}', "cçsşCÇSŞ"),
+];
+
+/// `letter` with the combining `mark`, `\i` and `\j` being read as `i` and `j`.
+fn accented(letter: char, mark: char) -> String {
+    let precomposed = PRECOMPOSED
+        .iter()
+        .find(|(m, _)| *m == mark)
+        .and_then(|(_, pairs)| {
+            let pairs: Vec<char> = pairs.chars().collect();
+            pairs
+                .chunks(2)
+                .find(|pair| pair[0] == letter)
+                .map(|pair| pair[1])
+        });
+    match precomposed {
+        Some(accented) => accented.to_string(),
+        None => format!("{}{}", letter, mark),
+    }
+}
+
+/// The letters written as commands, such as `\ss` for `ß`.
+fn special_letter(name: &str) -> Option<&'static str> {
+    let letter = match name {
+        "ss" => "ß",
+        "o" => "ø",
+        "O" => "Ø",
+        "ae" => "æ",
+        "AE" => "Æ",
+        "oe" => "œ",
+        "OE" => "Œ",
+        "aa" => "å",
+        "AA" => "Å",
+        "l" => "ł",
+        "L" => "Ł",
+        "i" => "ı",
+        "j" => "ȷ",
+        "textasciitilde" => "~",
+        "textasciicircum" => "^",
+        "textbackslash" => "\\",
+        _ => return None,
+    };
+    Some(letter)
+}
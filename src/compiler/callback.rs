@@ -1,16 +1,84 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt, fs, io,
+};
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct CallbackValue {
+    /// The page shown as the "Previous Level", the root page if not embedded.
+    /// When embedded by several pages, the first of them by slug.
     pub parent: String,
     
     /// Used to record which sections reference the current section.
-    pub backlinks: HashSet<String>,
+    /// Kept sorted so that rendering is deterministic.
+    pub backlinks: BTreeSet<String>,
+
+    /// The text around the first link of each of the `backlinks`,
+    /// by the slug of the linking page with its `:metadata` badge clipped.
+    pub snippets: BTreeMap<String, String>,
+
+    /// The pages embedding the current section.
+    pub contexts: BTreeSet<String>,
+
+    /// The pages listing the current section in their references, directly or through
+    /// the pages they embed, see [`crate::compiler::state::CompileState::cited_by`].
+    #[serde(default)]
+    pub citers: BTreeSet<String>,
+}
+
+/// The backlinks and parents a page gained or lost since the last compilation,
+/// see [`Callback::deltas`].
+#[derive(Debug, Serialize)]
+pub struct CallbackDelta {
+    pub slug: String,
+    pub added_backlinks: Vec<String>,
+    pub removed_backlinks: Vec<String>,
+    pub added_parents: Vec<String>,
+    pub removed_parents: Vec<String>,
 }
 
-#[derive(Debug)]
+impl fmt::Display for CallbackDelta {
+    /// One line per non-empty change, e.g. `+2 backlinks on [foo] (from bar, baz)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changes = [
+            ('+', "backlink", &self.added_backlinks),
+            ('-', "backlink", &self.removed_backlinks),
+            ('+', "parent", &self.added_parents),
+            ('-', "parent", &self.removed_parents),
+        ];
+        let lines: Vec<String> = changes
+            .iter()
+            .filter(|(_, _, slugs)| !slugs.is_empty())
+            .map(|(sign, noun, slugs)| {
+                let plural = if slugs.len() == 1 { "" } else { "s" };
+                format!(
+                    "{}{} {}{} on [{}] (from {})",
+                    sign,
+                    slugs.len(),
+                    noun,
+                    plural,
+                    self.slug,
+                    slugs.join(", ")
+                )
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Callback(pub HashMap<String, CallbackValue>);
 
+impl Default for Callback {
+    fn default() -> Self {
+        Callback::new()
+    }
+}
+
 impl Callback {
     pub fn new() -> Callback {
         Callback(HashMap::new())
@@ -28,8 +96,21 @@ impl Callback {
             Some(_) => {
                 let mut existed = self.0.remove(&child_slug).unwrap();
                 existed.backlinks.extend(value.backlinks);
+                for (slug, snippet) in value.snippets {
+                    existed.snippets.entry(slug).or_insert(snippet);
+                }
+                existed.contexts.extend(value.contexts);
+                existed.citers.extend(value.citers);
                 
-                if existed.parent == "index" && value.parent != "index" {
+                /*
+                 * All the embedding pages are kept in `contexts`, and the navigation
+                 * shows the first of them by slug, whatever the order of the merges.
+                 */
+                let root_slug = config::root_slug();
+                let is_embedded = |parent: &str| parent != root_slug;
+                if is_embedded(&value.parent)
+                    && (!is_embedded(&existed.parent) || value.parent < existed.parent)
+                {
                     existed.parent = value.parent;
                 }
                 self.0.insert(child_slug.to_string(), existed);
@@ -38,11 +119,15 @@ impl Callback {
     }
 
     pub fn insert_parent(&mut self, child_slug: String, parent: String) {
+        let contexts = BTreeSet::from([crate::slug::clip_metadata_badge(&parent)]);
         self.insert(
             child_slug,
             CallbackValue {
                 parent,
-                backlinks: HashSet::new(),
+                backlinks: BTreeSet::new(),
+                snippets: BTreeMap::new(),
+                contexts,
+                citers: BTreeSet::new(),
             },
         );
     }
@@ -54,9 +139,145 @@ impl Callback {
         self.insert(
             child_slug,
             CallbackValue {
-                parent: "index".to_string(),
-                backlinks: HashSet::from_iter(backlinks),
+                parent: config::root_slug(),
+                backlinks: BTreeSet::from_iter(backlinks),
+                snippets: BTreeMap::new(),
+                contexts: BTreeSet::new(),
+                citers: BTreeSet::new(),
+            },
+        );
+    }
+
+    /// Record the link from `backlink` to `child_slug`, with the text around it.
+    /// Only the first snippet of each linking page is kept.
+    pub fn insert_backlink(&mut self, child_slug: String, backlink: String, snippet: String) {
+        let snippets = BTreeMap::from([(crate::slug::clip_metadata_badge(&backlink), snippet)]);
+        self.insert(
+            child_slug,
+            CallbackValue {
+                parent: config::root_slug(),
+                backlinks: BTreeSet::from([backlink]),
+                snippets,
+                contexts: BTreeSet::new(),
+                citers: BTreeSet::new(),
             },
         );
     }
+
+    /// Record that `citer` lists the reference `child_slug` in its references.
+    pub fn insert_citer(&mut self, child_slug: String, citer: String) {
+        self.insert(
+            child_slug,
+            CallbackValue {
+                parent: config::root_slug(),
+                backlinks: BTreeSet::new(),
+                snippets: BTreeMap::new(),
+                contexts: BTreeSet::new(),
+                citers: BTreeSet::from([citer]),
+            },
+        );
+    }
+
+    /// The slugs whose parent, backlinks, snippets, contexts or citers differ in `old`,
+    /// including those only in one of them.
+    pub fn changed_slugs(&self, old: &Callback) -> BTreeSet<String> {
+        let slugs = self.0.keys().chain(old.0.keys());
+        slugs
+            .filter(|slug| self.0.get(*slug) != old.0.get(*slug))
+            .cloned()
+            .collect()
+    }
+
+    /// The backlinks and parents of each slug added or removed since `old`, by slug.
+    /// Backlinks from metadata are attributed to their page.
+    pub fn deltas(&self, old: &Callback) -> Vec<CallbackDelta> {
+        let empty = CallbackValue {
+            parent: String::new(),
+            backlinks: BTreeSet::new(),
+            snippets: BTreeMap::new(),
+            contexts: BTreeSet::new(),
+            citers: BTreeSet::new(),
+        };
+        let difference = |a: &BTreeSet<String>, b: &BTreeSet<String>| -> Vec<String> {
+            let clip = |set: &BTreeSet<String>| -> BTreeSet<String> {
+                set.iter().map(|s| crate::slug::clip_metadata_badge(s)).collect()
+            };
+            clip(a).difference(&clip(b)).cloned().collect()
+        };
+
+        let slugs: BTreeSet<&String> = self.0.keys().chain(old.0.keys()).collect();
+        slugs
+            .into_iter()
+            .map(|slug| {
+                let new = self.0.get(slug).unwrap_or(&empty);
+                let old = old.0.get(slug).unwrap_or(&empty);
+                CallbackDelta {
+                    slug: slug.to_string(),
+                    added_backlinks: difference(&new.backlinks, &old.backlinks),
+                    removed_backlinks: difference(&old.backlinks, &new.backlinks),
+                    added_parents: difference(&new.contexts, &old.contexts),
+                    removed_parents: difference(&old.contexts, &new.contexts),
+                }
+            })
+            .filter(|delta| {
+                !(delta.added_backlinks.is_empty()
+                    && delta.removed_backlinks.is_empty()
+                    && delta.added_parents.is_empty()
+                    && delta.removed_parents.is_empty())
+            })
+            .collect()
+    }
+
+    pub fn load(path: &str) -> io::Result<Callback> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The callback of a compile pass where `parent` embeds `c`, and `b` links it.
+    fn pass(parent: &str) -> Callback {
+        let mut callback = Callback::new();
+        callback.insert_backlinks("c".to_string(), ["b".to_string()]);
+        callback.insert_parent("c".to_string(), parent.to_string());
+        callback
+    }
+
+    #[test]
+    fn parent_is_independent_of_merge_order() {
+        let _guard = config::lock_test_config(config::test_config("."));
+        let mut forward = pass("a");
+        forward.merge(pass("b"));
+        let mut backward = pass("b");
+        backward.merge(pass("a"));
+
+        for callback in [&forward, &backward] {
+            let value = &callback.0["c"];
+            assert_eq!(value.parent, "a");
+            assert_eq!(value.contexts, BTreeSet::from(["a", "b"].map(String::from)));
+        }
+        assert!(forward.changed_slugs(&backward).is_empty());
+    }
+
+    #[test]
+    fn root_parent_never_replaces_an_embedding_page() {
+        let _guard = config::lock_test_config(config::test_config("."));
+        let mut callback = pass("b");
+        callback.insert_backlinks("c".to_string(), ["d".to_string()]);
+        assert_eq!(callback.0["c"].parent, "b");
+
+        let mut callback = Callback::new();
+        callback.insert_backlinks("c".to_string(), ["d".to_string()]);
+        assert_eq!(callback.0["c"].parent, config::root_slug());
+        callback.merge(pass("b"));
+        assert_eq!(callback.0["c"].parent, "b");
+    }
 }
@@ -0,0 +1,79 @@
+//! The links and embeds written in another case than the slug of their page, which reach
+//! it on a case-insensitive filesystem but not once deployed.
+
+use std::collections::BTreeMap;
+
+use super::{aliases::redirect_links, diagnostics::Severity, state::CompileState};
+
+/// Rewrite the links and embeds of the pending pages to a slug differing from theirs only
+/// in case to that slug, with a warning for each of them. Run after
+/// [`super::aliases::insert_aliases`], so that the aliases are matched too.
+pub fn resolve_cases(state: &mut CompileState) {
+    let pages = state
+        .pages
+        .pending()
+        .map(|(slug, _)| (slug.to_string(), slug.to_string()));
+    let targets: BTreeMap<String, String> = pages.chain(state.aliases.clone()).collect();
+    // the page of each slug or alias in lowercase.
+    let mut cases: BTreeMap<String, String> = BTreeMap::new();
+    for (slug, target) in &targets {
+        cases
+            .entry(slug.to_lowercase())
+            .or_insert(target.to_string());
+    }
+
+    let redirected = redirect_links(state, |slug| match targets.contains_key(slug) {
+        true => None,
+        false => cases.get(&slug.to_lowercase()).cloned(),
+    });
+    for (referrer, former, kind) in redirected {
+        let canonical = &cases[&former.to_lowercase()];
+        let verb = match kind {
+            "embed" => "embeds",
+            _ => "links to",
+        };
+        let message = format!(
+            "{} [{}] rather than [{}], which works only on a case-insensitive filesystem, update the {} to [{}].",
+            verb, former, canonical, kind, canonical
+        );
+        let referrer = Some(referrer.as_str());
+        state
+            .diagnostics
+            .report(Severity::Warning, Some(&former), referrer, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compiler::{test_forest, writer::Writer},
+        config,
+    };
+
+    #[test]
+    fn links_and_embeds_in_another_case_are_resolved() {
+        let config = config::test_config("kodama-tests");
+        let index = "---\ntitle: Index\n---\n\nSee [](Notes.md).\n\n[](/NOTES.md#:embed)\n";
+        let notes = "---\ntitle: Notes\n---\n\nNotes body.\n";
+        let (forest, _guard) = test_forest(&[("index", index), ("notes", notes)], config);
+
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(html.contains(r#"href="/notes""#));
+        assert!(html.contains("Notes body."));
+        let mut warnings: Vec<&str> = forest
+            .diagnostics()
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .map(|d| d.message.as_str())
+            .collect();
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            [
+                "embeds [NOTES] rather than [notes], which works only on a case-insensitive filesystem, update the embed to [notes].",
+                "links to [Notes] rather than [notes], which works only on a case-insensitive filesystem, update the link to [notes].",
+            ]
+        );
+    }
+}
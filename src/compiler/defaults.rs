@@ -0,0 +1,136 @@
+//! The metadata written once for many pages: the `[metadata.defaults]` of `kodama.toml`,
+//! and the [`config::META_FILE_NAME`] of each directory for the pages beneath it.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use crate::{
+    config::{self, CONFIG_FILE_NAME, META_FILE_NAME},
+    entry::HTMLMetaData,
+    process::embed_markdown::parse_metadata,
+    recorder::ParseRecorder,
+    slug,
+};
+
+use super::{diagnostics::Severity, state::CompileState};
+
+/// The origin of the metadata written by a page itself, see
+/// [`CompileState::metadata_origins`].
+pub const FROM_PAGE: &str = "the page";
+
+/// The origin of the metadata filled in when a page misses it, such as its `title`.
+pub const FROM_FALLBACK: &str = "the fallback to";
+
+/// The kind of the `origin` of a metadata value in [`CompileState::metadata_origins`]:
+/// `"page"`, `"directory"` for a [`META_FILE_NAME`], `"global"` for [`CONFIG_FILE_NAME`],
+/// `"git"`, `"fallback"`, or `"computed"` without an origin.
+pub fn origin_kind(origin: Option<&str>) -> &'static str {
+    // the variables replaced in a value follow its origin, see [`super::variables`].
+    let Some(origin) = origin.map(|origin| origin.split(", with ").next().unwrap_or(origin)) else {
+        return "computed";
+    };
+    match origin {
+        FROM_PAGE => "page",
+        _ if origin == format!("`{}`", CONFIG_FILE_NAME) => "global",
+        _ if origin.ends_with(&format!("{}`", META_FILE_NAME)) => "directory",
+        _ if origin.starts_with(FROM_FALLBACK) => "fallback",
+        _ => "git",
+    }
+}
+
+/// Fill in the metadata of the pending pages, `source_paths` being their source files
+/// relative to `root_dir` by slug, from the defaults: a page keeps its own values, then
+/// takes those of the nearest directory having them, then those of `kodama.toml`.
+/// Records where each value comes from in [`CompileState::metadata_origins`].
+pub fn insert_defaults(
+    state: &mut CompileState,
+    source_paths: &BTreeMap<String, String>,
+    root_dir: &str,
+) {
+    let mut warnings = vec![];
+    let global = parse_defaults(&config::metadata_defaults(), CONFIG_FILE_NAME);
+    let global = global.unwrap_or_else(|message| {
+        warnings.push(message);
+        HTMLMetaData::default()
+    });
+
+    let mut directories: HashMap<String, Option<HTMLMetaData>> = HashMap::new();
+    for (slug, shallow) in state.pages.pending_mut() {
+        let Some(path) = source_paths.get(slug) else {
+            continue;
+        };
+        let mut origins: BTreeMap<String, String> = shallow
+            .metadata
+            .0
+            .keys()
+            .map(|key| (key.to_string(), FROM_PAGE.to_string()))
+            .collect();
+
+        // the nearest directory first.
+        let mut layers = vec![];
+        for directory in Path::new(path).ancestors().skip(1) {
+            let meta_path = slug::pretty_path(&directory.join(META_FILE_NAME));
+            let layer = directories
+                .entry(meta_path.to_string())
+                .or_insert_with(|| read_defaults(root_dir, &meta_path, &mut warnings));
+            if let Some(layer) = layer {
+                layers.push((meta_path, layer.clone()));
+            }
+        }
+        layers.push((CONFIG_FILE_NAME.to_string(), global.clone()));
+
+        for (origin, layer) in layers {
+            for (key, value) in layer.0 {
+                if shallow.metadata.0.contains_key(&key) {
+                    continue;
+                }
+                if let Some(items) = layer.1.get(&key) {
+                    shallow.metadata.1.insert(key.to_string(), items.clone());
+                }
+                origins.insert(key.to_string(), format!("`{}`", origin));
+                shallow.metadata.0.insert(key, value);
+            }
+        }
+        state.metadata_origins.insert(slug.to_string(), origins);
+    }
+
+    for message in warnings {
+        state
+            .diagnostics
+            .report(Severity::Warning, None, None, message);
+    }
+}
+
+/// The defaults in the file `meta_path` relative to `root_dir`, if it exists.
+fn read_defaults(
+    root_dir: &str,
+    meta_path: &str,
+    warnings: &mut Vec<String>,
+) -> Option<HTMLMetaData> {
+    let source = std::fs::read_to_string(config::join_path(root_dir, meta_path)).ok()?;
+    let defaults = toml::from_str(&source)
+        .map_err(|err| format!("`{}` is ignored: {}", meta_path, err.message()))
+        .and_then(|values| parse_defaults(&config::metadata_text(&values), meta_path));
+    defaults.map_err(|message| warnings.push(message)).ok()
+}
+
+/// The `values` parsed as the front matter of a page, in the file `origin`.
+pub fn parse_defaults(
+    values: &BTreeMap<String, String>,
+    origin: &str,
+) -> Result<HTMLMetaData, String> {
+    let mut metadata = HTMLMetaData::default();
+    if values.is_empty() {
+        return Ok(metadata);
+    }
+    let lines: Vec<String> = values
+        .iter()
+        .map(|(key, value)| format!("{}: {}", key, value.replace('\n', " ")))
+        .collect();
+    let mut recorder = ParseRecorder::new(origin.to_string());
+    parse_metadata(&lines.join("\n"), &mut metadata, &mut recorder)
+        .map_err(|err| format!("`{}` is ignored: {:?}", origin, err))?;
+    Ok(metadata)
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+use super::CompileError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// The slug this diagnostic is about.
+    pub slug: Option<String>,
+
+    /// The slug of the page that refers to `slug`.
+    pub referrer: Option<String>,
+
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.referrer {
+            Some(referrer) => write!(f, "{}: [{}] {}", self.severity, referrer, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+impl From<CompileError> for Diagnostic {
+    fn from(err: CompileError) -> Diagnostic {
+        let severity = match err.is_warning() {
+            true => Severity::Warning,
+            false => Severity::Error,
+        };
+        let (slug, referrer, message) = match err {
+            CompileError::Missing(slug, referrer) => {
+                let message = format!("attempting to fetch a non-existent [{}].", slug);
+                (Some(slug), referrer, message)
+            }
+            CompileError::MissingEmbed(slug, referrer) => {
+                let message = format!("attempting to embed a non-existent [{}].", slug);
+                (Some(slug), Some(referrer), message)
+            }
+            CompileError::DuplicateSlug(slug, a, b) => {
+                let message = format!("[{}] is produced by both `{}` and `{}`.", slug, a, b);
+                (Some(slug), None, message)
+            }
+            CompileError::CaseCollision(slug, a, other, b) => {
+                let message = format!(
+                    "[{}] of `{}` and [{}] of `{}` differ only in case, which is the same file on a case-insensitive filesystem.",
+                    slug, a, other, b
+                );
+                (Some(slug), None, message)
+            }
+            CompileError::ReservedSlug(slug, path) => {
+                let message = format!(
+                    "[{}] of `{}` is under `{}`, which is reserved for the generated pages.",
+                    slug,
+                    path,
+                    config::reserved_prefix()
+                );
+                (Some(slug), None, message)
+            }
+            CompileError::InvalidSlug(slug, path, c) => {
+                let message = format!(
+                    "[{}] of `{}` has the character {:?}, which is not allowed in the output paths, rename the file.",
+                    slug, path, c
+                );
+                (Some(slug), None, message)
+            }
+            CompileError::AliasCollision(alias, slug, other) if alias == other => {
+                let message = format!("[{}] is an alias of [{}], but also a page.", alias, slug);
+                (Some(alias), None, message)
+            }
+            CompileError::AliasCollision(alias, slug, other) => {
+                let message = format!(
+                    "[{}] is an alias of both [{}] and [{}].",
+                    alias, other, slug
+                );
+                (Some(alias), None, message)
+            }
+            err => (None, None, format!("{:?}", err)),
+        };
+
+        Diagnostic {
+            severity,
+            slug,
+            referrer,
+            message,
+        }
+    }
+}
+
+/// Warnings and errors accumulated during compilation.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics(vec![])
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.0.push(diagnostic);
+    }
+
+    pub fn report(
+        &mut self,
+        severity: Severity,
+        slug: Option<&str>,
+        referrer: Option<&str>,
+        message: String,
+    ) {
+        self.push(Diagnostic {
+            severity,
+            slug: slug.map(str::to_string),
+            referrer: referrer.map(str::to_string),
+            message,
+        });
+    }
+
+    pub fn warning(&mut self, slug: &str, referrer: &str, message: String) {
+        self.report(Severity::Warning, Some(slug), Some(referrer), message);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.0.extend(other.0);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.iter().filter(|d| d.severity == severity).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    pub fn has_warnings(&self) -> bool {
+        self.count(Severity::Warning) > 0
+    }
+}
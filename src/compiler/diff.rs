@@ -0,0 +1,176 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use walkdir::WalkDir;
+
+use crate::{config, slug};
+
+use super::{state::CompileState, writer::Writer, CompileError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        };
+        f.pad(s)
+    }
+}
+
+/// A page whose freshly rendered HTML differs from the one in the old output dir.
+#[derive(Debug)]
+pub struct PageChange {
+    pub slug: String,
+    pub kind: ChangeKind,
+
+    /// The HTML file, relative to the output dir.
+    pub html_url: String,
+
+    /// The HTML in the old output dir, `None` if the page was added.
+    pub old: Option<String>,
+
+    /// The freshly rendered HTML, `None` if the page was removed.
+    pub new: Option<String>,
+
+    /// Whether the page was added or removed, or its source file changed
+    /// since the last compilation, see [`CompileState::modified`].
+    pub own: bool,
+
+    /// Changed pages that this page depends on: its embeds, links, references,
+    /// parent and backlinks.
+    pub causes: Vec<String>,
+}
+
+impl PageChange {
+    /// Whether the page changed only because of the pages it depends on.
+    pub fn is_ripple(&self) -> bool {
+        !self.own && !self.causes.is_empty()
+    }
+
+    /// A unified diff of the old and new HTML, with a line break after each tag.
+    pub fn unified_diff(&self) -> String {
+        let split = |html: &Option<String>| html.as_deref().unwrap_or("").replace('>', ">\n");
+        let (old, new) = (split(&self.old), split(&self.new));
+        let old_name = format!("a/{}", self.html_url);
+        let new_name = format!("b/{}", self.html_url);
+        similar::TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(&old_name, &new_name)
+            .to_string()
+    }
+}
+
+/// Render the `slugs` of `state` and compare them with the HTML files in `old_output_dir`,
+/// including those which are no longer produced. Nothing is written.
+pub fn diff_output(
+    slugs: &[String],
+    state: &CompileState,
+    old_output_dir: &str,
+) -> Result<Vec<PageChange>, CompileError> {
+    let root_slug = config::root_slug();
+
+    let mut rendered: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for slug in slugs {
+        // `index.html` is taken by the root page.
+        if *slug != root_slug && slug == "index" {
+            continue;
+        }
+        if let Some(section) = state.pages.get(slug) {
+            let (html, _) = Writer::html_doc(section, state);
+            rendered.insert(slug.to_string(), (config::output_html_name(slug), html));
+        }
+    }
+
+    let mut changes: BTreeMap<String, PageChange> = BTreeMap::new();
+    for (slug, (html_url, html)) in &rendered {
+        let old_path = Path::new(old_output_dir).join(html_url);
+        let (kind, old) = match std::fs::read_to_string(&old_path) {
+            Err(_) => (ChangeKind::Added, None),
+            Ok(old) if old == *html => continue,
+            Ok(old) => (ChangeKind::Changed, Some(old)),
+        };
+        changes.insert(
+            slug.to_string(),
+            PageChange {
+                slug: slug.to_string(),
+                kind,
+                html_url: html_url.to_string(),
+                own: kind == ChangeKind::Added || state.modified.contains(slug),
+                old,
+                new: Some(html.to_string()),
+                causes: vec![],
+            },
+        );
+    }
+
+    let html_urls: BTreeSet<&String> = rendered.values().map(|(html_url, _)| html_url).collect();
+    for entry in WalkDir::new(old_output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "html") {
+            continue;
+        }
+        let relative = path.strip_prefix(old_output_dir).unwrap_or(path);
+        let html_url = slug::pretty_path(relative);
+        if html_urls.contains(&html_url) {
+            continue;
+        }
+        let old = std::fs::read_to_string(path).map_err(|e| {
+            CompileError::IO(Some(concat!(file!(), '#', line!())), e, html_url.clone())
+        })?;
+        let slug = config::html_name_slug(&html_url);
+        changes.insert(
+            slug.to_string(),
+            PageChange {
+                slug,
+                kind: ChangeKind::Removed,
+                html_url,
+                own: true,
+                old: Some(old),
+                new: None,
+                causes: vec![],
+            },
+        );
+    }
+
+    let changed: BTreeSet<String> = changes.keys().cloned().collect();
+    for change in changes.values_mut() {
+        change.causes = dependencies(&change.slug, state)
+            .into_iter()
+            .filter(|slug| *slug != change.slug && changed.contains(slug))
+            .collect();
+    }
+    Ok(changes.into_values().collect())
+}
+
+/// The pages whose contents or titles appear in the HTML of `slug`.
+fn dependencies(slug: &str, state: &CompileState) -> BTreeSet<String> {
+    let mut dependencies = BTreeSet::new();
+    if let Some(section) = state.pages.get(slug) {
+        dependencies.extend(section.transitive_embeds.iter().cloned());
+        dependencies.extend(section.references.iter().cloned());
+        dependencies.extend(section.further_reading.iter().cloned());
+    }
+    if let Some(edges) = state.graph.get(slug) {
+        dependencies.extend(edges.links.iter().cloned());
+    }
+    if let Some(callback) = state.callback.0.get(slug) {
+        dependencies.insert(callback.parent.to_string());
+        let backlinks = callback.backlinks.iter();
+        dependencies.extend(backlinks.map(|s| slug::clip_metadata_badge(s)));
+    }
+    dependencies
+}
@@ -0,0 +1,81 @@
+//! The slugs frozen in `slugs.toml` by `kodama slugs freeze`, kept by their source files
+//! whatever the slug settings, so that the urls published once do not change.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use crate::config::SLUGS_FILE_NAME;
+
+use super::{aliases::redirect_links, state::CompileState, CompileError};
+
+/// The frozen slugs of `slugs.toml` in `root_dir` by source path, relative to it,
+/// none if there is no such file.
+pub fn read_frozen_slugs(root_dir: &Path) -> Result<BTreeMap<String, String>, CompileError> {
+    let path = root_dir.join(SLUGS_FILE_NAME);
+    match fs::read_to_string(&path) {
+        Err(_) => Ok(BTreeMap::new()),
+        Ok(content) => toml::from_str(&content).map_err(|e| {
+            let path = path.display().to_string();
+            CompileError::Syntax(Some(concat!(file!(), '#', line!())), Box::new(e), path)
+        }),
+    }
+}
+
+/// Write `slugs.toml` in `root_dir` with the slug of each source path of `slugs`.
+pub fn write_frozen_slugs(
+    root_dir: &Path,
+    slugs: &BTreeMap<String, String>,
+) -> Result<(), std::io::Error> {
+    let table = toml::to_string(slugs).map_err(std::io::Error::other)?;
+    let content = format!(
+        "# The slug of each source file, kept whatever the slug settings.\n# Written by `kodama slugs freeze`, and read at each build.\n\n{}",
+        table
+    );
+    fs::write(root_dir.join(SLUGS_FILE_NAME), content)
+}
+
+/// The slug of the source file `relative_path`: its frozen slug of `frozen` if any, else
+/// `computed`. A frozen slug other than `computed` is added to `drifted`, with `computed`.
+pub fn frozen_slug(
+    frozen: &BTreeMap<String, String>,
+    relative_path: &str,
+    computed: String,
+    drifted: &mut BTreeMap<String, String>,
+) -> String {
+    match frozen.get(relative_path) {
+        Some(slug) if *slug != computed => {
+            drifted.insert(slug.to_string(), computed);
+            slug.to_string()
+        }
+        _ => computed,
+    }
+}
+
+/// The source paths of `frozen` which are not among the source files `paths`,
+/// each with its frozen slug.
+pub fn missing_sources<'a>(
+    frozen: &'a BTreeMap<String, String>,
+    paths: &BTreeSet<String>,
+) -> Vec<(&'a String, &'a String)> {
+    frozen
+        .iter()
+        .filter(|(path, _)| !paths.contains(*path))
+        .collect()
+}
+
+/// Rewrite the links and embeds to the computed slug of a page of
+/// [`CompileState::drifted_slugs`] to its frozen slug, unless another page has that slug.
+pub fn redirect_drifted(state: &mut CompileState) {
+    let computed: BTreeMap<String, String> = state
+        .drifted_slugs
+        .iter()
+        .filter(|(_, computed)| !state.pages.contains(computed))
+        .map(|(frozen, computed)| (computed.to_string(), frozen.to_string()))
+        .collect();
+    if !computed.is_empty() {
+        redirect_links(state, |slug| computed.get(slug).cloned());
+    }
+}
@@ -0,0 +1,115 @@
+//! The `updated` of the pages filled in from the git history, see [`config::is_git_dates`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    process::Command,
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    config,
+    date::Date,
+    entry::{MetaData, KEY_UPDATED},
+};
+
+use super::{diagnostics::Severity, section::HTMLContent, state::CompileState};
+
+/// Fill in the `updated` of the pending pages without one, `source_paths` being their source
+/// files relative to `root_dir` by slug, from the last commit changing each file.
+///
+/// A file not committed yet is dated by its modification time in UTC, with a warning.
+/// Outside of a git repository, all files are dated so, with a single warning.
+pub fn insert_updated(
+    state: &mut CompileState,
+    source_paths: &BTreeMap<String, String>,
+    root_dir: &str,
+) {
+    let commit_dates = last_commit_dates(root_dir);
+    let mut warnings = vec![];
+    for (slug, shallow) in state.pages.pending_mut() {
+        let Some(path) = source_paths.get(slug) else {
+            continue;
+        };
+        if shallow.metadata.get(KEY_UPDATED).is_some() {
+            continue;
+        }
+
+        let committed = commit_dates.as_ref().map(|dates| dates.get(path));
+        let origin = match committed {
+            Some(Some(_)) => "the git history",
+            _ => "the modification time",
+        };
+        let date = match committed {
+            Some(Some(date)) => Some(*date),
+            Some(None) => {
+                let message = format!(
+                    "[{}] `{}` is not committed to git, so its `updated` is its modification time.",
+                    slug, path
+                );
+                warnings.push((slug.to_string(), message));
+                modified_date(&config::join_path(root_dir, path))
+            }
+            None => modified_date(&config::join_path(root_dir, path)),
+        };
+        if let Some(date) = date {
+            let updated = HTMLContent::Plain(date.to_string());
+            shallow.metadata.0.insert(KEY_UPDATED.to_string(), updated);
+            state
+                .metadata_origins
+                .entry(slug.to_string())
+                .or_default()
+                .insert(KEY_UPDATED.to_string(), origin.to_string());
+        }
+    }
+
+    if commit_dates.is_none() {
+        let message = format!(
+            "`{}` is not in a git repository, so the `updated` of the pages are the modification times of their files.",
+            root_dir
+        );
+        state
+            .diagnostics
+            .report(Severity::Warning, None, None, message);
+    }
+    for (slug, message) in warnings {
+        state
+            .diagnostics
+            .report(Severity::Warning, Some(&slug), None, message);
+    }
+}
+
+/// The date of the last commit changing each file under `root_dir`, by its path relative
+/// to `root_dir`, read in a single walk of the history. `None` if `root_dir` is not in
+/// a git repository or `git` cannot be run.
+fn last_commit_dates(root_dir: &str) -> Option<HashMap<String, Date>> {
+    let output = Command::new("git")
+        .current_dir(root_dir)
+        .args(["-c", "core.quotePath=false", "log", "--format=%x00%cI"])
+        .args(["--name-only", "--relative", "--", "."])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // each commit is a line `\0` followed by its date, then the files it changed,
+    // the most recent commit first.
+    let mut dates = HashMap::new();
+    let mut date = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(committed) = line.strip_prefix('\0') {
+            // `YYYY-MM-DDTHH:MM:SS+HH:MM`, kept to the minutes.
+            date = committed.get(..16).and_then(Date::parse);
+        } else if let (false, Some(date)) = (line.is_empty(), date) {
+            dates.entry(line.to_string()).or_insert(date);
+        }
+    }
+    Some(dates)
+}
+
+/// The modification time of the file `path`.
+fn modified_date(path: &str) -> Option<Date> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Date::from_unix(secs))
+}
@@ -0,0 +1,30 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Outgoing edges of a page, recorded while it is compiled.
+/// Edges from the metadata of a page are attributed to the page itself.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageEdges {
+    /// Pages embedded by this page.
+    pub embeds: BTreeSet<String>,
+
+    /// Pages linked by this page.
+    pub links: BTreeSet<String>,
+
+    /// Reference pages linked by this page.
+    pub references: BTreeSet<String>,
+
+    /// Anchors linked by this page, each with the page it is on.
+    #[serde(default)]
+    pub anchors: BTreeSet<(String, String)>,
+}
+
+impl PageEdges {
+    pub fn merge(&mut self, other: PageEdges) {
+        self.embeds.extend(other.embeds);
+        self.links.extend(other.links);
+        self.references.extend(other.references);
+        self.anchors.extend(other.anchors);
+    }
+}
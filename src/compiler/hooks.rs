@@ -0,0 +1,88 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use crate::config;
+
+use super::section::Section;
+
+/// A transformation of a freshly compiled section, given its slug.
+/// An `Err` is reported as an error diagnostic of the slug.
+pub type PostHook = Box<dyn Fn(&str, &mut Section) -> Result<(), String> + Send + Sync>;
+
+/// The post hooks of a compilation, run in the order they were added.
+#[derive(Default)]
+pub struct PostHooks(Vec<PostHook>);
+
+impl std::fmt::Debug for PostHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PostHooks({})", self.0.len())
+    }
+}
+
+impl PostHooks {
+    /// The command hooks of [`config::post_hooks`].
+    pub fn from_config() -> PostHooks {
+        PostHooks(config::post_hooks().into_iter().map(command_hook).collect())
+    }
+
+    pub fn push(&mut self, hook: PostHook) {
+        self.0.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run every hook on `section`, returning the error messages of those which failed.
+    pub fn run(&self, slug: &str, section: &mut Section) -> Vec<String> {
+        self.0
+            .iter()
+            .filter_map(|hook| hook(slug, section).err())
+            .collect()
+    }
+}
+
+/// A hook running `command`, which receives the section as JSON on stdin and prints
+/// the transformed section as JSON on stdout. The slug is passed in `KODAMA_SLUG`.
+pub fn command_hook(command: String) -> PostHook {
+    Box::new(move |slug, section| {
+        let mut args = command.split_whitespace();
+        let program = args.next().ok_or("the post hook command is empty.")?;
+        let input = serde_json::to_string(section).map_err(|e| e.to_string())?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .env("KODAMA_SLUG", slug)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run the post hook `{}`: {}.", command, e))?;
+
+        // written from another thread, so that a large output cannot block the input.
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("failed to run the post hook `{}`: {}.", command, e))?;
+        let _ = writer.join();
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match stderr.trim() {
+                "" => output.status.to_string(),
+                stderr => stderr.to_string(),
+            };
+            return Err(format!("the post hook `{}` failed: {}", command, reason));
+        }
+        *section = serde_json::from_slice(&output.stdout).map_err(|e| {
+            format!(
+                "the post hook `{}` printed an invalid section: {}.",
+                command, e
+            )
+        })?;
+        Ok(())
+    })
+}
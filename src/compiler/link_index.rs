@@ -0,0 +1,191 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, entry::MetaData, slug};
+
+use super::{
+    callback::{Callback, CallbackValue},
+    state::CompileState,
+};
+
+/// Bumped whenever a field of [`LinkIndex`] or [`LinkEntry`] changes meaning or is removed.
+pub const LINK_INDEX_VERSION: u32 = 1;
+
+/// The file written under the output directory, see [`config::is_emit_link_index`].
+pub const LINK_INDEX_PATH: &str = ".kodama/links.json";
+
+/// The relations between the compiled pages, written as [`LINK_INDEX_PATH`]
+/// for the tools working on the forest without parsing its HTML.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "pages": {
+///     "a": {
+///       "title": "Alpha",
+///       "parent": "index",
+///       "parents": ["index"],
+///       "backlinks": ["b"],
+///       "links": ["c"],
+///       "references": ["c"],
+///       "words": 420,
+///       "reading_minutes": 3
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkIndex {
+    pub version: u32,
+
+    /// By slug, in slug order.
+    pub pages: BTreeMap<String, LinkEntry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkEntry {
+    /// The plain text title of the page, if any.
+    pub title: Option<String>,
+
+    /// The page shown as the "Previous Level" of this page, the root page if not embedded.
+    pub parent: Option<String>,
+
+    /// The pages embedding this page.
+    pub parents: BTreeSet<String>,
+
+    /// The pages linking this page.
+    pub backlinks: BTreeSet<String>,
+
+    /// The pages linked by this page.
+    pub links: BTreeSet<String>,
+
+    /// The reference pages linked by this page.
+    pub references: BTreeSet<String>,
+
+    /// The words of the page and its reading time, see [`config::word_count`].
+    pub words: usize,
+    pub reading_minutes: usize,
+}
+
+impl LinkIndex {
+    pub fn new(state: &CompileState) -> LinkIndex {
+        let pages = state
+            .pages
+            .compiled()
+            .iter()
+            .map(|(slug, section)| {
+                let callback = state.callback.0.get(slug);
+                let edges = state.graph.get(slug);
+                let entry = LinkEntry {
+                    title: state
+                        .get_metadata(slug)
+                        .and_then(|m| m.page_title())
+                        .cloned(),
+                    parent: callback.map(|c| c.parent.to_string()),
+                    parents: callback.map(|c| c.contexts.clone()).unwrap_or_default(),
+                    backlinks: callback
+                        .into_iter()
+                        .flat_map(|c| c.backlinks.iter())
+                        .map(|s| slug::clip_metadata_badge(s))
+                        .collect(),
+                    links: edges.map(|e| e.links.clone()).unwrap_or_default(),
+                    references: edges.map(|e| e.references.clone()).unwrap_or_default(),
+                    words: section.stats.counted_words(),
+                    reading_minutes: section.stats.reading_minutes(),
+                };
+                (slug.to_string(), entry)
+            })
+            .collect();
+        LinkIndex {
+            version: LINK_INDEX_VERSION,
+            pages,
+        }
+    }
+
+    pub fn read(path: &str) -> io::Result<LinkIndex> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// The parents, contexts and backlinks of the pages, without the backlink snippets.
+    pub fn callback(&self) -> Callback {
+        let mut callback = Callback::new();
+        for (slug, entry) in &self.pages {
+            let Some(parent) = &entry.parent else {
+                continue;
+            };
+            let value = CallbackValue {
+                parent: parent.to_string(),
+                backlinks: entry.backlinks.clone(),
+                snippets: BTreeMap::new(),
+                contexts: entry.parents.clone(),
+                citers: BTreeSet::new(),
+            };
+            callback.insert(slug.to_string(), value);
+        }
+        callback
+    }
+}
+
+/// Write the [`LinkIndex`] of `state` into the output directory, unless disabled.
+pub fn write_link_index(state: &CompileState) {
+    if !config::is_emit_link_index() {
+        return;
+    }
+    let path = config::output_path(LINK_INDEX_PATH);
+    if let Err(err) = LinkIndex::new(state).write(&path) {
+        eprintln!("{:?}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::test_forest;
+
+    #[test]
+    fn link_index_round_trip() {
+        let config = config::test_config("kodama-tests");
+        let index = "---\ntitle: Index\n---\n\n[](/a.md#:embed)\n";
+        let a = "---\ntitle: Alpha\n---\n\nSee [](b.md).\n";
+        let b = "---\ntitle: Beta\ntaxon: Reference\n---\n\nBeta body.\n";
+        let (forest, _guard) = test_forest(&[("index", index), ("a", a), ("b", b)], config);
+
+        let path = std::env::temp_dir().join(format!("kodama-links-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        LinkIndex::new(&forest.state).write(path).unwrap();
+        let link_index = LinkIndex::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(link_index.version, LINK_INDEX_VERSION);
+        let a = &link_index.pages["a"];
+        assert_eq!(a.title.as_deref(), Some("Alpha"));
+        assert_eq!(a.parent.as_deref(), Some("index"));
+        assert_eq!(a.parents, BTreeSet::from(["index".to_string()]));
+        assert_eq!(a.links, BTreeSet::from(["b".to_string()]));
+        assert_eq!(a.references, BTreeSet::from(["b".to_string()]));
+        let b = &link_index.pages["b"];
+        assert_eq!(b.backlinks, BTreeSet::from(["a".to_string()]));
+
+        let callback = link_index.callback();
+        assert_eq!(callback.0["a"].parent, "index");
+        assert_eq!(
+            callback.0["a"].contexts,
+            forest.state.callback.0["a"].contexts
+        );
+        assert_eq!(
+            callback.0["b"].backlinks,
+            forest.state.callback.0["b"].backlinks
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    config::{self, NumberingScope},
+    entry::{EntryMetaData, MetaData, KEY_NUMBER},
+};
+
+use super::{
+    counter::Counter,
+    section::{Section, SectionContent, SectionOption},
+    taxon::Taxon,
+};
+
+/// Numbered by the `+` of the embed or by the taxon, see [`Taxon::is_numbered`].
+pub fn is_numbered(metadata: &EntryMetaData, option: &SectionOption) -> bool {
+    option.numbering || metadata.data_taxon().is_some_and(|t| Taxon::is_numbered(t))
+}
+
+/// The `number` of a numbered section, shown instead of the one it would be given,
+/// such as `A.` for `number: A`.
+pub fn manual_number(metadata: &EntryMetaData) -> Option<String> {
+    let number = metadata.get_str(KEY_NUMBER)?.trim();
+    let number = number.strip_suffix('.').unwrap_or(number);
+    (!number.is_empty()).then(|| format!("{}.", number))
+}
+
+/// Numbered and counted among its siblings, that is without a [`manual_number`].
+pub fn is_counted(metadata: &EntryMetaData, option: &SectionOption) -> bool {
+    is_numbered(metadata, option) && manual_number(metadata).is_none()
+}
+
+/// The slugs of the cross-references `[#](slug)` in `section` and the sections it embeds,
+/// see [`crate::html_flake::html_cross_reference`].
+pub fn cross_references(section: &Section) -> BTreeSet<String> {
+    const PREFIX: &str = "<!--number:";
+    let mut slugs = BTreeSet::new();
+    for child in &section.children {
+        match child {
+            SectionContent::Plain(html) => {
+                let mut rest = html.as_str();
+                while let Some(start) = rest.find(PREFIX) {
+                    rest = &rest[start + PREFIX.len()..];
+                    let Some(end) = rest.find("-->") else {
+                        break;
+                    };
+                    slugs.insert(rest[..end].to_string());
+                }
+            }
+            SectionContent::Embed(embed) => slugs.extend(cross_references(&embed.section)),
+        }
+    }
+    slugs
+}
+
+/// The numbers of the numbered sections embedded from the root page, such as `3.2.`,
+/// see [`crate::config::is_auto_numbering`].
+///
+/// A section embedded in several places has a number for each of its embeds, so the
+/// numbers are keyed by the chain of slugs from the root page down to the embed.
+/// The pages not embedded from the root page, compiled by the residued cleanup,
+/// have no number. With [`NumberingScope::Chapter`], the pages embedded by the root
+/// page are counted whether numbered or not, so that the sections of the second
+/// of them are numbered `2.1.`, `2.2.`, ...
+#[derive(Debug, Default)]
+pub struct Numbering {
+    /// By the slugs of the chain joined by `/`.
+    numbers: BTreeMap<String, String>,
+
+    /// The chain of the first embed of each page, which its own page is numbered as.
+    occurrences: HashMap<String, Vec<String>>,
+
+    /// Numbered as a page alone, see [`Numbering::in_page`].
+    in_page: bool,
+}
+
+impl Numbering {
+    pub fn new(root: &Section) -> Numbering {
+        let mut numbering = Numbering::default();
+        let mut chain = vec![root.slug()];
+        numbering.occurrences.insert(root.slug(), chain.clone());
+        let chapters = config::numbering_scope() == NumberingScope::Chapter;
+        numbering.visit(root, &mut Counter::init(), &mut chain, chapters);
+        numbering
+    }
+
+    /// The numbers of the page `section` alone, as written without
+    /// [`config::is_auto_numbering`]: the page itself is numbered if its taxon is,
+    /// and the sections embedded by a section not counted are numbered apart.
+    pub fn in_page(section: &Section) -> Numbering {
+        let mut numbering = Numbering {
+            in_page: true,
+            ..Numbering::default()
+        };
+        let mut chain = vec![section.slug()];
+        numbering.occurrences.insert(section.slug(), chain.clone());
+        let option = SectionOption::default();
+        let counter = &mut Counter::init();
+        numbering.enter(
+            section,
+            &section.metadata,
+            &option,
+            counter,
+            &mut chain,
+            false,
+        );
+        numbering
+    }
+
+    /// The counted siblings are numbered in order, and the sections embedded by
+    /// a counted section one level deeper. All of them are counted as `chapters`.
+    fn visit(
+        &mut self,
+        section: &Section,
+        counter: &mut Counter,
+        chain: &mut Vec<String>,
+        chapters: bool,
+    ) {
+        for child in &section.children {
+            let SectionContent::Embed(embed) = child else {
+                continue;
+            };
+            let slug = embed.section.slug();
+            if chain.contains(&slug) {
+                continue;
+            }
+            chain.push(slug.to_string());
+            self.occurrences
+                .entry(slug)
+                .or_insert_with(|| chain.clone());
+            let metadata = embed.metadata();
+            self.enter(
+                &embed.section,
+                &metadata,
+                &embed.option,
+                counter,
+                chain,
+                chapters,
+            );
+            chain.pop();
+        }
+    }
+
+    /// Number the section at the end of `chain`, a `chapter` being counted anyway,
+    /// then the sections it embeds.
+    fn enter(
+        &mut self,
+        section: &Section,
+        metadata: &EntryMetaData,
+        option: &SectionOption,
+        counter: &mut Counter,
+        chain: &mut Vec<String>,
+        chapter: bool,
+    ) {
+        let is_counted = is_counted(metadata, option);
+        if is_counted || chapter {
+            counter.step_mut();
+        }
+        let manual = manual_number(metadata).filter(|_| is_numbered(metadata, option));
+        if let Some(number) = manual.or_else(|| is_counted.then(|| counter.display())) {
+            self.numbers.insert(chain.join("/"), number);
+        }
+        match is_counted || chapter {
+            true => self.visit(section, &mut counter.left_shift(), chain, false),
+            false if self.in_page => self.visit(section, &mut counter.clone(), chain, false),
+            false => self.visit(section, counter, chain, false),
+        }
+    }
+
+    /// The number of the section embedded through `chain` in the page `chain[0]`,
+    /// following the first embed of that page from the root page.
+    pub fn number(&self, chain: &[String]) -> Option<&String> {
+        let (page, rest) = chain.split_first()?;
+        let mut key = self.occurrences.get(page)?.clone();
+        key.extend(rest.iter().cloned());
+        self.numbers.get(&key.join("/"))
+    }
+
+    /// The number of `target` where the page `page` first numbers it, or else the number
+    /// of its own page.
+    pub fn cross_reference(&self, page: &str, target: &str) -> Option<&String> {
+        let suffix = format!("/{}", target);
+        self.page_numbers(page)
+            .into_iter()
+            .find(|(key, _)| key.ends_with(&suffix) || *key == target)
+            .map(|(_, number)| number)
+            .or_else(|| self.number(&[target.to_string()]))
+    }
+
+    /// The numbers shown in the page `slug`, in order of their chains.
+    pub fn page_numbers(&self, slug: &str) -> Vec<(&String, &String)> {
+        let Some(prefix) = self.occurrences.get(slug).map(|chain| chain.join("/")) else {
+            return vec![];
+        };
+        self.numbers
+            .range(prefix.to_string()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter(|(key, _)| key.len() == prefix.len() || key[prefix.len()..].starts_with('/'))
+            .collect()
+    }
+}
@@ -0,0 +1,195 @@
+//! The metadata of each written page as JSON next to its HTML, for the scripts of the site
+//! such as search previews, see [`config::is_emit_page_meta`].
+
+use std::{collections::BTreeMap, fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config,
+    entry::{EntryMetaData, MetaData, KEY_BACKLINK_COUNT, KEY_TAGS},
+};
+
+use super::{section::HTMLContent, state::CompileState};
+
+/// Bumped whenever a field of [`PageMeta`] changes meaning or is removed.
+pub const PAGE_META_VERSION: u32 = 1;
+
+/// The metadata of a compiled page, written as `<slug>.meta.json` in the output directory.
+/// The values are plain text, but those of [`config::page_meta_html`] kept as HTML.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "slug": "a",
+///   "title": "Alpha",
+///   "taxon": "Definition",
+///   "date": "2024-03-05",
+///   "tags": ["rust"],
+///   "words": 420,
+///   "reading_minutes": 3,
+///   "backlink_count": 2,
+///   "indexed": true,
+///   "metadata": { "title": "Alpha", "status": "evergreen" }
+/// }
+/// ```
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageMeta {
+    pub version: u32,
+    pub slug: String,
+
+    /// The plain text title of the page, if any.
+    pub title: Option<String>,
+
+    /// The canonical taxon of the page, if any, see [`config::taxon_id`].
+    pub taxon: Option<String>,
+
+    /// The `date` as written.
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+
+    /// The words of the page and its reading time, see [`config::word_count`].
+    pub words: usize,
+    pub reading_minutes: usize,
+
+    /// The listed pages linking this page.
+    pub backlink_count: usize,
+
+    /// Whether the search of the site may list the page, see [`CompileState::is_indexed`].
+    pub indexed: bool,
+
+    /// All the metadata of the page by key, without the virtual keys such as
+    /// `:backlink-count`.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl PageMeta {
+    /// The metadata of the compiled page `slug`, if any.
+    pub fn new(state: &CompileState, slug: &str) -> Option<PageMeta> {
+        let section = state.pages.get(slug)?;
+        let metadata = &section.metadata;
+        let html_keys = config::page_meta_html();
+        let value = |key: &str, value: &str| match html_keys.iter().any(|k| k == key) {
+            true => value.to_string(),
+            false => plain_text(value),
+        };
+
+        let backlink_count = state
+            .get_metadata(slug)
+            .and_then(|m| m.get_str(KEY_BACKLINK_COUNT))
+            .and_then(|count| count.parse().ok())
+            .unwrap_or_default();
+        Some(PageMeta {
+            version: PAGE_META_VERSION,
+            slug: slug.to_string(),
+            title: metadata.page_title().map(|title| plain_text(title)),
+            taxon: metadata
+                .data_taxon()
+                .filter(|taxon| !taxon.is_empty())
+                .map(|taxon| config::taxon_id(taxon)),
+            date: metadata.date().map(|date| plain_text(date)),
+            tags: metadata.get_list(KEY_TAGS).unwrap_or_default().to_vec(),
+            words: section.stats.counted_words(),
+            reading_minutes: section.stats.reading_minutes(),
+            backlink_count,
+            indexed: state.is_indexed(slug),
+            metadata: Self::metadata(metadata, value),
+        })
+    }
+
+    fn metadata(
+        metadata: &EntryMetaData,
+        value: impl Fn(&str, &str) -> String,
+    ) -> BTreeMap<String, String> {
+        metadata
+            .0
+            .iter()
+            .filter(|(key, _)| !key.starts_with(':'))
+            .map(|(key, v)| (key.to_string(), value(key, v)))
+            .collect()
+    }
+
+    pub fn read(path: &str) -> io::Result<PageMeta> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
+
+/// The output file of the [`PageMeta`] of `slug`, relative to the output dir,
+/// next to [`config::output_html_name`].
+pub fn page_meta_name(slug: &str) -> String {
+    let html_name = config::output_html_name(slug);
+    let name = html_name.strip_suffix(".html").unwrap_or(&html_name);
+    format!("{}.meta.json", name)
+}
+
+/// Write the [`PageMeta`] of each of the compiled `slugs`, if enabled.
+pub fn write_page_meta(state: &CompileState, slugs: &[String]) {
+    if !config::is_emit_page_meta() {
+        return;
+    }
+    for slug in slugs {
+        let Some(page_meta) = PageMeta::new(state, slug) else {
+            continue;
+        };
+        let path = config::output_path(&page_meta_name(slug));
+        if let Err(err) = page_meta.write(&path) {
+            eprintln!("{:?}", err);
+        }
+    }
+}
+
+/// The text of the HTML `html`, without its tags and unescaped.
+fn plain_text(html: &str) -> String {
+    let text = HTMLContent::Plain(html.to_string()).remove_all_tags();
+    htmlize::unescape(text.trim()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::test_forest;
+
+    #[test]
+    fn page_meta_round_trip() {
+        let mut config = config::test_config("kodama-tests");
+        config.page_meta_html = vec!["summary".to_string()];
+        let index = "---\ntitle: Index\n---\n\nSee [](a.md).\n";
+        let a = concat!(
+            "---\ntitle: Alpha *one*\ntaxon: Definition\ndate: 2024-03-05\n",
+            "tags: rust, web\nstatus: *evergreen*\nsummary: *short*\n---\n\nAlpha body.\n"
+        );
+        let (forest, _guard) = test_forest(&[("index", index), ("a", a)], config);
+
+        let page_meta = PageMeta::new(&forest.state, "a").unwrap();
+        assert_eq!(page_meta.title.as_deref(), Some("Alpha one"));
+        assert_eq!(page_meta.taxon.as_deref(), Some("definition"));
+        assert_eq!(page_meta.date.as_deref(), Some("2024-03-05"));
+        assert_eq!(page_meta.tags, ["rust", "web"]);
+        assert_eq!(page_meta.backlink_count, 1);
+        assert_eq!(page_meta.metadata["status"], "evergreen");
+        assert_eq!(page_meta.metadata["summary"], "<em>short</em>");
+        assert!(!page_meta.metadata.keys().any(|key| key.starts_with(':')));
+
+        let path = std::env::temp_dir().join(format!("kodama-meta-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        page_meta.write(path).unwrap();
+        let read = PageMeta::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(read, page_meta);
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let page_meta: PageMeta = serde_json::from_str(r#"{"version": 1, "slug": "a"}"#).unwrap();
+        assert_eq!(page_meta.slug, "a");
+        assert_eq!(page_meta.title, None);
+        assert!(page_meta.tags.is_empty() && page_meta.metadata.is_empty());
+    }
+}
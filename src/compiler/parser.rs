@@ -1,13 +1,14 @@
-use std::{collections::HashMap, vec};
+use std::{collections::HashSet, sync::LazyLock, vec};
 
-use pulldown_cmark::{html, CowStr, Event, Options, Tag, TagEnd};
+use pulldown_cmark::{html, BrokenLink, CowStr, Event, Options, Tag, TagEnd};
+use regex_lite::Regex;
 
 use crate::{
     config::input_path, entry::HTMLMetaData, process::processer::Processer, recorder::ParseRecorder,
 };
 
 use super::{
-    section::{LazyContent, LazyContents},
+    section::{heading_anchor, LazyContent, LazyContents},
     CompileError, HTMLContent, ShallowSection,
 };
 
@@ -17,13 +18,23 @@ pub const OPTIONS: Options = Options::ENABLE_MATH
     .union(Options::ENABLE_SMART_PUNCTUATION)
     .union(Options::ENABLE_FOOTNOTES);
 
+/// The metadata of a page `slug` about to be parsed.
+fn initial_metadata(slug: &str) -> HTMLMetaData {
+    let mut metadata = HTMLMetaData::default();
+    metadata
+        .0
+        .insert("slug".to_string(), HTMLContent::Plain(slug.to_string()));
+    metadata
+}
+
+/// Read the source file `fullname` of `slug`, relative to the workspace dir.
 pub fn initialize(
     slug: &str,
-) -> Result<(String, HashMap<String, HTMLContent>, ParseRecorder), CompileError> {
+    fullname: &str,
+) -> Result<(String, HTMLMetaData, ParseRecorder), CompileError> {
     // global data store
-    let mut metadata: HashMap<String, HTMLContent> = HashMap::new();
-    let fullname = format!("{}.md", slug);
-    metadata.insert("slug".to_string(), HTMLContent::Plain(slug.to_string()));
+    let metadata = initial_metadata(slug);
+    let fullname = fullname.to_string();
 
     // local contents recorder
     let markdown_path = input_path(&fullname);
@@ -38,7 +49,29 @@ pub fn initialize(
     }
 }
 
-pub fn parse_markdown(slug: &str) -> Result<ShallowSection, CompileError> {
+/// Parse the markdown file `relative_path` of `slug`, which differ in the
+/// [`crate::config::SlugMode`]s other than `path`.
+pub fn parse_markdown(slug: &str, relative_path: &str) -> Result<ShallowSection, CompileError> {
+    let (source, metadata, recorder) = initialize(slug, relative_path)?;
+    parse_markdown_with(&source, metadata, recorder)
+}
+
+/// Parse the markdown `source` of the file `relative_path` of `slug` without reading it.
+pub fn parse_markdown_source(
+    slug: &str,
+    relative_path: &str,
+    source: &str,
+) -> Result<ShallowSection, CompileError> {
+    let metadata = initial_metadata(slug);
+    let recorder = ParseRecorder::new(relative_path.to_string());
+    parse_markdown_with(source, metadata, recorder)
+}
+
+fn parse_markdown_with(
+    source: &str,
+    mut metadata: HTMLMetaData,
+    mut recorder: ParseRecorder,
+) -> Result<ShallowSection, CompileError> {
     let mut processers: Vec<Box<dyn Processer>> = vec![
         Box::new(crate::process::footnote::Footnote),
         Box::new(crate::process::figure::Figure),
@@ -47,15 +80,7 @@ pub fn parse_markdown(slug: &str) -> Result<ShallowSection, CompileError> {
         Box::new(crate::process::embed_markdown::Embed),
     ];
 
-    let (source, mut metadata, mut recorder) = initialize(slug)?;
-    let contents = parse_content(
-        &source,
-        &mut recorder,
-        &mut metadata,
-        &mut processers,
-        false,
-    )?;
-    let metadata = HTMLMetaData(metadata);
+    let contents = parse_content(source, &mut recorder, &mut metadata, &mut processers, false)?;
 
     return Ok(ShallowSection {
         metadata,
@@ -78,22 +103,40 @@ pub fn parse_spanned_markdown(
     let content = parse_content(
         &markdown_input,
         &mut recorder,
-        &mut HashMap::new(),
+        &mut HTMLMetaData::default(),
         &mut processers,
         true,
     )?;
     return Ok(content);
 }
 
+/// `[@key, locator]`, which is not a link for CommonMark, as the link to `@key`
+/// titled `locator`, see [`crate::process::embed_markdown::Embed`].
+pub fn cite_link<'a>(reference: &str) -> Option<(CowStr<'a>, CowStr<'a>)> {
+    let citation = reference.strip_prefix('@')?;
+    let (key, locator) = citation.split_once(',').unwrap_or((citation, ""));
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = format!("@{}", key);
+    Some((url.into(), locator.trim().to_string().into()))
+}
+
 pub fn parse_content(
     markdown_input: &str,
     recorder: &mut ParseRecorder,
-    metadata: &mut HashMap<String, HTMLContent>,
+    metadata: &mut HTMLMetaData,
     processers: &mut Vec<Box<dyn Processer>>,
     ignore_paragraph: bool,
 ) -> Result<HTMLContent, CompileError> {
     let mut contents: LazyContents = vec![];
-    let parser = pulldown_cmark::Parser::new_ext(&markdown_input, OPTIONS);
+    let mut cite = |link: BrokenLink<'_>| cite_link(&link.reference);
+    let parser = pulldown_cmark::Parser::new_with_broken_link_callback(
+        markdown_input,
+        OPTIONS,
+        Some(&mut cite),
+    );
 
     for mut event in parser {
         match &event {
@@ -203,6 +246,13 @@ pub fn parse_content(
         }
     }
 
+    let mut taken = HashSet::new();
+    for content in contents.iter_mut() {
+        if let LazyContent::Plain(html) = content {
+            *html = heading_ids(html, &mut taken);
+        }
+    }
+
     if contents.len() == 1 {
         if let LazyContent::Plain(html) = &contents[0] {
             return Ok(HTMLContent::Plain(html.to_string()));
@@ -210,3 +260,28 @@ pub fn parse_content(
     }
     Ok(HTMLContent::Lazy(contents))
 }
+
+/// The headings of `html` without attributes given the [`heading_anchor`] of their text
+/// as `id`, so that the links `[Text](slug#anchor)` reach them, with `-2`, `-3`… for the
+/// anchors already `taken` on the page.
+fn heading_ids(html: &str, taken: &mut HashSet<String>) -> String {
+    static RE_HEADING: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"(?s)<h([1-6])>(.*?)</h[1-6]>"#).unwrap());
+
+    RE_HEADING
+        .replace_all(html, |capture: &regex_lite::Captures<'_>| {
+            let text = HTMLContent::Plain(capture[2].to_string()).remove_all_tags();
+            let anchor = heading_anchor(&text);
+            if anchor.is_empty() {
+                return capture[0].to_string();
+            }
+            let mut id = anchor.to_string();
+            let mut n = 1;
+            while !taken.insert(id.to_string()) {
+                n += 1;
+                id = format!("{}-{}", anchor, n);
+            }
+            format!("<h{} id=\"{}\">{}", &capture[1], id, &capture[0][4..])
+        })
+        .to_string()
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use super::{callback::Callback, section::Section};
+
+/// The pages related to each page of `slugs`, most related first, at most `max_related` of
+/// them and each scoring at least `min_score`. The score of two pages is the Jaccard index
+/// of their references plus that of the pages embedding them, so it lies in `0..=2`.
+///
+/// Only pages sharing a reference or a parent are compared, through inverted indices, so
+/// the cost grows with the number of such pairs instead of the square of the pages.
+pub fn related_pages(
+    slugs: &[String],
+    pages: &BTreeMap<String, Arc<Section>>,
+    callback: &Callback,
+    max_related: usize,
+    min_score: f64,
+) -> BTreeMap<String, Vec<String>> {
+    let references: HashMap<&str, BTreeSet<&str>> = slugs
+        .iter()
+        .filter_map(|slug| pages.get(slug).map(|section| (slug.as_str(), section)))
+        .map(|(slug, section)| {
+            let references = section.references.iter().map(String::as_str);
+            (slug, references.filter(|r| *r != slug).collect())
+        })
+        .collect();
+    let parents: HashMap<&str, BTreeSet<&str>> = slugs
+        .iter()
+        .filter_map(|slug| callback.0.get(slug).map(|value| (slug.as_str(), value)))
+        .map(|(slug, value)| (slug, value.contexts.iter().map(String::as_str).collect()))
+        .collect();
+
+    let citers = inverted(&references);
+    let siblings = inverted(&parents);
+
+    let mut related = BTreeMap::new();
+    for slug in slugs {
+        let slug = slug.as_str();
+        let shared_references = shared(slug, &references, &citers);
+        let shared_parents = shared(slug, &parents, &siblings);
+
+        let mut candidates: BTreeSet<&str> = shared_references.keys().copied().collect();
+        candidates.extend(shared_parents.keys());
+        let mut scored: Vec<(f64, &str)> = candidates
+            .into_iter()
+            .map(|other| {
+                let score = jaccard(slug, other, &references, &shared_references)
+                    + jaccard(slug, other, &parents, &shared_parents);
+                (score, other)
+            })
+            .filter(|(score, _)| *score >= min_score)
+            .collect();
+        // candidates are ordered by slug, and the sort is stable.
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(max_related);
+
+        if !scored.is_empty() {
+            let slugs = scored.into_iter().map(|(_, s)| s.to_string()).collect();
+            related.insert(slug.to_string(), slugs);
+        }
+    }
+    related
+}
+
+/// From each page to its items, to each item to the pages having it.
+fn inverted<'a>(items: &HashMap<&'a str, BTreeSet<&'a str>>) -> HashMap<&'a str, Vec<&'a str>> {
+    let mut inverted: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (slug, items) in items {
+        for item in items {
+            inverted.entry(item).or_default().push(slug);
+        }
+    }
+    inverted
+}
+
+/// The number of items `slug` shares with each page sharing at least one.
+fn shared<'a>(
+    slug: &str,
+    items: &HashMap<&str, BTreeSet<&str>>,
+    inverted: &HashMap<&str, Vec<&'a str>>,
+) -> HashMap<&'a str, usize> {
+    let mut shared = HashMap::new();
+    for item in items.get(slug).into_iter().flatten() {
+        for other in inverted.get(item).into_iter().flatten() {
+            if *other != slug {
+                *shared.entry(*other).or_insert(0) += 1;
+            }
+        }
+    }
+    shared
+}
+
+fn jaccard(
+    slug: &str,
+    other: &str,
+    items: &HashMap<&str, BTreeSet<&str>>,
+    shared: &HashMap<&str, usize>,
+) -> f64 {
+    let shared = *shared.get(other).unwrap_or(&0);
+    if shared == 0 {
+        return 0.0;
+    }
+    let len = |slug| items.get(slug).map_or(0, BTreeSet::len);
+    shared as f64 / (len(slug) + len(other) - shared) as f64
+}
@@ -0,0 +1,296 @@
+//! Moving a page to another slug, as `kodama mv`: the links, embeds and citations of the
+//! former slug in the source files are rewritten to the new one.
+
+use std::{collections::BTreeMap, ops::Range, path::Path, sync::LazyLock};
+
+use pulldown_cmark::{BrokenLink, Event, Tag, TagEnd};
+use regex_lite::Regex;
+
+use crate::{config, process::embed_markdown::page_target, slug};
+
+use super::{
+    parser::{cite_link, OPTIONS},
+    CompileError, Workspace,
+};
+
+/// The target `from` written at `range` of a source file, to be written `to`.
+#[derive(Debug)]
+pub struct Rewrite {
+    pub range: Range<usize>,
+    pub from: String,
+    pub to: String,
+}
+
+/// The moves and rewrites of `kodama mv`, see [`plan_rename`].
+#[derive(Debug)]
+pub struct Rename {
+    /// The source file of the page, relative to the workspace dir.
+    pub source: String,
+
+    /// The source file of the page once moved, relative to the workspace dir.
+    pub target: String,
+
+    /// The rewrites of each source file, the moved one under its former path,
+    /// in the order they are written.
+    pub rewrites: BTreeMap<String, Vec<Rewrite>>,
+}
+
+impl Rename {
+    pub fn count(&self) -> usize {
+        self.rewrites.values().map(Vec::len).sum()
+    }
+}
+
+/// A link, an embed or a citation of a page, written as `written` at `range`.
+struct Target {
+    range: Range<usize>,
+    written: String,
+    slug: String,
+    is_citation: bool,
+}
+
+/// Plan the move of the page `old` of `workspace` to the slug `new`: its source file goes
+/// to that of `new`, and the targets of `old` in every source file are rewritten to `new`,
+/// keeping their anchors and actions. The `./` and `../` targets of the moved file are
+/// written from the workspace dir if it changes directory.
+pub fn plan_rename(
+    workspace_dir: &str,
+    workspace: &Workspace,
+    old: &str,
+    new: &str,
+) -> Result<Rename, CompileError> {
+    let source = workspace.source_paths[old].to_string();
+    let ext = &workspace.slug_exts[old];
+    let target = match config::is_flatten_slugs() {
+        true => match Path::new(&source).parent().and_then(Path::to_str) {
+            Some(dir) if !dir.is_empty() => format!("{}/{}.{}", dir, new, ext),
+            _ => format!("{}.{}", new, ext),
+        },
+        false => format!("{}.{}", new, ext),
+    };
+    let moves_dir = Path::new(&source).parent() != Path::new(&target).parent();
+
+    let mut rewrites = BTreeMap::new();
+    for (slug, path) in &workspace.source_paths {
+        let full_path = config::join_path(workspace_dir, path);
+        let content = std::fs::read_to_string(&full_path)
+            .map_err(|e| CompileError::IO(Some(concat!(file!(), '#', line!())), e, full_path))?;
+        let targets = match workspace.slug_exts[slug] {
+            slug::Ext::Markdown => markdown_targets(&content, path, 0),
+            slug::Ext::Typst => typst_targets(&content),
+        };
+
+        let mut edits = vec![];
+        for target in targets {
+            let to = if target.slug == old {
+                retarget(&target.written, new, target.is_citation)
+            } else if slug == old && moves_dir && is_relative(&target.written) {
+                slug::resolve_link(&target.written, path)
+            } else {
+                continue;
+            };
+            edits.push(Rewrite {
+                range: target.range,
+                from: target.written,
+                to,
+            });
+        }
+        if !edits.is_empty() {
+            rewrites.insert(path.to_string(), edits);
+        }
+    }
+
+    Ok(Rename {
+        source,
+        target,
+        rewrites,
+    })
+}
+
+/// Write the source files of `rename` with their rewrites, the moved one at its new path,
+/// with its former slug `alias` added to its `aliases` if given. Returns whether the alias
+/// could be added, which is not the case of a Typst file.
+pub fn apply_rename(
+    workspace_dir: &str,
+    rename: &Rename,
+    alias: Option<&str>,
+) -> Result<bool, std::io::Error> {
+    let mut is_aliased = false;
+    for (path, rewrites) in &rename.rewrites {
+        if *path == rename.source {
+            continue;
+        }
+        let full_path = config::join_path(workspace_dir, path);
+        let content = std::fs::read_to_string(&full_path)?;
+        std::fs::write(&full_path, rewrite(&content, rewrites))?;
+    }
+
+    let source_path = config::join_path(workspace_dir, &rename.source);
+    let mut content = std::fs::read_to_string(&source_path)?;
+    if let Some(rewrites) = rename.rewrites.get(&rename.source) {
+        content = rewrite(&content, rewrites);
+    }
+    if let Some(alias) = alias.filter(|_| rename.source.ends_with(".md")) {
+        content = add_alias(&content, alias);
+        is_aliased = true;
+    }
+    let target_path = config::join_path(workspace_dir, &rename.target);
+    if let Some(parent) = Path::new(&target_path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&target_path, content)?;
+    std::fs::remove_file(&source_path)?;
+    Ok(is_aliased)
+}
+
+/// The line of the byte `offset` of `content`, from 1.
+pub fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+/// The targets of the links, embeds and citations of the markdown `content` of the source
+/// file `current`, parsed as the compiler does, with the links in the values of its metadata.
+fn markdown_targets(content: &str, current: &str, base: usize) -> Vec<Target> {
+    let mut cite = |link: BrokenLink<'_>| cite_link(&link.reference);
+    let parser =
+        pulldown_cmark::Parser::new_with_broken_link_callback(content, OPTIONS, Some(&mut cite));
+
+    let mut targets = vec![];
+    let mut is_metadata = false;
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::MetadataBlock(_)) => is_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => is_metadata = false,
+            Event::Text(_) if is_metadata => {
+                let mut start = range.start;
+                for line in content[range].split_inclusive('\n') {
+                    if let Some((key, value)) = line.split_once(':') {
+                        let value_start = start + key.len() + 1;
+                        targets.extend(markdown_targets(value, current, base + value_start));
+                    }
+                    start += line.len();
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                let span = &content[range.clone()];
+                if let Some(target) = link_target(span, &dest_url, current) {
+                    let start = base + range.start;
+                    targets.push(Target {
+                        range: start + target.range.start..start + target.range.end,
+                        ..target
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    targets
+}
+
+/// The target of the link to `dest_url` written as `span` in the source file `current`,
+/// its range relative to `span`.
+fn link_target(span: &str, dest_url: &str, current: &str) -> Option<Target> {
+    if let Some(key) = dest_url.strip_prefix('@') {
+        let start = span.find(dest_url)? + 1;
+        return Some(Target {
+            range: start..start + key.len(),
+            written: key.to_string(),
+            slug: slug::to_slug(key),
+            is_citation: true,
+        });
+    }
+    let written = page_target(dest_url)?;
+    if written.is_empty() {
+        return None;
+    }
+    let start = span.rfind(dest_url)?;
+    Some(Target {
+        range: start..start + written.len(),
+        written: written.to_string(),
+        slug: slug::to_slug(&slug::resolve_link(written, current)),
+        is_citation: false,
+    })
+}
+
+/// The targets of the `#embed("url")` and `#local("slug")` calls of a Typst file.
+fn typst_targets(content: &str) -> Vec<Target> {
+    static RE_TARGET: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r##"#(?:embed|local)\(\s*"([^"#]*)"##).unwrap());
+
+    RE_TARGET
+        .captures_iter(content)
+        .map(|capture| {
+            let written = capture.get(1).unwrap();
+            Target {
+                range: written.range(),
+                written: written.as_str().to_string(),
+                slug: slug::to_slug(written.as_str()),
+                is_citation: false,
+            }
+        })
+        .collect()
+}
+
+fn is_relative(written: &str) -> bool {
+    written.starts_with("./") || written.starts_with("../")
+}
+
+/// The target `written` of the former slug as that of `new`, in the same form:
+/// from the workspace dir, with the same extension if any.
+fn retarget(written: &str, new: &str, is_citation: bool) -> String {
+    if is_citation {
+        return new.to_string();
+    }
+    let prefix = match written.starts_with('/') || is_relative(written) {
+        true => "/",
+        false => "",
+    };
+    match Path::new(written).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}{}.{}", prefix, new, ext),
+        None => format!("{}{}", prefix, new),
+    }
+}
+
+fn rewrite(content: &str, rewrites: &[Rewrite]) -> String {
+    let mut rewritten = content.to_string();
+    for rewrite in rewrites.iter().rev() {
+        rewritten.replace_range(rewrite.range.clone(), &rewrite.to);
+    }
+    rewritten
+}
+
+/// The markdown `content` with `alias` added to the `aliases` of its metadata.
+fn add_alias(content: &str, alias: &str) -> String {
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return format!("---\naliases: {}\n---\n\n{}", alias, content);
+    };
+    let Some(end) = rest.find("\n---") else {
+        return format!("---\naliases: {}\n---\n\n{}", alias, content);
+    };
+    let block = &rest[..end];
+
+    let mut lines: Vec<String> = block.lines().map(str::to_string).collect();
+    let position = lines.iter().position(|line| line.starts_with("aliases:"));
+    match position {
+        Some(i) => {
+            let value = lines[i]["aliases:".len()..].trim().to_string();
+            if value.is_empty() {
+                // a YAML sequence of `- alias` lines.
+                let items = lines[i + 1..]
+                    .iter()
+                    .take_while(|line| line.trim_start().starts_with("- "))
+                    .count();
+                lines.insert(i + 1 + items, format!("  - {}", alias));
+            } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                lines[i] = match items.trim().is_empty() {
+                    true => format!("aliases: [{}]", alias),
+                    false => format!("aliases: [{}, {}]", items.trim(), alias),
+                };
+            } else {
+                lines[i] = format!("aliases: {}, {}", value, alias);
+            }
+        }
+        None => lines.push(format!("aliases: {}", alias)),
+    }
+    format!("---\n{}{}", lines.join("\n"), &rest[end..])
+}
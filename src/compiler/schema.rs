@@ -0,0 +1,122 @@
+//! The metadata of the pages checked against the `[metadata.schema]` of `kodama.toml`,
+//! see [`config::MetadataSchema`].
+
+use std::collections::BTreeMap;
+
+use crate::{
+    config::{self, MetadataSchema},
+    entry::{is_known_metadata, HTMLMetaData, MetaData},
+};
+
+use super::{defaults::FROM_PAGE, diagnostics::Severity, state::CompileState, taxon::Taxon};
+
+/// Report the pending pages, `source_paths` being their source files by slug, missing
+/// a required key or having a value not allowed, and with [`MetadataSchema::strict`],
+/// those having a key neither known to kodama nor declared.
+/// The schema of the taxon of a page, if any, adds to the schema of every page.
+pub fn validate_metadata(state: &mut CompileState, source_paths: &BTreeMap<String, String>) {
+    let schema = config::metadata_schema();
+    if schema == MetadataSchema::default() {
+        return;
+    }
+
+    let mut reports = vec![];
+    for (slug, shallow) in state.pages.pending() {
+        let Some(path) = source_paths.get(slug) else {
+            continue;
+        };
+        let metadata = &shallow.metadata;
+        let (required, optional, values) = page_schema(&schema, metadata);
+        let origin = |key: &str| {
+            let origin = state
+                .metadata_origins
+                .get(slug)
+                .and_then(|origins| origins.get(key));
+            match origin {
+                Some(origin) if origin != FROM_PAGE => format!(" from {}", origin),
+                _ => String::new(),
+            }
+        };
+
+        for key in &required {
+            if metadata.0.contains_key(key) {
+                continue;
+            }
+            let message = format!("`{}` misses the required key `{}`.", path, key);
+            reports.push((Severity::Error, slug.to_string(), message));
+        }
+
+        for (key, allowed) in &values {
+            let items = match metadata.get_list(key) {
+                Some(items) => items.to_vec(),
+                None => metadata.get_str(key).cloned().into_iter().collect(),
+            };
+            for item in items.iter().filter(|item| !allowed.contains(item)) {
+                let message = format!(
+                    "`{}` has `{}: {}`{}, which is not one of `{}`.",
+                    path,
+                    key,
+                    item,
+                    origin(key),
+                    allowed.join("`, `")
+                );
+                reports.push((Severity::Error, slug.to_string(), message));
+            }
+        }
+
+        if !schema.strict {
+            continue;
+        }
+        let mut keys: Vec<&String> = metadata.0.keys().collect();
+        keys.sort();
+        for key in keys {
+            let declared = required.contains(key)
+                || optional.contains(key)
+                || values.contains_key(key.as_str());
+            if declared || is_known_metadata(key) {
+                continue;
+            }
+            let message = format!(
+                "`{}` has the unknown key `{}`{}, declare it in `[metadata.schema]` of `{}`.",
+                path,
+                key,
+                origin(key),
+                config::CONFIG_FILE_NAME
+            );
+            reports.push((Severity::Warning, slug.to_string(), message));
+        }
+    }
+
+    for (severity, slug, message) in reports {
+        state
+            .diagnostics
+            .report(severity, Some(&slug), None, message);
+    }
+}
+
+/// The required and the optional keys and the allowed values of the page of `metadata`,
+/// those of its taxon added to those of every page.
+fn page_schema(
+    schema: &MetadataSchema,
+    metadata: &HTMLMetaData,
+) -> (Vec<String>, Vec<String>, BTreeMap<String, Vec<String>>) {
+    let (mut required, mut optional) = (schema.required.clone(), schema.optional.clone());
+    let mut values = schema.values.clone();
+
+    let taxon = metadata
+        .taxon()
+        .map(|taxon| config::taxon_id(Taxon::to_data_taxon(&taxon.remove_all_tags())));
+    let taxon_schema = taxon.and_then(|taxon| {
+        schema
+            .taxa
+            .iter()
+            .find(|(id, _)| id.eq_ignore_ascii_case(&taxon))
+            .map(|(_, taxon_schema)| taxon_schema)
+    });
+    if let Some(taxon_schema) = taxon_schema {
+        required.extend(taxon_schema.required.iter().cloned());
+        optional.extend(taxon_schema.optional.iter().cloned());
+        values.extend(taxon_schema.values.clone());
+    }
+    (required, optional, values)
+}
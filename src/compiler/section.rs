@@ -1,10 +1,25 @@
+use indexmap::IndexSet;
 use regex_lite::Regex;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, mem, sync::LazyLock};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+    sync::{Arc, LazyLock},
+};
 
-use crate::entry::{EntryMetaData, HTMLMetaData, MetaData};
+use crate::{
+    config::{self, WordCount},
+    entry::{
+        EntryMetaData, HTMLMetaData, MetaData, KEY_DATA_TAXON, KEY_READINGTIME, KEY_TAXON,
+        KEY_WORDCOUNT,
+    },
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::taxon::Taxon;
+
+#[derive(Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct SectionOption {
     pub numbering: bool, // default: false
 
@@ -13,20 +28,25 @@ pub struct SectionOption {
 
     /// Display in catalog
     pub catalog: bool, // default: true
+
+    /// Embed the section even if it is a reference
+    #[serde(default)]
+    pub force: bool, // default: false
 }
 
 impl Default for SectionOption {
     fn default() -> Self {
-        SectionOption::new(false, true, true)
+        SectionOption::new(false, true, true, false)
     }
 }
 
 impl SectionOption {
-    pub fn new(numbering: bool, details_open: bool, catalog: bool) -> SectionOption {
+    pub fn new(numbering: bool, details_open: bool, catalog: bool, force: bool) -> SectionOption {
         SectionOption {
             numbering,
             details_open,
             catalog,
+            force,
         }
     }
 }
@@ -36,12 +56,36 @@ pub struct EmbedContent {
     pub url: String,
     pub title: Option<String>,
     pub option: SectionOption,
+
+    /// Metadata of the embedded section replaced in this embed only.
+    /// An empty value removes the key.
+    #[serde(default)]
+    pub metadata_overrides: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalLink {
     pub slug: String,
     pub text: Option<String>,
+
+    /// Written `[Text](slug#:further)`, listing a reference under "Further reading"
+    /// instead of citing it, see [`Section::further_reading`].
+    #[serde(default)]
+    pub further: bool,
+
+    /// Written `[Text](slug#anchor)`, the anchor linked to on the page `slug`,
+    /// which is the current page for `[Text](#anchor)`.
+    #[serde(default)]
+    pub anchor: Option<String>,
+}
+
+/// `[@key]` or `[@key, locator]`, citing the reference page of slug `key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub key: String,
+
+    /// Where in the reference, e.g. `p. 12`.
+    pub locator: Option<String>,
 }
 
 /// Plain HTMLs & lazy embedding HTMLs, This means that
@@ -51,6 +95,7 @@ pub enum LazyContent {
     Plain(String),
     Embed(EmbedContent),
     Local(LocalLink),
+    Cite(Citation),
 }
 
 pub type LazyContents = Vec<LazyContent>;
@@ -85,10 +130,19 @@ impl HTMLContent {
         }
     }
 
+    pub fn as_lazy(&self) -> Option<&LazyContents> {
+        if let HTMLContent::Lazy(lazy_contents) = self {
+            Some(lazy_contents)
+        } else {
+            None
+        }
+    }
+
     pub fn remove_all_tags(&self) -> String {
         static RE_TAGS: LazyLock<Regex> = LazyLock::new(|| {
             let attrs = r#"(\s+[a-zA-Z-]+(="([^"\\]|\\[\s\S])*")?)*"#;
-            Regex::new(&format!(r#"<[A-Za-z]+{}\s*/?>|</[A-Za-z]+>"#, attrs)).unwrap()
+            let tag = "[A-Za-z][A-Za-z0-9-]*";
+            Regex::new(&format!(r#"<{}{}\s*/?>|</{}>"#, tag, attrs, tag)).unwrap()
         });
 
         let remove_tag = |s| {
@@ -121,6 +175,7 @@ impl HTMLContent {
                             .as_ref()
                             .map(|s| remove_tag(s))
                             .unwrap_or_default(),
+                        LazyContent::Cite(citation) => format!("[@{}]", citation.key),
                     };
                     str.push_str(&s);
                 }
@@ -128,6 +183,31 @@ impl HTMLContent {
             }
         }
     }
+
+    /// The text of the first heading `<h1>` to `<h6>` written in this content, without its
+    /// tags, a link in it taken as its text or slug. The embedded sections are left out.
+    pub fn first_heading(&self) -> Option<String> {
+        static RE_HEADING: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"(?s)<h[1-6](\s[^>]*)?>(.*?)</h[1-6]>").unwrap());
+
+        let html = match self {
+            HTMLContent::Plain(s) => s.to_string(),
+            HTMLContent::Lazy(contents) => contents
+                .iter()
+                .map(|content| match content {
+                    LazyContent::Plain(s) => s.to_string(),
+                    LazyContent::Local(local) => {
+                        local.text.clone().unwrap_or_else(|| local.slug.to_string())
+                    }
+                    _ => String::new(),
+                })
+                .collect(),
+        };
+        let heading = RE_HEADING.captures(&html)?.get(2)?.as_str();
+        let heading = HTMLContent::Plain(heading.to_string()).remove_all_tags();
+        let heading = heading.trim();
+        (!heading.is_empty()).then(|| heading.to_string())
+    }
 }
 
 pub struct HTMLContentBuilder {
@@ -135,6 +215,12 @@ pub struct HTMLContentBuilder {
     content: String,
 }
 
+impl Default for HTMLContentBuilder {
+    fn default() -> Self {
+        HTMLContentBuilder::new()
+    }
+}
+
 impl HTMLContentBuilder {
     pub fn new() -> HTMLContentBuilder {
         HTMLContentBuilder {
@@ -193,38 +279,327 @@ impl ShallowSection {
 
 pub type SectionContents = Vec<SectionContent>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SectionContent {
     Plain(String),
-    Embed(Section),
+    Embed(EmbedSection),
+}
+
+/// A compiled section shared by all of its embeds,
+/// together with the overrides of one particular embed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedSection {
+    pub section: Arc<Section>,
+    pub option: SectionOption,
+    pub title: Option<String>,
+    pub metadata_overrides: HashMap<String, String>,
+}
+
+impl EmbedSection {
+    /// A toplevel section, embedded nowhere.
+    pub fn new(section: Arc<Section>) -> EmbedSection {
+        EmbedSection {
+            section,
+            option: SectionOption::default(),
+            title: None,
+            metadata_overrides: HashMap::new(),
+        }
+    }
+
+    /// The metadata of the section with the overrides of this embed applied.
+    /// The shared section itself is never modified.
+    pub fn metadata(&self) -> Cow<'_, EntryMetaData> {
+        if self.title.is_none() && self.metadata_overrides.is_empty() {
+            return Cow::Borrowed(&self.section.metadata);
+        }
+
+        let mut metadata = self.section.metadata.clone();
+        for (key, value) in &self.metadata_overrides {
+            if value.is_empty() {
+                metadata.0.remove(key);
+                if key == KEY_TAXON {
+                    metadata.0.remove(KEY_DATA_TAXON);
+                }
+                continue;
+            }
+            if key == KEY_TAXON {
+                let data_taxon =
+                    Taxon::to_data_taxon(&HTMLContent::Plain(value.to_string()).remove_all_tags())
+                        .to_string();
+                metadata.update(KEY_DATA_TAXON.to_string(), data_taxon);
+            }
+            metadata.update(key.to_string(), value.to_string());
+        }
+        if let Some(title) = &self.title {
+            metadata.update("title".to_string(), title.to_string());
+        }
+        Cow::Owned(metadata)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Section {
     pub metadata: EntryMetaData,
     pub children: SectionContents,
-    pub option: SectionOption,
-    pub references: HashSet<String>,
+    /// The reference pages cited by this section and its open embedded sections,
+    /// in the order of their first citation.
+    pub references: IndexSet<String>,
+
+    /// The reference pages linked as further reading by this section and its open
+    /// embedded sections, listed apart from the [`Section::references`] and not numbered.
+    #[serde(default)]
+    pub further_reading: IndexSet<String>,
+
+    /// Hash of the metadata and the contents, including those of the embedded sections.
+    #[serde(skip)]
+    pub fingerprint: u64,
+
+    /// Slugs of the sections embedded in this section, directly or transitively.
+    #[serde(skip)]
+    pub transitive_embeds: HashSet<String>,
+
+    #[serde(default)]
+    pub stats: SectionStats,
+}
+
+/// Sizes of a section. The `total_` counts include the embedded sections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionStats {
+    /// Words of the plain contents, without the HTML tags, see [`SectionStats::count_words`].
+    pub words: usize,
+    pub total_words: usize,
+
+    /// Local links, including the embeds rendered as links.
+    pub local_links: usize,
+    pub total_local_links: usize,
+
+    /// Direct embeds.
+    pub embeds: usize,
+    pub total_embeds: usize,
+
+    pub references: usize,
+}
+
+impl SectionStats {
+    fn new(children: &SectionContents, references: &IndexSet<String>) -> SectionStats {
+        let mut stats = SectionStats {
+            references: references.len(),
+            ..SectionStats::default()
+        };
+        let count_code = config::is_word_count_code();
+        for child in children {
+            match child {
+                SectionContent::Plain(html) => {
+                    stats.words += SectionStats::count_words(html, count_code);
+                    stats.local_links += html.matches(r#"class="link local"#).count();
+                }
+                SectionContent::Embed(embed) => {
+                    let child = &embed.section.stats;
+                    stats.embeds += 1;
+                    stats.total_embeds += child.total_embeds;
+                    stats.total_words += child.total_words;
+                    stats.total_local_links += child.total_local_links;
+                }
+            }
+        }
+        stats.total_embeds += stats.embeds;
+        stats.total_words += stats.words;
+        stats.total_local_links += stats.local_links;
+        stats
+    }
+
+    /// The words of the plain text of `html`, each CJK character counting as a word,
+    /// and without the code blocks unless `count_code`.
+    fn count_words(html: &str, count_code: bool) -> usize {
+        let mut html = Cow::Borrowed(html);
+        if !count_code {
+            let mut rest = html.as_ref();
+            let mut without_code = String::new();
+            static RE_PRE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<pre[\s>]").unwrap());
+            while let Some(start) = RE_PRE.find(rest).map(|pre| pre.start()) {
+                without_code.push_str(&rest[..start]);
+                rest = rest[start..]
+                    .split_once("</pre>")
+                    .map_or("", |(_, after)| after);
+            }
+            without_code.push_str(rest);
+            html = Cow::Owned(without_code);
+        }
+        let text = HTMLContent::Plain(html.into_owned()).remove_all_tags();
+        let text = htmlize::unescape(text);
+
+        let mut words = 0;
+        for token in text.split_whitespace() {
+            // the runs of other characters between the CJK ones are words too.
+            let mut in_word = false;
+            for c in token.chars() {
+                if is_cjk(c) {
+                    words += 1;
+                    in_word = false;
+                } else if c.is_alphanumeric() && !in_word {
+                    words += 1;
+                    in_word = true;
+                }
+            }
+        }
+        words
+    }
+
+    /// The words of [`config::word_count`].
+    pub fn counted_words(&self) -> usize {
+        match config::word_count() {
+            WordCount::Inclusive => self.total_words,
+            WordCount::Exclusive => self.words,
+        }
+    }
+
+    /// Minutes needed to read the [`SectionStats::counted_words`] at
+    /// [`config::words_per_minute`], rounded up.
+    pub fn reading_minutes(&self) -> usize {
+        self.counted_words().div_ceil(config::words_per_minute())
+    }
 }
 
 impl Section {
     pub fn new(
-        metadata: EntryMetaData,
+        mut metadata: EntryMetaData,
         children: SectionContents,
-        references: HashSet<String>,
+        references: IndexSet<String>,
     ) -> Section {
+        let stats = SectionStats::new(&children, &references);
+        metadata.update(KEY_WORDCOUNT.to_string(), stats.counted_words().to_string());
+        let reading_minutes = stats.reading_minutes().to_string();
+        metadata.update(KEY_READINGTIME.to_string(), reading_minutes);
+
+        let fingerprint = Section::to_fingerprint(&metadata, &children);
+        let transitive_embeds = children
+            .iter()
+            .filter_map(|child| match child {
+                SectionContent::Plain(_) => None,
+                SectionContent::Embed(embed) => Some(&embed.section),
+            })
+            .flat_map(|section| {
+                let embeds = section.transitive_embeds.iter().cloned();
+                embeds.chain(std::iter::once(section.slug()))
+            })
+            .collect();
         Section {
             metadata,
             children,
-            option: SectionOption::new(false, true, true),
             references,
+            further_reading: IndexSet::new(),
+            fingerprint,
+            transitive_embeds,
+            stats,
         }
     }
 
+    /// The slug of this section followed by [`Section::transitive_embeds`], sorted.
+    pub fn sources(&self) -> Vec<String> {
+        let mut embeds: Vec<String> = self.transitive_embeds.iter().cloned().collect();
+        embeds.sort();
+        std::iter::once(self.slug()).chain(embeds).collect()
+    }
+
+    fn to_fingerprint(metadata: &EntryMetaData, children: &SectionContents) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        metadata
+            .0
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .hash(&mut hasher);
+        metadata.1.hash(&mut hasher);
+        for child in children {
+            match child {
+                SectionContent::Plain(html) => html.hash(&mut hasher),
+                SectionContent::Embed(embed) => {
+                    embed.section.fingerprint.hash(&mut hasher);
+                    embed.option.hash(&mut hasher);
+                    embed.title.hash(&mut hasher);
+                    let overrides: BTreeMap<_, _> = embed.metadata_overrides.iter().collect();
+                    overrides.hash(&mut hasher);
+                }
+            }
+        }
+        hasher.finish()
+    }
+
     pub fn slug(&self) -> String {
         self.metadata.slug().unwrap().to_string()
     }
 
+    /// The children under the heading `anchor`, up to the next heading of the same
+    /// or a higher level. A heading matches if its `id` is `anchor`, or its [`heading_anchor`]
+    /// is that of `anchor`. Returns `None` if there is no such heading.
+    pub fn slice(&self, anchor: &str) -> Option<SectionContents> {
+        static RE_HEADING: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"(?s)<h([1-6])([^>]*)>(.*?)</h[1-6]>"#).unwrap());
+        static RE_ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\sid="([^"]*)""#).unwrap());
+
+        let anchor_text = crate::slug::normalize(anchor);
+        let mut level: Option<usize> = None;
+        let mut sliced: SectionContents = vec![];
+        for content in &self.children {
+            let html = match content {
+                SectionContent::Plain(html) => html,
+                SectionContent::Embed(_) => {
+                    if level.is_some() {
+                        sliced.push(content.clone());
+                    }
+                    continue;
+                }
+            };
+
+            let mut start = level.map(|_| 0);
+            for capture in RE_HEADING.captures_iter(html) {
+                let heading = capture.get(0).unwrap();
+                let heading_level: usize = capture[1].parse().unwrap();
+                match level {
+                    None => {
+                        let id = RE_ID.captures(&capture[2]).map(|c| c[1].to_string());
+                        let text = HTMLContent::Plain(capture[3].to_string()).remove_all_tags();
+                        if id.as_deref() == Some(anchor) || heading_anchor(&text) == anchor_text {
+                            level = Some(heading_level);
+                            start = Some(heading.start());
+                        }
+                    }
+                    Some(level) if heading_level <= level => {
+                        let start = start.unwrap_or(0);
+                        sliced.push(SectionContent::Plain(
+                            html[start..heading.start()].to_string(),
+                        ));
+                        return Some(sliced);
+                    }
+                    Some(_) => (),
+                }
+            }
+            if let Some(start) = start {
+                sliced.push(SectionContent::Plain(html[start..].to_string()));
+            }
+        }
+
+        level.map(|_| sliced)
+    }
+
+    /// The `id`s of the elements of this section and the sections it embeds, such as
+    /// those of its headings, which the links `[Text](slug#anchor)` reach.
+    pub fn anchors(&self) -> BTreeSet<String> {
+        static RE_ID: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r#"<[a-zA-Z][^>]*?\sid="([^"]*)""#).unwrap());
+
+        let mut anchors = BTreeSet::new();
+        for child in &self.children {
+            match child {
+                SectionContent::Plain(html) => {
+                    let ids = RE_ID.captures_iter(html).map(|c| c[1].to_string());
+                    anchors.extend(ids);
+                }
+                SectionContent::Embed(embed) => anchors.extend(embed.section.anchors()),
+            }
+        }
+        anchors
+    }
+
     pub fn spanned(&self) -> String {
         self.children
             .iter()
@@ -236,3 +611,54 @@ impl Section {
             .unwrap_or_default()
     }
 }
+
+/// The anchor of a heading without an explicit `id`,
+/// e.g. `Proof of Lemma 1` becomes `proof-of-lemma-1`, see [`crate::slug::normalize`].
+pub fn heading_anchor(text: &str) -> String {
+    crate::slug::normalize(text)
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Whether `c` is a Chinese character or a Japanese kana, written without spaces
+/// between the words.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2EBEF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiler::test_forest,
+        config,
+        entry::{MetaData, KEY_WORDCOUNT},
+    };
+
+    #[test]
+    fn words_leave_out_the_markup_and_the_code() {
+        let mut config = config::test_config("kodama-tests");
+        config.word_count_code = false;
+        let body =
+            "## Tom &amp; Jerry\n\nThey <preview>run</preview> fast.\n\n```\nlet x = 1;\n```";
+        let index = format!("---\ntitle: Index\n---\n\n{}\n", body);
+        let (forest, _guard) = test_forest(&[("index", &index)], config);
+
+        let section = &forest.compiled()["index"];
+        assert_eq!(section.stats.words, 5);
+        let wordcount = section.metadata.get(KEY_WORDCOUNT);
+        assert_eq!(wordcount.map(String::as_str), Some("5"));
+    }
+}
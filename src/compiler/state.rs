@@ -1,192 +1,2801 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use indexmap::IndexSet;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    config,
-    entry::{EntryMetaData, HTMLMetaData, MetaData, KEY_SLUG},
+    config::{self, BacklinksOrder, Expired},
+    date::Date,
+    entry::{
+        EntryMetaData, HTMLMetaData, MetaData, MetaDataLists, KEY_AUTHOR, KEY_BACKLINK_COUNT,
+        KEY_BIBTEX_TYPE, KEY_BIB_IGNORE, KEY_CHILD_TAXON, KEY_DATE, KEY_DOI, KEY_EXPIRES,
+        KEY_INLINE_REFERENCE_PREFIX, KEY_JOURNAL, KEY_LANG, KEY_ORDER, KEY_ORPHAN, KEY_PAGE_TITLE,
+        KEY_PARENT_COUNT, KEY_SERIES_INDEX, KEY_SLUG, KEY_STUB, KEY_TAGS, KEY_TAXON, KEY_TITLE,
+        KEY_URL, KEY_YEAR,
+    },
+    process::embed_markdown::display_taxon,
     slug,
 };
 
 use super::{
-    callback::Callback,
-    section::{HTMLContent, LazyContent, Section, SectionContent, SectionContents, ShallowSection},
+    bibtex,
+    callback::{Callback, CallbackDelta, CallbackValue},
+    defaults::FROM_FALLBACK,
+    diagnostics::{Diagnostics, Severity},
+    graph::PageEdges,
+    hooks::{PostHook, PostHooks},
+    numbering::{self, Numbering},
+    related,
+    section::{
+        EmbedContent, EmbedSection, HTMLContent, LazyContent, LocalLink, Section, SectionContent,
+        SectionContents, ShallowSection,
+    },
+    store::{PageStatus, PageStore},
     taxon::Taxon,
+    timings::{Phase, Timings},
+    CompileError,
 };
 
+/// The slug of the page listing all the backlinks of `slug`, `-/backlinks/slug`.
+pub fn backlinks_slug(slug: &str) -> String {
+    slug::synthetic_slug("backlinks", slug)
+}
+
+/// Move the pinned pages of `slugs` first, then those having an `order`, by ascending
+/// `order`, keeping the order of `slugs` otherwise, see [`crate::entry::KEY_PINNED`] and
+/// [`crate::entry::KEY_ORDER`].
+pub fn sort_by_order<'a>(
+    slugs: &mut [String],
+    metadata: impl Fn(&str) -> Option<&'a HTMLMetaData>,
+) {
+    let pinned = |slug: &str| metadata(slug).is_some_and(|m| m.is_pinned());
+    let order = |slug: &str| metadata(slug).and_then(|m| m.order());
+    slugs.sort_by(|a, b| {
+        let by_order = match (order(a), order(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        pinned(b).cmp(&pinned(a)).then(by_order)
+    });
+}
+
+/// The generated page listing the pages with `featured: true`, to be embedded by others,
+/// such as the root page, `-/featured`, see [`crate::entry::KEY_FEATURED`].
+pub fn featured_slug() -> String {
+    slug::synthetic_slug("featured", "")
+}
+
+/// Characters kept on each side of a link in the snippet of a backlink.
+const BACKLINK_SNIPPET_CHARS: usize = 75;
+
+/// `value` in JSON with the keys of its maps sorted, so that it hashes the same in every run.
+fn stable_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).unwrap().to_string()
+}
+
+/// A page compiled by [`CompileState::compile_page`], not yet merged into the state.
+#[derive(Serialize, Deserialize)]
+struct CompiledPage {
+    slug: String,
+    section: Arc<Section>,
+    callback: Callback,
+    edges: PageEdges,
+    diagnostics: Diagnostics,
+
+    /// The sections of the metadata values, see [`CompileState::metadata_to_section`].
+    /// Only their records are merged, see [`CompileState::merge_records`].
+    metadata_pages: Vec<CompiledPage>,
+}
+
 #[derive(Debug)]
 pub struct CompileState {
-    pub residued: HashMap<String, ShallowSection>,
-    pub compiled: HashMap<String, Section>,
+    pub pages: PageStore,
     pub metadata: HashMap<String, HTMLMetaData>,
     pub callback: Callback,
+
+    /// Outgoing edges of every compiled page, see [`CompileState::dependency_graph`].
+    pub graph: BTreeMap<String, PageEdges>,
+
+    /// Slugs not reached from the root page, compiled by the residued cleanup.
+    pub unreached: Vec<String>,
+
+    /// Slugs of the sections currently being compiled, outermost first.
+    /// Used to detect embed cycles.
+    pub compiling: Vec<String>,
+
+    /// Warnings and errors collected during compilation.
+    pub diagnostics: Diagnostics,
+
+    /// Slugs whose source file changed since the last compilation.
+    pub modified: BTreeSet<String>,
+
+    /// Whether the compiled pages are saved to the cache, and loaded from it unless
+    /// [`config::is_no_cache`], see [`CompileState::page_key`].
+    pub section_cache: bool,
+
+    /// Slugs whose callback data changed since the last compilation,
+    /// see [`CompileState::reconcile_callback`].
+    pub callback_changed: BTreeSet<String>,
+
+    /// The backlinks and parents changed since the last compilation, if any was saved.
+    pub callback_deltas: Vec<CallbackDelta>,
+
+    /// Slugs of the stub pages synthesized for missing pages, see [`config::is_allow_stubs`].
+    pub stubs: Vec<String>,
+
+    /// The frozen slugs other than those computed from their source files now, each with
+    /// the computed one, see [`crate::compiler::frozen`].
+    pub drifted_slugs: BTreeMap<String, String>,
+
+    /// The pages marked `draft` left out of the build, whose links and embeds are rendered
+    /// as plain text, see [`config::is_drafts`]. So are the expired pages with
+    /// [`config::Expired::Unpublish`].
+    pub drafts: BTreeSet<String>,
+
+    /// The source pages past their `expires`, see [`MetaData::is_expired`].
+    pub expired: BTreeSet<String>,
+
+    /// The pages of [`CompileState::expired`] which were not as of the last compilation,
+    /// see [`CompileState::reconcile_expired`].
+    pub newly_expired: Vec<String>,
+
+    /// The page of each alias, see [`crate::compiler::aliases`].
+    pub aliases: BTreeMap<String, String>,
+
+    /// Slugs of the pages listing all the backlinks of a page, see [`config::max_backlinks`].
+    pub backlink_pages: Vec<String>,
+
+    /// Slug of the page listing the incoming links of every page, see [`config::mentions_page`].
+    pub mentions_page: Option<String>,
+
+    /// Slug of the page listing every reference page, see [`config::bibliography_page`].
+    pub bibliography_page: Option<String>,
+
+    /// Slug of the page listing the pages by license, see [`config::license_page`].
+    pub license_page: Option<String>,
+
+    /// [`featured_slug`] if some pages are featured.
+    pub featured_page: Option<String>,
+
+    /// Slugs of the reference pages imported from [`config::bibtex`].
+    pub bibtex_pages: Vec<String>,
+
+    /// The references declared in the metadata of the pages, by `@` and their cite key,
+    /// along with the page declaring them. They are listed in the references and the
+    /// bibliography like the reference pages, see [`CompileState::reference_metadata`].
+    pub inline_references: BTreeMap<String, (String, HTMLMetaData)>,
+
+    /// Slugs of the pages listing the pages of a taxon, then of the page listing
+    /// those pages, see [`config::taxon_pages`].
+    pub taxon_pages: Vec<String>,
+
+    /// Slugs of the pages listing the pages of a tag, then of the page listing
+    /// those pages, see [`config::tag_pages`].
+    pub tag_pages: Vec<String>,
+
+    /// Slugs of the pages listing the pages of an author, then of the page listing
+    /// those pages, see [`config::author_pages`].
+    pub author_pages: Vec<String>,
+
+    /// The parts of each `series` in order, with its name, by the slug of the page listing
+    /// them, see [`CompileState::prepare_series`].
+    pub series: BTreeMap<String, (String, Vec<String>)>,
+
+    /// Slugs of the pages listing the parts of a series, then of the page listing
+    /// those pages, see [`config::series_pages`].
+    pub series_pages: Vec<String>,
+
+    /// Where each metadata value of the source pages comes from, by slug and key,
+    /// such as "the page" or "`notes/meta.toml`", see [`crate::compiler::defaults`].
+    pub metadata_origins: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// The related pages of each page, most related first, see [`config::max_related`].
+    pub related: BTreeMap<String, Vec<String>>,
+
+    /// The numbers of the sections embedded from the root page,
+    /// see [`config::is_auto_numbering`].
+    pub numbering: Numbering,
+
+    /// Time spent on each page, if `--timings` is given.
+    pub timings: Option<Timings>,
+
+    /// Run on each compiled page before it is stored, see [`CompileState::add_post_hook`].
+    pub post_hooks: PostHooks,
+}
+
+impl Default for CompileState {
+    fn default() -> Self {
+        CompileState::new()
+    }
 }
 
-impl CompileState {
-    pub fn new() -> CompileState {
-        CompileState {
-            residued: HashMap::new(),
-            compiled: HashMap::new(),
-            metadata: HashMap::new(),
-            callback: Callback::new(),
+impl CompileState {
+    pub fn new() -> CompileState {
+        CompileState {
+            pages: PageStore::new(),
+            metadata: HashMap::new(),
+            callback: Callback::new(),
+            graph: BTreeMap::new(),
+            unreached: vec![],
+            compiling: vec![],
+            diagnostics: Diagnostics::new(),
+            modified: BTreeSet::new(),
+            section_cache: false,
+            callback_changed: BTreeSet::new(),
+            callback_deltas: vec![],
+            stubs: vec![],
+            drifted_slugs: BTreeMap::new(),
+            backlink_pages: vec![],
+            mentions_page: None,
+            bibliography_page: None,
+            license_page: None,
+            featured_page: None,
+            taxon_pages: vec![],
+            tag_pages: vec![],
+            author_pages: vec![],
+            series: BTreeMap::new(),
+            series_pages: vec![],
+            drafts: BTreeSet::new(),
+            expired: BTreeSet::new(),
+            newly_expired: vec![],
+            aliases: BTreeMap::new(),
+            metadata_origins: BTreeMap::new(),
+            bibtex_pages: vec![],
+            inline_references: BTreeMap::new(),
+            related: BTreeMap::new(),
+            numbering: Numbering::default(),
+            timings: config::is_timings().then(Timings::new),
+            post_hooks: PostHooks::from_config(),
+        }
+    }
+
+    /// The pages written besides the source files:
+    /// the stubs, the backlink pages, the mentions page, the bibliography page,
+    /// the license page, the featured page, the taxon pages, the tag pages, the author pages and the series pages.
+    pub fn generated_slugs(&self) -> Vec<String> {
+        let slugs = self.stubs.iter().chain(&self.backlink_pages);
+        let slugs = slugs
+            .chain(&self.mentions_page)
+            .chain(&self.bibliography_page)
+            .chain(&self.license_page)
+            .chain(&self.featured_page)
+            .chain(&self.taxon_pages)
+            .chain(&self.tag_pages)
+            .chain(&self.author_pages)
+            .chain(&self.series_pages);
+        slugs.cloned().collect()
+    }
+
+    /// The pages written besides those of the source files: the generated pages
+    /// and the pages imported from [`config::bibtex`].
+    pub fn extra_slugs(&self) -> Vec<String> {
+        let mut slugs = self.generated_slugs();
+        slugs.extend(self.bibtex_pages.iter().cloned());
+        slugs
+    }
+
+    /// Add a pending reference page for each entry of the BibTeX file `path` of content
+    /// `source`, its slug being the cite key. A page already having that slug is kept.
+    /// Returns the slugs of the imported pages.
+    pub fn import_bibtex(&mut self, source: &str, path: &str) -> Vec<String> {
+        let entries = match bibtex::parse_bibtex(source) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let message = format!("`{}` is not imported: {}", path, err);
+                self.diagnostics
+                    .report(Severity::Error, None, None, message);
+                return vec![];
+            }
+        };
+
+        let mut slugs = vec![];
+        for entry in entries {
+            let slug = slug::to_slug(&entry.key);
+            if self.pages.contains(&slug) {
+                let message = format!(
+                    "[{}] is not imported from `{}`, as a page already has this slug.",
+                    slug, path
+                );
+                self.diagnostics
+                    .report(Severity::Warning, Some(&slug), None, message);
+                continue;
+            }
+
+            let mut metadata = HashMap::new();
+            let mut insert = |key: &str, value: String| {
+                let value = htmlize::escape_text(value).to_string();
+                metadata.insert(key.to_string(), HTMLContent::Plain(value));
+            };
+            insert(KEY_SLUG, slug.to_string());
+            insert(
+                KEY_TITLE,
+                entry.text("title").unwrap_or(entry.key.to_string()),
+            );
+            insert(KEY_TAXON, display_taxon("reference", None));
+            insert(KEY_BIBTEX_TYPE, entry.kind.to_string());
+            let author = entry.text("author").or_else(|| entry.text("editor"));
+            let fields = [
+                (KEY_AUTHOR, author),
+                (KEY_YEAR, entry.text("year")),
+                (KEY_JOURNAL, entry.container()),
+                (KEY_DOI, entry.text("doi")),
+                (KEY_URL, entry.text("url")),
+            ];
+            for (key, value) in fields {
+                if let Some(value) = value {
+                    insert(key, value);
+                }
+            }
+            let content = match entry.text("abstract") {
+                Some(text) => format!("<p>{}</p>", htmlize::escape_text(text)),
+                None => String::new(),
+            };
+            let shallow = ShallowSection {
+                metadata: HTMLMetaData(metadata, MetaDataLists::new()),
+                content: HTMLContent::Plain(content),
+            };
+            self.pages.insert_pending(slug.to_string(), shallow);
+            slugs.push(slug);
+        }
+        self.bibtex_pages.extend(slugs.iter().cloned());
+        slugs
+    }
+
+    /// The reference pages as BibTeX entries in slug order, their cite keys being their
+    /// slugs, or only those referenced by a compiled page with `used_only`.
+    pub fn bibtex_entries(&self, used_only: bool) -> Vec<bibtex::BibEntry> {
+        let mut slugs: BTreeSet<&String> = match used_only {
+            true => self
+                .pages
+                .compiled()
+                .values()
+                .flat_map(|section| section.references.iter())
+                .collect(),
+            false => self.pages.compiled().keys().collect(),
+        };
+        slugs.retain(|slug| self.is_reference(slug));
+
+        let text = |metadata: &HTMLMetaData, key: &str| {
+            metadata
+                .get_str(key)
+                .map(|value| htmlize::unescape(value).to_string())
+        };
+        slugs
+            .into_iter()
+            .filter_map(|slug| self.get_metadata(slug).map(|metadata| (slug, metadata)))
+            .map(|(slug, metadata)| {
+                let container = text(metadata, KEY_JOURNAL);
+                let kind = text(metadata, KEY_BIBTEX_TYPE).unwrap_or_else(|| {
+                    let kind = if container.is_some() {
+                        "article"
+                    } else {
+                        "misc"
+                    };
+                    kind.to_string()
+                });
+                let fields = [
+                    ("author", text(metadata, KEY_AUTHOR)),
+                    ("title", text(metadata, KEY_PAGE_TITLE)),
+                    (bibtex::BibEntry::container_field(&kind), container),
+                    ("year", text(metadata, KEY_YEAR)),
+                    ("doi", text(metadata, KEY_DOI)),
+                    ("url", text(metadata, KEY_URL)),
+                ];
+                let fields = fields
+                    .into_iter()
+                    .filter_map(|(name, value)| value.map(|value| (name.to_string(), value)))
+                    .collect();
+                bibtex::BibEntry {
+                    kind,
+                    key: slug.to_string(),
+                    fields,
+                }
+            })
+            .collect()
+    }
+
+    /// Run `hook` on every page compiled from now on, after the hooks added before it.
+    pub fn add_post_hook(&mut self, hook: PostHook) {
+        self.post_hooks.push(hook);
+    }
+
+    /// Compile `slug` and the pages it transitively embeds.
+    /// Runs [`CompileState::prepare_metadata`] first if no entry point has done so.
+    pub fn compile(&mut self, slug: &str) -> Result<Arc<Section>, CompileError> {
+        if self.metadata.is_empty() {
+            self.prepare_metadata();
+        }
+        self.fetch_section(slug)
+    }
+
+    /// Collect the metadata of all pending pages, so that links to pages
+    /// which are not compiled yet can resolve their titles.
+    /// Every entry point must run this before compiling any page.
+    ///
+    /// A page without a `title` is titled by its first heading, or else by its slug in
+    /// title case, see [`slug::to_title`], with a diagnostic.
+    pub fn prepare_metadata(&mut self) {
+        let mut fallbacks = vec![];
+        let metadata: Vec<(String, HTMLMetaData)> = self
+            .pages
+            .pending_mut()
+            .map(|(key, value)| {
+                if value.metadata.title().is_none() && !slug::is_metadata_slug(key) {
+                    let (title, origin) = match value.content.first_heading() {
+                        Some(heading) => (heading, "its first heading"),
+                        None => (
+                            htmlize::escape_text(slug::to_title(key)).to_string(),
+                            "its slug",
+                        ),
+                    };
+                    let message = format!(
+                        "[{}] has no `title`, so it is titled `{}` by {}.",
+                        key,
+                        htmlize::unescape(&title),
+                        origin
+                    );
+                    let origin = format!("{} {}", FROM_FALLBACK, origin);
+                    fallbacks.push((key.to_string(), message, origin));
+                    let title = HTMLContent::Plain(title);
+                    value.metadata.0.insert(KEY_TITLE.to_string(), title);
+                }
+                value.metadata.compute_textual_attrs();
+                (key.to_string(), value.metadata.clone())
+            })
+            .collect();
+        for (slug, message, origin) in fallbacks {
+            self.metadata_origins
+                .entry(slug.to_string())
+                .or_default()
+                .insert(KEY_TITLE.to_string(), origin);
+            self.diagnostics
+                .report(Severity::Info, Some(&slug), None, message);
+        }
+
+        // only the pages prepared for the first time, as this runs again for generated pages.
+        let unknown_taxa: BTreeSet<&str> = metadata
+            .iter()
+            .filter(|(slug, _)| !self.metadata.contains_key(slug))
+            .filter_map(|(_, metadata)| metadata.data_taxon())
+            .filter(|taxon| !taxon.is_empty() && config::taxon_entry(taxon).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown_taxa.is_empty() {
+            let taxa: Vec<&str> = unknown_taxa.into_iter().collect();
+            let message = format!(
+                "unknown taxa `{}`, declare them as `[taxa.<id>]` in `{}`.",
+                taxa.join("`, `"),
+                config::CONFIG_FILE_NAME
+            );
+            self.diagnostics
+                .report(Severity::Warning, None, None, message);
+        }
+        let mut declared: Vec<_> = metadata
+            .iter()
+            .filter(|(slug, _)| !self.metadata.contains_key(slug))
+            .collect();
+        declared.sort_by_key(|(slug, _)| slug.to_string());
+        let inline_references: Vec<(String, String, HTMLMetaData)> = declared
+            .into_iter()
+            .flat_map(|(slug, metadata)| {
+                metadata.0.iter().filter_map(|(key, value)| {
+                    let key = key.strip_prefix(KEY_INLINE_REFERENCE_PREFIX)?;
+                    let fields = value.as_string()?;
+                    Some((
+                        slug.to_string(),
+                        key.to_string(),
+                        Self::inline_reference(key, fields),
+                    ))
+                })
+            })
+            .collect();
+        self.metadata.extend(metadata);
+        for (slug, key, reference) in inline_references {
+            self.declare_inline_reference(&slug, &key, reference);
+        }
+    }
+
+    /// The metadata of the inline reference `key` declared as `author: ...; title: ...`.
+    fn inline_reference(key: &str, fields: &str) -> HTMLMetaData {
+        let mut metadata = HashMap::new();
+        let fields = crate::process::embed_markdown::parse_metadata_overrides(fields);
+        for (name, value) in fields {
+            metadata.insert(name, HTMLContent::Plain(value));
+        }
+        let title = match metadata.get(KEY_TITLE) {
+            Some(HTMLContent::Plain(title)) => title.to_string(),
+            _ => key.to_string(),
+        };
+        metadata.insert(KEY_TITLE.to_string(), HTMLContent::Plain(title.to_string()));
+        metadata.insert(KEY_PAGE_TITLE.to_string(), HTMLContent::Plain(title));
+        HTMLMetaData(metadata, MetaDataLists::new())
+    }
+
+    /// Keep the inline reference `key` of the page `slug`, unless a page or another
+    /// inline reference already has that key.
+    fn declare_inline_reference(&mut self, slug: &str, key: &str, reference: HTMLMetaData) {
+        let cite_slug = slug::to_slug(key);
+        let inline_slug = format!("@{}", cite_slug);
+        let message = match self.inline_references.get(&inline_slug) {
+            _ if self.metadata.contains_key(&cite_slug) => {
+                format!(
+                    "[{}] is declared inline but is a page, citing the page.",
+                    cite_slug
+                )
+            }
+            Some((declaring, _)) => format!(
+                "[{}] is declared inline by both [{}] and [{}], keeping the former.",
+                cite_slug, declaring, slug
+            ),
+            None => {
+                let reference = (slug.to_string(), reference);
+                self.inline_references.insert(inline_slug, reference);
+                return;
+            }
+        };
+        self.diagnostics
+            .report(Severity::Warning, Some(&cite_slug), Some(slug), message);
+    }
+
+    /// The metadata of the reference page or of the inline reference `slug`,
+    /// the latter starting with `@`.
+    pub fn reference_metadata(&self, slug: &str) -> Option<&HTMLMetaData> {
+        match slug.starts_with('@') {
+            true => self
+                .inline_references
+                .get(slug)
+                .map(|(_, metadata)| metadata),
+            false => self.get_metadata(slug),
+        }
+    }
+
+    /// Where the reference `slug` links: its page, or the `url` of an inline reference.
+    pub fn reference_href(&self, slug: &str) -> Option<String> {
+        match slug.starts_with('@') {
+            true => self.reference_metadata(slug)?.get_str(KEY_URL).cloned(),
+            false => Some(config::full_html_url(slug)),
+        }
+    }
+
+    /// Add a pending stub page for every missing slug embedded or linked
+    /// by a pending page, listing the pages which wanted it.
+    pub fn prepare_stubs(&mut self) {
+        let mut wanted: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (slug, shallow) in self.pages.pending() {
+            // embeds are dropped from the metadata, see `CompileState::metadata_without_embeds`.
+            let metadata_links = shallow
+                .metadata
+                .0
+                .values()
+                .filter_map(HTMLContent::as_lazy)
+                .flatten()
+                .filter(|lazy_content| !matches!(lazy_content, LazyContent::Embed(_)));
+            let contents = shallow.content.as_lazy().into_iter().flatten();
+            for lazy_content in contents.chain(metadata_links) {
+                let target = match lazy_content {
+                    LazyContent::Plain(_) => continue,
+                    LazyContent::Embed(embed_content) => {
+                        slug::to_slug(slug::split_anchor(&embed_content.url).0)
+                    }
+                    LazyContent::Local(local_link) => local_link.slug.to_string(),
+                    LazyContent::Cite(citation) => slug::to_slug(&citation.key),
+                };
+                let target = slug::clip_metadata_badge(&target);
+                if !self.pages.contains(&target) && !self.drafts.contains(&target) {
+                    wanted.entry(target).or_default().insert(slug.to_string());
+                }
+            }
+        }
+
+        for (slug, referrers) in wanted {
+            let items: String = referrers
+                .iter()
+                .map(|referrer| {
+                    let title = self
+                        .get_metadata(referrer)
+                        .map_or("", |s| s.page_title().map_or("", |s| s));
+                    let link = crate::html_flake::html_link(
+                        &config::full_html_url(referrer),
+                        &format!("{} [{}]", title, referrer),
+                        title,
+                        crate::recorder::State::LocalLink.strify(),
+                    );
+                    format!("<li>{}</li>", link)
+                })
+                .collect();
+            let html = format!(
+                "<p>This page has not been written yet. It is wanted by:</p><ul>{}</ul>",
+                items
+            );
+
+            let mut metadata = HashMap::new();
+            metadata.insert(KEY_SLUG.to_string(), HTMLContent::Plain(slug.to_string()));
+            metadata.insert(KEY_TITLE.to_string(), HTMLContent::Plain(slug.to_string()));
+            metadata.insert(KEY_STUB.to_string(), HTMLContent::Plain("true".to_string()));
+            let shallow = ShallowSection {
+                metadata: HTMLMetaData(metadata, MetaDataLists::new()),
+                content: HTMLContent::Plain(html),
+            };
+            self.pages.insert_pending(slug.to_string(), shallow);
+            self.stubs.push(slug);
+        }
+        self.prepare_metadata();
+    }
+
+    /// Take the pending pages marked `draft` out of the build, unless [`config::is_drafts`],
+    /// before anything is compiled so that nothing of them is embedded in other pages.
+    /// The expired pages are collected into [`CompileState::expired`], and taken out too
+    /// with [`config::Expired::Unpublish`].
+    fn exclude_drafts(&mut self) {
+        self.expired = self
+            .pages
+            .pending()
+            .filter(|(slug, shallow)| {
+                !slug::is_metadata_slug(slug) && shallow.metadata.is_expired()
+            })
+            .map(|(slug, _)| slug.to_string())
+            .collect();
+        let unpublish = config::expired() == Expired::Unpublish;
+        let drafts: Vec<String> = self
+            .pages
+            .pending()
+            .filter(|(slug, shallow)| {
+                (shallow.metadata.is_draft() && !config::is_drafts())
+                    || (unpublish && self.expired.contains(*slug))
+            })
+            .map(|(slug, _)| slug.to_string())
+            .collect();
+        for slug in drafts {
+            self.pages.take_pending(&slug);
+            self.drafts.insert(slug);
+        }
+    }
+
+    /// Why the page `slug` of [`CompileState::drafts`] is left out of the build.
+    fn unpublished_reason(&self, slug: &str) -> &'static str {
+        match self.expired.contains(slug) && config::expired() == Expired::Unpublish {
+            true => "expired",
+            false => "a draft",
+        }
+    }
+
+    /// The HTML of a link or an embed of the draft `slug` left out of the build:
+    /// its `text`, or the slug, unlinked, so that nothing of the draft is published.
+    /// The draft is never numbered, so a cross reference `#` shows the slug too.
+    fn draft_text(slug: &str, text: Option<&str>) -> String {
+        let text = text.filter(|text| !text.is_empty() && *text != "#");
+        text.unwrap_or(slug).to_string()
+    }
+
+    /// Compile `slugs` and the pages they transitively embed.
+    pub fn compile_only(&mut self, slugs: &[String]) {
+        let start = Timings::start(&self.timings);
+        self.exclude_drafts();
+        self.prepare_metadata();
+        if config::is_allow_stubs() {
+            self.prepare_stubs();
+        }
+
+        for slug in slugs {
+            if self.drafts.contains(slug) {
+                continue;
+            }
+            if let Err(err) = self.compile(slug) {
+                self.diagnostics.push(err.into());
+            }
+        }
+        Timings::stop(&self.timings, start, Phase::Compile, None);
+    }
+
+    pub fn compile_all(&mut self) {
+        let start = Timings::start(&self.timings);
+        self.exclude_drafts();
+        let synthetic = |kind: String| slug::synthetic_slug(&kind, "");
+        if let Some(slug) = config::mentions_page().map(synthetic) {
+            self.mentions_page = self.prepare_page(&slug, "Mentions", vec![]).then_some(slug);
+        }
+        if let Some(slug) = config::bibliography_page().map(synthetic) {
+            let prepared = self.prepare_page(&slug, "Bibliography", vec![]);
+            self.bibliography_page = prepared.then_some(slug);
+        }
+        if let Some(slug) = config::license_page().map(synthetic) {
+            self.license_page = self.prepare_page(&slug, "Licenses", vec![]).then_some(slug);
+        }
+        self.prepare_metadata();
+        if let Some(slug) = config::taxon_pages().map(synthetic) {
+            self.prepare_taxon_pages(&slug);
+        }
+        if let Some(prefix) = config::tag_pages() {
+            self.prepare_tag_pages(&prefix);
+        }
+        if let Some(prefix) = config::author_pages() {
+            self.prepare_author_pages(&prefix);
+        }
+        self.prepare_series();
+        self.prepare_featured_page();
+        if config::is_allow_stubs() {
+            self.prepare_stubs();
+        }
+
+        let root_slug = config::root_slug();
+        let dependencies = self.embed_dependencies();
+        self.unreached = Self::unreached(&dependencies, &root_slug);
+        if !self.pages.is_pending(&root_slug) {
+            let err = CompileError::Missing(root_slug.to_string(), None);
+            self.diagnostics.push(err.into());
+        }
+
+        for batch in Self::batches(&dependencies) {
+            let pages: Vec<CompiledPage> = batch
+                .par_iter()
+                .map(|slug| self.timed_compile_page(self.pages.get_pending(slug).unwrap()))
+                .collect();
+            pages.into_iter().for_each(|page| {
+                self.merge_page(page);
+            });
+        }
+
+        /*
+         * Pages in or depending on an embed cycle, compiled one by one
+         * so that the cycles are detected.
+         */
+        let mut residued_slugs: Vec<String> = self.pages.pending_slugs();
+        residued_slugs.retain(|slug| !slug::is_metadata_slug(slug));
+        residued_slugs.sort_by_key(|slug| (*slug != root_slug, slug.to_string()));
+        for slug in residued_slugs {
+            if !self.pages.is_pending(&slug) {
+                continue; // embedded by a previously compiled page.
+            }
+            if let Err(err) = self.compile(&slug) {
+                self.diagnostics.push(err.into());
+            }
+        }
+
+        if let Some(slug) = self.mentions_page.clone() {
+            self.fill_mentions_page(&slug);
+        }
+        if let Some(slug) = self.bibliography_page.clone() {
+            self.fill_bibliography_page(&slug);
+        }
+        if let Some(slug) = self.license_page.clone() {
+            self.fill_license_page(&slug);
+        }
+        if let Some(max_backlinks) = config::max_backlinks() {
+            self.prepare_backlink_pages(max_backlinks);
+        }
+        if let Some(max_related) = config::max_related() {
+            self.prepare_related(max_related);
+        }
+        if config::is_auto_numbering() {
+            if let Some(root) = self.pages.get(&root_slug) {
+                self.numbering = Numbering::new(root);
+            }
+        }
+        self.report_cross_references();
+        self.report_broken_anchors();
+        self.report_unused_references();
+        self.report_incomplete_references();
+        self.report_invalid_dates();
+        self.report_invalid_orders();
+        self.report_licenses();
+        self.finalize_metadata();
+        Timings::stop(&self.timings, start, Phase::Compile, None);
+    }
+
+    /// Compare the [`CompileState::expired`] with those saved by the last compilation,
+    /// which are replaced, into [`CompileState::newly_expired`]. Without them, no page
+    /// is newly expired.
+    pub fn reconcile_expired(&mut self) {
+        let path = config::expired_path();
+        let previous: Option<BTreeSet<String>> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok());
+        if let Some(previous) = previous {
+            self.newly_expired = self.expired.difference(&previous).cloned().collect();
+        }
+        let json = serde_json::to_string(&self.expired).unwrap();
+        if let Err(err) = std::fs::write(&path, json) {
+            eprintln!("{:?}", err);
+        }
+    }
+
+    /// Compare the callback with the one saved by the last compilation, which is replaced.
+    /// A page whose source is unchanged may still gain or lose a backlink or a context.
+    /// Without a saved callback, every page is changed but no delta is reported.
+    pub fn reconcile_callback(&mut self) {
+        let path = config::callback_path();
+        match Callback::load(&path) {
+            Err(_) => self.callback_changed = self.callback.changed_slugs(&Callback::new()),
+            Ok(old) => {
+                self.callback_changed = self.callback.changed_slugs(&old);
+                self.callback_deltas = self.callback.deltas(&old);
+            }
+        }
+        if let Err(err) = self.callback.save(&path) {
+            eprintln!("{:?}", err);
+        }
+    }
+
+    /// Whether the page `slug` may render differently since the last compilation:
+    /// a page it embeds or references changed, or its callback data did.
+    /// An inline reference changes with the page declaring it.
+    pub fn is_affected(&self, slug: &str) -> bool {
+        self.callback_changed.contains(slug)
+            || self.pages.get(slug).is_none_or(|section| {
+                let sources = section.sources().into_iter();
+                let references = section.references.iter();
+                let references = references.chain(&section.further_reading).map(|reference| {
+                    match self.inline_references.get(reference) {
+                        Some((page, _)) => page.to_string(),
+                        None => reference.to_string(),
+                    }
+                });
+                let mut slugs = sources.chain(references);
+                slugs.any(|slug| self.modified.contains(&slug))
+            })
+    }
+
+    /// The taxon and number of `target` as cross-referenced in the page `section`,
+    /// such as `Theorem 3.2`, see [`Numbering::cross_reference`].
+    pub fn cross_reference(&self, section: &Section, target: &str) -> Option<String> {
+        let page = section.slug();
+        let number = match config::is_auto_numbering() {
+            true => self.numbering.cross_reference(&page, target).cloned(),
+            false => Numbering::in_page(section)
+                .cross_reference(&page, target)
+                .cloned(),
+        }?;
+        let number = number.strip_suffix('.').unwrap_or(&number);
+        let taxon = self
+            .get_metadata(target)
+            .and_then(|m| m.data_taxon())
+            .filter(|taxon| !taxon.is_empty());
+        match taxon {
+            Some(taxon) => Some(format!("{} {}", taxon, number)),
+            None => Some(number.to_string()),
+        }
+    }
+
+    /// Report the cross-references to a page not numbered where they are written,
+    /// which fall back to its taxon and title.
+    fn report_cross_references(&mut self) {
+        let mut unnumbered = vec![];
+        for (slug, section) in self.pages.compiled() {
+            if slug::is_metadata_slug(slug) {
+                continue;
+            }
+            for target in numbering::cross_references(section) {
+                if self.cross_reference(section, &target).is_none() {
+                    unnumbered.push((slug.to_string(), target));
+                }
+            }
+        }
+        for (slug, target) in unnumbered {
+            let message = format!(
+                "[{}] has no number here, showing its title instead.",
+                target
+            );
+            self.diagnostics
+                .report(Severity::Info, Some(&target), Some(&slug), message);
+        }
+    }
+
+    /// Report the links `[Text](slug#anchor)` to an anchor their page does not have,
+    /// see [`Section::anchors`] and [`config::broken_links`].
+    fn report_broken_anchors(&mut self) {
+        let severity = match config::broken_links() {
+            config::BrokenLinks::Error => Severity::Error,
+            config::BrokenLinks::Warn => Severity::Warning,
+            config::BrokenLinks::Ignore => return,
+        };
+        let mut anchors: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        let mut broken = vec![];
+        for (slug, edges) in &self.graph {
+            for (target, anchor) in &edges.anchors {
+                let Some(section) = self.pages.get(target) else {
+                    continue; // reported as a broken link.
+                };
+                let ids = anchors.entry(target).or_insert_with(|| section.anchors());
+                if !ids.contains(anchor) {
+                    broken.push((slug.to_string(), target.to_string(), anchor.to_string()));
+                }
+            }
+        }
+        for (slug, target, anchor) in broken {
+            let message = format!(
+                "linking to [{}#{}], an anchor [{}] does not have.",
+                target, anchor, target
+            );
+            self.diagnostics
+                .report(severity, Some(&target), Some(&slug), message);
+        }
+    }
+
+    fn report_unused_references(&mut self) {
+        for slug in self.unused_references() {
+            let message = format!("[{}] is a reference cited by no page.", slug);
+            self.diagnostics
+                .report(Severity::Info, Some(&slug), None, message);
+        }
+    }
+
+    /// Warn about the reference pages missing some of the [`config::reference_fields`],
+    /// or having them empty, apart from those of their `bib-ignore`.
+    fn report_incomplete_references(&mut self) {
+        let fields = config::reference_fields();
+        let mut incomplete = vec![];
+        for (slug, section) in self.pages.compiled() {
+            if slug::is_metadata_slug(slug) || !self.is_reference(slug) {
+                continue;
+            }
+            let metadata = &section.metadata;
+            let ignored: Vec<&str> = metadata
+                .get_str(KEY_BIB_IGNORE)
+                .map(|keys| keys.split(',').map(str::trim).collect())
+                .unwrap_or_default();
+            let missing: Vec<String> = fields
+                .iter()
+                .filter(|key| !ignored.contains(&key.as_str()))
+                .filter(|key| {
+                    metadata
+                        .get_str(key)
+                        .is_none_or(|value| value.trim().is_empty())
+                })
+                .map(|key| format!("`{}`", key))
+                .collect();
+            if !missing.is_empty() {
+                incomplete.push((slug.to_string(), missing));
+            }
+        }
+        for (slug, missing) in incomplete {
+            let message = format!("[{}] is a reference missing {}.", slug, missing.join(", "));
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+        }
+    }
+
+    /// Warn about the `date` metadata which [`MetaData::parsed_date`] cannot parse,
+    /// and likewise the `expires`.
+    fn report_invalid_dates(&mut self) {
+        let invalid: Vec<(String, &str, String)> = self
+            .pages
+            .compiled()
+            .iter()
+            .filter(|(slug, _)| !slug::is_metadata_slug(slug))
+            .flat_map(|(slug, section)| {
+                let metadata = &section.metadata;
+                let date = metadata.date().filter(|_| metadata.parsed_date().is_none());
+                let expires = metadata
+                    .get_str(KEY_EXPIRES)
+                    .filter(|expires| Date::parse_cached(expires).is_none());
+                [(KEY_DATE, date), (KEY_EXPIRES, expires)]
+                    .into_iter()
+                    .filter_map(|(key, date)| Some((slug.to_string(), key, date?.to_string())))
+            })
+            .collect();
+        for (slug, key, date) in invalid {
+            let message = format!(
+                "[{}] has the {} `{}`, which is no day written as `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM` or `YYYY/MM/DD`.",
+                slug, key, date
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+        }
+    }
+
+    /// Warn about the `order` metadata which [`MetaData::order`] cannot parse.
+    fn report_invalid_orders(&mut self) {
+        let invalid: Vec<(String, String)> = self
+            .pages
+            .compiled()
+            .iter()
+            .filter(|(slug, _)| !slug::is_metadata_slug(slug))
+            .filter_map(|(slug, section)| {
+                let metadata = &section.metadata;
+                let order = metadata
+                    .get_str(KEY_ORDER)
+                    .filter(|_| metadata.order().is_none())?;
+                Some((slug.to_string(), order.to_string()))
+            })
+            .collect();
+        for (slug, order) in invalid {
+            let message = format!(
+                "[{}] has `order: {}`, which is not a number, so it is listed after the ordered pages.",
+                slug, order
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+        }
+    }
+
+    /// With [`config::licenses`] declared, warn about the pages whose `license` is not
+    /// among them, and about the pages embedding a page under a more restrictive license,
+    /// which republishes it.
+    fn report_licenses(&mut self) {
+        if config::licenses().is_empty() {
+            return;
+        }
+        let license = |slug: &str| self.get_metadata(slug).and_then(|m| m.license());
+        let mut messages = vec![];
+        for slug in self.source_slugs() {
+            let Some(child_license) = license(&slug) else {
+                continue;
+            };
+            let Some(child_rank) = config::license_rank(&child_license) else {
+                let message = format!(
+                    "[{}] has the license `{}`, which is not declared in `[licenses]` of `{}`.",
+                    slug,
+                    child_license,
+                    config::CONFIG_FILE_NAME
+                );
+                messages.push((slug.to_string(), None, message));
+                continue;
+            };
+            let parents: BTreeSet<String> = self
+                .callback
+                .0
+                .get(&slug)
+                .into_iter()
+                .flat_map(|callback| &callback.contexts)
+                .map(|parent| slug::clip_metadata_badge(parent))
+                .collect();
+            for parent in parents {
+                let Some(parent_license) = license(&parent) else {
+                    continue;
+                };
+                let Some(parent_rank) = config::license_rank(&parent_license) else {
+                    continue;
+                };
+                if child_rank > parent_rank {
+                    let message = format!(
+                        "embeds [{}], whose license `{}` is more restrictive than its own `{}`.",
+                        slug, child_license, parent_license
+                    );
+                    messages.push((slug.to_string(), Some(parent), message));
+                }
+            }
+        }
+        for (slug, referrer, message) in messages {
+            let referrer = referrer.as_deref();
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), referrer, message);
+        }
+    }
+
+    /// Set the virtual metadata depending on the pages linking or embedding each page,
+    /// which is only complete once all pages are compiled.
+    fn finalize_metadata(&mut self) {
+        let counts: Vec<(String, usize, usize)> = self
+            .metadata
+            .keys()
+            .map(|slug| {
+                let parents = self.callback.0.get(slug).map_or(0, |c| c.contexts.len());
+                (slug.to_string(), self.listed_backlinks(slug).len(), parents)
+            })
+            .collect();
+        for (slug, backlinks, parents) in counts {
+            let metadata = self.metadata.get_mut(&slug).unwrap();
+            let backlinks = HTMLContent::Plain(backlinks.to_string());
+            metadata.0.insert(KEY_BACKLINK_COUNT.to_string(), backlinks);
+            let parents = HTMLContent::Plain(parents.to_string());
+            metadata.0.insert(KEY_PARENT_COUNT.to_string(), parents);
+        }
+    }
+
+    /// Relate the source pages which do not opt out with `related: false`.
+    fn prepare_related(&mut self, max_related: usize) {
+        let generated = self.generated_slugs();
+        let slugs: Vec<String> = self
+            .pages
+            .compiled()
+            .keys()
+            .filter(|slug| !generated.contains(slug) && !slug::is_metadata_slug(slug))
+            .filter(|slug| self.is_enable_related(slug) && self.is_listed(slug))
+            .cloned()
+            .collect();
+        self.related = related::related_pages(
+            &slugs,
+            self.pages.compiled(),
+            &self.callback,
+            max_related,
+            config::min_related_score(),
+        );
+    }
+
+    /// Add a generated page `slug`, titled `title`, as a pending page, so that it can be
+    /// linked like any other. Its content is compiled from `contents`, or only filled by
+    /// [`CompileState::fill_page`] once all pages are compiled.
+    /// Returns whether no page already had this slug. A page having the slug of the
+    /// generated one without [`config::reserved_prefix`], as a forest written before it,
+    /// is warned about.
+    fn prepare_page(&mut self, slug: &str, title: &str, contents: Vec<LazyContent>) -> bool {
+        if self.pages.contains(slug) {
+            let message = format!(
+                "[{}] is not generated, as a page already has this slug.",
+                slug
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(slug), None, message);
+            return false;
+        }
+        let prefix = config::reserved_prefix();
+        let former = slug.strip_prefix(&prefix).filter(|_| !prefix.is_empty());
+        if let Some(former) = former.filter(|former| self.pages.contains(former)) {
+            let message = format!(
+                "[{}] is no longer the generated page, which is now [{}], so the links to [{}] reach the source page.",
+                former, slug, former
+            );
+            self.diagnostics
+                .report(Severity::Warning, Some(former), None, message);
+        }
+        let mut metadata = HashMap::new();
+        metadata.insert(KEY_SLUG.to_string(), HTMLContent::Plain(slug.to_string()));
+        metadata.insert(KEY_TITLE.to_string(), HTMLContent::Plain(title.to_string()));
+        metadata.insert(
+            KEY_ORPHAN.to_string(),
+            HTMLContent::Plain("true".to_string()),
+        );
+        let content = match contents.is_empty() {
+            true => HTMLContent::Plain(String::new()),
+            false => HTMLContent::Lazy(contents),
+        };
+        let shallow = ShallowSection {
+            metadata: HTMLMetaData(metadata, MetaDataLists::new()),
+            content,
+        };
+        self.pages.insert_pending(slug.to_string(), shallow);
+        true
+    }
+
+    /// Add a page listing the pages of each taxon, but the drafts, by their `order`, then in
+    /// [`config::taxon_pages_order`], and a page `overview_slug` listing those pages.
+    /// Their links are compiled as those of a source page, so they are backlinks too.
+    fn prepare_taxon_pages(&mut self, overview_slug: &str) {
+        let only = config::taxon_pages_only();
+        let mut taxa: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let inherited_taxa = self.inherited_taxa();
+        for (slug, metadata) in &self.metadata {
+            let inherited = inherited_taxa
+                .get(slug)
+                .map(|taxon| Taxon::to_data_taxon(&display_taxon(taxon, None)).to_string());
+            let taxon = metadata
+                .data_taxon()
+                .filter(|taxon| !taxon.is_empty())
+                .or(inherited.as_ref());
+            let Some(taxon) = taxon else {
+                continue;
+            };
+            if !self.is_listed(slug) {
+                continue;
+            }
+            if !only.is_empty() && !only.iter().any(|t| t.eq_ignore_ascii_case(taxon)) {
+                continue;
+            }
+            // the pages of a taxon in any language, by its name in the default one.
+            let taxon = config::taxon_display(taxon, None).unwrap_or(taxon.to_string());
+            taxa.entry(taxon).or_default().push(slug.to_string());
+        }
+
+        let link = |slug: &str| {
+            LazyContent::Local(LocalLink {
+                slug: slug.to_string(),
+                text: None,
+                further: false,
+                anchor: None,
+            })
+        };
+        let plain = |html: &str| LazyContent::Plain(html.to_string());
+        let mut overview = vec![plain("<ul>")];
+        for (taxon, mut slugs) in taxa {
+            self.order_pages(&mut slugs, config::taxon_pages_order());
+            let count = match slugs.len() {
+                1 => "1 page".to_string(),
+                n => format!("{} pages", n),
+            };
+            let mut contents = vec![plain(&format!("<p>{}.</p><ul>", count))];
+            for slug in &slugs {
+                let pin = self.pin_html(slug);
+                contents.extend([plain("<li>"), link(slug), plain(&format!("{}</li>", pin))]);
+            }
+            contents.push(plain("</ul>"));
+
+            let slug = config::taxon_page_slug(&taxon);
+            if self.prepare_page(&slug, &Taxon::plural(&taxon), contents) {
+                overview.extend([plain("<li>"), link(&slug)]);
+                overview.push(plain(&format!(" ({})</li>", slugs.len())));
+                self.taxon_pages.push(slug);
+            }
+        }
+        overview.push(plain("</ul>"));
+        if self.prepare_page(overview_slug, "Taxa", overview) {
+            self.taxon_pages.push(overview_slug.to_string());
+        }
+        self.prepare_metadata();
+    }
+
+    /// Add a page listing the pages of each of the `tags`, but the drafts and the expired
+    /// pages, by their `order`, then newest first, and a page `prefix` listing those pages
+    /// with their counts. A tag is named as first written, by slug, and its pages are those
+    /// whose tags have the same slug.
+    fn prepare_tag_pages(&mut self, prefix: &str) {
+        let mut tags: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+        let mut slugs: Vec<&String> = self.metadata.keys().collect();
+        slugs.sort();
+        for slug in slugs {
+            let metadata = &self.metadata[slug];
+            if !self.is_listed(slug) || self.expired.contains(slug) {
+                continue;
+            }
+            for tag in metadata.get_list(KEY_TAGS).unwrap_or_default() {
+                let (_, slugs) = tags
+                    .entry(config::prefixed_slug(prefix, tag))
+                    .or_insert_with(|| (tag.to_string(), vec![]));
+                if !slugs.contains(slug) {
+                    slugs.push(slug.to_string());
+                }
+            }
+        }
+
+        let groups = tags
+            .into_iter()
+            .map(|(slug, (tag, mut slugs))| {
+                self.order_pages(&mut slugs, BacklinksOrder::DateDesc);
+                let tag = htmlize::escape_text(tag).to_string();
+                (slug, (tag, vec![], slugs))
+            })
+            .collect();
+        let overview_slug = slug::synthetic_slug(prefix, "");
+        self.tag_pages = self.prepare_listing_pages(&overview_slug, "Tags", groups);
+    }
+
+    /// Add a page listing the pages of each author, but the drafts, the unlisted pages and
+    /// the expired pages, by their `order`, then newest first, and a page `prefix` listing
+    /// those pages with their counts. An author is named by [`config::author_name`], and
+    /// their page links their homepage, if any.
+    fn prepare_author_pages(&mut self, prefix: &str) {
+        let mut authors: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+        let mut slugs: Vec<&String> = self.metadata.keys().collect();
+        slugs.sort();
+        for slug in slugs {
+            if !self.is_listed(slug) || self.expired.contains(slug) {
+                continue;
+            }
+            for author in self.metadata[slug].authors() {
+                let (_, slugs) = authors
+                    .entry(config::prefixed_slug(prefix, &author))
+                    .or_insert_with(|| (author.to_string(), vec![]));
+                if !slugs.contains(slug) {
+                    slugs.push(slug.to_string());
+                }
+            }
+        }
+
+        let groups = authors
+            .into_iter()
+            .map(|(slug, (author, mut slugs))| {
+                self.order_pages(&mut slugs, BacklinksOrder::DateDesc);
+                let name = htmlize::escape_text(config::author_name(&author)).to_string();
+                let homepage = config::author_entry(&author).and_then(|entry| entry.homepage);
+                let intro = homepage.map(|homepage| {
+                    let homepage = htmlize::escape_attribute(homepage);
+                    let external = crate::recorder::State::ExternalLink.strify();
+                    let link = crate::html_flake::html_link(&homepage, "", &homepage, external);
+                    LazyContent::Plain(format!("<p>{}</p>", link))
+                });
+                (slug, (name, intro.into_iter().collect(), slugs))
+            })
+            .collect();
+        let overview_slug = slug::synthetic_slug(prefix, "");
+        self.author_pages = self.prepare_listing_pages(&overview_slug, "Authors", groups);
+    }
+
+    /// Add a page for each of the `groups`, by slug, listing its pages in order after
+    /// its intro, and a page `overview_slug` listing those pages with their counts.
+    /// Returns the slugs of the pages added, the overview last.
+    fn prepare_listing_pages(
+        &mut self,
+        overview_slug: &str,
+        overview_title: &str,
+        groups: BTreeMap<String, (String, Vec<LazyContent>, Vec<String>)>,
+    ) -> Vec<String> {
+        let link = |slug: &str| {
+            LazyContent::Local(LocalLink {
+                slug: slug.to_string(),
+                text: None,
+                further: false,
+                anchor: None,
+            })
+        };
+        let plain = |html: &str| LazyContent::Plain(html.to_string());
+        let mut prepared = vec![];
+        let mut overview = vec![plain("<ul>")];
+        for (slug, (title, mut contents, slugs)) in groups {
+            contents.push(plain("<ul>"));
+            for slug in &slugs {
+                let pin = self.pin_html(slug);
+                contents.extend([plain("<li>"), link(slug), plain(&format!("{}</li>", pin))]);
+            }
+            contents.push(plain("</ul>"));
+
+            if self.prepare_page(&slug, &title, contents) {
+                overview.extend([plain("<li>"), link(&slug)]);
+                overview.push(plain(&format!(" ({})</li>", slugs.len())));
+                prepared.push(slug);
+            }
+        }
+        overview.push(plain("</ul>"));
+        if self.prepare_page(overview_slug, overview_title, overview) {
+            prepared.push(overview_slug.to_string());
+        }
+        self.prepare_metadata();
+        prepared
+    }
+
+    /// Group the pages but the drafts and the unlisted pages by their `series` into
+    /// [`CompileState::series`], ordered by their `order`, their `series-index`, then by date,
+    /// with a warning for the indices which are no numbers, taken twice or skipped.
+    /// With [`config::series_pages`], add a page listing the parts of each series, and
+    /// a page listing those pages.
+    fn prepare_series(&mut self) {
+        let prefix = config::series_pages();
+        let prefix = prefix
+            .as_deref()
+            .unwrap_or(config::DEFAULT_SERIES_PAGES_PREFIX);
+        let mut series: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+        let mut slugs: Vec<&String> = self.metadata.keys().collect();
+        slugs.sort();
+        for slug in slugs {
+            if !self.is_listed(slug) {
+                continue;
+            }
+            let Some(name) = self.metadata[slug].series() else {
+                continue;
+            };
+            let (_, slugs) = series
+                .entry(config::prefixed_slug(prefix, &name))
+                .or_insert_with(|| (name, vec![]));
+            slugs.push(slug.to_string());
+        }
+
+        let mut warnings = vec![];
+        let index_of = |slug: &str| {
+            let index = self.get_metadata(slug)?.get_str(KEY_SERIES_INDEX)?;
+            index.trim().parse::<usize>().ok()
+        };
+        for (name, slugs) in series.values_mut() {
+            for slug in slugs.iter() {
+                let index = self
+                    .get_metadata(slug)
+                    .and_then(|m| m.get_str(KEY_SERIES_INDEX));
+                if let (Some(index), None) = (index, index_of(slug)) {
+                    let message = format!(
+                        "[{}] has `series-index: {}`, which is not a number, so it is ordered by date in the series `{}`.",
+                        slug, index, name
+                    );
+                    warnings.push((slug.to_string(), message));
+                }
+            }
+            self.order_pages(slugs, BacklinksOrder::DateAsc);
+            slugs.sort_by_key(|slug| (index_of(slug).is_none(), index_of(slug)));
+
+            let mut expected = 1;
+            let mut previous: Option<(&String, usize)> = None;
+            for slug in slugs.iter() {
+                let Some(index) = index_of(slug) else {
+                    continue;
+                };
+                match previous {
+                    Some((other, other_index)) if other_index == index => {
+                        let message = format!(
+                            "[{}] and [{}] are both part {} of the series `{}`.",
+                            other, slug, index, name
+                        );
+                        warnings.push((slug.to_string(), message));
+                    }
+                    _ if index > expected => {
+                        let missing = match index - expected {
+                            1 => format!("part {}", expected),
+                            _ => format!("parts {} to {}", expected, index - 1),
+                        };
+                        let place = match previous {
+                            Some((other, _)) => format!("between [{}] and [{}]", other, slug),
+                            None => format!("before [{}]", slug),
+                        };
+                        let message =
+                            format!("The series `{}` misses the {} {}.", name, missing, place);
+                        warnings.push((slug.to_string(), message));
+                    }
+                    _ => {}
+                }
+                expected = index + 1;
+                previous = Some((slug, index));
+            }
+            sort_by_order(slugs, |slug| self.get_metadata(slug));
+        }
+        for (slug, message) in warnings {
+            self.diagnostics
+                .report(Severity::Warning, Some(&slug), None, message);
+        }
+
+        if config::series_pages().is_some() && !series.is_empty() {
+            let groups = series
+                .iter()
+                .map(|(slug, (name, slugs))| {
+                    let name = htmlize::escape_text(name).to_string();
+                    (slug.to_string(), (name, vec![], slugs.to_vec()))
+                })
+                .collect();
+            let overview_slug = slug::synthetic_slug(prefix, "");
+            self.series_pages = self.prepare_listing_pages(&overview_slug, "Series", groups);
+        }
+        self.series = series;
+    }
+
+    /// Add the page [`featured_slug`] listing the pages with `featured: true`, but the
+    /// drafts, the unlisted pages and the expired pages, by their `order`, then newest
+    /// first, if there are any.
+    fn prepare_featured_page(&mut self) {
+        let mut slugs: Vec<String> = self
+            .metadata
+            .iter()
+            .filter(|(slug, metadata)| metadata.is_featured() && self.is_listed(slug))
+            .filter(|(slug, _)| !self.expired.contains(*slug))
+            .map(|(slug, _)| slug.to_string())
+            .collect();
+        if slugs.is_empty() {
+            return;
+        }
+        slugs.sort();
+        self.order_pages(&mut slugs, BacklinksOrder::DateDesc);
+
+        let plain = |html: &str| LazyContent::Plain(html.to_string());
+        let mut contents = vec![plain("<ul>")];
+        for slug in &slugs {
+            let link = LazyContent::Local(LocalLink {
+                slug: slug.to_string(),
+                text: None,
+                further: false,
+                anchor: None,
+            });
+            let pin = self.pin_html(slug);
+            contents.extend([plain("<li>"), link, plain(&format!("{}</li>", pin))]);
+        }
+        contents.push(plain("</ul>"));
+        let slug = featured_slug();
+        if self.prepare_page(&slug, "Featured", contents) {
+            self.featured_page = Some(slug);
+        }
+        self.prepare_metadata();
+    }
+
+    /// The name and the parts of the series the page `slug` is a part of,
+    /// see [`CompileState::series`].
+    pub fn series_of(&self, slug: &str) -> Option<(&String, &String, &[String])> {
+        let name = self.get_metadata(slug)?.series()?;
+        let prefix = config::series_pages();
+        let prefix = prefix
+            .as_deref()
+            .unwrap_or(config::DEFAULT_SERIES_PAGES_PREFIX);
+        let series_slug = config::prefixed_slug(prefix, &name);
+        let (series_slug, (name, slugs)) = self.series.get_key_value(&series_slug)?;
+        slugs
+            .iter()
+            .any(|s| s == slug)
+            .then_some((series_slug, name, slugs.as_slice()))
+    }
+
+    /// Sort `slugs` by their `order`, then in `order` by their prepared metadata, so before
+    /// they are compiled, unlike [`CompileState::sorted_pages`].
+    fn order_pages(&self, slugs: &mut [String], order: BacklinksOrder) {
+        let metadata = |slug: &str| self.get_metadata(slug);
+        let title = |slug: &str| metadata(slug).and_then(|m| m.page_title()).cloned();
+        let date = |slug: &str| metadata(slug).and_then(|m| m.parsed_date());
+        match order {
+            BacklinksOrder::Title => slugs.sort_by_cached_key(|slug| title(slug)),
+            BacklinksOrder::DateAsc => {
+                slugs.sort_by_cached_key(|slug| (date(slug).is_none(), date(slug), title(slug)))
+            }
+            BacklinksOrder::DateDesc => slugs.sort_by_cached_key(|slug| {
+                (date(slug).is_none(), Reverse(date(slug)), title(slug))
+            }),
+        }
+        sort_by_order(slugs, metadata);
+    }
+
+    /// Replace the content of the page `slug` added by [`CompileState::prepare_page`].
+    fn fill_page(&mut self, slug: &str, html: String) {
+        let Some(compiled) = self.pages.get(slug) else {
+            return;
+        };
+        let metadata = compiled.metadata.clone();
+        let children = vec![SectionContent::Plain(html)];
+        let section = Section::new(metadata, children, IndexSet::new());
+        self.pages
+            .insert_compiled(slug.to_string(), Arc::new(section));
+    }
+
+    /// The pin after the page `slug` in the generated listings, if it is pinned.
+    fn pin_html(&self, slug: &str) -> String {
+        match self.get_metadata(slug).is_some_and(|m| m.is_pinned()) {
+            true => crate::html_flake::html_pin(),
+            false => String::new(),
+        }
+    }
+
+    fn link_html(&self, slug: &str) -> String {
+        let title = self
+            .get_metadata(slug)
+            .map_or("", |s| s.page_title().map_or("", |s| s));
+        crate::html_flake::html_link(
+            &config::full_html_url(slug),
+            &format!("{} [{}]", title, slug),
+            title,
+            crate::recorder::State::LocalLink.strify(),
+        )
+    }
+
+    /// The source pages, without the generated ones, see [`CompileState::generated_slugs`].
+    fn source_slugs(&self) -> Vec<String> {
+        let generated = self.generated_slugs();
+        self.pages
+            .compiled()
+            .keys()
+            .filter(|slug| !generated.contains(slug) && !slug::is_metadata_slug(slug))
+            .cloned()
+            .collect()
+    }
+
+    /// Every source page by slug, with the pages linking or embedding it nested under it.
+    /// The pages nothing links to or embeds are listed apart.
+    fn fill_mentions_page(&mut self, mentions_slug: &str) {
+        let root_slug = config::root_slug();
+        let (mut mentioned, mut unreferenced) = (String::new(), String::new());
+        for slug in self.source_slugs() {
+            if !self.is_listed(&slug) {
+                continue;
+            }
+            let incoming = match self.callback.0.get(&slug) {
+                None => vec![],
+                Some(callback) => {
+                    let slugs = callback.backlinks.iter().chain(&callback.contexts);
+                    let slugs = slugs.filter(|s| self.is_listed(&slug::clip_metadata_badge(s)));
+                    self.sorted_pages(&slugs.cloned().collect())
+                }
+            };
+            if incoming.is_empty() {
+                if slug != root_slug {
+                    unreferenced.push_str(&format!("<li>{}</li>", self.link_html(&slug)));
+                }
+                continue;
+            }
+            let items: String = incoming
+                .iter()
+                .map(|s| format!("<li>{}</li>", self.link_html(s)))
+                .collect();
+            let link = self.link_html(&slug);
+            mentioned.push_str(&format!("<li>{}<ul>{}</ul></li>", link, items));
+        }
+
+        let mut html = format!("<ul>{}</ul>", mentioned);
+        if !unreferenced.is_empty() {
+            html.push_str(&format!("<h2>Unreferenced</h2><ul>{}</ul>", unreferenced));
+        }
+        self.fill_page(mentions_slug, html);
+    }
+
+    /// The listed source pages grouped by their [`MetaData::license`], the declared licenses
+    /// first in order, then the others by id, then the pages without a license.
+    fn fill_license_page(&mut self, license_slug: &str) {
+        let licenses = config::licenses();
+        let mut groups: BTreeMap<(usize, String), (String, Vec<String>)> = BTreeMap::new();
+        let mut unlicensed = vec![];
+        for slug in self.source_slugs() {
+            if !self.is_listed(&slug) {
+                continue;
+            }
+            let Some(license) = self.get_metadata(&slug).and_then(|m| m.license()) else {
+                unlicensed.push(slug);
+                continue;
+            };
+            let key = match config::license_rank(&license) {
+                Some(rank) => (rank, licenses[rank].0.to_string()),
+                None => (licenses.len(), license.to_lowercase()),
+            };
+            let (_, slugs) = groups.entry(key).or_insert_with(|| (license, vec![]));
+            slugs.push(slug);
+        }
+
+        let items = |slugs: &mut Vec<String>| -> String {
+            self.order_pages(slugs, BacklinksOrder::Title);
+            slugs
+                .iter()
+                .map(|slug| format!("<li>{}{}</li>", self.link_html(slug), self.pin_html(slug)))
+                .collect()
+        };
+        let mut html = String::from("<ul>");
+        for (license, mut slugs) in groups.into_values() {
+            let name = htmlize::escape_text(config::license_name(&license)).to_string();
+            let name = match config::license_entry(&license).and_then(|entry| entry.url) {
+                Some(url) => format!(
+                    "<a href=\"{}\">{}</a>",
+                    htmlize::escape_attribute(url),
+                    name
+                ),
+                None => name,
+            };
+            let count = slugs.len();
+            html.push_str(&format!(
+                "<li>{} ({})<ul>{}</ul></li>",
+                name,
+                count,
+                items(&mut slugs)
+            ));
+        }
+        html.push_str("</ul>");
+        if !unlicensed.is_empty() {
+            html.push_str(&format!(
+                "<h2>Unlicensed</h2><ul>{}</ul>",
+                items(&mut unlicensed)
+            ));
+        }
+        self.fill_page(license_slug, html);
+    }
+
+    /// Every reference page and inline reference once, by their `order`, then in
+    /// [`config::bibliography_sort`] order, with the pages citing it nested under it.
+    fn fill_bibliography_page(&mut self, bibliography_slug: &str) {
+        let mut citers: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        for (slug, edges) in &self.graph {
+            for reference in &edges.references {
+                let citer = slug::clip_metadata_badge(slug);
+                if self.is_listed(&citer) {
+                    citers.entry(reference).or_default().insert(citer);
+                }
+            }
+        }
+
+        // pages without a key come after those with it.
+        let sort_keys = config::bibliography_sort();
+        let sort_key = |slug: &String| -> Vec<(bool, String)> {
+            let metadata = self.reference_metadata(slug);
+            sort_keys
+                .iter()
+                .map(|key| {
+                    let value = match key.as_str() {
+                        KEY_TITLE => metadata.and_then(|m| m.page_title()),
+                        key => metadata.and_then(|m| m.get_str(key)),
+                    };
+                    (
+                        value.is_none(),
+                        value.map_or(String::new(), |s| s.to_lowercase()),
+                    )
+                })
+                .collect()
+        };
+        let mut references: Vec<String> = self.source_slugs();
+        references.retain(|slug| self.is_reference(slug) && self.is_listed(slug));
+        references.extend(self.inline_references.keys().cloned());
+        references.sort_by_cached_key(|slug| (sort_key(slug), slug.to_string()));
+        sort_by_order(&mut references, |slug| self.reference_metadata(slug));
+
+        let items: String = references
+            .iter()
+            .enumerate()
+            .map(|(index, slug)| {
+                let href = self.reference_href(slug);
+                let entry = self
+                    .reference_metadata(slug)
+                    .and_then(|m| m.to_bibliography_entry(href.as_deref(), Some(index + 1)));
+                let entry = entry.unwrap_or_else(|| match slug.starts_with('@') {
+                    true => self
+                        .reference_metadata(slug)
+                        .and_then(|m| m.page_title().cloned())
+                        .unwrap_or_default(),
+                    false => self.link_html(slug),
+                });
+                let cited_by: String = citers
+                    .get(slug.as_str())
+                    .into_iter()
+                    .flatten()
+                    .map(|citer| format!("<li>{}</li>", self.link_html(citer)))
+                    .collect();
+                let cited_by = match cited_by.is_empty() {
+                    true => String::new(),
+                    false => format!("<ul>{}</ul>", cited_by),
+                };
+                match slug.starts_with('@') {
+                    true => format!(r#"<li class="inline-reference">{}{}</li>"#, entry, cited_by),
+                    false => format!("<li>{}{}</li>", entry, cited_by),
+                }
+            })
+            .collect();
+        self.fill_page(bibliography_slug, format!("<ul>{}</ul>", items));
+    }
+
+    /// Add a page [`backlinks_slug`] listing all the backlinks of every compiled page
+    /// which has more than `max_backlinks` of them.
+    fn prepare_backlink_pages(&mut self, max_backlinks: usize) {
+        let slugs: Vec<String> = self
+            .pages
+            .compiled()
+            .keys()
+            .filter(|slug| self.listed_backlinks(slug).len() > max_backlinks)
+            .cloned()
+            .collect();
+        for slug in slugs {
+            let callback = self.callback.0.get(&slug).unwrap();
+            let items: String = self
+                .listed_backlinks(&slug)
+                .iter()
+                .map(|backlink| {
+                    let title = self
+                        .get_metadata(backlink)
+                        .map_or("", |s| s.page_title().map_or("", |s| s));
+                    let link = crate::html_flake::html_link(
+                        &config::full_html_url(backlink),
+                        &format!("{} [{}]", title, backlink),
+                        title,
+                        crate::recorder::State::LocalLink.strify(),
+                    );
+                    let snippet = callback
+                        .snippets
+                        .get(backlink)
+                        .map(|s| crate::html_flake::html_backlink_snippet(s))
+                        .unwrap_or_default();
+                    format!("<li>{}{}</li>", link, snippet)
+                })
+                .collect();
+
+            let page_title = self
+                .get_metadata(&slug)
+                .map_or("", |s| s.page_title().map_or("", |s| s));
+            let title = format!("Backlinks of {}", page_title);
+            let backlinks_slug = backlinks_slug(&slug);
+            let mut metadata = EntryMetaData(HashMap::new(), MetaDataLists::new());
+            metadata.update(KEY_SLUG.to_string(), backlinks_slug.to_string());
+            metadata.update(KEY_TITLE.to_string(), title.to_string());
+            metadata.update(KEY_PAGE_TITLE.to_string(), title);
+            let children = vec![SectionContent::Plain(format!("<ul>{}</ul>", items))];
+            let section = Section::new(metadata, children, IndexSet::new());
+
+            // not compiled by `compile_page`, so it links to nothing and has no backlinks.
+            self.callback.insert(
+                backlinks_slug.to_string(),
+                CallbackValue {
+                    parent: slug,
+                    backlinks: BTreeSet::new(),
+                    snippets: BTreeMap::new(),
+                    contexts: BTreeSet::new(),
+                    citers: BTreeSet::new(),
+                },
+            );
+            self.pages
+                .insert_compiled(backlinks_slug.to_string(), Arc::new(section));
+            self.backlink_pages.push(backlinks_slug);
+        }
+    }
+
+    /// The slugs embedded by each pending page, in its content or its metadata.
+    /// References which are cited instead of embedded are left out.
+    fn embed_dependencies(&self) -> BTreeMap<String, Vec<String>> {
+        self.pages
+            .pending()
+            .map(|(slug, shallow)| (slug.to_string(), self.embed_slugs(shallow)))
+            .collect()
+    }
+
+    fn embed_slugs(&self, shallow: &ShallowSection) -> Vec<String> {
+        let lazy_contents = match &shallow.content {
+            HTMLContent::Plain(_) => return vec![],
+            HTMLContent::Lazy(lazy_contents) => lazy_contents,
+        };
+        lazy_contents
+            .iter()
+            .filter_map(|lazy_content| match lazy_content {
+                LazyContent::Embed(embed_content) => Some(embed_content),
+                _ => None,
+            })
+            .map(|embed_content| {
+                let child_slug = slug::to_slug(slug::split_anchor(&embed_content.url).0);
+                let inherited = self.inherited_taxon(&shallow.metadata, embed_content, &child_slug);
+                let is_reference =
+                    self.is_reference(&child_slug) || inherited.is_some_and(Taxon::is_reference);
+                (child_slug, embed_content.option.force || !is_reference)
+            })
+            .filter(|(child_slug, _)| !slug::is_metadata_slug(child_slug))
+            .filter(|(child_slug, _)| !self.drafts.contains(child_slug))
+            .filter(|(child_slug, _)| self.is_enable_embed(child_slug))
+            .filter(|(_, is_embedded)| *is_embedded)
+            .map(|(child_slug, _)| child_slug)
+            .collect()
+    }
+
+    /// Group the pages into batches, each embedding only pages of the previous batches,
+    /// so that the pages of a batch can be compiled in parallel.
+    /// Pages in or depending on an embed cycle are left out.
+    fn batches(dependencies: &BTreeMap<String, Vec<String>>) -> Vec<Vec<String>> {
+        let mut done: HashSet<&String> = HashSet::new();
+        let mut batches = vec![];
+        loop {
+            let batch: Vec<&String> = dependencies
+                .iter()
+                .filter(|(slug, _)| !done.contains(slug))
+                .filter(|(_, children)| {
+                    children
+                        .iter()
+                        .all(|child| done.contains(child) || !dependencies.contains_key(child))
+                })
+                .map(|(slug, _)| slug)
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
+            done.extend(batch.iter().copied());
+            batches.push(batch.into_iter().map(|s| s.to_string()).collect());
+        }
+        batches
+    }
+
+    /// Pages not embedded, directly or transitively, by the root page, in the order the
+    /// residued cleanup reaches them. Each of them in turn reaches the pages it embeds.
+    fn unreached(dependencies: &BTreeMap<String, Vec<String>>, root_slug: &str) -> Vec<String> {
+        fn reach<'a>(
+            dependencies: &'a BTreeMap<String, Vec<String>>,
+            slug: &str,
+            reached: &mut HashSet<&'a str>,
+        ) {
+            let mut stack: Vec<&str> = vec![slug];
+            while let Some(slug) = stack.pop() {
+                if let Some((slug, children)) = dependencies.get_key_value(slug) {
+                    if reached.insert(slug) {
+                        stack.extend(children.iter().map(String::as_str));
+                    }
+                }
+            }
+        }
+
+        let mut reached: HashSet<&str> = HashSet::new();
+        reach(dependencies, root_slug, &mut reached);
+        let mut unreached = vec![];
+        for slug in dependencies.keys() {
+            if !reached.contains(slug.as_str()) {
+                unreached.push(slug.to_string());
+                reach(dependencies, slug, &mut reached);
+            }
+        }
+        unreached
+    }
+
+    fn fetch_section(&mut self, slug: &str) -> Result<Arc<Section>, CompileError> {
+        match self.pages.status(slug) {
+            PageStatus::Compiled => Ok(Arc::clone(self.pages.get(slug).unwrap())),
+            PageStatus::Pending => {
+                let shallow = self.pages.take_pending(slug).unwrap();
+                Ok(self.compile_shallow(shallow))
+            }
+            PageStatus::Missing => Err(CompileError::Missing(slug.to_string(), None)),
+        }
+    }
+
+    /// Compile the pages embedded by `shallow` first, then `shallow` itself.
+    fn compile_shallow(&mut self, shallow: ShallowSection) -> Arc<Section> {
+        let slug = shallow.slug();
+        self.compiling.push(slug.to_string());
+        for child_slug in self.embed_slugs(&shallow) {
+            if !self.compiling.contains(&child_slug) {
+                // a missing page is reported by `compile_page`.
+                let _ = self.fetch_section(&child_slug);
+            }
+        }
+
+        let page = self.timed_compile_page(&shallow);
+        self.compiling.pop();
+        self.merge_page(page)
+    }
+
+    fn merge_page(&mut self, page: CompiledPage) -> Arc<Section> {
+        let (slug, section) = self.merge_records(page);
+        let section = self.run_post_hooks(&slug, section);
+        self.pages.insert_compiled(slug, Arc::clone(&section));
+        section
+    }
+
+    /// Run the post hooks on the freshly compiled `section` of `slug`,
+    /// so that the pages embedding it see the transformed section.
+    fn run_post_hooks(&mut self, slug: &str, section: Arc<Section>) -> Arc<Section> {
+        if self.post_hooks.is_empty() {
+            return section;
+        }
+        let mut section = Arc::unwrap_or_clone(section);
+        for message in self.post_hooks.run(slug, &mut section) {
+            let message = format!("[{}] {}", slug, message);
+            self.diagnostics
+                .report(Severity::Error, Some(slug), None, message);
         }
+        let Section {
+            metadata,
+            children,
+            references,
+            further_reading,
+            ..
+        } = section;
+        Arc::new(Section {
+            further_reading,
+            ..Section::new(metadata, children, references)
+        })
     }
 
-    pub fn compile(&mut self, slug: &str) -> &Section {
-        self.fetch_section(slug).unwrap()
+    /// Merge everything recorded while compiling `page` but the section itself.
+    /// The sections of the metadata are not stored, so they never shadow a real page.
+    fn merge_records(&mut self, page: CompiledPage) -> (String, Arc<Section>) {
+        // before the metadata, so that the snippets of the content come first.
+        self.callback.merge(page.callback);
+        page.metadata_pages.into_iter().for_each(|metadata_page| {
+            self.merge_records(metadata_page);
+        });
+
+        self.graph
+            .entry(slug::clip_metadata_badge(&page.slug))
+            .or_default()
+            .merge(page.edges);
+        self.diagnostics.extend(page.diagnostics);
+
+        (page.slug, page.section)
     }
 
-    pub fn compile_all(&mut self) {
-        self.metadata = self
-            .residued
-            .iter_mut()
-            .map(|(key, value)| {
-                value.metadata.compute_textual_attrs();
-                (key.to_string(), value.metadata.clone())
-            })
-            .collect();
+    fn timed_compile_page(&self, shallow: &ShallowSection) -> CompiledPage {
+        let start = Timings::start(&self.timings);
+        let page = match self.section_cache {
+            true => self.cached_compile_page(shallow),
+            false => self.compile_page(shallow),
+        };
+        Timings::stop(&self.timings, start, Phase::Compile, Some(&page.slug));
+        page
+    }
 
-        self.compile("index");
-        /*
-         * Unlinked or unembedded pages.
-         */
-        let residued_slugs: Vec<String> = self.residued.keys().map(|s| s.to_string()).collect();
-        for slug in residued_slugs {
-            self.compile(&slug);
+    /// [`CompileState::compile_page`], or the page compiled by the last compilation if its
+    /// [`CompileState::page_key`] is the same. Otherwise the page is saved to the cache.
+    fn cached_compile_page(&self, shallow: &ShallowSection) -> CompiledPage {
+        let Some(key) = self.page_key(shallow) else {
+            return self.compile_page(shallow);
+        };
+        let path = config::section_path(&shallow.slug());
+        let cached = (!config::is_no_cache())
+            .then(|| std::fs::read_to_string(&path).ok())
+            .flatten()
+            .and_then(|json| serde_json::from_str::<(u64, CompiledPage)>(&json).ok());
+        if let Some((_, page)) = cached.filter(|(cached_key, _)| *cached_key == key) {
+            return page;
         }
-    }
 
-    fn fetch_section(&mut self, slug: &str) -> Option<&Section> {
-        if self.compiled.contains_key(slug) {
-            return Some(self.compiled.get(slug).unwrap());
+        let page = self.compile_page(shallow);
+        let json = serde_json::to_string(&(key, &page)).unwrap();
+        if let Err(err) = std::fs::write(&path, json) {
+            eprintln!("{:?}", err);
         }
+        page
+    }
 
-        if self.residued.contains_key(slug) {
-            let shallow = self.residued.remove(slug).unwrap();
-            return Some(self.compile_shallow(shallow));
+    /// The key of the page compiled from `shallow` in the cache: the hash of the options,
+    /// of `shallow`, of the compiled sections it embeds, which cover their own embeds, and of
+    /// the metadata of the pages it refers to, so that a title change dirties the pages
+    /// linking to it. `None` while one of its embeds is not compiled, as in an embed cycle.
+    fn page_key(&self, shallow: &ShallowSection) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        config::fingerprint().hash(&mut hasher);
+        stable_json(shallow).hash(&mut hasher);
+        for child_slug in self.embed_slugs(shallow) {
+            let is_compiling =
+                self.pages.is_pending(&child_slug) || self.compiling.contains(&child_slug);
+            match self.pages.get(&child_slug) {
+                Some(section) => section.fingerprint.hash(&mut hasher),
+                None if is_compiling => return None,
+                None => None::<u64>.hash(&mut hasher),
+            }
         }
+        for slug in Self::referred_slugs(shallow) {
+            slug.hash(&mut hasher);
+            self.metadata.get(&slug).map(stable_json).hash(&mut hasher);
+            self.drafts.contains(&slug).hash(&mut hasher);
+            self.expired.contains(&slug).hash(&mut hasher);
+            let inline_reference = self.inline_references.get(&slug);
+            inline_reference.map(stable_json).hash(&mut hasher);
+        }
+        Some(hasher.finish())
+    }
 
-        None // unreachable!("CompileState::fetch_section")
+    /// The slugs `shallow` embeds, links to or cites in its content and its metadata,
+    /// with its own, and the pages of those which are the metadata of a page.
+    fn referred_slugs(shallow: &ShallowSection) -> BTreeSet<String> {
+        let mut slugs = BTreeSet::from([shallow.slug()]);
+        for value in shallow.metadata.0.values().chain([&shallow.content]) {
+            let HTMLContent::Lazy(lazy_contents) = value else {
+                continue;
+            };
+            for lazy_content in lazy_contents {
+                match lazy_content {
+                    LazyContent::Plain(_) => {}
+                    LazyContent::Embed(embed_content) => {
+                        let child_url = slug::split_anchor(&embed_content.url).0;
+                        slugs.insert(slug::to_slug(child_url));
+                    }
+                    LazyContent::Local(local_link) => {
+                        slugs.insert(local_link.slug.to_string());
+                    }
+                    LazyContent::Cite(citation) => {
+                        let cite_slug = slug::to_slug(&citation.key);
+                        slugs.insert(format!("@{}", cite_slug));
+                        slugs.insert(cite_slug);
+                    }
+                }
+            }
+        }
+        let bases: Vec<String> = slugs.iter().map(|s| slug::clip_metadata_badge(s)).collect();
+        slugs.extend(bases);
+        slugs
     }
 
-    fn compile_shallow(&mut self, shallow: ShallowSection) -> &Section {
+    /// Compile `shallow`, whose embedded pages must have been compiled already.
+    /// Nothing is recorded in `self`, see [`CompileState::merge_page`].
+    fn compile_page(&self, shallow: &ShallowSection) -> CompiledPage {
         let slug = shallow.slug();
         let mut children: SectionContents = vec![];
-        let mut references: HashSet<String> = HashSet::new();
+        let mut references: IndexSet<String> = IndexSet::new();
+        let mut further_reading: IndexSet<String> = IndexSet::new();
+        let mut edges = PageEdges::default();
+        let mut callback: Callback = Callback::new();
+        let mut diagnostics = Diagnostics::new();
 
-        match &shallow.content {
-            HTMLContent::Plain(html) => {
-                children.push(SectionContent::Plain(html.to_string()));
-            }
-            HTMLContent::Lazy(lazy_contents) => {
-                let mut callback: Callback = Callback::new();
+        if let HTMLContent::Plain(html) = &shallow.content {
+            children.push(SectionContent::Plain(html.to_string()));
+        }
+        if let HTMLContent::Lazy(lazy_contents) = &shallow.content {
+            for (index, lazy_content) in lazy_contents.iter().enumerate() {
+                match lazy_content {
+                    LazyContent::Plain(html) => {
+                        children.push(SectionContent::Plain(html.to_string()));
+                    }
+                    LazyContent::Embed(embed_content) => {
+                        let (child_url, anchor) = slug::split_anchor(&embed_content.url);
+                        let child_slug = slug::to_slug(child_url);
+                        if slug::is_metadata_slug(&child_slug) {
+                            let base_slug =
+                                Self::metadata_slug_to_base(&child_slug, &slug, &mut diagnostics);
+                            edges.links.insert(base_slug.to_string());
+                            let html = self.placeholder_link(&base_slug, &embed_content.title);
+                            children.push(SectionContent::Plain(html));
+                            continue;
+                        }
+
+                        if self.drafts.contains(&child_slug) {
+                            let message = format!(
+                                "[{}] is {}, so it is not embedded.",
+                                child_slug,
+                                self.unpublished_reason(&child_slug)
+                            );
+                            let referrer = Some(slug.as_str());
+                            diagnostics.report(
+                                Severity::Info,
+                                Some(&child_slug),
+                                referrer,
+                                message,
+                            );
+                            let text =
+                                Self::draft_text(&child_slug, embed_content.title.as_deref());
+                            children.push(SectionContent::Plain(text));
+                            continue;
+                        }
 
-                for lazy_content in lazy_contents {
-                    match lazy_content {
-                        LazyContent::Plain(html) => {
-                            children.push(SectionContent::Plain(html.to_string()));
+                        if !self.is_enable_embed(&child_slug) {
+                            let message = format!(
+                                "[{}] disables embedding, linking to it instead.",
+                                child_slug
+                            );
+                            let referrer = Some(slug.as_str());
+                            diagnostics.report(
+                                Severity::Info,
+                                Some(&child_slug),
+                                referrer,
+                                message,
+                            );
+                            edges.links.insert(child_slug.to_string());
+                            let html = self.placeholder_link(&child_slug, &embed_content.title);
+                            children.push(SectionContent::Plain(html));
+                            continue;
                         }
-                        LazyContent::Embed(embed_content) => {
-                            let child_slug = slug::to_slug(&embed_content.url);
-                            let refered = match self.fetch_section(&child_slug) {
-                                Some(refered_section) => refered_section,
-                                None => {
-                                    eprintln!(
-                                        "Error: [{}] attempting to fetch a non-existent [{}].",
-                                        slug, child_slug,
-                                    );
-                                    continue;
-                                }
-                            };
-
-                            if embed_content.option.details_open {
-                                references.extend(refered.references.clone());
+
+                        let inherited_taxon =
+                            self.inherited_taxon(&shallow.metadata, embed_content, &child_slug);
+                        let is_reference = self.is_reference(&child_slug)
+                            || inherited_taxon.is_some_and(Taxon::is_reference);
+                        if is_reference && !embed_content.option.force {
+                            let message = format!(
+                                "[{}] is a reference, citing it instead of embedding.",
+                                child_slug
+                            );
+                            diagnostics.warning(&child_slug, &slug, message);
+                            edges.links.insert(child_slug.to_string());
+                            edges.references.insert(child_slug.to_string());
+                            if self.is_listed(&child_slug) {
+                                references.insert(child_slug.to_string());
                             }
-                            callback.insert_parent(child_slug, slug.to_string());
-
-                            let mut child_section = refered.clone();
-                            child_section.option = embed_content.option.clone();
-                            if let Some(title) = &embed_content.title {
-                                child_section
-                                    .metadata
-                                    .update("title".to_string(), title.to_string())
-                            };
-                            children.push(SectionContent::Embed(child_section));
+                            let html = self.placeholder_link(&child_slug, &embed_content.title);
+                            let marker = crate::html_flake::html_reference_marker(&child_slug);
+                            children.push(SectionContent::Plain(html + &marker));
+                            continue;
+                        }
+
+                        edges.embeds.insert(child_slug.to_string());
+                        if let Some(cycle) = self.embed_cycle(&child_slug) {
+                            let message = format!("embed cycle detected: {}.", cycle.join(" -> "));
+                            diagnostics.warning(&child_slug, &slug, message);
+                            let html = self.placeholder_link(&child_slug, &embed_content.title);
+                            children.push(SectionContent::Plain(html));
+                            continue;
                         }
-                        LazyContent::Local(local_link) => {
-                            let link_slug = &local_link.slug;
-                            let article_title = self
-                                .get_metadata(&link_slug)
-                                .map_or("", |s| s.page_title().map_or("", |s| s));
-
-                            if self.is_reference(&link_slug) {
-                                references.insert(link_slug.to_string());
+
+                        let refered = match self.pages.get(&child_slug) {
+                            Some(refered_section) => Arc::clone(refered_section),
+                            None => {
+                                let err = CompileError::MissingEmbed(child_slug, slug.to_string());
+                                diagnostics.push(err.into());
+                                continue;
+                            }
+                        };
+
+                        let refered = match anchor {
+                            None => refered,
+                            Some(anchor) => {
+                                Self::slice_section(refered, anchor, &slug, &mut diagnostics)
                             }
+                        };
+
+                        if embed_content.option.details_open {
+                            references.extend(refered.references.clone());
+                            further_reading.extend(refered.further_reading.clone());
+                        }
+                        let mut metadata_overrides =
+                            Self::metadata_overrides(embed_content, &slug, &mut diagnostics);
+                        if let Some(taxon) = inherited_taxon {
+                            let lang = self
+                                .get_metadata(&child_slug)
+                                .and_then(|metadata| metadata.get_str(KEY_LANG))
+                                .map(String::as_str);
+                            let taxon = display_taxon(taxon, lang);
+                            metadata_overrides.insert(KEY_TAXON.to_string(), taxon);
+                        }
+                        callback.insert_parent(child_slug, slug.to_string());
+
+                        let embed = EmbedSection {
+                            section: refered,
+                            option: embed_content.option.clone(),
+                            title: embed_content.title.clone(),
+                            metadata_overrides,
+                        };
+                        let chain = [slug.to_string()];
+                        children.push(Self::limit_embed_depth(embed, &chain, &mut diagnostics));
+                    }
+                    LazyContent::Local(local_link) => {
+                        let link_slug = match slug::is_metadata_slug(&local_link.slug) {
+                            true => Self::metadata_slug_to_base(
+                                &local_link.slug,
+                                &slug,
+                                &mut diagnostics,
+                            ),
+                            false => local_link.slug.to_string(),
+                        };
+                        let link_slug = &link_slug;
+                        if self.drafts.contains(link_slug) {
+                            let message = format!(
+                                "[{}] is {}, so it is not linked to.",
+                                link_slug,
+                                self.unpublished_reason(link_slug)
+                            );
+                            let referrer = Some(slug.as_str());
+                            diagnostics.report(Severity::Info, Some(link_slug), referrer, message);
+                            let text = Self::draft_text(link_slug, local_link.text.as_deref());
+                            children.push(SectionContent::Plain(text));
+                            continue;
+                        }
+                        let article_title = self
+                            .get_metadata(link_slug)
+                            .map_or("", |s| s.page_title().map_or("", |s| s));
 
-                            /*
-                             * Making oneself the content of a backlink should not be expected behavior.
-                             */
-                            if *link_slug != slug
-                                && format!("{}:metadata", link_slug) != slug
-                                && self.is_enable_backlinks(&link_slug)
-                            {
-                                callback.insert_backlinks(
-                                    link_slug.to_string(),
-                                    vec![slug.to_string()],
-                                );
+                        edges.links.insert(link_slug.to_string());
+                        if self.is_reference(link_slug) {
+                            if self.is_listed(link_slug) {
+                                match local_link.further {
+                                    true => further_reading.insert(link_slug.to_string()),
+                                    false => references.insert(link_slug.to_string()),
+                                };
                             }
+                            edges.references.insert(link_slug.to_string());
+                        }
+
+                        /*
+                         * Making oneself the content of a backlink should not be expected behavior.
+                         */
+                        if *link_slug != slug
+                            && slug::metadata_slug(link_slug) != slug
+                            && self.is_enable_backlinks(link_slug)
+                            && self.is_listed(&slug::clip_metadata_badge(&slug))
+                            && !self.is_backlink_suppressed(&slug, link_slug)
+                        {
+                            let snippet = self.backlink_snippet(lazy_contents, index);
+                            callback.insert_backlink(
+                                link_slug.to_string(),
+                                slug.to_string(),
+                                snippet,
+                            );
+                        }
 
-                            let local_link = local_link.text.clone();
-                            let text = local_link.unwrap_or(article_title.to_string());
+                        let further = local_link.further;
+                        let anchor = local_link.anchor.clone();
+                        let local_link = local_link.text.clone();
+                        let text = match local_link.as_deref() {
+                            // the numbers are only known once all pages are compiled.
+                            Some("#") => {
+                                let taxon = self
+                                    .get_metadata(link_slug)
+                                    .and_then(|m| m.data_taxon())
+                                    .filter(|taxon| !taxon.is_empty());
+                                let fallback = match taxon {
+                                    Some(taxon) => format!("{} {}", taxon, article_title),
+                                    None => article_title.to_string(),
+                                };
+                                crate::html_flake::html_cross_reference(link_slug, &fallback)
+                            }
+                            _ => local_link.unwrap_or(article_title.to_string()),
+                        };
+                        let is_broken = self.get_metadata(link_slug).is_none();
+                        if text.is_empty() && !is_broken {
+                            let message = format!(
+                                "the link to [{}] has an empty text, as its title is empty.",
+                                link_slug
+                            );
+                            diagnostics.warning(link_slug, &slug, message);
+                        }
+                        if is_broken {
+                            let reserved = slug::synthetic_slug(link_slug, "");
+                            let reserved = Some(reserved)
+                                .filter(|reserved| self.get_metadata(reserved).is_some());
+                            Self::report_broken_link(
+                                link_slug,
+                                reserved.as_deref(),
+                                &slug,
+                                &mut diagnostics,
+                            );
+                        }
 
-                            let html = crate::html_flake::html_link(
-                                &config::full_html_url(link_slug),
-                                &format!("{} [{}]", article_title, link_slug),
-                                &text,
-                                crate::recorder::State::LocalLink.strify(),
+                        let class_name = crate::recorder::State::LocalLink.strify();
+                        let class_name = match is_broken {
+                            true => format!("{} broken", class_name),
+                            false => class_name.to_string(),
+                        };
+                        let href = match &anchor {
+                            Some(anchor) => {
+                                let target = (link_slug.to_string(), anchor.to_string());
+                                edges.anchors.insert(target);
+                                format!("{}#{}", config::full_html_url(link_slug), anchor)
+                            }
+                            None => config::full_html_url(link_slug),
+                        };
+                        let html = crate::html_flake::html_link(
+                            &href,
+                            &format!("{} [{}]", article_title, link_slug),
+                            &text,
+                            &class_name,
+                        );
+                        // further reading is not numbered among the citations.
+                        let marker = match self.is_reference(link_slug) && !further {
+                            true => crate::html_flake::html_reference_marker(link_slug),
+                            false => String::new(),
+                        };
+                        children.push(SectionContent::Plain(html + &marker));
+                    }
+                    LazyContent::Cite(citation) => {
+                        let cite_slug = &slug::to_slug(&citation.key);
+                        let inline_slug = format!("@{}", cite_slug);
+                        let inline = self
+                            .get_metadata(cite_slug)
+                            .is_none()
+                            .then(|| self.inline_references.get(&inline_slug))
+                            .flatten();
+                        if let Some((_, metadata)) = inline {
+                            // listed in the references, but never linked to.
+                            references.insert(inline_slug.to_string());
+                            edges.references.insert(inline_slug.to_string());
+                            let page_title = metadata.page_title().map_or("", |s| s);
+                            let html = crate::html_flake::html_inline_citation(
+                                metadata.get_str(KEY_URL).map(String::as_str),
+                                page_title,
+                                metadata.get_str(KEY_AUTHOR).map(String::as_str),
+                                metadata.get_str(KEY_YEAR).map(String::as_str),
+                                citation.locator.as_deref(),
+                            );
+                            let html = crate::html_flake::html_citation_marker(
+                                &inline_slug,
+                                citation.locator.as_deref(),
+                                &html,
                             );
-                            children.push(SectionContent::Plain(html.to_string()));
+                            let marker = crate::html_flake::html_reference_marker(&inline_slug);
+                            children.push(SectionContent::Plain(html + &marker));
+                            continue;
+                        }
+                        let Some(metadata) = self.get_metadata(cite_slug) else {
+                            let message = format!("citing a non-existent [{}].", cite_slug);
+                            diagnostics.warning(cite_slug, &slug, message);
+                            let raw = format!("[@{}]", citation.key);
+                            children.push(SectionContent::Plain(raw));
+                            continue;
+                        };
+
+                        // registered as a reference like a local link to it.
+                        edges.links.insert(cite_slug.to_string());
+                        if self.is_reference(cite_slug) {
+                            if self.is_listed(cite_slug) {
+                                references.insert(cite_slug.to_string());
+                            }
+                            edges.references.insert(cite_slug.to_string());
                         }
+
+                        let page_title = metadata.page_title().map_or("", |s| s);
+                        let html = crate::html_flake::html_citation(
+                            &config::full_html_url(cite_slug),
+                            &format!("{} [{}]", page_title, cite_slug),
+                            metadata.get_str(KEY_AUTHOR).map(String::as_str),
+                            metadata.get_str(KEY_YEAR).map(String::as_str),
+                            citation.locator.as_deref(),
+                        );
+                        let html = crate::html_flake::html_citation_marker(
+                            cite_slug,
+                            citation.locator.as_deref(),
+                            &html,
+                        );
+                        let marker = match self.is_reference(cite_slug) {
+                            true => crate::html_flake::html_reference_marker(cite_slug),
+                            false => String::new(),
+                        };
+                        children.push(SectionContent::Plain(html + &marker));
                     }
                 }
+            }
+        }
 
-                self.callback.merge(callback);
+        // the inverse of the references, with the same opt-outs as the backlinks.
+        for reference in references.iter().chain(&further_reading) {
+            if *reference != slug::clip_metadata_badge(&slug)
+                && !reference.starts_with('@')
+                && self.is_enable_backlinks(reference)
+                && !self.is_backlink_suppressed(&slug, reference)
+            {
+                let citer = slug::clip_metadata_badge(&slug);
+                callback.insert_citer(reference.to_string(), citer);
             }
-        };
+        }
 
         // compile metadata
-        let mut metadata = EntryMetaData(HashMap::new());
+        let mut metadata = EntryMetaData(HashMap::new(), MetaDataLists::new());
+        let mut metadata_pages = vec![];
         metadata.update(KEY_SLUG.to_string(), slug.to_string());
-        shallow.metadata.keys().for_each(|key| {
+        let mut keys: Vec<&String> = shallow.metadata.keys().collect();
+        keys.sort();
+        for key in keys {
             if key == KEY_SLUG {
-                return;
+                continue;
             }
             let value = shallow.metadata.get(key).unwrap();
-            let spanned: ShallowSection = Self::metadata_to_section(value, &slug);
-            let compiled = self.compile_shallow(spanned);
-            let html = compiled.spanned();
-            metadata.update(key.to_string(), html);
-        });
+            let value = Self::metadata_without_embeds(value, key, &slug, &mut diagnostics);
+            let spanned: ShallowSection = Self::metadata_to_section(&value, &slug);
+            let compiled = self.compile_page(&spanned);
+            metadata.update(key.to_string(), compiled.section.spanned());
+            metadata_pages.push(compiled);
+        }
+        // the items as written, rather than split from the compiled values.
+        metadata.1 = shallow.metadata.1.clone();
+
+        CompiledPage {
+            section: Arc::new(Section {
+                further_reading,
+                ..Section::new(metadata, children, references)
+            }),
+            slug,
+            callback,
+            edges,
+            diagnostics,
+            metadata_pages,
+        }
+    }
+
+    /// The plain text around the local link `lazy_contents[index]`,
+    /// at most [`BACKLINK_SNIPPET_CHARS`] characters on each side.
+    fn backlink_snippet(&self, lazy_contents: &[LazyContent], index: usize) -> String {
+        // whitespaces are collapsed, but kept at both ends to separate the words of the links.
+        let text_of = |lazy_content: &LazyContent| match lazy_content {
+            LazyContent::Plain(html) => {
+                let text = HTMLContent::Plain(html.to_string()).remove_all_tags();
+                let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                let space = |c: Option<char>| match c.is_some_and(char::is_whitespace) {
+                    true => " ",
+                    false => "",
+                };
+                let (first, last) = (text.chars().next(), text.chars().last());
+                Some(format!("{}{}{}", space(first), words, space(last)))
+            }
+            LazyContent::Local(local_link) => Some(local_link.text.clone().unwrap_or_else(|| {
+                self.get_metadata(&local_link.slug)
+                    .and_then(|m| m.page_title())
+                    .map_or(String::new(), |s| s.to_string())
+            })),
+            LazyContent::Cite(citation) => Some(format!("[@{}]", citation.key)),
+            // the snippet does not cross embeds.
+            LazyContent::Embed(_) => None,
+        };
+        let gather = |contents: &mut dyn Iterator<Item = &LazyContent>, rev: bool| {
+            let mut chars: Vec<char> = vec![];
+            for text in contents.map_while(text_of) {
+                match rev {
+                    true => chars.splice(0..0, text.chars()),
+                    false => chars.splice(chars.len().., text.chars()),
+                };
+                if chars.len() > BACKLINK_SNIPPET_CHARS {
+                    break;
+                }
+            }
+            chars
+        };
+
+        let mut before = gather(&mut lazy_contents[..index].iter().rev(), true);
+        if before.len() > BACKLINK_SNIPPET_CHARS {
+            before.drain(..before.len() - BACKLINK_SNIPPET_CHARS);
+            before.insert(0, '…');
+        }
+        let mut after = gather(&mut lazy_contents[index + 1..].iter(), false);
+        if after.len() > BACKLINK_SNIPPET_CHARS {
+            after.truncate(BACKLINK_SNIPPET_CHARS);
+            after.push('…');
+        }
+
+        let link_text = text_of(&lazy_contents[index]).unwrap_or_default();
+        let before: String = before.into_iter().collect();
+        let after: String = after.into_iter().collect();
+        format!("{}{}{}", before, link_text, after)
+            .trim()
+            .to_string()
+    }
+
+    /// Report the link from `referrer` to the non-existent `target`, see [`config::broken_links`],
+    /// pointing to the generated page `reserved` if it is `target` under
+    /// [`config::reserved_prefix`], as it was before.
+    fn report_broken_link(
+        target: &str,
+        reserved: Option<&str>,
+        referrer: &str,
+        diagnostics: &mut Diagnostics,
+    ) {
+        let severity = match config::broken_links() {
+            config::BrokenLinks::Error => Severity::Error,
+            config::BrokenLinks::Warn => Severity::Warning,
+            config::BrokenLinks::Ignore => return,
+        };
+        let referrer = slug::clip_metadata_badge(referrer);
+        let message = match reserved {
+            Some(reserved) => format!(
+                "linking to a non-existent [{}], the generated page is now [{}].",
+                target, reserved
+            ),
+            None => format!("linking to a non-existent [{}].", target),
+        };
+        diagnostics.report(severity, Some(target), Some(&referrer), message);
+    }
+
+    /// The base slug of the pseudo-slug `target` linked or embedded by `referrer`.
+    fn metadata_slug_to_base(
+        target: &str,
+        referrer: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> String {
+        let base_slug = slug::clip_metadata_badge(target);
+        let message = format!(
+            "[{}] is the metadata of [{}], linking to [{}] instead.",
+            target, base_slug, base_slug
+        );
+        diagnostics.warning(target, &slug::clip_metadata_badge(referrer), message);
+        base_slug
+    }
+
+    /// The `child-taxon` of the `parent` page, taken by its embed of `child_slug`
+    /// when neither the page `child_slug` nor the embed has a taxon.
+    fn inherited_taxon<'a>(
+        &self,
+        parent: &'a HTMLMetaData,
+        embed_content: &EmbedContent,
+        child_slug: &str,
+    ) -> Option<&'a str> {
+        let taxon = parent
+            .get_str(KEY_CHILD_TAXON)
+            .map(|taxon| taxon.trim())
+            .filter(|taxon| !taxon.is_empty())?;
+        let has_taxon = self
+            .get_metadata(child_slug)
+            .is_some_and(|metadata| metadata.data_taxon().is_some_and(|t| !t.is_empty()));
+        let overridden = embed_content.metadata_overrides.contains_key(KEY_TAXON);
+        (!has_taxon && !overridden).then_some(taxon)
+    }
+
+    /// The taxa the pages take from their parents, see [`CompileState::inherited_taxon`],
+    /// from the first of them in slug order.
+    fn inherited_taxa(&self) -> BTreeMap<String, String> {
+        let mut pages: Vec<(&String, &ShallowSection)> = self.pages.pending().collect();
+        pages.sort_by_key(|(slug, _)| *slug);
+        let mut taxa = BTreeMap::new();
+        for (_, shallow) in pages {
+            let HTMLContent::Lazy(lazy_contents) = &shallow.content else {
+                continue;
+            };
+            for lazy_content in lazy_contents {
+                let LazyContent::Embed(embed_content) = lazy_content else {
+                    continue;
+                };
+                let child_slug = slug::to_slug(slug::split_anchor(&embed_content.url).0);
+                if let Some(taxon) =
+                    self.inherited_taxon(&shallow.metadata, embed_content, &child_slug)
+                {
+                    taxa.entry(child_slug).or_insert_with(|| taxon.to_string());
+                }
+            }
+        }
+        taxa
+    }
+
+    /// The metadata overrides of `embed_content`, except for `slug`,
+    /// which would change the identity of the embedded page.
+    fn metadata_overrides(
+        embed_content: &EmbedContent,
+        referrer: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> HashMap<String, String> {
+        let mut overrides = embed_content.metadata_overrides.clone();
+        if overrides.remove(KEY_SLUG).is_some() {
+            let child_slug = slug::to_slug(slug::split_anchor(&embed_content.url).0);
+            let message = format!(
+                "the slug of embedded [{}] cannot be overridden.",
+                child_slug
+            );
+            diagnostics.warning(&child_slug, referrer, message);
+        }
+        overrides
+    }
+
+    /// The part of `section` under the heading `anchor`, embedded by `referrer`.
+    /// Falls back to the whole section if there is no such heading.
+    fn slice_section(
+        section: Arc<Section>,
+        anchor: &str,
+        referrer: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> Arc<Section> {
+        let child_slug = section.slug();
+        match section.slice(anchor) {
+            Some(children) => Arc::new(Section {
+                further_reading: section.further_reading.clone(),
+                ..Section::new(
+                    section.metadata.clone(),
+                    children,
+                    section.references.clone(),
+                )
+            }),
+            None => {
+                let message = format!(
+                    "[{}] has no heading `{}`, embedding the whole page instead.",
+                    child_slug, anchor
+                );
+                diagnostics.warning(&child_slug, referrer, message);
+                section
+            }
+        }
+    }
+
+    /// Return the chain `child_slug -> ... -> child_slug` if embedding `child_slug`
+    /// into the section currently being compiled would close a cycle.
+    fn embed_cycle(&self, child_slug: &str) -> Option<Vec<String>> {
+        let start = self.compiling.iter().position(|s| s == child_slug)?;
+        let mut cycle = self.compiling[start..].to_vec();
+        cycle.push(child_slug.to_string());
+        Some(cycle)
+    }
+
+    /// `embed` nested in the sections `chain`, the page first, as a link if it is deeper than
+    /// [`config::max_embed_depth`], else with its embeds that deep linked instead.
+    fn limit_embed_depth(
+        embed: EmbedSection,
+        chain: &[String],
+        diagnostics: &mut Diagnostics,
+    ) -> SectionContent {
+        match config::max_embed_depth() {
+            Some(max_depth) => Self::limited_embed(&embed, chain, max_depth, diagnostics)
+                .unwrap_or(SectionContent::Embed(embed)),
+            None => SectionContent::Embed(embed),
+        }
+    }
+
+    /// See [`CompileState::limit_embed_depth`], `None` if `embed` is not that deep.
+    /// The shared sections are never modified.
+    fn limited_embed(
+        embed: &EmbedSection,
+        chain: &[String],
+        max_depth: usize,
+        diagnostics: &mut Diagnostics,
+    ) -> Option<SectionContent> {
+        let slug = embed.section.slug();
+        if chain.len() > max_depth {
+            let message = format!(
+                "embed depth exceeds {}, [{}] is linked instead: {} -> {}.",
+                max_depth,
+                slug,
+                chain.join(" -> "),
+                slug
+            );
+            diagnostics.warning(&slug, &chain[0], message);
+            return Some(SectionContent::Plain(Self::embed_link(&embed.metadata())));
+        }
+        if embed.section.transitive_embeds.is_empty() {
+            return None;
+        }
+
+        let chain: Vec<String> = chain.iter().cloned().chain([slug]).collect();
+        let mut children = embed.section.children.clone();
+        let mut is_limited = false;
+        for child in children.iter_mut() {
+            if let SectionContent::Embed(embed) = child {
+                if let Some(limited) = Self::limited_embed(embed, &chain, max_depth, diagnostics) {
+                    *child = limited;
+                    is_limited = true;
+                }
+            }
+        }
+        if !is_limited {
+            return None;
+        }
+        let mut section = Section::clone(&embed.section);
+        section.children = children;
+        Some(SectionContent::Embed(EmbedSection {
+            section: Arc::new(section),
+            ..embed.clone()
+        }))
+    }
+
+    /// The link to the section of `metadata`, in place of an embed nested too deep.
+    fn embed_link(metadata: &EntryMetaData) -> String {
+        let slug = metadata.slug().unwrap();
+        let title = metadata.title().map_or("", |s| s);
+        let page_title = metadata.page_title().map_or("", |s| s);
+        crate::html_flake::html_link(
+            &config::full_html_url(slug),
+            &format!("{} [{}]", page_title, slug),
+            title,
+            crate::recorder::State::LocalLink.strify(),
+        )
+    }
 
-        // remove from `self.residued` after compiled.
-        self.residued.remove(&slug);
+    /// A plain local link used in place of an embed that cannot be expanded.
+    fn placeholder_link(&self, child_slug: &str, title: &Option<String>) -> String {
+        let article_title = self
+            .get_metadata(child_slug)
+            .map_or("", |s| s.page_title().map_or("", |s| s));
+        let text = title.as_deref().unwrap_or(article_title);
 
-        let section = Section::new(metadata, children, references);
-        self.compiled.insert(slug.to_string(), section);
-        self.compiled.get(&slug).unwrap()
+        crate::html_flake::html_link(
+            &config::full_html_url(child_slug),
+            &format!("{} [{}]", article_title, child_slug),
+            text,
+            crate::recorder::State::LocalLink.strify(),
+        )
+    }
+
+    /// The `value` of the metadata `key` of `slug` without its embeds,
+    /// which would pull whole pages into the metadata.
+    fn metadata_without_embeds(
+        value: &HTMLContent,
+        key: &str,
+        slug: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> HTMLContent {
+        let HTMLContent::Lazy(lazy_contents) = value else {
+            return value.clone();
+        };
+        let lazy_contents = lazy_contents
+            .iter()
+            .filter(|lazy_content| match lazy_content {
+                LazyContent::Embed(embed_content) => {
+                    let child_slug = slug::to_slug(slug::split_anchor(&embed_content.url).0);
+                    let message = format!(
+                        "the metadata `{}` embeds [{}], but embeds are not allowed in metadata.",
+                        key, child_slug
+                    );
+                    diagnostics.warning(&child_slug, slug, message);
+                    false
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        HTMLContent::Lazy(lazy_contents)
     }
 
     pub fn metadata_to_section(content: &HTMLContent, current_slug: &str) -> ShallowSection {
         let mut metadata = HashMap::new();
         metadata.insert(
             KEY_SLUG.to_string(),
-            HTMLContent::Plain(format!("{}:metadata", current_slug)),
+            HTMLContent::Plain(slug::metadata_slug(current_slug)),
         );
 
         return ShallowSection {
-            metadata: HTMLMetaData(metadata),
+            metadata: HTMLMetaData(metadata, MetaDataLists::new()),
             content: content.clone(),
         };
     }
 
+    /// Pages that are neither reached from the root page nor embedded or linked
+    /// by any other page, excluding those marked as `orphan: true`.
+    pub fn orphans(&self) -> Vec<String> {
+        let referred: HashSet<&String> = self
+            .graph
+            .iter()
+            .flat_map(|(slug, edges)| {
+                edges
+                    .embeds
+                    .iter()
+                    .chain(edges.links.iter())
+                    .filter(move |target| *target != slug)
+            })
+            .collect();
+
+        self.unreached
+            .iter()
+            .filter(|slug| self.is_listed(slug))
+            .filter(|slug| !referred.contains(slug))
+            .filter(|slug| !self.get_metadata(slug).is_some_and(|m| m.is_orphan()))
+            .map(|slug| slug.to_string())
+            .collect()
+    }
+
+    /// Reference pages in the references or the further reading of no compiled page,
+    /// excluding those marked as `ignore-unused: true`.
+    pub fn unused_references(&self) -> Vec<String> {
+        let cited: HashSet<String> = self
+            .pages
+            .compiled()
+            .values()
+            .flat_map(|section| section.references.iter().chain(&section.further_reading))
+            .map(|reference| slug::clip_metadata_badge(reference))
+            .collect();
+        self.metadata
+            .keys()
+            .filter(|slug| !slug::is_metadata_slug(slug) && self.is_reference(slug))
+            .filter(|slug| !cited.contains(*slug))
+            .filter(|slug| {
+                !self
+                    .get_metadata(slug)
+                    .is_some_and(|m| m.is_ignore_unused())
+            })
+            .map(|slug| slug.to_string())
+            .collect()
+    }
+
+    /// The outgoing embeds, links and references of every compiled page.
+    pub fn dependency_graph(&self) -> &BTreeMap<String, PageEdges> {
+        &self.graph
+    }
+
     pub fn get_metadata(&self, slug: &str) -> Option<&HTMLMetaData> {
         self.metadata.get(slug)
     }
@@ -198,6 +2807,166 @@ impl CompileState {
             .unwrap_or(true)
     }
 
+    /// `slug` followed by the chain of pages embedding it, each being the first context of
+    /// the previous one by slug. Stops before a page already in the chain.
+    pub fn context_path(&self, slug: &str) -> Vec<String> {
+        let mut path = vec![slug.to_string()];
+        let mut visited = HashSet::from([slug.to_string()]);
+        while let Some(parent) = self
+            .callback
+            .0
+            .get(path.last().unwrap())
+            .and_then(|callback| callback.contexts.first())
+            .filter(|parent| visited.insert(parent.to_string()))
+        {
+            path.push(parent.to_string());
+        }
+        path
+    }
+
+    /// The pages of `slugs`, with the `:metadata` badges clipped, in [`config::backlinks_order`].
+    pub fn sorted_pages(&self, slugs: &BTreeSet<String>) -> Vec<String> {
+        let slugs: BTreeSet<String> = slugs
+            .iter()
+            .map(|slug| crate::slug::clip_metadata_badge(slug))
+            .collect();
+        let mut slugs: Vec<String> = slugs.into_iter().collect();
+
+        let metadata = |slug: &str| self.pages.get(slug).map(|s| &s.metadata);
+        let title = |slug: &str| metadata(slug).and_then(|m| m.page_title()).cloned();
+        let date = |slug: &str| metadata(slug).and_then(|m| m.parsed_date());
+        match config::backlinks_order() {
+            BacklinksOrder::Title => slugs.sort_by_cached_key(|slug| title(slug)),
+            BacklinksOrder::DateAsc => {
+                slugs.sort_by_cached_key(|slug| (date(slug).is_none(), date(slug), title(slug)))
+            }
+            BacklinksOrder::DateDesc => slugs.sort_by_cached_key(|slug| {
+                (date(slug).is_none(), Reverse(date(slug)), title(slug))
+            }),
+        }
+        slugs
+    }
+
+    /// The pages of `slugs` grouped by their `data-taxon`, keeping their order within each
+    /// group. The groups follow [`config::taxon_order`], then the other taxa by name,
+    /// then the pages without a taxon.
+    pub fn taxon_groups(&self, slugs: &[String]) -> Vec<(Option<String>, Vec<String>)> {
+        let order = config::taxon_order();
+        let priority = |taxon: &Option<String>| match taxon {
+            None => (order.len() + 1, String::new()),
+            Some(taxon) => match order.iter().position(|t| t.eq_ignore_ascii_case(taxon)) {
+                Some(index) => (index, String::new()),
+                None => (order.len(), taxon.to_string()),
+            },
+        };
+
+        let mut groups: BTreeMap<(usize, String), (Option<String>, Vec<String>)> = BTreeMap::new();
+        for slug in slugs {
+            let taxon = self
+                .get_metadata(slug)
+                .and_then(|m| m.data_taxon())
+                .filter(|t| !t.is_empty())
+                .cloned();
+            let group = groups.entry(priority(&taxon)).or_insert((taxon, vec![]));
+            group.1.push(slug.to_string());
+        }
+        groups.into_values().collect()
+    }
+
+    /// The pages linking to `slug` and listed in its backlinks,
+    /// which leave out the pages embedding it, see [`CompileState::sorted_pages`].
+    pub fn listed_backlinks(&self, slug: &str) -> Vec<String> {
+        match self.callback.0.get(slug) {
+            None => vec![],
+            Some(callback) => self
+                .sorted_pages(&callback.backlinks)
+                .into_iter()
+                .filter(|slug| !callback.contexts.contains(slug))
+                .collect(),
+        }
+    }
+
+    /// The pages citing the reference `slug`, in [`config::backlinks_order`], each
+    /// citation listed once by the outermost page showing it: a page embedded by
+    /// another citing page is left out. The root page, embedding most pages, is only
+    /// listed when it cites `slug` itself, and the generated pages are never listed.
+    pub fn cited_by(&self, slug: &str) -> Vec<String> {
+        let Some(callback) = self.callback.0.get(slug) else {
+            return vec![];
+        };
+        let citers = &callback.citers;
+        let root_slug = config::root_slug();
+        let generated = self.generated_slugs();
+        let is_cited_by_context = |citer: &str| {
+            self.callback.0.get(citer).is_some_and(|callback| {
+                callback
+                    .contexts
+                    .iter()
+                    .any(|context| *context != root_slug && citers.contains(context))
+            })
+        };
+        let cites_itself = |citer: &str| {
+            self.graph
+                .get(citer)
+                .is_some_and(|edges| edges.references.contains(slug))
+        };
+        let shown: BTreeSet<String> = citers
+            .iter()
+            .filter(|citer| !generated.contains(citer))
+            .filter(|citer| match **citer == root_slug {
+                true => cites_itself(citer),
+                false => !is_cited_by_context(citer),
+            })
+            .cloned()
+            .collect();
+        self.sorted_pages(&shown)
+    }
+
+    /// Whether the page `source`, or the page of the metadata `source`,
+    /// keeps itself out of the backlinks of `target`.
+    pub fn is_backlink_suppressed(&self, source: &str, target: &str) -> bool {
+        self.metadata
+            .get(&slug::clip_metadata_badge(source))
+            .map(|e| e.is_backlink_suppressed(target))
+            .unwrap_or(false)
+    }
+
+    pub fn is_enable_related(&self, slug: &str) -> bool {
+        self.metadata
+            .get(slug)
+            .map(|e| e.is_enable_related())
+            .unwrap_or(true)
+    }
+
+    /// Whether `slug` may be listed among other pages: in the generated pages, the orphans,
+    /// the backlinks, the references and the related pages. The drafts, the stubs, the
+    /// metadata and the pages marked `unlisted: true` or `link: false` are still reachable
+    /// by their links, but never listed.
+    pub fn is_listed(&self, slug: &str) -> bool {
+        if slug::is_metadata_slug(slug) {
+            return false;
+        }
+        self.metadata.get(slug).is_none_or(|metadata| {
+            !(metadata.is_unlisted()
+                || !metadata.is_enable_link()
+                || metadata.is_draft()
+                || metadata.is_stub())
+        })
+    }
+
+    /// Whether the page `slug` may be listed by the search engines and the search of the site:
+    /// a page [`CompileState::is_listed`] without `noindex` in its `robots`.
+    pub fn is_indexed(&self, slug: &str) -> bool {
+        self.is_listed(slug) && self.metadata.get(slug).is_none_or(|m| !m.is_noindex())
+    }
+
+    pub fn is_enable_embed(&self, slug: &str) -> bool {
+        self.metadata
+            .get(slug)
+            .map(|e| e.is_enable_embed())
+            .unwrap_or(true)
+    }
+
     pub fn is_reference(&self, slug: &str) -> bool {
         self.metadata
             .get(slug)
@@ -205,3 +2974,307 @@ impl CompileState {
             .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{test_forest, writer::Writer, CompiledForest};
+
+    fn page(title: &str, body: &str) -> String {
+        format!("---\ntitle: {}\n---\n\n{}\n", title, body)
+    }
+
+    #[test]
+    fn embeds_deeper_than_max_depth_are_linked() {
+        let mut config = config::test_config("kodama-tests");
+        config.max_embed_depth = Some(1);
+        let index = page("Index", "[](/a.md#:embed)");
+        let a = page("Alpha", "Alpha body.\n\n[](/b.md#:embed)");
+        let b = page("Beta", "Beta body.");
+        let (forest, _guard) = test_forest(&[("index", &index), ("a", &a), ("b", &b)], config);
+
+        let html = |slug: &str| Writer::html_doc(&forest.compiled()[slug], &forest.state).0;
+        assert!(html("index").contains("Alpha body."));
+        assert!(!html("index").contains("Beta body."));
+        assert!(html("index").contains(r#"href="/b""#));
+        assert!(html("a").contains("Beta body."));
+
+        let warnings: Vec<&String> = forest
+            .diagnostics()
+            .iter()
+            .filter(|d| d.message.starts_with("embed depth exceeds"))
+            .map(|d| &d.message)
+            .collect();
+        assert_eq!(
+            warnings,
+            ["embed depth exceeds 1, [b] is linked instead: index -> a -> b."]
+        );
+    }
+
+    #[test]
+    fn pages_without_link_are_not_listed() {
+        let mut config = config::test_config("kodama-tests");
+        config.tag_pages = Some(config::DEFAULT_TAG_PAGES_PREFIX.to_string());
+        let index = page("Index", "[](a.md) and [](b.md).");
+        let a = "---\ntitle: Alpha\ntags: t\n---\n\nSee [](/index.md).\n";
+        let b = "---\ntitle: Beta\ntags: t\nlink: false\n---\n\nSee [](/index.md).\n";
+        let (forest, _guard) = test_forest(&[("index", &index), ("a", a), ("b", b)], config);
+
+        let state = &forest.state;
+        assert!(state.is_listed("a"));
+        assert!(!state.is_listed("b"));
+        let tag_slug = config::prefixed_slug(config::DEFAULT_TAG_PAGES_PREFIX, "t");
+        let tag_page = Writer::html_doc(&forest.compiled()[&tag_slug], state).0;
+        assert!(tag_page.contains("Alpha"));
+        assert!(!tag_page.contains("Beta"));
+        let backlinks = state.listed_backlinks("index");
+        assert!(backlinks.iter().any(|backlink| backlink == "a"));
+        assert!(!backlinks.iter().any(|backlink| backlink == "b"));
+    }
+
+    fn draft_forest(drafts: bool) -> (CompiledForest, std::sync::MutexGuard<'static, ()>) {
+        let mut config = config::test_config("kodama-tests");
+        config.drafts = drafts;
+        let index = page(
+            "Index",
+            "Intro.\n\n[](/secret.md#:embed)\n\nSee [](secret.md).",
+        );
+        let secret = "---\ntitle: Secret Draft\ndraft: true\n---\n\nSecret body.\n";
+        test_forest(&[("index", &index), ("secret", secret)], config)
+    }
+
+    #[test]
+    fn drafts_are_not_published() {
+        let (forest, _guard) = draft_forest(false);
+
+        assert!(!forest.slugs.contains(&"secret".to_string()));
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(!html.contains("Secret"));
+        assert!(!html.contains(r#"href="/secret""#));
+        assert!(html.contains("secret"));
+    }
+
+    #[test]
+    fn drafts_are_built_with_drafts() {
+        let (forest, _guard) = draft_forest(true);
+
+        assert!(forest.slugs.contains(&"secret".to_string()));
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(html.contains("Secret body."));
+        let html = Writer::html_doc(&forest.compiled()["secret"], &forest.state).0;
+        assert!(html.contains(&crate::html_flake::html_draft_banner()));
+    }
+
+    fn warnings(forest: &CompiledForest, prefix: &str) -> Vec<String> {
+        forest
+            .diagnostics()
+            .iter()
+            .filter(|d| d.severity == Severity::Warning && d.message.starts_with(prefix))
+            .map(|d| d.message.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn mutual_embeds_are_a_cycle() {
+        let config = config::test_config("kodama-tests");
+        let index = page("Index", "[](/a.md#:embed)");
+        let a = page("Alpha", "Alpha body.\n\n[](/b.md#:embed)");
+        let b = page("Beta", "Beta body.\n\n[](/a.md#:embed)");
+        let (forest, _guard) = test_forest(&[("index", &index), ("a", &a), ("b", &b)], config);
+
+        let cycles = warnings(&forest, "embed cycle detected");
+        assert_eq!(cycles, ["embed cycle detected: a -> b -> a."]);
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(html.contains("Alpha body.") && html.contains("Beta body."));
+        assert!(html.contains(r#"href="/a""#));
+    }
+
+    #[test]
+    fn self_embed_is_a_cycle() {
+        let config = config::test_config("kodama-tests");
+        let index = page("Index", "Index body.\n\n[](/index.md#:embed)");
+        let (forest, _guard) = test_forest(&[("index", &index)], config);
+
+        let cycles = warnings(&forest, "embed cycle detected");
+        assert_eq!(cycles, ["embed cycle detected: index -> index."]);
+        let index = &forest.compiled()["index"];
+        assert!(!(index.children.iter()).any(|child| matches!(child, SectionContent::Embed(_))));
+    }
+
+    #[test]
+    fn embed_overrides_stay_in_the_embed() {
+        let config = config::test_config("kodama-tests");
+        let overrides = "taxon: lemma; author: Ann; date: 2024-01-02; slug: other";
+        let index = page("Index", &format!("[](/b.md#:embed \"{}\")", overrides));
+        let b = "---\ntitle: Beta\ntaxon: definition\nauthor: Bob\ndate: 2023-05-06\n---\n\nBeta body.\n";
+        let (forest, _guard) = test_forest(&[("index", &index), ("b", b)], config);
+
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(html.contains("Lemma.") && !html.contains("Definition."));
+        assert!(html.contains("Ann") && !html.contains("Bob"));
+        assert!(html.contains("2024") && !html.contains("2023"));
+
+        let html = Writer::html_doc(&forest.compiled()["b"], &forest.state).0;
+        assert!(html.contains("Definition.") && !html.contains("Lemma."));
+        assert!(html.contains("Bob") && !html.contains("Ann"));
+        assert!(html.contains("2023") && !html.contains("2024"));
+        assert_eq!(forest.compiled()["b"].slug(), "b");
+
+        let warnings = warnings(&forest, "the slug of embedded");
+        assert_eq!(warnings, ["the slug of embedded [b] cannot be overridden."]);
+    }
+
+    #[test]
+    fn sources_are_the_transitive_embeds() {
+        let mut config = config::test_config("kodama-tests");
+        config.emit_sources = true;
+        let index = page("Index", "[](/a.md#:embed)\n\nSee [](c.md).");
+        let a = page("Alpha", "[](/b.md#:embed)");
+        let b = page("Beta", "Beta body.");
+        let c = page("Charlie", "Charlie body.");
+        let sources = [("index", &*index), ("a", &*a), ("b", &*b), ("c", &*c)];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        let compiled = forest.compiled();
+        assert_eq!(compiled["index"].sources(), ["index", "a", "b"]);
+        assert_eq!(compiled["a"].sources(), ["a", "b"]);
+        assert_eq!(compiled["b"].sources(), ["b"]);
+        let html = Writer::html_doc(&compiled["index"], &forest.state).0;
+        assert!(html.contains("<!-- sources: index, a, b -->"));
+    }
+
+    #[test]
+    fn suppressed_backlinks_are_left_out() {
+        let config = config::test_config("kodama-tests");
+        let index = page("Index", "See [](a.md) and [](b.md).");
+        let a = page("Alpha", "Alpha body.");
+        let b = page("Beta", "Beta body.");
+        let c = "---\ntitle: Charlie\nbacklinks-suppress: b\n---\n\n[](a.md), [](b.md).\n";
+        let d = "---\ntitle: Delta\nbacklinks-suppress: true\n---\n\n[](a.md), [](b.md).\n";
+        let sources = [
+            ("index", &*index),
+            ("a", &*a),
+            ("b", &*b),
+            ("c", c),
+            ("d", d),
+        ];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        let backlinks = |slug: &str| forest.state.callback.0[slug].backlinks.clone();
+        assert_eq!(
+            backlinks("a"),
+            BTreeSet::from(["c", "index"].map(String::from))
+        );
+        assert_eq!(backlinks("b"), BTreeSet::from(["index".to_string()]));
+    }
+
+    fn expiry_forest(expired: Expired) -> (CompiledForest, std::sync::MutexGuard<'static, ()>) {
+        let mut config = config::test_config("kodama-tests");
+        config.now = Date::parse("2026-06-01T12:00");
+        config.expired = expired;
+        let index = page("Index", "See [](a.md), [](b.md) and [](c.md).");
+        let a = "---\ntitle: Alpha\nexpires: 2026-05-31\n---\n\nAlpha body.\n";
+        let b = "---\ntitle: Beta\nexpires: 2026-06-01\n---\n\nBeta body.\n";
+        let c = "---\ntitle: Charlie\nexpires: 2026-06-01T00:00\n---\n\nCharlie body.\n";
+        test_forest(&[("index", &index), ("a", a), ("b", b), ("c", c)], config)
+    }
+
+    #[test]
+    fn expired_pages_are_archived() {
+        let (forest, _guard) = expiry_forest(Expired::Archive);
+
+        assert_eq!(
+            forest.state.expired,
+            BTreeSet::from(["a", "c"].map(String::from))
+        );
+        let html = |slug: &str| Writer::html_doc(&forest.compiled()[slug], &forest.state).0;
+        assert!(html("a").contains("Archived: this page expired on 2026-05-31."));
+        assert!(!html("b").contains("Archived: this page expired"));
+        assert!(html("c").contains("Archived: this page expired"));
+        assert!(html("index").contains(r#"href="/a""#));
+    }
+
+    #[test]
+    fn expired_pages_are_unpublished() {
+        let (forest, _guard) = expiry_forest(Expired::Unpublish);
+
+        assert!(!forest.compiled().contains_key("a"));
+        assert!(forest.compiled().contains_key("b"));
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(!html.contains(r#"href="/a""#));
+        assert!(html.contains(r#"href="/b""#));
+        let message = "[a] is expired, so it is not linked to.";
+        assert!(forest.diagnostics().iter().any(|d| d.message == message));
+    }
+
+    /// The source pages linked by the generated page `slug`, in order.
+    fn listed(forest: &CompiledForest, slug: &str) -> Vec<String> {
+        let html = Writer::html_doc(&forest.compiled()[slug], &forest.state).0;
+        html.split(r#"href="/"#)
+            .skip(1)
+            .map(|s| s.split('"').next().unwrap().to_string())
+            .filter(|slug| !slug.is_empty() && !slug::is_reserved_slug(slug))
+            .collect()
+    }
+
+    #[test]
+    fn listings_are_sorted_by_order_first() {
+        let mut config = config::test_config("kodama-tests");
+        config.tag_pages = Some("tags".to_string());
+        let tagged = |title: &str, extra: &str| {
+            format!("---\ntitle: {}\ntags: rust\n{}---\n\nBody.\n", title, extra)
+        };
+        let index = page("Index", "Index body.");
+        let a = tagged("Alpha", "date: 2024-01-01\n");
+        let b = tagged("Beta", "date: 2024-06-01\n");
+        let c = tagged("Charlie", "order: 0.5\n");
+        let d = tagged("Delta", "order: -1\ndate: 2020-01-01\n");
+        let e = tagged("Echo", "order: soon\ndate: 2025-01-01\n");
+        let sources = [
+            ("index", &*index),
+            ("a", &*a),
+            ("b", &*b),
+            ("c", &*c),
+            ("d", &*d),
+            ("e", &*e),
+        ];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        assert_eq!(listed(&forest, "-/tags/rust"), ["d", "c", "e", "b", "a"]);
+        assert_eq!(
+            warnings(&forest, "[e]"),
+            ["[e] has `order: soon`, which is not a number, so it is listed after the ordered pages."]
+        );
+    }
+
+    #[test]
+    fn pinned_pages_lead_the_listings() {
+        let mut config = config::test_config("kodama-tests");
+        config.tag_pages = Some("tags".to_string());
+        let tagged = |title: &str, extra: &str| {
+            format!("---\ntitle: {}\ntags: rust\n{}---\n\nBody.\n", title, extra)
+        };
+        let index = page("Index", "[](/-/featured#:embed)");
+        let a = tagged("Alpha", "pinned: true\nfeatured: true\ndate: 2024-01-01\n");
+        let b = tagged("Beta", "pinned: true\ndate: 2024-06-01\n");
+        let c = tagged("Charlie", "order: 1\nfeatured: true\n");
+        let d = tagged("Delta", "date: 2025-01-01\nfeatured: true\n");
+        let e = tagged("Echo", "pinned: true\nfeatured: true\ndraft: true\n");
+        let f = tagged("Foxtrot", "pinned: true\nfeatured: true\nunlisted: true\n");
+        let sources = [
+            ("index", &*index),
+            ("a", &*a),
+            ("b", &*b),
+            ("c", &*c),
+            ("d", &*d),
+            ("e", &*e),
+            ("f", &*f),
+        ];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        assert_eq!(listed(&forest, "-/tags/rust"), ["b", "a", "c", "d"]);
+        assert_eq!(listed(&forest, &featured_slug()), ["a", "c", "d"]);
+        let html = Writer::html_doc(&forest.compiled()["-/tags/rust"], &forest.state).0;
+        assert_eq!(html.matches(&crate::html_flake::html_pin()).count(), 2);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    sync::Arc,
+};
+
+use super::section::{Section, ShallowSection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageStatus {
+    /// Parsed but not compiled yet.
+    Pending,
+    Compiled,
+
+    /// Neither parsed nor compiled, e.g. the target of a broken link.
+    Missing,
+}
+
+/// The pages of a compilation by slug, each of which is either pending or compiled.
+/// Iteration is ordered by slug.
+#[derive(Debug, Default)]
+pub struct PageStore {
+    pending: BTreeMap<String, ShallowSection>,
+    compiled: BTreeMap<String, Arc<Section>>,
+}
+
+impl PageStore {
+    pub fn new() -> PageStore {
+        PageStore::default()
+    }
+
+    pub fn status(&self, slug: &str) -> PageStatus {
+        if self.compiled.contains_key(slug) {
+            PageStatus::Compiled
+        } else if self.pending.contains_key(slug) {
+            PageStatus::Pending
+        } else {
+            PageStatus::Missing
+        }
+    }
+
+    /// Whether `slug` is pending or compiled.
+    pub fn contains(&self, slug: &str) -> bool {
+        self.status(slug) != PageStatus::Missing
+    }
+
+    pub fn is_pending(&self, slug: &str) -> bool {
+        self.pending.contains_key(slug)
+    }
+
+    /// Add a parsed page, replacing the pending page of the same slug if any.
+    pub fn insert_pending(&mut self, slug: String, shallow: ShallowSection) {
+        self.pending.insert(slug, shallow);
+    }
+
+    pub fn get_pending(&self, slug: &str) -> Option<&ShallowSection> {
+        self.pending.get(slug)
+    }
+
+    /// Remove the pending page `slug` to compile it.
+    /// It stays [`PageStatus::Missing`] until [`PageStore::insert_compiled`].
+    pub fn take_pending(&mut self, slug: &str) -> Option<ShallowSection> {
+        self.pending.remove(slug)
+    }
+
+    /// Record the compiled `section` of `slug`, which is no longer pending.
+    pub fn insert_compiled(&mut self, slug: String, section: Arc<Section>) {
+        self.pending.remove(&slug);
+        self.compiled.insert(slug, section);
+    }
+
+    pub fn get(&self, slug: &str) -> Option<&Arc<Section>> {
+        self.compiled.get(slug)
+    }
+
+    pub fn pending(&self) -> btree_map::Iter<'_, String, ShallowSection> {
+        self.pending.iter()
+    }
+
+    pub fn pending_mut(&mut self) -> btree_map::IterMut<'_, String, ShallowSection> {
+        self.pending.iter_mut()
+    }
+
+    /// The slugs of the pending pages.
+    pub fn pending_slugs(&self) -> Vec<String> {
+        self.pending.keys().cloned().collect()
+    }
+
+    /// The compiled sections by slug.
+    pub fn compiled(&self) -> &BTreeMap<String, Arc<Section>> {
+        &self.compiled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use indexmap::IndexSet;
+
+    use super::*;
+    use crate::{
+        compiler::section::HTMLContent,
+        entry::{EntryMetaData, HTMLMetaData, MetaDataLists, KEY_SLUG},
+    };
+
+    fn shallow(slug: &str) -> ShallowSection {
+        let mut metadata = HTMLMetaData::default();
+        let slug = HTMLContent::Plain(slug.to_string());
+        metadata.0.insert(KEY_SLUG.to_string(), slug);
+        ShallowSection {
+            metadata,
+            content: HTMLContent::Plain(String::new()),
+        }
+    }
+
+    fn section(slug: &str) -> Arc<Section> {
+        let mut metadata = EntryMetaData(HashMap::new(), MetaDataLists::new());
+        metadata.0.insert(KEY_SLUG.to_string(), slug.to_string());
+        Arc::new(Section::new(metadata, vec![], IndexSet::new()))
+    }
+
+    #[test]
+    fn pending_then_compiled() {
+        let mut pages = PageStore::new();
+        assert_eq!(pages.status("a"), PageStatus::Missing);
+
+        pages.insert_pending("a".to_string(), shallow("a"));
+        assert_eq!(pages.status("a"), PageStatus::Pending);
+        assert!(pages.contains("a") && pages.is_pending("a"));
+
+        let taken = pages.take_pending("a").unwrap();
+        assert_eq!(taken.slug(), "a");
+        assert_eq!(pages.status("a"), PageStatus::Missing);
+
+        pages.insert_compiled("a".to_string(), section("a"));
+        assert_eq!(pages.status("a"), PageStatus::Compiled);
+        assert!(pages.contains("a") && !pages.is_pending("a"));
+        assert_eq!(pages.get("a").unwrap().slug(), "a");
+    }
+
+    #[test]
+    fn pending_is_removed_once() {
+        let mut pages = PageStore::new();
+        pages.insert_pending("a".to_string(), shallow("a"));
+
+        assert!(pages.take_pending("a").is_some());
+        assert!(pages.take_pending("a").is_none());
+        // removing the page taken already when it is compiled is harmless.
+        pages.insert_compiled("a".to_string(), section("a"));
+        assert_eq!(pages.status("a"), PageStatus::Compiled);
+        assert!(pages.pending_slugs().is_empty());
+    }
+
+    #[test]
+    fn compiled_without_taking_is_no_longer_pending() {
+        let mut pages = PageStore::new();
+        pages.insert_pending("a".to_string(), shallow("a"));
+        pages.insert_compiled("a".to_string(), section("a"));
+
+        assert_eq!(pages.status("a"), PageStatus::Compiled);
+        assert!(pages.get_pending("a").is_none());
+        assert!(pages.take_pending("a").is_none());
+    }
+
+    #[test]
+    fn slugs_are_ordered() {
+        let mut pages = PageStore::new();
+        for slug in ["c", "a", "b"] {
+            pages.insert_pending(slug.to_string(), shallow(slug));
+        }
+        assert_eq!(pages.pending_slugs(), ["a", "b", "c"]);
+
+        for slug in ["b", "a"] {
+            pages.take_pending(slug);
+            pages.insert_compiled(slug.to_string(), section(slug));
+        }
+        let compiled: Vec<&String> = pages.compiled().keys().collect();
+        assert_eq!(compiled, ["a", "b"]);
+        assert_eq!(pages.pending_slugs(), ["c"]);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Taxon {
     pub numbering: Option<String>,
@@ -30,8 +32,40 @@ impl Taxon {
         }
     }
 
-    pub fn is_reference(s: &str) -> bool {
-        s.to_lowercase().starts_with("reference.") || s.starts_with("参考")
+    /// Whether `data_taxon` is declared as a reference, see [`config::taxon_entry`].
+    pub fn is_reference(data_taxon: &str) -> bool {
+        config::taxon_entry(data_taxon).is_some_and(|entry| entry.reference)
+    }
+
+    /// Whether the embeds of `data_taxon` are always numbered.
+    pub fn is_numbered(data_taxon: &str) -> bool {
+        config::taxon_entry(data_taxon).is_some_and(|entry| entry.numbering)
+    }
+
+    /// The English plural of `s`, or `s` itself if it does not end with a Latin letter.
+    pub fn plural(s: &str) -> String {
+        let ends_with = |suffixes: &[&str]| suffixes.iter().any(|suffix| s.ends_with(suffix));
+        if !s.ends_with(|c: char| c.is_ascii_alphabetic()) {
+            s.to_string()
+        } else if ends_with(&["s", "x", "z", "ch", "sh"]) {
+            format!("{}es", s)
+        } else if s.ends_with('y') && !ends_with(&["ay", "ey", "oy", "uy"]) {
+            format!("{}ies", &s[..s.len() - 1])
+        } else {
+            format!("{}s", s)
+        }
+    }
+
+    /// The canonical id of the taxon shown as `data_taxon`, see [`config::taxon_id`],
+    /// in lowercase with runs of other characters than letters and digits as `-`,
+    /// such as `worked-example` for `Worked Example`, so that it can be a class name.
+    pub fn class_name(data_taxon: &str) -> String {
+        let id = config::taxon_id(data_taxon).to_lowercase();
+        let words: Vec<&str> = id
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        words.join("-")
     }
 
     pub fn to_data_taxon(taxon_display: &str) -> &str {
@@ -41,3 +75,47 @@ impl Taxon {
         &taxon_display[0..dot]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        compiler::{test_forest, writer::Writer},
+        config::TaxonEntry,
+    };
+
+    #[test]
+    fn class_names_are_sanitized_ids() {
+        let mut config = config::test_config("kodama-tests");
+        let remark = TaxonEntry {
+            names: BTreeMap::from([("zh".to_string(), "注记".to_string())]),
+            ..TaxonEntry::default()
+        };
+        config.taxa.insert("remark".to_string(), remark);
+        let _guard = config::lock_test_config(config);
+
+        assert_eq!(Taxon::class_name("Definition"), "definition");
+        assert_eq!(Taxon::class_name("注记"), "remark");
+        assert_eq!(Taxon::class_name(" Worked  Example "), "worked-example");
+        assert_eq!(Taxon::class_name("a\"><script>x"), "a-script-x");
+        assert_eq!(Taxon::class_name("C++ / Rust"), "c-rust");
+        assert_eq!(Taxon::class_name("!?"), "");
+    }
+
+    #[test]
+    fn embeds_are_classed_by_taxon() {
+        let config = config::test_config("kodama-tests");
+        let index = "---\ntitle: Index\n---\n\n[Custom title](/a.md#:embed)\n";
+        let a = "---\ntitle: Alpha\ntaxon: Definition\n---\n\n[](/b.md#:embed)\n";
+        let b = "---\ntitle: Beta\ntaxon: Lemma\n---\n\nBeta body.\n";
+        let (forest, _guard) = test_forest(&[("index", index), ("a", a), ("b", b)], config);
+
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        assert!(html.contains("Custom title"));
+        let classed = |id: &str| format!(r#"taxon-{}" data-taxon="{}">"#, id, id);
+        assert!(html.contains(&classed("definition")));
+        assert!(html.contains(&classed("lemma")));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Parse,
+    Compile,
+    Write,
+}
+
+/// Wall-clock time of one page, in milliseconds.
+/// Compile time excludes the pages it embeds, which are timed on their own.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PageTiming {
+    pub parse: f64,
+    pub compile: f64,
+    pub write: f64,
+}
+
+impl PageTiming {
+    pub fn total(&self) -> f64 {
+        self.parse + self.compile + self.write
+    }
+
+    fn phase_mut(&mut self, phase: Phase) -> &mut f64 {
+        match phase {
+            Phase::Parse => &mut self.parse,
+            Phase::Compile => &mut self.compile,
+            Phase::Write => &mut self.write,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimingReport {
+    /// Wall-clock time of each phase, in milliseconds.
+    pub phases: PageTiming,
+    pub pages: BTreeMap<String, PageTiming>,
+}
+
+/// Timings collected from several threads, only present if `--timings` is given.
+#[derive(Debug, Default)]
+pub struct Timings(Mutex<TimingReport>);
+
+impl Timings {
+    pub fn new() -> Timings {
+        Timings(Mutex::new(TimingReport::default()))
+    }
+
+    /// The start of a measurement, `None` if `timings` is disabled.
+    pub fn start(timings: &Option<Timings>) -> Option<Instant> {
+        timings.as_ref().map(|_| Instant::now())
+    }
+
+    /// Record the time since `start` as the `phase` of `slug`,
+    /// or as the total of `phase` if `slug` is `None`.
+    pub fn stop(
+        timings: &Option<Timings>,
+        start: Option<Instant>,
+        phase: Phase,
+        slug: Option<&str>,
+    ) {
+        if let (Some(timings), Some(start)) = (timings, start) {
+            timings.record(phase, slug, start.elapsed());
+        }
+    }
+
+    fn record(&self, phase: Phase, slug: Option<&str>, duration: Duration) {
+        let millis = duration.as_secs_f64() * 1000.0;
+        let mut report = self.0.lock().unwrap();
+        let timing = match slug {
+            None => &mut report.phases,
+            Some(slug) => report.pages.entry(slug.to_string()).or_default(),
+        };
+        *timing.phase_mut(phase) += millis;
+    }
+
+    pub fn report(&self) -> TimingReport {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl TimingReport {
+    /// The phase totals and the `top` slowest pages.
+    pub fn display(&self, top: usize) -> String {
+        let phases = &self.phases;
+        let mut lines = vec![format!(
+            "Timings: parse {:.1} ms, compile {:.1} ms, write {:.1} ms",
+            phases.parse, phases.compile, phases.write
+        )];
+
+        let mut pages: Vec<(&String, &PageTiming)> = self.pages.iter().collect();
+        pages.sort_by(|a, b| b.1.total().total_cmp(&a.1.total()).then(a.0.cmp(b.0)));
+        for (slug, timing) in pages.into_iter().take(top) {
+            lines.push(format!(
+                "  {:>8.1} ms  {} (parse {:.1}, compile {:.1}, write {:.1})",
+                timing.total(),
+                slug,
+                timing.parse,
+                timing.compile,
+                timing.write
+            ));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,82 @@
+//! The same page in other languages, written as its `translations`: each of them lists
+//! the others, and they are linked above the page and by `hreflang` from its head.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    config,
+    entry::{HTMLMetaData, MetaData, KEY_LANG},
+    slug,
+};
+
+use super::{diagnostics::Severity, state::CompileState};
+
+/// Warn of the `translations` of the pending pages which are not pages, which do not list
+/// the page back, or which are in the same `lang` as the page.
+pub fn check_translations(state: &mut CompileState) {
+    let pending: BTreeMap<&String, &HTMLMetaData> = state
+        .pages
+        .pending()
+        .map(|(slug, shallow)| (slug, &shallow.metadata))
+        .collect();
+    let mut warnings = vec![];
+    for (&slug, &metadata) in &pending {
+        for translation in translation_slugs(metadata, &state.aliases) {
+            let Some(&other) = pending.get(&translation) else {
+                let message = format!(
+                    "[{}] lists [{}] as a translation, which is not a page.",
+                    slug, translation
+                );
+                warnings.push((slug.to_string(), message));
+                continue;
+            };
+            if !translation_slugs(other, &state.aliases).contains(slug) {
+                let message = format!(
+                    "[{}] lists [{}] as a translation, but [{}] does not list [{}].",
+                    slug, translation, translation, slug
+                );
+                warnings.push((slug.to_string(), message));
+            }
+            let lang = page_lang(metadata);
+            if lang.is_some() && lang == page_lang(other) {
+                let message = format!(
+                    "[{}] and its translation [{}] are both in `{}`.",
+                    slug,
+                    translation,
+                    lang.unwrap_or_default()
+                );
+                warnings.push((slug.to_string(), message));
+            }
+        }
+    }
+    for (slug, message) in warnings {
+        state
+            .diagnostics
+            .report(Severity::Warning, Some(&slug), None, message);
+    }
+}
+
+/// The slugs of the `translations` in `metadata`, an alias taken as its page,
+/// see [`CompileState::aliases`].
+pub fn translation_slugs<V: Clone>(
+    metadata: &impl MetaData<V>,
+    aliases: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut slugs: Vec<String> = vec![];
+    for translation in metadata.translations() {
+        let translation = slug::to_slug(&translation);
+        let translation = aliases.get(&translation).cloned().unwrap_or(translation);
+        if !slugs.contains(&translation) {
+            slugs.push(translation);
+        }
+    }
+    slugs
+}
+
+/// The `lang` of the page of `metadata`, or else [`config::default_lang`].
+pub fn page_lang<V: Clone>(metadata: &impl MetaData<V>) -> Option<String> {
+    (metadata
+        .get_str(KEY_LANG)
+        .map(|lang| lang.trim().to_string()))
+    .or_else(config::default_lang)
+}
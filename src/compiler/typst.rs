@@ -2,12 +2,12 @@ use super::html_parser::{HTMLParser, HTMLTagKind};
 use super::section::{EmbedContent, LocalLink, SectionOption};
 use super::section::{HTMLContent, HTMLContentBuilder, LazyContent};
 use super::{CompileError, ShallowSection};
-use crate::entry::HTMLMetaData;
+use crate::config;
+use crate::entry::{split_list, HTMLMetaData, MetaData};
 use crate::process::embed_markdown;
-use crate::slug::to_slug;
+use crate::slug::{split_anchor, to_slug};
 use crate::typst_cli;
 use std::borrow::Cow;
-use std::collections::HashMap;
 use std::str;
 
 fn parse_bool(m: Option<&Cow<'_, str>>, def: bool) -> bool {
@@ -21,7 +21,7 @@ fn parse_bool(m: Option<&Cow<'_, str>>, def: bool) -> bool {
 fn parse_typst_html(
     html_str: &str,
     relative_path: &str,
-    metadata: &mut HashMap<String, HTMLContent>,
+    metadata: &mut HTMLMetaData,
 ) -> Result<HTMLContent, CompileError> {
     let mut builder = HTMLContentBuilder::new();
     let mut cursor: usize = 0;
@@ -52,17 +52,18 @@ fn parse_typst_html(
         match span.kind {
             HTMLTagKind::Meta => {
                 let key = attr("key")?.as_ref();
-                let mut val = if let Some(value) = span.attrs.get("value") {
-                    HTMLContent::Plain(value.to_string())
+                let val = if let Some(value) = span.attrs.get("value") {
+                    if config::is_list_key(key) {
+                        let items = split_list(value);
+                        metadata.1.insert(key.to_string(), items.clone());
+                        HTMLContent::Plain(items.join(", "))
+                    } else {
+                        HTMLContent::Plain(value.to_string())
+                    }
                 } else {
-                    parse_typst_html(span.body, relative_path, &mut HashMap::new())?
+                    parse_typst_html(span.body, relative_path, &mut HTMLMetaData::default())?
                 };
-                if key == "taxon" {
-                    if let HTMLContent::Plain(v) = val {
-                        val = HTMLContent::Plain(embed_markdown::display_taxon(&v));
-                    }
-                }
-                metadata.insert(key.to_string(), val);
+                metadata.0.insert(key.to_string(), val);
             }
             HTMLTagKind::Embed => {
                 let def = SectionOption::default();
@@ -72,16 +73,34 @@ fn parse_typst_html(
                 let numbering = parse_bool(span.attrs.get("numbering"), def.numbering);
                 let details_open = parse_bool(span.attrs.get("open"), def.details_open);
                 let catalog = parse_bool(span.attrs.get("catalog"), def.catalog);
+                let force = parse_bool(span.attrs.get("force"), def.force);
+                let mut metadata_overrides = span
+                    .attrs
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        let key = name.strip_prefix("meta-")?;
+                        Some((key.to_string(), value.to_string()))
+                    })
+                    .collect();
+                embed_markdown::display_taxon_override(&mut metadata_overrides);
                 builder.push(LazyContent::Embed(EmbedContent {
                     url,
                     title,
-                    option: SectionOption::new(numbering, details_open, catalog),
+                    option: SectionOption::new(numbering, details_open, catalog, force),
+                    metadata_overrides,
                 }))
             }
             HTMLTagKind::Local { span: _ } => {
-                let slug = to_slug(attr("slug")?);
+                let (url, anchor) = split_anchor(attr("slug")?);
+                let slug = to_slug(url);
                 let text = value();
-                builder.push(LazyContent::Local(LocalLink { slug, text }))
+                let further = parse_bool(span.attrs.get("further"), false);
+                builder.push(LazyContent::Local(LocalLink {
+                    slug,
+                    text,
+                    further,
+                    anchor: anchor.map(str::to_string),
+                }))
             }
         }
     }
@@ -91,9 +110,13 @@ fn parse_typst_html(
     Ok(builder.build())
 }
 
-pub fn parse_typst(slug: &str, root_dir: &str) -> Result<ShallowSection, CompileError> {
-    let relative_path = format!("{}.typst", slug);
-    let html_str = typst_cli::file_to_html(&relative_path, root_dir).map_err(|e| {
+/// Parse the typst file `relative_path` of `slug`, see [`super::parser::parse_markdown`].
+pub fn parse_typst(
+    slug: &str,
+    relative_path: &str,
+    root_dir: &str,
+) -> Result<ShallowSection, CompileError> {
+    let html_str = typst_cli::file_to_html(relative_path, root_dir).map_err(|e| {
         CompileError::IO(
             Some(concat!(file!(), '#', line!())),
             e,
@@ -101,13 +124,22 @@ pub fn parse_typst(slug: &str, root_dir: &str) -> Result<ShallowSection, Compile
         )
     })?;
 
-    let mut metadata: HashMap<String, HTMLContent> = HashMap::new();
-    metadata.insert("slug".to_string(), HTMLContent::Plain(slug.to_string()));
+    let mut metadata = HTMLMetaData::default();
+    metadata
+        .0
+        .insert("slug".to_string(), HTMLContent::Plain(slug.to_string()));
 
-    let content = parse_typst_html(&html_str, &relative_path, &mut metadata)?;
+    let content = parse_typst_html(&html_str, relative_path, &mut metadata)?;
+    if let Some(HTMLContent::Plain(taxon)) = metadata.get("taxon") {
+        let lang = match metadata.get("lang") {
+            Some(HTMLContent::Plain(lang)) => Some(lang.as_str()),
+            _ => None,
+        };
+        let display = embed_markdown::display_taxon(taxon, lang);
+        metadata
+            .0
+            .insert("taxon".to_string(), HTMLContent::Plain(display));
+    }
 
-    Ok(ShallowSection {
-        metadata: HTMLMetaData(metadata),
-        content,
-    })
+    Ok(ShallowSection { metadata, content })
 }
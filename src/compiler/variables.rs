@@ -0,0 +1,231 @@
+//! The `{key}` variables in the metadata values, resolved against the other keys of the page,
+//! then the `[metadata.variables]` of `kodama.toml`. A `{{` is a literal `{`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::LazyLock,
+};
+
+use regex_lite::Regex;
+
+use crate::{
+    config::{self, CONFIG_FILE_NAME},
+    entry::HTMLMetaData,
+};
+
+use super::{
+    defaults::{parse_defaults, FROM_PAGE},
+    diagnostics::Severity,
+    section::{HTMLContent, HTMLContentBuilder, LazyContent, LazyContents},
+    state::CompileState,
+};
+
+/// Replace the variables in the metadata of the pending pages, those filled in from the
+/// defaults included, see [`crate::compiler::defaults::insert_defaults`].
+///
+/// A variable neither a key of the page nor defined in `kodama.toml` is left as is,
+/// with a warning, and so are the variables defined through themselves.
+/// The variables replaced in a value are added to its origin in
+/// [`CompileState::metadata_origins`].
+pub fn insert_variables(state: &mut CompileState) {
+    let mut warnings = BTreeSet::new();
+    let globals = parse_defaults(&config::metadata_variables(), CONFIG_FILE_NAME);
+    let globals = globals.unwrap_or_else(|message| {
+        warnings.insert((None, message));
+        HTMLMetaData::default()
+    });
+
+    let mut replaced = vec![];
+    for (slug, shallow) in state.pages.pending_mut() {
+        let metadata = &mut shallow.metadata;
+        let lists = metadata.1.values().flatten();
+        if !metadata.0.values().any(has_braces) && !lists.into_iter().any(|s| s.contains('{')) {
+            continue;
+        }
+
+        let mut resolver = Resolver {
+            slug,
+            page: &metadata.0,
+            globals: &globals.0,
+            resolved: HashMap::new(),
+            keys: vec![],
+            used: BTreeSet::new(),
+            warnings: &mut warnings,
+        };
+        let mut keys: Vec<&String> = metadata.0.keys().collect();
+        keys.sort();
+        let mut values = vec![];
+        for key in keys {
+            values.push((key.to_string(), resolver.key(key)));
+            if !resolver.used.is_empty() {
+                let used: Vec<String> = std::mem::take(&mut resolver.used).into_iter().collect();
+                replaced.push((slug.to_string(), key.to_string(), used));
+            }
+        }
+        let mut lists = vec![];
+        for (key, items) in &metadata.1 {
+            resolver.keys.push(key.to_string());
+            let items = items.iter().map(|item| resolver.text(item)).collect();
+            resolver.keys.pop();
+            lists.push((key.to_string(), items));
+        }
+
+        metadata.0.extend(values);
+        metadata.1.extend(lists);
+    }
+
+    for (slug, key, used) in replaced {
+        let origins = state.metadata_origins.entry(slug).or_default();
+        let origin = origins.get(&key).map_or(FROM_PAGE, String::as_str);
+        let origin = format!("{}, with the variables `{}`", origin, used.join("`, `"));
+        origins.insert(key, origin);
+    }
+
+    for (slug, message) in warnings {
+        state
+            .diagnostics
+            .report(Severity::Warning, slug.as_deref(), None, message);
+    }
+}
+
+struct Resolver<'a> {
+    slug: &'a str,
+    page: &'a HashMap<String, HTMLContent>,
+    globals: &'a HashMap<String, HTMLContent>,
+
+    /// The values with their variables replaced, by key.
+    resolved: HashMap<String, Option<LazyContents>>,
+
+    /// The keys being resolved, the innermost last.
+    keys: Vec<String>,
+
+    /// The variables replaced since last taken.
+    used: BTreeSet<String>,
+    warnings: &'a mut BTreeSet<(Option<String>, String)>,
+}
+
+impl Resolver<'_> {
+    /// The value of the page key `key` with its variables replaced.
+    fn key(&mut self, key: &str) -> HTMLContent {
+        // Resolved again to collect its variables into `used`.
+        self.resolved.remove(key);
+        let contents = self.variable(key).unwrap_or_default();
+        let mut builder = HTMLContentBuilder::new();
+        contents
+            .into_iter()
+            .for_each(|content| builder.push(content));
+        builder.build()
+    }
+
+    /// The value of the variable `name` with its variables replaced, `None` if undefined
+    /// or defined through itself.
+    fn variable(&mut self, name: &str) -> Option<LazyContents> {
+        if let Some(resolved) = self.resolved.get(name) {
+            return resolved.clone();
+        }
+        if let Some(start) = self.keys.iter().position(|key| key == name) {
+            let cycle = self.keys[start..].join("` -> `");
+            let message = format!(
+                "[{}] `{}` -> `{}` refer to each other, so `{{{}}}` is left as is.",
+                self.slug, cycle, name, name
+            );
+            self.warn(message);
+            return None;
+        }
+        let Some(value) = self.page.get(name).or_else(|| self.globals.get(name)) else {
+            let key = self.keys.last().map_or(name, String::as_str);
+            let message = format!(
+                "[{}] `{}` uses the undefined variable `{{{}}}`, define it in `[metadata.variables]` of `{}`.",
+                self.slug, key, name, CONFIG_FILE_NAME
+            );
+            self.warn(message);
+            return None;
+        };
+
+        let contents = match value {
+            HTMLContent::Plain(s) => vec![LazyContent::Plain(s.to_string())],
+            HTMLContent::Lazy(contents) => contents.clone(),
+        };
+        self.keys.push(name.to_string());
+        let mut resolved = vec![];
+        for content in contents {
+            let LazyContent::Plain(s) = content else {
+                resolved.push(content);
+                continue;
+            };
+            for piece in pieces(&s) {
+                match piece {
+                    Piece::Text(text) => resolved.push(LazyContent::Plain(text.to_string())),
+                    Piece::Variable(variable) => match self.variable(variable) {
+                        Some(contents) => {
+                            self.used.insert(variable.to_string());
+                            resolved.extend(contents)
+                        }
+                        None => resolved.push(LazyContent::Plain(format!("{{{}}}", variable))),
+                    },
+                }
+            }
+        }
+        self.keys.pop();
+
+        self.resolved
+            .insert(name.to_string(), Some(resolved.clone()));
+        Some(resolved)
+    }
+
+    /// The list item `item` with its variables replaced by their text.
+    fn text(&mut self, item: &str) -> String {
+        let mut text = String::new();
+        for piece in pieces(item) {
+            match piece {
+                Piece::Text(s) => text.push_str(s),
+                Piece::Variable(variable) => match self.variable(variable) {
+                    Some(contents) => {
+                        let value = HTMLContent::Lazy(contents).remove_all_tags();
+                        text.push_str(&htmlize::unescape(value));
+                    }
+                    None => text.push_str(&format!("{{{}}}", variable)),
+                },
+            }
+        }
+        text
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.insert((Some(self.slug.to_string()), message));
+    }
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+/// The text and the variables of `s`, a `{{` being the text `{`.
+fn pieces(s: &str) -> Vec<Piece<'_>> {
+    static RE_VARIABLE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\{\{|\{([A-Za-z_][A-Za-z0-9_-]*)\}").unwrap());
+
+    let mut pieces = vec![];
+    let mut cursor = 0;
+    for capture in RE_VARIABLE.captures_iter(s) {
+        let all = capture.get(0).unwrap();
+        pieces.push(Piece::Text(&s[cursor..all.start()]));
+        match capture.get(1) {
+            Some(name) => pieces.push(Piece::Variable(name.as_str())),
+            None => pieces.push(Piece::Text("{")),
+        }
+        cursor = all.end();
+    }
+    pieces.push(Piece::Text(&s[cursor..]));
+    pieces
+}
+
+fn has_braces(value: &HTMLContent) -> bool {
+    match value {
+        HTMLContent::Plain(s) => s.contains('{'),
+        HTMLContent::Lazy(contents) => contents
+            .iter()
+            .any(|content| matches!(content, LazyContent::Plain(s) if s.contains('{'))),
+    }
+}
@@ -1,30 +1,55 @@
-use std::{collections::HashSet, ops::Not, path::Path};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Not,
+    path::Path,
+    sync::Arc,
+};
+
+use indexmap::IndexSet;
 
 use crate::{
     compiler::counter::Counter,
-    config::{self, verify_update_hash},
-    entry::MetaData,
+    config::{self, verify_update_fingerprint, verify_update_hash, CitationStyle},
+    entry::{
+        EntryMetaData, HTMLMetaData, MetaData, KEY_CITATION_STYLE, KEY_DESCRIPTION, KEY_EXPIRES,
+        KEY_IMAGE, KEY_LANG,
+    },
     html,
     html_flake::{self, html_article_inner},
 };
 
 use super::{
     callback::CallbackValue,
-    section::{Section, SectionContent},
-    state::CompileState,
+    numbering::{self, Numbering},
+    section::{EmbedSection, HTMLContent, Section, SectionContent, SectionOption},
+    state::{backlinks_slug, CompileState},
     taxon::Taxon,
+    timings::{Phase, Timings},
+    translations,
 };
 
+/// The characters of a page summarized for its link previews, see [`Writer::summary`].
+const SUMMARY_LEN: usize = 160;
+
 pub struct Writer {}
 
 impl Writer {
-    pub fn write(section: &Section, state: &CompileState) {
-        let (html, page_title) = Writer::html_doc(section, state);
-        let html_url = format!("{}.html", section.slug());
+    pub fn write(section: &Arc<Section>, state: &CompileState) {
+        let html_url = config::output_html_name(&section.slug());
         let filepath = crate::config::output_path(&html_url);
-
         let relative_path = config::join_path(&config::output_dir(), &html_url);
-        if verify_update_hash(&relative_path, &html).expect("Writer::write@hash") {
+
+        let fingerprint = Writer::page_fingerprint(section, state);
+        let is_dirty = verify_update_fingerprint(&relative_path, fingerprint)
+            .expect("Writer::write@fingerprint");
+        if !is_dirty && !config::is_no_cache() && Path::new(&filepath).exists() {
+            return;
+        }
+
+        let (html, page_title) = Writer::html_doc(section, state);
+        if config::is_no_cache()
+            || verify_update_hash(&relative_path, &html).expect("Writer::write@hash")
+        {
             match std::fs::write(&filepath, html) {
                 Ok(()) => {
                     let output_path = crate::slug::pretty_path(Path::new(&html_url));
@@ -36,23 +61,85 @@ impl Writer {
     }
 
     pub fn write_needed_slugs(all_slugs: &Vec<String>, state: &CompileState) {
+        let start = Timings::start(&state.timings);
+        let root_slug = config::root_slug();
         all_slugs
             .iter()
-            .for_each(|slug| match state.compiled.get(slug) {
+            // `index.html` is taken by the root page.
+            .filter(|slug| *slug == &root_slug || *slug != "index")
+            .filter(|slug| !config::is_rerender_affected() || Writer::is_affected(slug, state))
+            .for_each(|slug| match state.pages.get(slug) {
                 /*
-                 * No need for `state.compiled.remove(slug)` here,
+                 * No need for `state.pages.take_pending(slug)` here,
                  * because writing to a file does not require a mutable reference
                  * of the [`Section`].
                  */
                 None => eprintln!("Slug `{}` not in compiled entries.", slug),
-                Some(section) => Writer::write(section, &state),
+                Some(section) => {
+                    let start = Timings::start(&state.timings);
+                    Writer::write(section, state);
+                    Timings::stop(&state.timings, start, Phase::Write, Some(slug));
+                }
             });
+        Timings::stop(&state.timings, start, Phase::Write, None);
     }
 
-    pub fn html_doc(section: &Section, state: &CompileState) -> (String, String) {
+    fn is_affected(slug: &str, state: &CompileState) -> bool {
+        let html_url = config::output_html_name(slug);
+        state.is_affected(slug) || !Path::new(&config::output_path(&html_url)).exists()
+    }
+
+    pub fn html_doc(section: &Arc<Section>, state: &CompileState) -> (String, String) {
         let mut counter = Counter::init();
 
-        let (article_inner, items) = Writer::section_to_html(section, &mut counter, true, false);
+        let toplevel = EmbedSection::new(Arc::clone(section));
+        let (mut article_inner, items) =
+            Writer::section_to_html(&toplevel, &mut counter, &state.numbering, true, false, &[]);
+        let references = Writer::listed_references(section, state);
+        let further_reading = Writer::listed_further_reading(section, state, &references);
+        let style = Writer::citation_style(section);
+        let (html, footnotes) = Writer::style_citations(&article_inner, &references, style);
+        article_inner = Writer::number_references(&html, &references, style);
+        article_inner = Writer::number_cross_references(&article_inner, section, state);
+        if let Some(series) = Writer::series(state, section) {
+            article_inner.push_str(&series);
+        }
+        if !footnotes.is_empty() {
+            article_inner.push_str(&html_flake::html_footnotes(&footnotes));
+        }
+        if let Some(entry) = state
+            .is_reference(&section.slug())
+            .then(|| section.metadata.to_bibliography_entry(None, None))
+            .flatten()
+        {
+            // right after the header of the page.
+            let entry = format!("</summary><p>{}</p>", entry);
+            article_inner = article_inner.replacen("</summary>", &entry, 1);
+        }
+        if let Some(badge) = Writer::embed_badge(state, section) {
+            article_inner = format!("{}{}", badge, article_inner);
+        }
+        let (translations_html, alternate_links) = Writer::translations(state, section);
+        article_inner = format!("{}{}", translations_html, article_inner);
+        if let Some(expires) = section
+            .metadata
+            .get(KEY_EXPIRES)
+            .filter(|_| state.expired.contains(&section.slug()))
+        {
+            let date = section.metadata.format_date(expires);
+            article_inner = format!(
+                "{}{}",
+                html_flake::html_archived_banner(&date),
+                article_inner
+            );
+        }
+        if config::is_drafts() && section.metadata.is_draft() {
+            article_inner = format!("{}{}", html_flake::html_draft_banner(), article_inner);
+        }
+        if config::is_emit_sources() {
+            let sources = section.sources().join(", ");
+            article_inner = format!("<!-- sources: {} -->{}", sources, article_inner);
+        }
         let catalog_html = items
             .is_empty()
             .not()
@@ -63,11 +150,21 @@ impl Writer {
         let html_header = Writer::header(state, &slug);
 
         let callback = state.callback.0.get(&slug);
-        let footer_html = Writer::footer(state, &section.references, callback);
+        let footer_html = Writer::footer(state, section, &references, &further_reading, callback);
         let page_title = section.metadata.page_title().map_or("", |s| s.as_str());
+        let lang = section
+            .metadata
+            .get_str(KEY_LANG)
+            .cloned()
+            .or_else(config::default_lang)
+            .unwrap_or_else(|| "en-US".to_string());
 
+        let social_meta = Writer::social_meta(section, page_title);
+        let robots = html_flake::html_robots(&section.metadata.robots());
         let html = crate::html_flake::html_doc(
             &page_title,
+            &lang,
+            &format!("{}{}{}", robots, social_meta, alternate_links),
             &html_header,
             &article_inner,
             &footer_html,
@@ -77,6 +174,381 @@ impl Writer {
         (html, page_title.to_string())
     }
 
+    /// The references listed at the end of the page `section`, once its open embedded
+    /// sections merged theirs: each compiled page once, the page itself left out, in
+    /// order of first citation with [`Writer::is_numbered_references`] and by slug otherwise,
+    /// followed by the further reading with [`config::is_merge_further_reading`].
+    fn listed_references(section: &Section, state: &CompileState) -> Vec<String> {
+        let mut references = Writer::listed(section, state, &section.references);
+        if !Writer::is_numbered_references(section) {
+            references.sort();
+        }
+        if config::is_merge_further_reading() {
+            let mut further_reading = Writer::listed(section, state, &section.further_reading);
+            further_reading.sort();
+            references.extend(further_reading);
+        }
+        references.into_iter().collect()
+    }
+
+    /// The [`Section::further_reading`] of the page `section` not among its `references`
+    /// by slug, listed apart unless [`config::is_merge_further_reading`].
+    fn listed_further_reading(
+        section: &Section,
+        state: &CompileState,
+        references: &[String],
+    ) -> Vec<String> {
+        if config::is_merge_further_reading() {
+            return vec![];
+        }
+        let mut further_reading = Writer::listed(section, state, &section.further_reading);
+        further_reading.retain(|slug| !references.contains(slug));
+        further_reading.sort();
+        further_reading.into_iter().collect()
+    }
+
+    /// The compiled pages and inline references among `slugs`, the page itself left out.
+    fn listed(
+        section: &Section,
+        state: &CompileState,
+        slugs: &IndexSet<String>,
+    ) -> IndexSet<String> {
+        let slug = section.slug();
+        slugs
+            .iter()
+            .map(|reference| crate::slug::clip_metadata_badge(reference))
+            .filter(|reference| *reference != slug)
+            .filter(|reference| {
+                state.pages.get(reference).is_some()
+                    || state.reference_metadata(reference).is_some()
+            })
+            .collect()
+    }
+
+    /// The `citation-style` of the page `section`, or else [`config::citation_style`].
+    fn citation_style(section: &Section) -> CitationStyle {
+        section
+            .metadata
+            .get_str(KEY_CITATION_STYLE)
+            .and_then(|style| style.parse().ok())
+            .unwrap_or_else(config::citation_style)
+    }
+
+    /// The references of the page `section` are numbered, with
+    /// [`config::is_numbered_references`] or [`CitationStyle::Numeric`].
+    fn is_numbered_references(section: &Section) -> bool {
+        config::is_numbered_references()
+            || Writer::citation_style(section) == CitationStyle::Numeric
+    }
+
+    /// Rewrite the citations marked by [`html_flake::html_citation_marker`] in `style`,
+    /// along with the footnotes of [`CitationStyle::Footnote`] in order.
+    /// Only their rendering changes: the references were recorded on compilation.
+    fn style_citations(
+        html: &str,
+        references: &[String],
+        style: CitationStyle,
+    ) -> (String, Vec<String>) {
+        const PREFIX: &str = "<!--citation:";
+        const LOCATOR: &str = "<!--/locator-->";
+        const SUFFIX: &str = "<!--/citation-->";
+        let mut footnotes = vec![];
+        let mut result = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(PREFIX) {
+            let Some(end) = rest[start..].find(SUFFIX).map(|end| start + end) else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let marker = &rest[start + PREFIX.len()..end];
+            let marker = marker
+                .split_once("-->")
+                .and_then(|(slug, rest)| Some((slug, rest.split_once(LOCATOR)?)));
+            if let Some((slug, (locator, citation))) = marker {
+                let slug = crate::slug::clip_metadata_badge(slug);
+                let index = references.iter().position(|reference| *reference == slug);
+                match (style, index) {
+                    (CitationStyle::Numeric, Some(index)) => {
+                        result.push_str(&html_flake::html_numeric_citation(index + 1, locator))
+                    }
+                    (CitationStyle::Footnote, _) => {
+                        footnotes.push(citation.to_string());
+                        result.push_str(&html_flake::html_footnote_marker(footnotes.len()));
+                    }
+                    _ => result.push_str(citation),
+                }
+            }
+            rest = &rest[end + SUFFIX.len()..];
+        }
+        result.push_str(rest);
+        (result, footnotes)
+    }
+
+    /// Replace the markers after the citations of references by their numbers in the
+    /// `references` of the page, or remove them, see [`config::is_numbered_references`].
+    /// The citations in embedded sections are numbered in the sequence of the page,
+    /// and those of the other styles than [`CitationStyle::AuthorYear`] are not.
+    fn number_references(html: &str, references: &[String], style: CitationStyle) -> String {
+        const PREFIX: &str = "<!--reference:";
+        let numbered = config::is_numbered_references() && style == CitationStyle::AuthorYear;
+        let mut result = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(PREFIX) {
+            let Some(end) = rest[start..].find("-->").map(|end| start + end) else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let slug = &rest[start + PREFIX.len()..end];
+            let index = references.iter().position(|reference| reference == slug);
+            if let Some(index) = index.filter(|_| numbered) {
+                result.push_str(&html_flake::html_reference_number(index + 1));
+            }
+            rest = &rest[end + "-->".len()..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Replace the cross-references `[#](slug)` in the page `section` by the taxon and
+    /// number of `slug`, or by its taxon and title if it has no number there.
+    fn number_cross_references(html: &str, section: &Section, state: &CompileState) -> String {
+        const PREFIX: &str = "<!--number:";
+        const SUFFIX: &str = "<!--/number-->";
+        let mut result = String::new();
+        let mut rest = html;
+        while let Some(start) = rest.find(PREFIX) {
+            let Some(end) = rest[start..].find(SUFFIX).map(|end| start + end) else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let marker = &rest[start + PREFIX.len()..end];
+            if let Some((slug, fallback)) = marker.split_once("-->") {
+                let text = state.cross_reference(section, slug);
+                result.push_str(text.as_deref().unwrap_or(fallback));
+            }
+            rest = &rest[end + SUFFIX.len()..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// The navigation of `section` within its series, see [`CompileState::series_of`].
+    /// The series is linked if it has a page, see [`config::series_pages`].
+    fn series(state: &CompileState, section: &Section) -> Option<String> {
+        let slug = section.slug();
+        let (series_slug, name, slugs) = state.series_of(&slug)?;
+        let current = slugs.iter().position(|s| *s == slug)?;
+        let parts: Vec<(String, String)> = slugs
+            .iter()
+            .map(|slug| {
+                let title = state.get_metadata(slug).and_then(|m| m.page_title());
+                let title = title.map_or(slug.to_string(), String::to_string);
+                (config::full_html_url(slug), title)
+            })
+            .collect();
+        let url = state
+            .series_pages
+            .contains(series_slug)
+            .then(|| config::full_html_url(series_slug));
+        let name = htmlize::escape_text(name);
+        Some(html_flake::html_series(
+            &name,
+            url.as_deref(),
+            &parts,
+            current,
+        ))
+    }
+
+    /// The line linking the written `translations` of `section` in their languages
+    /// and the `hreflang` links to them and to itself, both empty without any.
+    fn translations(state: &CompileState, section: &Section) -> (String, String) {
+        let slug = section.slug();
+        let translations: Vec<(String, &HTMLMetaData)> =
+            translations::translation_slugs(&section.metadata, &state.aliases)
+                .into_iter()
+                .filter(|translation| {
+                    *translation != slug && state.pages.get(translation).is_some()
+                })
+                .filter_map(|translation| {
+                    let metadata = state.get_metadata(&translation)?;
+                    Some((translation, metadata))
+                })
+                .collect();
+        if translations.is_empty() {
+            return (String::new(), String::new());
+        }
+
+        let lang = |lang: Option<String>| lang.unwrap_or_else(|| "en-US".to_string());
+        let page_lang = lang(translations::page_lang(&section.metadata));
+        let mut alternates = vec![(page_lang.to_string(), config::full_html_url(&slug))];
+        let mut links = vec![];
+        for (translation, metadata) in &translations {
+            let url = config::full_html_url(translation);
+            let title = metadata
+                .page_title()
+                .map_or(translation.as_str(), String::as_str);
+            let translation_lang = lang(translations::page_lang(*metadata));
+            links.push((
+                translation_lang.to_string(),
+                url.to_string(),
+                title.to_string(),
+            ));
+            alternates.push((translation_lang, url));
+        }
+        (
+            html_flake::html_translations(&page_lang, &links),
+            html_flake::html_alternate_links(&alternates),
+        )
+    }
+
+    /// The tags describing `section` in its head for the link previews of other sites,
+    /// unless disabled globally or by the page.
+    fn social_meta(section: &Section, page_title: &str) -> String {
+        if !config::is_social_meta() || !section.metadata.is_enable_social_meta() {
+            return String::new();
+        }
+        let plain = |html: &str| htmlize::unescape(html).to_string();
+        let description = match section.metadata.get_str(KEY_DESCRIPTION) {
+            Some(description) => {
+                plain(&HTMLContent::Plain(description.to_string()).remove_all_tags())
+            }
+            None => Writer::summary(section),
+        };
+        let image = section
+            .metadata
+            .get_str(KEY_IMAGE)
+            .map(|image| match image.contains("://") {
+                true => image.to_string(),
+                false => config::full_url(image),
+            });
+        let slug = section.slug();
+        let kind = match slug == config::root_slug() {
+            true => "website",
+            false => "article",
+        };
+        html_flake::html_social_meta(
+            &plain(page_title),
+            &description,
+            &config::full_html_url(&slug),
+            kind,
+            image.as_deref(),
+        )
+    }
+
+    /// The text of `section` itself, without its embedded sections, cut at a word
+    /// after [`SUMMARY_LEN`] characters.
+    fn summary(section: &Section) -> String {
+        let html: String = section
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SectionContent::Plain(html) => Some(html.as_str()),
+                SectionContent::Embed(_) => None,
+            })
+            .collect();
+        // the markers of the references are comments.
+        let mut rest = html.as_str();
+        let mut uncommented = String::new();
+        while let Some((before, after)) = rest.split_once("<!--") {
+            uncommented.push_str(before);
+            rest = after.split_once("-->").map_or("", |(_, after)| after);
+        }
+        uncommented.push_str(rest);
+        let text = HTMLContent::Plain(uncommented).remove_all_tags();
+        let text = htmlize::unescape(text).to_string();
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let mut summary = String::new();
+        for word in words {
+            if summary.chars().count() >= SUMMARY_LEN {
+                summary.push('…');
+                break;
+            }
+            if !summary.is_empty() {
+                summary.push(' ');
+            }
+            summary.push_str(word);
+        }
+        summary
+    }
+
+    /// "Transcluded in N places" if `section` is embedded by at least
+    /// [`config::embed_badge_min`] pages and shows its contexts.
+    fn embed_badge(state: &CompileState, section: &Section) -> Option<String> {
+        let min = config::embed_badge_min()?;
+        let count = state.callback.0.get(&section.slug())?.contexts.len();
+        (count >= min && section.metadata.is_enable_context())
+            .then(|| html_flake::html_embed_badge(count))
+    }
+
+    /// Hash of everything the HTML of `section` depends on: its contents, the titles of
+    /// its parent and of the linked pages, its references, its backlinks and the config.
+    fn page_fingerprint(section: &Section, state: &CompileState) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        config::fingerprint().hash(&mut hasher);
+        section.fingerprint.hash(&mut hasher);
+
+        let fingerprint_of = |slug: &str| state.pages.get(slug).map(|s| s.fingerprint);
+        let further_reading = section.further_reading.iter();
+        let mut references: Vec<&String> =
+            section.references.iter().chain(further_reading).collect();
+        references.sort();
+        for slug in references {
+            fingerprint_of(slug).hash(&mut hasher);
+            if let Some((page, _)) = state.inline_references.get(slug) {
+                fingerprint_of(page).hash(&mut hasher);
+            }
+        }
+
+        if let Some(callback) = state.callback.0.get(&section.slug()) {
+            callback.parent.hash(&mut hasher);
+            fingerprint_of(&callback.parent).hash(&mut hasher);
+            for slug in &callback.backlinks {
+                slug.hash(&mut hasher);
+                fingerprint_of(&crate::slug::clip_metadata_badge(slug)).hash(&mut hasher);
+            }
+            for slug in &callback.contexts {
+                slug.hash(&mut hasher);
+                fingerprint_of(slug).hash(&mut hasher);
+                if config::is_transitive_contexts() {
+                    for slug in state.context_path(slug).iter().skip(1) {
+                        slug.hash(&mut hasher);
+                        fingerprint_of(slug).hash(&mut hasher);
+                    }
+                }
+            }
+        }
+        for slug in state.related.get(&section.slug()).into_iter().flatten() {
+            slug.hash(&mut hasher);
+            fingerprint_of(slug).hash(&mut hasher);
+        }
+        for slug in numbering::cross_references(section) {
+            state.cross_reference(section, &slug).hash(&mut hasher);
+        }
+        if state.is_reference(&section.slug()) {
+            for slug in state.cited_by(&section.slug()) {
+                slug.hash(&mut hasher);
+                fingerprint_of(&slug).hash(&mut hasher);
+            }
+        }
+        state.expired.contains(&section.slug()).hash(&mut hasher);
+        if let Some((_, _, slugs)) = state.series_of(&section.slug()) {
+            for slug in slugs {
+                slug.hash(&mut hasher);
+                fingerprint_of(slug).hash(&mut hasher);
+            }
+        }
+        for slug in translations::translation_slugs(&section.metadata, &state.aliases) {
+            slug.hash(&mut hasher);
+            fingerprint_of(&slug).hash(&mut hasher);
+        }
+        state
+            .numbering
+            .page_numbers(&section.slug())
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
     fn header(state: &CompileState, slug: &str) -> String {
         state
             .callback
@@ -84,7 +556,7 @@ impl Writer {
             .get(slug)
             .and_then(|callback| {
                 let parent = &callback.parent;
-                state.compiled.get(parent).map(|section| {
+                state.pages.get(parent).map(|section| {
                     let href = config::full_html_url(parent);
                     let title = section.metadata.title().map_or("", |s| s);
                     let page_title = section.metadata.page_title().map_or("", |s| s);
@@ -94,50 +566,168 @@ impl Writer {
             .unwrap_or_default()
     }
 
+    /// `references` and `further_reading` are the [`Writer::listed_references`] and the
+    /// [`Writer::listed_further_reading`] of `section`.
     fn footer(
         state: &CompileState,
-        references: &HashSet<String>,
+        section: &Section,
+        references: &[String],
+        further_reading: &[String],
         callback: Option<&CallbackValue>,
     ) -> String {
-        let mut references: Vec<&String> = references.iter().collect();
-        references.sort();
-
+        let numbered = Writer::is_numbered_references(section);
         let references_html = references
             .iter()
-            .map(|slug| {
-                let slug = slug.to_string();
-                let section = state.compiled.get(&slug).unwrap();
-                Writer::footer_section_to_html(section)
-            })
+            .enumerate()
+            .map(|(index, slug)| Writer::listed_reference(state, slug, index, numbered))
             .reduce(|s, t| s + &t)
             .map(|s| html_flake::html_footer_section("References", &s))
             .unwrap_or_default();
 
-        let backlinks_html = callback
+        let further_reading_html = further_reading
+            .iter()
+            .enumerate()
+            .map(|(index, slug)| Writer::listed_reference(state, slug, index, false))
+            .reduce(|s, t| s + &t)
+            .map(|s| {
+                html_flake::html_footer_section_with_id("further-reading", "Further reading", &s)
+            })
+            .unwrap_or_default();
+
+        let contexts_html = callback
+            .filter(|_| section.metadata.is_enable_context())
             .map(|s| {
-                let mut backlinks: Vec<&String> = s.backlinks.iter().collect();
-                backlinks.sort();
-                backlinks
+                state
+                    .sorted_pages(&s.contexts)
                     .iter()
                     .map(|slug| {
-                        let slug = Writer::clip_metadata_badge(slug);
-                        let section = state.compiled.get(&slug).unwrap();
-                        Writer::footer_section_to_html(section)
+                        let section = state.pages.get(slug).unwrap();
+                        let path = Writer::context_path(state, slug);
+                        Writer::footer_section_to_html(
+                            section,
+                            &section.metadata,
+                            None,
+                            path.as_deref(),
+                        )
                     })
                     .reduce(|s, t| s + &t)
-                    .map(|s| html_flake::html_footer_section("Backlinks", &s))
+                    .map(|s| html_flake::html_footer_section_with_id("contexts", "Contexts", &s))
                     .unwrap_or_default()
             })
             .unwrap_or_default();
 
-        html!(footer => (references_html) (backlinks_html))
+        let cited_by = state
+            .is_reference(&section.slug())
+            .then(|| state.cited_by(&section.slug()))
+            .filter(|_| section.metadata.is_enable_backlinks())
+            .unwrap_or_default();
+        let cited_by_html = cited_by
+            .iter()
+            .map(|slug| {
+                let section = state.pages.get(slug).unwrap();
+                Writer::footer_section_to_html(section, &section.metadata, None, None)
+            })
+            .reduce(|s, t| s + &t)
+            .map(|s| {
+                let title = format!("Cited by ({})", cited_by.len());
+                html_flake::html_footer_section_with_id("cited-by", &title, &s)
+            })
+            .unwrap_or_default();
+
+        // a page embedding the current page is only listed in the contexts.
+        let backlinks = state.listed_backlinks(&section.slug());
+        let max_backlinks = config::max_backlinks().unwrap_or(usize::MAX);
+        let show_all = match backlinks.len() > max_backlinks {
+            false => String::new(),
+            true => {
+                let slug = backlinks_slug(&section.slug());
+                let text = format!("Show all {} backlinks", backlinks.len());
+                let link = html_flake::html_link(
+                    &config::full_html_url(&slug),
+                    &text,
+                    &text,
+                    crate::recorder::State::LocalLink.strify(),
+                );
+                format!(r#"<section class="block">{}</section>"#, link)
+            }
+        };
+        let shown: Vec<String> = backlinks.iter().take(max_backlinks).cloned().collect();
+        let backlinks_html = callback
+            .and_then(|s| {
+                let items_html = |slugs: &[String]| {
+                    slugs
+                        .iter()
+                        .map(|slug| {
+                            let section = state.pages.get(slug).unwrap();
+                            let snippet = s.snippets.get(slug).map(String::as_str);
+                            let details = Writer::backlink_details(state, slug);
+                            let metadata = &section.metadata;
+                            let details = details.as_deref();
+                            Writer::footer_section_to_html(section, metadata, details, snippet)
+                        })
+                        .reduce(|s, t| s + &t)
+                };
+                match config::backlinks_group_by() {
+                    config::BacklinksGroupBy::None => items_html(&shown),
+                    config::BacklinksGroupBy::Taxon => state
+                        .taxon_groups(&shown)
+                        .into_iter()
+                        .map(|(taxon, slugs)| {
+                            let taxon = taxon.as_deref().unwrap_or("Untagged");
+                            let title = format!("{} ({})", taxon, slugs.len());
+                            let items = items_html(&slugs).unwrap_or_default();
+                            html_flake::html_backlinks_group(&title, &items)
+                        })
+                        .reduce(|s, t| s + &t),
+                }
+            })
+            .map(|s| html_flake::html_footer_section("Backlinks", &(s + &show_all)))
+            .unwrap_or_default();
+
+        let related_html = state
+            .related
+            .get(&section.slug())
+            .filter(|_| section.metadata.is_enable_related())
+            .and_then(|slugs| {
+                slugs
+                    .iter()
+                    .map(|slug| {
+                        let section = state.pages.get(slug).unwrap();
+                        Writer::footer_section_to_html(section, &section.metadata, None, None)
+                    })
+                    .reduce(|s, t| s + &t)
+            })
+            .map(|s| html_flake::html_footer_section("Related", &s))
+            .unwrap_or_default();
+
+        let license_html = section
+            .metadata
+            .license()
+            .map(|license| {
+                let url = config::license_entry(&license).and_then(|entry| entry.url);
+                html_flake::html_license(&config::license_name(&license), url.as_deref())
+            })
+            .unwrap_or_default();
+
+        html!(footer => (references_html) (further_reading_html) (contexts_html) (cited_by_html) (backlinks_html) (related_html) (license_html))
     }
 
-    fn clip_metadata_badge(slug: &str) -> String {
-        match slug.ends_with(":metadata") {
-            true => slug[0..slug.len() - ":metadata".len()].to_string(),
-            false => slug.to_string(),
+    /// "Appears in: B → A" for a context `B` embedded by `A`, see [`config::is_transitive_contexts`].
+    fn context_path(state: &CompileState, slug: &str) -> Option<String> {
+        let path = state.context_path(slug);
+        if !config::is_transitive_contexts() || path.len() < 2 {
+            return None;
         }
+        let titles: Vec<&str> = path
+            .iter()
+            .map(|slug| {
+                state
+                    .get_metadata(slug)
+                    .and_then(|m| m.page_title())
+                    .map_or(slug.as_str(), |s| s.as_str())
+            })
+            .collect();
+        Some(format!("Appears in: {}", titles.join(" → ")))
     }
 
     fn catalog_block(items: &str) -> String {
@@ -145,15 +735,20 @@ impl Writer {
           (html!(h1 => "Table of Contents")) (items))
     }
 
-    fn catalog_item(section: &Section, taxon: &str, child_html: &str) -> String {
-        let slug = &section.slug();
-        let title = section.metadata.title().map_or("", |s| s);
-        let page_title = section.metadata.page_title().map_or("", |s| s);
+    fn catalog_item(
+        metadata: &EntryMetaData,
+        option: &SectionOption,
+        taxon: &str,
+        child_html: &str,
+    ) -> String {
+        let slug = metadata.slug().unwrap();
+        let title = metadata.title().map_or("", |s| s);
+        let page_title = metadata.page_title().map_or("", |s| s);
         html_flake::catalog_item(
             slug,
             title,
             page_title,
-            section.option.details_open,
+            option.details_open,
             taxon,
             child_html,
         )
@@ -162,15 +757,104 @@ impl Writer {
     fn footer_content_to_html(content: &SectionContent) -> String {
         match content {
             SectionContent::Plain(s) => s.to_string(),
-            SectionContent::Embed(section) => Writer::footer_section_to_html(section),
+            SectionContent::Embed(embed) => {
+                Writer::footer_section_to_html(&embed.section, &embed.metadata(), None, None)
+            }
         }
     }
 
-    fn footer_section_to_html(section: &Section) -> String {
+    /// "Taxon, date" of the page linking a backlink, as far as they are known and enabled,
+    /// see [`config::is_backlinks_show_taxon`] and [`config::is_backlinks_show_date`].
+    fn backlink_details(state: &CompileState, slug: &str) -> Option<String> {
+        let metadata = state.get_metadata(slug)?;
+        let taxon = metadata
+            .data_taxon()
+            .filter(|_| config::is_backlinks_show_taxon());
+        let date = metadata
+            .display_date()
+            .filter(|_| config::is_backlinks_show_date());
+        let details: Vec<&str> = taxon
+            .into_iter()
+            .map(String::as_str)
+            .chain(date.as_deref())
+            .filter(|s| !s.is_empty())
+            .collect();
+        (!details.is_empty()).then(|| details.join(", "))
+    }
+
+    /// The entry of the reference `slug` listed at the `index` in a footer section.
+    fn listed_reference(state: &CompileState, slug: &str, index: usize, numbered: bool) -> String {
+        let html = match (state.pages.get(slug), config::footer_mode()) {
+            (None, _) => Some(Writer::inline_reference_entry(state, slug, index, numbered)),
+            (Some(section), config::FooterMode::Link) => {
+                Writer::reference_entry(section, index, numbered)
+            }
+            (Some(_), config::FooterMode::Embed) => None,
+        };
+        let html = html.unwrap_or_else(|| {
+            let section = state.pages.get(slug).unwrap();
+            Writer::footer_section_to_html(section, &section.metadata, None, None)
+        });
+        match numbered {
+            false => html,
+            true => html_flake::html_numbered_reference(index + 1, &html),
+        }
+    }
+
+    /// The bibliography entry of the reference `section` listed at `index` in the references,
+    /// numbered in the numeric style unless the references are already numbered.
+    fn reference_entry(section: &Section, index: usize, numbered: bool) -> Option<String> {
+        let href = config::full_html_url(&section.slug());
+        let number = (!numbered).then_some(index + 1);
+        let entry = section
+            .metadata
+            .to_bibliography_entry(Some(&href), number)?;
+        Some(format!(r#"<section class="block">{}</section>"#, entry))
+    }
+
+    /// The bibliography entry of the inline reference `slug`, or its title, in both footer
+    /// modes as it has no page, see [`CompileState::inline_references`].
+    fn inline_reference_entry(
+        state: &CompileState,
+        slug: &str,
+        index: usize,
+        numbered: bool,
+    ) -> String {
+        let metadata = state.reference_metadata(slug);
+        let href = state.reference_href(slug);
+        let number = (!numbered).then_some(index + 1);
+        let entry = (metadata.and_then(|m| m.to_bibliography_entry(href.as_deref(), number)))
+            .or_else(|| metadata.and_then(|m| m.page_title()).cloned())
+            .unwrap_or_default();
+        format!(
+            r#"<section class="block inline-reference">{}</section>"#,
+            entry
+        )
+    }
+
+    /// `metadata` is the metadata of `section` with the overrides of its embed applied.
+    /// `details` follow the title in the link mode, replacing the taxon if it is one of
+    /// them, see [`Writer::backlink_details`].
+    /// `snippet` is the text around the link of a backlink, shown in the link mode.
+    fn footer_section_to_html(
+        section: &Section,
+        metadata: &EntryMetaData,
+        details: Option<&str>,
+        snippet: Option<&str>,
+    ) -> String {
         match config::footer_mode() {
             config::FooterMode::Link => {
-                let summary = section.metadata.to_header(None, None);
-                format!(r#"<section class="block">{summary}</section>"#)
+                let title = details.map(|details| {
+                    let title = metadata.title().map_or("", |s| s);
+                    format!("{} — {}", title, details)
+                });
+                let taxon = (details.is_some() && config::is_backlinks_show_taxon()).then_some("");
+                let summary = metadata.to_header(title.as_deref(), taxon);
+                let snippet = snippet
+                    .filter(|s| !s.is_empty())
+                    .map(html_flake::html_backlink_snippet)
+                    .unwrap_or_default();
+                format!(r#"<section class="block">{summary}{snippet}</section>"#)
             }
             config::FooterMode::Embed => {
                 let contents = match section.children.len() > 0 {
@@ -182,28 +866,40 @@ impl Writer {
                         .reduce(|s, t| s + &t)
                         .unwrap(),
                 };
-                html_article_inner(&section.metadata, &contents, false, false, None, None)
+                html_article_inner(metadata, &contents, false, false, None, None)
             }
         }
     }
 
+    /// `chain` is the slugs of the embedding ancestors of `section`, outermost first.
     pub fn section_to_html(
-        section: &Section,
+        embed: &EmbedSection,
         counter: &mut Counter,
+        numbering: &Numbering,
         toplevel: bool,
         hide_metadata: bool,
+        chain: &[String],
     ) -> (String, String) {
-        let adhoc_taxon = Writer::taxon(section, counter);
+        let section = &embed.section;
+        let metadata = embed.metadata();
+        let option = &embed.option;
+
+        let mut chain = chain.to_vec();
+        chain.push(section.slug());
+
+        let is_numbered = numbering::is_numbered(&metadata, option);
+        let adhoc_taxon = Writer::taxon(&metadata, is_numbered, counter, numbering, &chain);
         let (contents, items) = match section.children.len() > 0 {
             false => (String::new(), String::new()),
             true => {
-                let mut subcounter = match section.option.numbering {
+                let mut subcounter = match numbering::is_counted(&metadata, option) {
                     true => counter.left_shift(),
                     false => counter.clone(),
                 };
                 let content_to_html = |c: &SectionContent| {
                     let is_collection = section.metadata.is_collect();
-                    Writer::content_to_html(c, &mut subcounter, !is_collection)
+                    let counter = &mut subcounter;
+                    Writer::content_to_html(c, counter, numbering, !is_collection, &chain)
                 };
                 section
                     .children
@@ -214,26 +910,24 @@ impl Writer {
             }
         };
 
-        let child_html = items
-            .is_empty()
-            .not()
-            .then(|| format!(r#"<ul class="block">{}</ul>"#, &items))
-            .unwrap_or_default();
+        let child_html = match items.is_empty() {
+            true => String::new(),
+            false => format!(r#"<ul class="block">{}</ul>"#, &items),
+        };
 
         let catalog_item = match toplevel {
             true => child_html,
-            false => section
-                .option
+            false => option
                 .catalog
-                .then(|| Writer::catalog_item(section, &adhoc_taxon, &child_html))
+                .then(|| Writer::catalog_item(&metadata, option, &adhoc_taxon, &child_html))
                 .unwrap_or(String::new()),
         };
 
         let article_inner = html_article_inner(
-            &section.metadata,
+            &metadata,
             &contents,
             hide_metadata,
-            section.option.details_open,
+            option.details_open,
             None,
             Some(adhoc_taxon.as_str()),
         );
@@ -244,24 +938,115 @@ impl Writer {
     fn content_to_html(
         content: &SectionContent,
         counter: &mut Counter,
+        numbering: &Numbering,
         hide_metadata: bool,
+        chain: &[String],
     ) -> (String, String) {
         match content {
             SectionContent::Plain(s) => (s.to_string(), String::new()),
-            SectionContent::Embed(section) => {
-                Writer::section_to_html(section, counter, false, hide_metadata)
+            SectionContent::Embed(embed) => {
+                Writer::section_to_html(embed, counter, numbering, false, hide_metadata, chain)
             }
         }
     }
 
-    fn taxon(section: &Section, counter: &mut Counter) -> String {
-        if section.option.numbering {
-            counter.step_mut();
-            let numbering = Some(counter.display());
-            let text = section.metadata.taxon().map_or("", |s| s);
+    /// The taxon followed by the number of the section in this page, or in the
+    /// whole forest with [`config::is_auto_numbering`], if it is numbered.
+    /// A [`numbering::manual_number`] is shown instead, and not counted.
+    fn taxon(
+        metadata: &EntryMetaData,
+        is_numbered: bool,
+        counter: &mut Counter,
+        numbering: &Numbering,
+        chain: &[String],
+    ) -> String {
+        if is_numbered {
+            let numbering = match numbering::manual_number(metadata) {
+                Some(number) => Some(number),
+                None if config::is_auto_numbering() => numbering.number(chain).cloned(),
+                None => {
+                    counter.step_mut();
+                    Some(counter.display())
+                }
+            };
+            let text = metadata.taxon().map_or("", |s| s);
             let taxon = Taxon::new(numbering, text.to_string());
             return taxon.display();
         }
-        section.metadata.taxon().map_or("", |s| s).to_string()
+        metadata.taxon().map_or("", |s| s).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::compiler::test_forest;
+
+    fn page(title: &str, body: &str) -> String {
+        format!("---\ntitle: {}\n---\n\n{}\n", title, body)
+    }
+
+    #[test]
+    fn embedding_pages_are_contexts_not_backlinks() {
+        let config = config::test_config("kodama-tests");
+        let index = page("Index", "[](/a.md#:embed)\n\nSee [](a.md).");
+        let a = page("Alpha", "Alpha body, see [](a.md).");
+        let b = page("Beta", "Beta links to [](a.md).");
+        let (forest, _guard) = test_forest(&[("index", &index), ("a", &a), ("b", &b)], config);
+
+        let callback = &forest.state.callback.0["a"];
+        assert_eq!(callback.contexts, BTreeSet::from(["index".to_string()]));
+        assert!(!callback.backlinks.contains("a"));
+        let html = Writer::html_doc(&forest.compiled()["a"], &forest.state).0;
+        let (contexts, backlinks) = html.split_once("<h1>Backlinks</h1>").unwrap();
+        assert!(contexts.contains("<h1>Contexts</h1>"));
+        assert!(contexts.contains("[index]"));
+        assert!(backlinks.contains("[b]"));
+        assert!(!backlinks.contains("[index]"));
+        assert!(!backlinks.contains("[a]"));
+    }
+
+    /// The slugs of the references listed in the footer of `index`, where `index` cites
+    /// `z` and embeds `a` citing `r` and `z`.
+    fn listed_references(numbered: bool) -> Vec<String> {
+        let mut config = config::test_config("kodama-tests");
+        config.numbered_references = numbered;
+        let index = page("Index", "See [](z.md).\n\n[](/a.md#:embed)");
+        let a = page("Alpha", "Alpha cites [](r.md) and [](z.md).");
+        let r = "---\ntitle: Ref\ntaxon: Reference\n---\n\nRef body.\n";
+        let z = "---\ntitle: Zed\ntaxon: Reference\n---\n\nZed body.\n";
+        let sources = [("index", &*index), ("a", &*a), ("r", r), ("z", z)];
+        let (forest, _guard) = test_forest(&sources, config);
+
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        let (_, footer) = html.split_once("<h1>References</h1>").unwrap();
+        footer
+            .split(r#"<a class="slug" href="/"#)
+            .skip(1)
+            .map(|s| s.split('"').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn references_of_open_embeds_are_listed_once() {
+        assert_eq!(listed_references(false), ["r", "z"]);
+        assert_eq!(listed_references(true), ["z", "r"]);
+    }
+
+    #[test]
+    fn description_of_a_page_starting_with_a_heading_is_its_text() {
+        let config = config::test_config("kodama-tests");
+        let index = page("Index", "## Overview\n\nThe body text.");
+        let (forest, _guard) = test_forest(&[("index", &index)], config);
+
+        let html = Writer::html_doc(&forest.compiled()["index"], &forest.state).0;
+        for tag in [
+            r#"<meta property="og:description" content="Overview The body text.">"#,
+            r#"<meta name="twitter:description" content="Overview The body text.">"#,
+        ] {
+            assert!(html.contains(tag), "{} not in {}", tag, html);
+        }
     }
 }
@@ -1,12 +1,21 @@
 use std::{
+    collections::BTreeMap,
     fs::{self, create_dir_all},
     hash::Hash,
     path::{Path, PathBuf},
-    sync::{LazyLock, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use indexmap::IndexMap;
+use serde::Deserialize;
 use walkdir::WalkDir;
 
+use crate::{compiler::CompileError, date::Date};
+
 #[derive(Clone, clap::ValueEnum)]
 pub enum FooterMode {
     Link,
@@ -22,28 +31,576 @@ impl ToString for FooterMode {
     }
 }
 
+/// How links to non-existent pages are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrokenLinks {
+    Error,
+    Warn,
+    Ignore,
+}
+
+/// How the names of the source files, the tags and the headings become slugs and anchors,
+/// see [`crate::slug::normalize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugMode {
+    /// The names as written.
+    Path,
+    /// The names with their accents composed, and whitespace as `-`.
+    Unicode,
+    /// As `Unicode`, the accented Latin letters becoming ASCII.
+    Translit,
+}
+
+impl SlugMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlugMode::Path => "path",
+            SlugMode::Unicode => "unicode",
+            SlugMode::Translit => "translit",
+        }
+    }
+}
+
+/// The case of the slugs. With `Preserve`, two source files whose slugs differ only
+/// in case are an error, as they are the same file on a case-insensitive filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugCase {
+    Preserve,
+    Lower,
+}
+
+/// The shape of the URLs of the pages, which also decides where they are written,
+/// see [`full_html_url`] and [`output_html_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlStyle {
+    /// `/foo.html`, written to `foo.html`.
+    Html,
+    /// `/foo/`, written to `foo/index.html`.
+    Directory,
+    /// `/foo`, written to `foo.html`, for the hosts serving it at `/foo`.
+    Bare,
+}
+
+impl UrlStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UrlStyle::Html => "html",
+            UrlStyle::Directory => "directory",
+            UrlStyle::Bare => "bare",
+        }
+    }
+}
+
+/// The order of the backlinks of a page. Pages without a `date` come last
+/// in the date orders, and ties are ordered by title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BacklinksOrder {
+    DateDesc,
+    DateAsc,
+    Title,
+}
+
+/// How the backlinks of a page are grouped, each group keeping the [`BacklinksOrder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BacklinksGroupBy {
+    None,
+    Taxon,
+}
+
+/// The words counted for a page, see [`crate::compiler::section::SectionStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordCount {
+    /// Along with those of the embedded pages.
+    Inclusive,
+    Exclusive,
+}
+
+/// What becomes of the pages past their `expires`, see [`crate::entry::KEY_EXPIRES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expired {
+    /// Written with a banner, but left out of the newest-first listings.
+    Archive,
+
+    /// Left out of the build, like the drafts.
+    Unpublish,
+}
+
+/// Where the [`crate::compiler::numbering::Numbering`] of the forest starts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberingScope {
+    Forest,
+    /// At each page embedded by the root page, whose position prefixes the numbers.
+    Chapter,
+}
+
+/// How the entries of the reference pages are written, see
+/// [`crate::html_flake::html_bibliography_entry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BibliographyStyle {
+    AuthorYear,
+    Numeric,
+}
+
+/// How the citations `[@slug]` read, chosen in `kodama.toml` and overridden by the
+/// `citation-style` metadata of a page, see `Writer::style_citations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationStyle {
+    /// `(Knuth 1984, p. 12)`.
+    AuthorYear,
+    /// `[3, p. 12]`, numbered in the references of the page.
+    Numeric,
+    /// A superscript number, the citations being listed at the end of the page.
+    Footnote,
+}
+
+impl std::str::FromStr for CitationStyle {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "author-year" => Ok(CitationStyle::AuthorYear),
+            "numeric" => Ok(CitationStyle::Numeric),
+            "footnote" => Ok(CitationStyle::Footnote),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The `[metadata]` table of [`CONFIG_FILE_NAME`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MetadataTable {
+    /// The `[metadata.defaults]` of every page, see [`metadata_defaults`].
+    pub defaults: BTreeMap<String, toml::Value>,
+
+    /// The `[metadata.schema]` of every page, see [`metadata_schema`].
+    pub schema: MetadataSchema,
+
+    /// The `[metadata.variables]` of every page, see [`metadata_variables`].
+    pub variables: BTreeMap<String, toml::Value>,
+}
+
+/// The keys the pages must or may have, checked by [`crate::compiler::schema`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct MetadataSchema {
+    /// Keys every page must have, reported as errors when missing.
+    pub required: Vec<String>,
+
+    /// Keys the pages may have besides those of kodama and the `required` ones.
+    pub optional: Vec<String>,
+
+    /// The values allowed for some keys, such as `lang = ["en", "zh"]`.
+    pub values: BTreeMap<String, Vec<String>>,
+
+    /// Warn of the keys which are neither known to kodama nor declared.
+    pub strict: bool,
+
+    /// The schema added for the pages of a taxon, by the id of the taxon,
+    /// such as `[metadata.schema.taxa.reference]`.
+    pub taxa: BTreeMap<String, TaxonSchema>,
+}
+
+/// The part of the [`MetadataSchema`] for the pages of a taxon.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct TaxonSchema {
+    pub required: Vec<String>,
+    pub optional: Vec<String>,
+    pub values: BTreeMap<String, Vec<String>>,
+}
+
+/// An entry of the taxon vocabulary, keyed by its canonical id such as `"theorem"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct TaxonEntry {
+    /// Shown instead of the capitalized id.
+    pub display: Option<String>,
+
+    /// Shown instead of the display name on the pages of a language, keyed by its
+    /// `lang` such as `"zh"`, see [`taxon_display`].
+    pub names: BTreeMap<String, String>,
+
+    /// The pages of this taxon are references, see
+    /// [`crate::compiler::state::CompileState::is_reference`].
+    pub reference: bool,
+
+    /// The embeds of this taxon are numbered as with `+`.
+    pub numbering: bool,
+
+    /// Slug of the page listing the pages of this taxon, see [`taxon_page_slug`].
+    pub page: Option<String>,
+}
+
+/// An author under `[authors.<key>]`, the key being written as the `author` of the pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct AuthorEntry {
+    /// Shown instead of the key.
+    pub name: Option<String>,
+
+    /// Linked from the page of the author, see [`author_pages`].
+    pub homepage: Option<String>,
+}
+
+/// A license under `[licenses.<id>]`, the id being written as the `license` of the pages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct LicenseEntry {
+    /// Shown instead of the id.
+    pub name: Option<String>,
+
+    /// Linked from the footer of the pages under the license.
+    pub url: Option<String>,
+}
+
+/// The taxa known without being declared, with whether they are references.
+pub const BUILTIN_TAXA: [(&str, bool); 18] = [
+    ("axiom", false),
+    ("conjecture", false),
+    ("construction", false),
+    ("corollary", false),
+    ("definition", false),
+    ("example", false),
+    ("exercise", false),
+    ("lemma", false),
+    ("notation", false),
+    ("person", false),
+    ("proof", false),
+    ("proposition", false),
+    ("reference", true),
+    ("remark", false),
+    ("theorem", false),
+    ("参考", true),
+    ("参考文献", true),
+    ("参考资料", true),
+];
+
+#[derive(Clone)]
 pub struct CompileConfig<S> {
     pub root_dir: S,
+
+    /// Slug of the page served at the site root.
+    pub root_slug: S,
     pub output_dir: S,
     pub base_url: S,
-    pub page_suffix: S,
+    pub url_style: UrlStyle,
     pub short_slug: bool,
     pub footer_mode: FooterMode,
 
     /// This is very useful for users who want to modify existing styles or configure other themes.
     pub disable_export_css: bool,
+
+    /// Embeds nested deeper than this are rendered as links. `None` means unlimited.
+    pub max_embed_depth: Option<usize>,
+
+    /// Keep the first file instead of failing when several files produce the same slug.
+    pub force: bool,
+
+    /// Reparse, recompile and rewrite every page, ignoring the cache.
+    pub no_cache: bool,
+
+    /// Compile without writing anything, neither the output nor the cache,
+    /// see [`crate::compiler::compile_dry_run`].
+    pub dry_run: bool,
+
+    /// Synthesize stub pages for missing slugs instead of reporting them.
+    pub allow_stubs: bool,
+
+    /// Compile the pages marked `draft` too, with a banner, instead of leaving them out.
+    pub drafts: bool,
+
+    /// Record the time spent on each page.
+    pub timings: bool,
+
+    /// Only write the pages affected by the changes since the last compilation,
+    /// see [`crate::compiler::state::CompileState::is_affected`].
+    pub rerender_affected: bool,
+
+    /// Write the sources embedded in each page as an HTML comment.
+    pub emit_sources: bool,
+
+    pub broken_links: BrokenLinks,
+
+    pub slug_mode: SlugMode,
+
+    pub slug_case: SlugCase,
+
+    /// The slugs of the source files are their names, without their directories.
+    pub flatten_slugs: bool,
+
+    /// The prefix of the slugs of the generated pages, which no source file may have,
+    /// see [`crate::slug::synthetic_slug`].
+    pub reserved_prefix: S,
+
+    /// The longest slug in bytes, the longer ones being truncated, see
+    /// [`crate::slug::path_to_slug`].
+    pub max_slug_length: usize,
+
+    pub backlinks_order: BacklinksOrder,
+
+    pub backlinks_group_by: BacklinksGroupBy,
+
+    /// Show the taxon and the `date` of each backlink after its title.
+    pub backlinks_show_taxon: bool,
+    pub backlinks_show_date: bool,
+
+    /// The taxa listed first when grouping by taxon, the others follow by name.
+    pub taxon_order: Vec<String>,
+
+    /// Backlinks shown on a page, the others are listed in a separate page.
+    pub max_backlinks: Option<usize>,
+
+    /// Slug of the page listing every reference page, see
+    /// [`crate::compiler::state::CompileState::generated_slugs`].
+    pub bibliography_page: Option<S>,
+
+    /// BibTeX file whose entries are imported as reference pages, relative to `root_dir`,
+    /// see [`crate::compiler::state::CompileState::import_bibtex`].
+    pub bibtex: Option<S>,
+
+    /// Metadata keys ordering the bibliography, `title` being the plain text title.
+    pub bibliography_sort: Vec<String>,
+
+    /// Metadata keys every reference page should have, warned about when missing.
+    pub reference_fields: Vec<String>,
+
+    /// Metadata keys whose values are lists separated by commas, see
+    /// [`crate::entry::MetaDataLists`]. [`DEFAULT_LIST_KEYS`] unless set.
+    pub list_keys: Vec<String>,
+
+    pub bibliography_style: BibliographyStyle,
+
+    pub citation_style: CitationStyle,
+
+    /// Slug of the page listing the pages generated for each taxon, see
+    /// [`crate::compiler::state::CompileState::generated_slugs`].
+    pub taxon_pages: Option<S>,
+
+    /// The taxa having a generated page, all of them if empty.
+    pub taxon_pages_only: Vec<String>,
+
+    /// The custom metadata keys shown as rows of the headers, in order, the other ones
+    /// hidden, see [`crate::entry::MetaData::display_keys`]. All shown as items if unset.
+    pub display_metadata: Option<Vec<String>>,
+
+    pub taxon_pages_order: BacklinksOrder,
+
+    /// Slug of the page listing the pages generated for each tag, whose slugs are
+    /// prefixed by it, see [`prefixed_slug`].
+    pub tag_pages: Option<S>,
+
+    /// Slug of the page listing the pages generated for each author, whose slugs are
+    /// prefixed by it, see [`prefixed_slug`].
+    pub author_pages: Option<S>,
+
+    /// The authors known by their keys, see [`AuthorEntry`].
+    pub authors: BTreeMap<String, AuthorEntry>,
+
+    /// The licenses known by their ids, from the least to the most restrictive,
+    /// see [`license_rank`].
+    pub licenses: Vec<(String, LicenseEntry)>,
+
+    /// The license of the pages without `license` metadata.
+    pub license: Option<S>,
+
+    /// Slug of the page listing the pages by license, see
+    /// [`crate::compiler::state::CompileState::generated_slugs`].
+    pub license_page: Option<S>,
+
+    /// Slug of the page listing the pages generated for each series, whose slugs are
+    /// prefixed by it, see [`prefixed_slug`].
+    pub series_pages: Option<S>,
+
+    /// Pages embedding a page needed to show a badge on it, none if unset.
+    pub embed_badge_min: Option<usize>,
+
+    /// Number the citations of references and list the references in that order.
+    pub numbered_references: bool,
+
+    /// List the further reading among the references instead of apart, see
+    /// [`crate::compiler::section::Section::further_reading`].
+    pub merge_further_reading: bool,
+
+    /// Number the numbered sections by their embeds from the root page instead of in
+    /// each page, see [`crate::compiler::numbering::Numbering`].
+    pub auto_numbering: bool,
+
+    pub numbering_scope: NumberingScope,
+
+    /// Show the chain of pages embedding each context,
+    /// see [`crate::compiler::state::CompileState::context_path`].
+    pub transitive_contexts: bool,
+
+    /// Slug of the page listing the incoming links of every page, see
+    /// [`crate::compiler::state::CompileState::generated_slugs`].
+    pub mentions_page: Option<S>,
+
+    /// Related pages shown on a page, see [`crate::compiler::related::related_pages`].
+    pub max_related: Option<usize>,
+
+    pub min_related_score: f64,
+
+    /// Write the links of all pages, see [`crate::compiler::link_index::LinkIndex`].
+    pub emit_link_index: bool,
+
+    /// Write the metadata of each page next to its HTML,
+    /// see [`crate::compiler::page_meta::PageMeta`].
+    pub emit_page_meta: bool,
+
+    /// The keys of the page metadata kept as HTML, the others being plain text.
+    pub page_meta_html: Vec<String>,
+
+    /// Describe each page for the link previews of other sites in its head,
+    /// see [`crate::html_flake::html_social_meta`].
+    pub social_meta: bool,
+
+    /// The directives for the search engines of the pages without `robots` metadata,
+    /// see [`crate::entry::KEY_ROBOTS`].
+    pub robots: Vec<String>,
+
+    /// Commands run on each compiled section, see [`crate::compiler::hooks::command_hook`].
+    pub post_hooks: Vec<String>,
+
+    /// The taxa declared in addition to [`BUILTIN_TAXA`], by canonical id.
+    pub taxa: BTreeMap<String, TaxonEntry>,
+
+    /// The metadata of the pages not setting it themselves, written as in the front matter,
+    /// see [`crate::compiler::defaults`].
+    pub metadata_defaults: BTreeMap<String, String>,
+
+    /// The keys the pages must or may have, see [`MetadataSchema`].
+    pub metadata_schema: MetadataSchema,
+
+    /// The values of the `{key}` variables in the metadata, written as in the front matter,
+    /// see [`crate::compiler::variables`].
+    pub metadata_variables: BTreeMap<String, String>,
+
+    /// The language of the pages without `lang` metadata, see [`crate::entry::KEY_LANG`].
+    pub lang: Option<S>,
+
+    /// How the parsed dates are shown, such as `"%-d %B %Y"`, or as written if unset,
+    /// see [`crate::date::Date::format`].
+    pub date_format: Option<S>,
+
+    /// The words of a page given by [`crate::entry::KEY_WORDCOUNT`].
+    pub word_count: WordCount,
+
+    /// Count the words of the code blocks too.
+    pub word_count_code: bool,
+
+    /// The reading speed giving [`crate::entry::KEY_READINGTIME`].
+    pub words_per_minute: usize,
+
+    /// Show the reading time in the header of each page.
+    pub show_reading_time: bool,
+
+    /// Fill in the `updated` of the pages without one from the date of the last commit
+    /// changing their source files, see [`crate::compiler::git_dates`].
+    pub git_dates: bool,
+
+    /// What becomes of the pages past their `expires`.
+    pub expired: Expired,
+
+    /// The date the pages expire against instead of today, see [`today`].
+    pub now: Option<Date>,
 }
 
 impl CompileConfig<&'static str> {
     pub const fn default() -> CompileConfig<&'static str> {
         CompileConfig {
             root_dir: "./",
+            root_slug: "index",
             output_dir: "./publish",
             base_url: "/",
-            page_suffix: "",
+            url_style: UrlStyle::Bare,
             short_slug: true,
             footer_mode: FooterMode::Link,
             disable_export_css: true,
+            max_embed_depth: None,
+            force: false,
+            no_cache: false,
+            dry_run: false,
+            allow_stubs: false,
+            drafts: false,
+            timings: false,
+            rerender_affected: false,
+            emit_sources: false,
+            broken_links: BrokenLinks::Warn,
+            slug_mode: SlugMode::Path,
+            slug_case: SlugCase::Preserve,
+            flatten_slugs: false,
+            reserved_prefix: DEFAULT_RESERVED_PREFIX,
+            max_slug_length: DEFAULT_MAX_SLUG_LENGTH,
+            backlinks_order: BacklinksOrder::DateDesc,
+            backlinks_group_by: BacklinksGroupBy::None,
+            backlinks_show_taxon: false,
+            backlinks_show_date: false,
+            taxon_order: Vec::new(),
+            max_backlinks: None,
+            embed_badge_min: None,
+            numbered_references: false,
+            merge_further_reading: false,
+            auto_numbering: false,
+            numbering_scope: NumberingScope::Forest,
+            transitive_contexts: false,
+            mentions_page: None,
+            bibliography_page: None,
+            bibtex: None,
+            taxon_pages: None,
+            taxon_pages_only: Vec::new(),
+            display_metadata: None,
+            taxon_pages_order: BacklinksOrder::Title,
+            tag_pages: None,
+            author_pages: None,
+            series_pages: Some(DEFAULT_SERIES_PAGES_PREFIX),
+            authors: BTreeMap::new(),
+            licenses: Vec::new(),
+            license: None,
+            license_page: None,
+            bibliography_sort: Vec::new(),
+            reference_fields: Vec::new(),
+            list_keys: Vec::new(),
+            bibliography_style: BibliographyStyle::AuthorYear,
+            citation_style: CitationStyle::AuthorYear,
+            max_related: None,
+            min_related_score: DEFAULT_MIN_RELATED_SCORE,
+            emit_link_index: true,
+            emit_page_meta: false,
+            page_meta_html: Vec::new(),
+            social_meta: true,
+            robots: Vec::new(),
+            post_hooks: Vec::new(),
+            taxa: BTreeMap::new(),
+            metadata_defaults: BTreeMap::new(),
+            metadata_variables: BTreeMap::new(),
+            metadata_schema: MetadataSchema {
+                required: Vec::new(),
+                optional: Vec::new(),
+                values: BTreeMap::new(),
+                strict: false,
+                taxa: BTreeMap::new(),
+            },
+            lang: None,
+            date_format: None,
+            word_count: WordCount::Inclusive,
+            word_count_code: true,
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            show_reading_time: false,
+            git_dates: false,
+            expired: Expired::Archive,
+            now: None,
         }
     }
 }
@@ -52,12 +609,86 @@ impl CompileConfig<String> {
     const fn empty() -> CompileConfig<String> {
         CompileConfig {
             root_dir: String::new(),
+            root_slug: String::new(),
             output_dir: String::new(),
             base_url: String::new(),
-            page_suffix: String::new(),
+            url_style: UrlStyle::Bare,
             short_slug: true,
             footer_mode: FooterMode::Link,
             disable_export_css: true,
+            max_embed_depth: None,
+            force: false,
+            no_cache: false,
+            dry_run: false,
+            allow_stubs: false,
+            drafts: false,
+            timings: false,
+            rerender_affected: false,
+            emit_sources: false,
+            broken_links: BrokenLinks::Warn,
+            slug_mode: SlugMode::Path,
+            slug_case: SlugCase::Preserve,
+            flatten_slugs: false,
+            reserved_prefix: String::new(),
+            max_slug_length: DEFAULT_MAX_SLUG_LENGTH,
+            backlinks_order: BacklinksOrder::DateDesc,
+            backlinks_group_by: BacklinksGroupBy::None,
+            backlinks_show_taxon: false,
+            backlinks_show_date: false,
+            taxon_order: Vec::new(),
+            max_backlinks: None,
+            embed_badge_min: None,
+            numbered_references: false,
+            merge_further_reading: false,
+            auto_numbering: false,
+            numbering_scope: NumberingScope::Forest,
+            transitive_contexts: false,
+            mentions_page: None,
+            bibliography_page: None,
+            bibtex: None,
+            taxon_pages: None,
+            taxon_pages_only: Vec::new(),
+            display_metadata: None,
+            taxon_pages_order: BacklinksOrder::Title,
+            tag_pages: None,
+            author_pages: None,
+            series_pages: None,
+            authors: BTreeMap::new(),
+            licenses: Vec::new(),
+            license: None,
+            license_page: None,
+            bibliography_sort: Vec::new(),
+            reference_fields: Vec::new(),
+            list_keys: Vec::new(),
+            bibliography_style: BibliographyStyle::AuthorYear,
+            citation_style: CitationStyle::AuthorYear,
+            max_related: None,
+            min_related_score: DEFAULT_MIN_RELATED_SCORE,
+            emit_link_index: true,
+            emit_page_meta: false,
+            page_meta_html: Vec::new(),
+            social_meta: true,
+            robots: Vec::new(),
+            post_hooks: Vec::new(),
+            taxa: BTreeMap::new(),
+            metadata_defaults: BTreeMap::new(),
+            metadata_variables: BTreeMap::new(),
+            metadata_schema: MetadataSchema {
+                required: Vec::new(),
+                optional: Vec::new(),
+                values: BTreeMap::new(),
+                strict: false,
+                taxa: BTreeMap::new(),
+            },
+            lang: None,
+            date_format: None,
+            word_count: WordCount::Inclusive,
+            word_count_code: true,
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            show_reading_time: false,
+            git_dates: false,
+            expired: Expired::Archive,
+            now: None,
         }
     }
 
@@ -72,12 +703,358 @@ impl CompileConfig<String> {
     ) -> CompileConfig<String> {
         CompileConfig {
             root_dir,
+            root_slug: DEFAULT_CONFIG.root_slug.to_string(),
             output_dir,
             base_url: normalize_base_url(&base_url),
-            page_suffix: to_page_suffix(disable_pretty_urls),
+            url_style: to_url_style(disable_pretty_urls),
             short_slug,
             footer_mode,
             disable_export_css,
+            max_embed_depth: None,
+            force: false,
+            no_cache: false,
+            dry_run: false,
+            allow_stubs: false,
+            drafts: false,
+            timings: false,
+            rerender_affected: false,
+            emit_sources: false,
+            broken_links: BrokenLinks::Warn,
+            slug_mode: SlugMode::Path,
+            slug_case: SlugCase::Preserve,
+            flatten_slugs: false,
+            reserved_prefix: DEFAULT_CONFIG.reserved_prefix.to_string(),
+            max_slug_length: DEFAULT_CONFIG.max_slug_length,
+            backlinks_order: BacklinksOrder::DateDesc,
+            backlinks_group_by: BacklinksGroupBy::None,
+            backlinks_show_taxon: false,
+            backlinks_show_date: false,
+            taxon_order: Vec::new(),
+            max_backlinks: None,
+            embed_badge_min: None,
+            numbered_references: false,
+            merge_further_reading: false,
+            auto_numbering: false,
+            numbering_scope: NumberingScope::Forest,
+            transitive_contexts: false,
+            mentions_page: None,
+            bibliography_page: None,
+            bibtex: None,
+            taxon_pages: None,
+            taxon_pages_only: Vec::new(),
+            display_metadata: None,
+            taxon_pages_order: BacklinksOrder::Title,
+            tag_pages: None,
+            author_pages: None,
+            series_pages: Some(DEFAULT_SERIES_PAGES_PREFIX.to_string()),
+            authors: BTreeMap::new(),
+            licenses: Vec::new(),
+            license: None,
+            license_page: None,
+            bibliography_sort: Vec::new(),
+            reference_fields: Vec::new(),
+            list_keys: DEFAULT_LIST_KEYS.map(String::from).to_vec(),
+            bibliography_style: BibliographyStyle::AuthorYear,
+            citation_style: CitationStyle::AuthorYear,
+            max_related: None,
+            min_related_score: DEFAULT_MIN_RELATED_SCORE,
+            emit_link_index: true,
+            emit_page_meta: false,
+            page_meta_html: Vec::new(),
+            social_meta: true,
+            robots: Vec::new(),
+            post_hooks: Vec::new(),
+            taxa: BTreeMap::new(),
+            metadata_defaults: BTreeMap::new(),
+            metadata_variables: BTreeMap::new(),
+            metadata_schema: MetadataSchema {
+                required: Vec::new(),
+                optional: Vec::new(),
+                values: BTreeMap::new(),
+                strict: false,
+                taxa: BTreeMap::new(),
+            },
+            lang: None,
+            date_format: None,
+            word_count: WordCount::Inclusive,
+            word_count_code: true,
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            show_reading_time: false,
+            git_dates: false,
+            expired: Expired::Archive,
+            now: None,
+        }
+    }
+}
+
+/// The configuration of a compilation, see [`crate::compile_tree`].
+pub type Config = CompileConfig<String>;
+
+pub const DEFAULT_MENTIONS_SLUG: &str = "mentions";
+
+pub const DEFAULT_BIBLIOGRAPHY_SLUG: &str = "bibliography";
+
+pub const DEFAULT_LICENSE_PAGE_SLUG: &str = "licenses";
+
+pub const DEFAULT_BIBLIOGRAPHY_SORT: [&str; 3] = ["author", "year", "title"];
+
+pub const DEFAULT_REFERENCE_FIELDS: [&str; 3] = ["author", "title", "year"];
+
+pub const DEFAULT_LIST_KEYS: [&str; 3] = ["tags", "authors", "aliases"];
+
+pub const DEFAULT_TAXON_PAGES_SLUG: &str = "taxa";
+
+pub const DEFAULT_RESERVED_PREFIX: &str = "-/";
+
+pub const DEFAULT_MAX_SLUG_LENGTH: usize = 200;
+
+pub const DEFAULT_TAG_PAGES_PREFIX: &str = "tags";
+
+pub const DEFAULT_AUTHOR_PAGES_PREFIX: &str = "authors";
+
+pub const DEFAULT_SERIES_PAGES_PREFIX: &str = "series";
+
+pub const DEFAULT_MIN_RELATED_SCORE: f64 = 0.2;
+
+pub const DEFAULT_WORDS_PER_MINUTE: usize = 200;
+
+pub const CONFIG_FILE_NAME: &str = "kodama.toml";
+
+/// Read in each directory of the workspace, as the metadata of the pages beneath it.
+pub const META_FILE_NAME: &str = "meta.toml";
+
+/// Read in the workspace dir, as the frozen slugs of the source files,
+/// see [`crate::compiler::frozen`].
+pub const SLUGS_FILE_NAME: &str = "slugs.toml";
+
+/// Options read from [`CONFIG_FILE_NAME`] in the project root.
+/// Command line arguments take precedence over these.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// Slug of the page served at the site root.
+    pub root: Option<String>,
+
+    pub max_embed_depth: Option<usize>,
+
+    /// Synthesize stub pages for embedded or linked slugs which do not exist.
+    pub allow_stubs: Option<bool>,
+
+    /// One of `"error"`, `"warn"` (the default) or `"ignore"`.
+    pub broken_links: Option<BrokenLinks>,
+
+    /// One of `"path"` (the default), `"unicode"` or `"translit"`.
+    pub slug_mode: Option<SlugMode>,
+
+    /// One of `"preserve"` (the default) or `"lower"`.
+    pub slug_case: Option<SlugCase>,
+
+    /// One of `"html"`, `"directory"` or `"bare"` (the default).
+    pub url_style: Option<UrlStyle>,
+
+    /// Whether the slugs of the source files are their names without their directories,
+    /// as in `groups` for `math/algebra/groups.md`, false by default.
+    pub flatten_slugs: Option<bool>,
+
+    /// The prefix of the slugs of the generated pages, `"-/"` by default, as in `-/tags` and
+    /// `-/tags/rust`. No source file may have a slug under it, and `""` lets them share
+    /// the namespace of the source files as before.
+    pub reserved_prefix: Option<String>,
+
+    /// The longest slug in bytes, 200 by default. A longer slug is cut, keeping its
+    /// directories, and ends with a hash of the whole, as `a-long-title-3f2a9c01`.
+    pub max_slug_length: Option<usize>,
+
+    /// One of `"date-desc"` (the default), `"date-asc"` or `"title"`.
+    pub backlinks_order: Option<BacklinksOrder>,
+
+    /// One of `"none"` (the default) or `"taxon"`.
+    pub backlinks_group_by: Option<BacklinksGroupBy>,
+
+    /// Show the taxon of each backlink after its title.
+    pub backlinks_show_taxon: Option<bool>,
+
+    /// Show the `date` of each backlink after its title.
+    pub backlinks_show_date: Option<bool>,
+
+    /// The taxa listed first when grouping backlinks by taxon, in order.
+    pub taxon_order: Option<Vec<String>>,
+
+    pub max_backlinks: Option<usize>,
+
+    /// Show "Transcluded in N places" on the pages embedded by at least this many pages.
+    pub embed_badge_min: Option<usize>,
+
+    /// Number the citations of references as `[1]`, `[2]`, ... in order of appearance.
+    pub numbered_references: Option<bool>,
+
+    /// List the links `[Text](slug#:further)` among the references, `false` by default.
+    pub merge_further_reading: Option<bool>,
+
+    /// Number the sections of numbered taxa as `Theorem 3.2` across the forest, counting
+    /// from the root page, instead of in each page.
+    pub auto_numbering: Option<bool>,
+
+    /// `"chapter"` to number the sections of each page embedded by the root page
+    /// as `2.1`, `2.2`, ... by its position, instead of across the forest.
+    pub numbering_scope: Option<NumberingScope>,
+
+    /// Show the pages embedding each context of a page, up to the root page.
+    pub transitive_contexts: Option<bool>,
+
+    /// Generate a page listing the incoming links of every page.
+    pub mentions: Option<bool>,
+
+    /// Slug of that page, `"mentions"` by default.
+    pub mentions_slug: Option<String>,
+
+    /// Generate a page listing every reference page with the pages citing it.
+    pub bibliography: Option<bool>,
+
+    /// Slug of that page, `"bibliography"` by default.
+    pub bibliography_slug: Option<String>,
+
+    /// A BibTeX file, such as `"refs.bib"`, whose entries are imported as reference pages.
+    pub bibtex: Option<String>,
+
+    /// Metadata keys ordering the bibliography, `["author", "year", "title"]` by default.
+    pub bibliography_sort: Option<Vec<String>>,
+
+    /// Metadata keys reported when missing on a reference page,
+    /// `["author", "title", "year"]` by default.
+    pub reference_fields: Option<Vec<String>>,
+
+    /// The custom metadata keys shown as rows of the header of a page, in this order,
+    /// such as `["status", "source"]`. The keys kodama does not know and which are not
+    /// listed are then hidden. All custom keys are shown if unset.
+    pub display_metadata: Option<Vec<String>>,
+
+    /// Metadata keys whose values are separated by commas, as in `tags: rust, compilers`,
+    /// `["tags", "authors", "aliases"]` by default.
+    pub list_keys: Option<Vec<String>>,
+
+    /// One of `"author-year"` (the default) or `"numeric"`.
+    pub bibliography_style: Option<BibliographyStyle>,
+
+    /// One of `"author-year"` (the default), `"numeric"` or `"footnote"`.
+    pub citation_style: Option<CitationStyle>,
+
+    /// Generate a page listing the pages of each taxon, and a page listing those pages.
+    pub taxon_pages: Option<bool>,
+
+    /// Slug of the latter, `"taxa"` by default.
+    pub taxon_pages_slug: Option<String>,
+
+    /// The taxa having a generated page, all of them by default.
+    pub taxon_pages_only: Option<Vec<String>>,
+
+    /// The order of the pages of a taxon, `"title"` (the default), `"date-desc"` or `"date-asc"`.
+    pub taxon_pages_order: Option<BacklinksOrder>,
+
+    /// Generate a page listing the pages of each of the `tags`, newest first,
+    /// and a page listing those pages.
+    pub tag_pages: Option<bool>,
+
+    /// Slug of the latter and prefix of the slugs of the former, `"tags"` by default.
+    pub tag_pages_prefix: Option<String>,
+
+    /// Generate a page listing the pages of each author, newest first,
+    /// and a page listing those pages.
+    pub author_pages: Option<bool>,
+
+    /// Slug of the latter and prefix of the slugs of the former, `"authors"` by default.
+    pub author_pages_prefix: Option<String>,
+
+    /// The name and the homepage of the authors, by the key written as their `author`.
+    pub authors: Option<BTreeMap<String, AuthorEntry>>,
+
+    /// The name and the URL of the licenses, by the id written as their `license`,
+    /// from the least to the most restrictive.
+    pub licenses: Option<IndexMap<String, LicenseEntry>>,
+
+    /// The id of the license of the pages without one.
+    pub license: Option<String>,
+
+    /// Generate a page listing the pages by license.
+    pub license_page: Option<bool>,
+
+    /// Slug of that page, `"licenses"` by default.
+    pub license_page_slug: Option<String>,
+
+    /// Generate a page listing the parts of each `series` in order, and a page listing
+    /// those pages, `true` by default.
+    pub series_pages: Option<bool>,
+
+    /// Slug of the latter and prefix of the slugs of the former, `"series"` by default.
+    pub series_pages_prefix: Option<String>,
+
+    /// Related pages shown on each page, none if unset.
+    pub max_related: Option<usize>,
+
+    /// Score in `0..=2` a page needs to be shown as related.
+    pub min_related_score: Option<f64>,
+
+    /// Write `.kodama/links.json` into the output directory, `true` by default.
+    pub emit_link_index: Option<bool>,
+
+    /// Write `<slug>.meta.json` next to the HTML of each page, `false` by default.
+    pub emit_page_meta: Option<bool>,
+
+    /// The keys whose values are kept as HTML in `<slug>.meta.json`, plain text otherwise.
+    pub page_meta_html: Option<Vec<String>>,
+
+    /// Write the Open Graph and Twitter card tags of each page, `true` by default.
+    pub social_meta: Option<bool>,
+
+    /// The `robots` of the pages not setting it, such as `["noindex"]`.
+    pub robots: Option<Vec<String>>,
+
+    /// Commands run on each compiled section, in order.
+    pub post_hooks: Option<Vec<String>>,
+
+    /// The taxon vocabulary as `[taxa.<id>]` tables, overriding the built-in taxa.
+    pub taxa: Option<BTreeMap<String, TaxonEntry>>,
+
+    /// The language of the pages, such as `"zh"`, unless they set their own.
+    pub lang: Option<String>,
+
+    /// How the dates are shown, such as `"%-d %B %Y"`, the month named in the `lang`.
+    pub date_format: Option<String>,
+
+    /// One of `"inclusive"` (the default), counting the words of the embedded pages
+    /// too, or `"exclusive"`.
+    pub word_count: Option<WordCount>,
+
+    /// Count the words of the code blocks, `true` by default.
+    pub word_count_code: Option<bool>,
+
+    /// The reading speed, 200 words per minute by default.
+    pub words_per_minute: Option<usize>,
+
+    /// Show the reading time in the header of each page, such as "~7 min read".
+    pub show_reading_time: Option<bool>,
+
+    /// Fill in the `updated` of each page from the git history.
+    pub git_dates: Option<bool>,
+
+    /// What becomes of the pages past their `expires`, `"archive"` (the default)
+    /// or `"unpublish"`.
+    pub expired: Option<Expired>,
+
+    /// The `[metadata.defaults]` of every page, overridden by those of its directories
+    /// in their `meta.toml` and by its own.
+    pub metadata: Option<MetadataTable>,
+}
+
+impl ConfigFile {
+    /// A missing config file is treated as an empty one.
+    pub fn read(root_dir: &str) -> Result<ConfigFile, CompileError> {
+        let path = join_path(root_dir, CONFIG_FILE_NAME);
+        match fs::read_to_string(&path) {
+            Err(_) => Ok(ConfigFile::default()),
+            Ok(content) => toml::from_str(&content).map_err(|e| {
+                CompileError::Syntax(Some(concat!(file!(), '#', line!())), Box::new(e), path)
+            }),
         }
     }
 }
@@ -99,6 +1076,13 @@ pub static CUSTOM_MATH_HTML: LazyLock<String> = LazyLock::new(|| {
         .unwrap_or(include_str!("include/import-math.html").to_string())
 });
 
+/// The redirect written at each alias, where `{url}` is the URL of the page
+/// and `{title}` its title, see [`crate::compiler::aliases`].
+pub static CUSTOM_REDIRECT_HTML: LazyLock<String> = LazyLock::new(|| {
+    fs::read_to_string(join_path(&root_dir(), "import-redirect.html"))
+        .unwrap_or(include_str!("include/redirect.html").to_string())
+});
+
 pub fn lock_config() -> std::sync::MutexGuard<'static, CompileConfig<std::string::String>> {
     CONFIG.lock().unwrap()
 }
@@ -113,18 +1097,51 @@ pub const CACHE_DIR_NAME: &str = ".cache";
 pub const BUFFER_FILE_NAME: &str = "buffer";
 pub const HASH_DIR_NAME: &str = "hash";
 pub const ENTRY_DIR_NAME: &str = "entry";
+pub const SECTION_DIR_NAME: &str = "section";
+pub const VERSION_FILE_NAME: &str = "version";
+pub const CALLBACK_FILE_NAME: &str = "callback.json";
+pub const EXPIRED_FILE_NAME: &str = "expired.json";
+pub const SLUG_MODE_FILE_NAME: &str = "slug-mode";
+
+/// Bumped whenever the format of the cache changes.
+pub const CACHE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+6");
+
+/// Held by the tests which set [`CONFIG`], as the tests run in parallel.
+#[cfg(test)]
+static TEST_CONFIG: Mutex<()> = Mutex::new(());
+
+/// Set [`CONFIG`] to `config` for the test holding the returned guard.
+#[cfg(test)]
+pub fn lock_test_config(config: CompileConfig<String>) -> std::sync::MutexGuard<'static, ()> {
+    let guard = TEST_CONFIG.lock().unwrap_or_else(|err| err.into_inner());
+    mutex_set(&CONFIG, config);
+    guard
+}
+
+/// The config of the tests, with the project root `root_dir`, which needs not exist.
+#[cfg(test)]
+pub fn test_config(root_dir: &str) -> CompileConfig<String> {
+    CompileConfig::new(
+        root_dir.to_string(),
+        "./publish".to_string(),
+        "/".to_string(),
+        false,
+        false,
+        FooterMode::Link,
+        true,
+    )
+}
 
 pub fn mutex_set<T>(source: &Mutex<T>, target: T) {
     let mut guard = source.lock().unwrap();
     *guard = target;
 }
 
-pub fn to_page_suffix(disable_pretty_urls: bool) -> String {
-    let page_suffix = match disable_pretty_urls {
-        true => ".html",
-        false => "",
-    };
-    page_suffix.into()
+pub fn to_url_style(disable_pretty_urls: bool) -> UrlStyle {
+    match disable_pretty_urls {
+        true => UrlStyle::Html,
+        false => UrlStyle::Bare,
+    }
 }
 
 pub fn normalize_base_url(base_url: &str) -> String {
@@ -138,6 +1155,10 @@ pub fn is_short_slug() -> bool {
     lock_config().short_slug
 }
 
+pub fn root_slug() -> String {
+    lock_config().root_slug.to_string()
+}
+
 pub fn root_dir() -> String {
     lock_config().root_dir.to_string()
 }
@@ -158,6 +1179,474 @@ pub fn disable_export_css() -> bool {
     lock_config().disable_export_css
 }
 
+pub fn broken_links() -> BrokenLinks {
+    lock_config().broken_links
+}
+
+pub fn slug_mode() -> SlugMode {
+    lock_config().slug_mode
+}
+
+pub fn slug_case() -> SlugCase {
+    lock_config().slug_case
+}
+
+pub fn is_flatten_slugs() -> bool {
+    lock_config().flatten_slugs
+}
+
+pub fn reserved_prefix() -> String {
+    lock_config().reserved_prefix.clone()
+}
+
+pub fn max_slug_length() -> usize {
+    lock_config().max_slug_length
+}
+
+pub fn backlinks_order() -> BacklinksOrder {
+    lock_config().backlinks_order
+}
+
+pub fn backlinks_group_by() -> BacklinksGroupBy {
+    lock_config().backlinks_group_by
+}
+
+pub fn is_backlinks_show_taxon() -> bool {
+    lock_config().backlinks_show_taxon
+}
+
+pub fn is_backlinks_show_date() -> bool {
+    lock_config().backlinks_show_date
+}
+
+pub fn taxon_order() -> Vec<String> {
+    lock_config().taxon_order.clone()
+}
+
+pub fn max_backlinks() -> Option<usize> {
+    lock_config().max_backlinks
+}
+
+pub fn is_numbered_references() -> bool {
+    lock_config().numbered_references
+}
+
+pub fn is_merge_further_reading() -> bool {
+    lock_config().merge_further_reading
+}
+
+pub fn embed_badge_min() -> Option<usize> {
+    lock_config().embed_badge_min
+}
+
+pub fn is_auto_numbering() -> bool {
+    lock_config().auto_numbering
+}
+
+pub fn numbering_scope() -> NumberingScope {
+    lock_config().numbering_scope
+}
+
+pub fn is_transitive_contexts() -> bool {
+    lock_config().transitive_contexts
+}
+
+pub fn mentions_page() -> Option<String> {
+    lock_config().mentions_page.clone()
+}
+
+pub fn bibliography_page() -> Option<String> {
+    lock_config().bibliography_page.clone()
+}
+
+pub fn bibtex() -> Option<String> {
+    lock_config().bibtex.clone()
+}
+
+pub fn default_lang() -> Option<String> {
+    lock_config().lang.clone()
+}
+
+pub fn date_format() -> Option<String> {
+    lock_config().date_format.clone()
+}
+
+pub fn word_count() -> WordCount {
+    lock_config().word_count
+}
+
+pub fn is_word_count_code() -> bool {
+    lock_config().word_count_code
+}
+
+pub fn words_per_minute() -> usize {
+    lock_config().words_per_minute
+}
+
+pub fn is_show_reading_time() -> bool {
+    lock_config().show_reading_time
+}
+
+pub fn is_git_dates() -> bool {
+    lock_config().git_dates
+}
+
+pub fn bibliography_style() -> BibliographyStyle {
+    lock_config().bibliography_style
+}
+
+pub fn citation_style() -> CitationStyle {
+    lock_config().citation_style
+}
+
+pub fn taxon_pages() -> Option<String> {
+    lock_config().taxon_pages.clone()
+}
+
+pub fn display_metadata() -> Option<Vec<String>> {
+    lock_config().display_metadata.clone()
+}
+
+pub fn taxon_pages_only() -> Vec<String> {
+    lock_config().taxon_pages_only.clone()
+}
+
+pub fn taxon_pages_order() -> BacklinksOrder {
+    lock_config().taxon_pages_order
+}
+
+/// The `page` of the taxon shown as `data_taxon`, or its plural in lowercase, under
+/// [`reserved_prefix`], such as `-/definitions` for `Definition` and `-/worked-examples`
+/// for `Worked Example`.
+pub fn taxon_page_slug(data_taxon: &str) -> String {
+    let page = taxon_entry(data_taxon)
+        .and_then(|entry| entry.page)
+        .unwrap_or_else(|| {
+            let id = data_taxon.trim().to_lowercase().replace(' ', "-");
+            crate::compiler::taxon::Taxon::plural(&id)
+        });
+    crate::slug::synthetic_slug(&page, "")
+}
+
+pub fn tag_pages() -> Option<String> {
+    lock_config().tag_pages.clone()
+}
+
+/// The page of the tag, the author or the series `name` under the `prefix` of
+/// [`tag_pages`], [`author_pages`] or [`series_pages`], in lowercase with spaces and dots as `-`, such as
+/// `-/tags/machine-learning` for `Machine Learning`, see [`crate::slug::synthetic_slug`].
+/// The [`crate::slug::REJECTED_CHARS`] are written `-` too.
+pub fn prefixed_slug(prefix: &str, name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let name = name
+        .replace([' ', '.'], "-")
+        .replace(crate::slug::REJECTED_CHARS, "-");
+    crate::slug::to_slug(&crate::slug::synthetic_slug(prefix, &name))
+}
+
+pub fn author_pages() -> Option<String> {
+    lock_config().author_pages.clone()
+}
+
+pub fn series_pages() -> Option<String> {
+    lock_config().series_pages.clone()
+}
+
+pub fn author_entry(author: &str) -> Option<AuthorEntry> {
+    lock_config().authors.get(author.trim()).cloned()
+}
+
+/// The declared `license`, its id in any case.
+pub fn license_entry(license: &str) -> Option<LicenseEntry> {
+    let licenses = &lock_config().licenses;
+    licenses
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(license.trim()))
+        .map(|(_, entry)| entry.clone())
+}
+
+/// The name shown for `license`, see [`LicenseEntry::name`].
+pub fn license_name(license: &str) -> String {
+    license_entry(license)
+        .and_then(|entry| entry.name)
+        .unwrap_or_else(|| license.trim().to_string())
+}
+
+/// The place of `license` among the declared licenses, the most restrictive last,
+/// `None` if undeclared.
+pub fn license_rank(license: &str) -> Option<usize> {
+    let licenses = &lock_config().licenses;
+    licenses
+        .iter()
+        .position(|(id, _)| id.eq_ignore_ascii_case(license.trim()))
+}
+
+pub fn licenses() -> Vec<(String, LicenseEntry)> {
+    lock_config().licenses.clone()
+}
+
+pub fn default_license() -> Option<String> {
+    lock_config().license.clone()
+}
+
+pub fn license_page() -> Option<String> {
+    lock_config().license_page.clone()
+}
+
+/// The name shown for `author`, see [`AuthorEntry::name`].
+pub fn author_name(author: &str) -> String {
+    author_entry(author)
+        .and_then(|entry| entry.name)
+        .unwrap_or_else(|| author.trim().to_string())
+}
+
+pub fn bibliography_sort() -> Vec<String> {
+    lock_config().bibliography_sort.clone()
+}
+
+pub fn reference_fields() -> Vec<String> {
+    lock_config().reference_fields.clone()
+}
+
+pub fn is_list_key(key: &str) -> bool {
+    lock_config()
+        .list_keys
+        .iter()
+        .any(|list_key| list_key == key)
+}
+
+pub fn max_related() -> Option<usize> {
+    lock_config().max_related
+}
+
+pub fn min_related_score() -> f64 {
+    lock_config().min_related_score
+}
+
+pub fn max_embed_depth() -> Option<usize> {
+    lock_config().max_embed_depth
+}
+
+pub fn is_force() -> bool {
+    lock_config().force
+}
+
+pub fn is_emit_sources() -> bool {
+    lock_config().emit_sources
+}
+
+pub fn is_emit_link_index() -> bool {
+    lock_config().emit_link_index
+}
+
+pub fn is_emit_page_meta() -> bool {
+    lock_config().emit_page_meta
+}
+
+pub fn page_meta_html() -> Vec<String> {
+    lock_config().page_meta_html.clone()
+}
+
+pub fn is_social_meta() -> bool {
+    lock_config().social_meta
+}
+
+pub fn robots() -> Vec<String> {
+    lock_config().robots.clone()
+}
+
+pub fn metadata_defaults() -> BTreeMap<String, String> {
+    lock_config().metadata_defaults.clone()
+}
+
+pub fn metadata_variables() -> BTreeMap<String, String> {
+    lock_config().metadata_variables.clone()
+}
+
+pub fn metadata_schema() -> MetadataSchema {
+    lock_config().metadata_schema.clone()
+}
+
+/// The `values` of a TOML table written as in the front matter,
+/// such as `[a, b]` for an array.
+pub fn metadata_text(values: &BTreeMap<String, toml::Value>) -> BTreeMap<String, String> {
+    fn text(value: &toml::Value) -> String {
+        match value {
+            toml::Value::String(s) => s.to_string(),
+            toml::Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(text).collect();
+                format!("[{}]", items.join(", "))
+            }
+            value => value.to_string(),
+        }
+    }
+    values
+        .iter()
+        .map(|(key, value)| (key.to_string(), text(value)))
+        .collect()
+}
+
+pub fn post_hooks() -> Vec<String> {
+    lock_config().post_hooks.clone()
+}
+
+/// The entry of the taxon shown as `data_taxon`, matched against the ids
+/// ignoring case and against the display names in any language, `None` for unknown taxa.
+pub fn taxon_entry(data_taxon: &str) -> Option<TaxonEntry> {
+    find_taxon(data_taxon).map(|(_, entry)| entry)
+}
+
+/// The canonical id of the taxon shown as `data_taxon`, such as `theorem` for a
+/// localized name of it, or `data_taxon` itself for unknown taxa.
+pub fn taxon_id(data_taxon: &str) -> String {
+    find_taxon(data_taxon).map_or_else(|| data_taxon.trim().to_string(), |(id, _)| id)
+}
+
+/// The canonical id along with the entry, see [`taxon_entry`].
+fn find_taxon(data_taxon: &str) -> Option<(String, TaxonEntry)> {
+    let data_taxon = data_taxon.trim();
+    let is_id = |id: &str| id.to_lowercase() == data_taxon.to_lowercase();
+    let is_name = |entry: &TaxonEntry| {
+        entry.display.as_deref() == Some(data_taxon)
+            || entry.names.values().any(|name| name == data_taxon)
+    };
+    let declared = lock_config()
+        .taxa
+        .iter()
+        .find(|(id, entry)| is_id(id) || is_name(entry))
+        .map(|(id, entry)| (id.to_string(), entry.clone()));
+    declared.or_else(|| {
+        BUILTIN_TAXA
+            .iter()
+            .find(|(id, _)| is_id(id))
+            .map(|&(id, reference)| {
+                let entry = TaxonEntry {
+                    reference,
+                    ..TaxonEntry::default()
+                };
+                (id.to_string(), entry)
+            })
+    })
+}
+
+/// The name of the taxon `data_taxon` on a page in `lang`, falling back to the
+/// [`default_lang`] and then to its display name, `None` to capitalize it as written.
+/// A language `zh-CN` also takes the names of `zh`.
+pub fn taxon_display(data_taxon: &str, lang: Option<&str>) -> Option<String> {
+    let (id, entry) = find_taxon(data_taxon)?;
+    let name = |lang: &str| {
+        let primary = lang.split('-').next().unwrap_or(lang);
+        entry.names.get(lang).or_else(|| entry.names.get(primary))
+    };
+    let default_lang = default_lang();
+    let localized = lang
+        .and_then(name)
+        .or_else(|| default_lang.as_deref().and_then(name));
+    if let Some(localized) = localized.or(entry.display.as_ref()) {
+        return Some(localized.to_string());
+    }
+    // written in another language, so shown as the canonical id.
+    match id.to_lowercase() != data_taxon.trim().to_lowercase() {
+        true => id
+            .split_at_checked(1)
+            .map(|(first, rest)| first.to_uppercase() + rest),
+        false => None,
+    }
+}
+
+pub fn is_rerender_affected() -> bool {
+    lock_config().rerender_affected
+}
+
+pub fn is_timings() -> bool {
+    lock_config().timings
+}
+
+pub fn is_allow_stubs() -> bool {
+    lock_config().allow_stubs
+}
+
+/// Whether the pages marked `draft` are compiled, see [`crate::entry::KEY_DRAFT`].
+pub fn expired() -> Expired {
+    lock_config().expired
+}
+
+/// The [`CompileConfig::now`] if given, or else the current date and time in UTC.
+pub fn today() -> Date {
+    lock_config().now.unwrap_or_else(|| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        Date::from_unix(now.map_or(0, |now| now.as_secs()))
+    })
+}
+
+pub fn is_drafts() -> bool {
+    lock_config().drafts
+}
+
+pub fn is_no_cache() -> bool {
+    lock_config().no_cache
+}
+
+pub fn is_dry_run() -> bool {
+    lock_config().dry_run
+}
+
+/// Hash of the options affecting the rendered HTML.
+pub fn fingerprint() -> u64 {
+    let config = lock_config();
+    let mut hasher = std::hash::DefaultHasher::new();
+    config.root_slug.hash(&mut hasher);
+    config.base_url.hash(&mut hasher);
+    config.url_style.hash(&mut hasher);
+    config.short_slug.hash(&mut hasher);
+    config.footer_mode.to_string().hash(&mut hasher);
+    config.max_embed_depth.hash(&mut hasher);
+    config.broken_links.hash(&mut hasher);
+    config.emit_sources.hash(&mut hasher);
+    config.drafts.hash(&mut hasher);
+    config.display_metadata.hash(&mut hasher);
+    config.expired.hash(&mut hasher);
+    config.backlinks_order.hash(&mut hasher);
+    config.backlinks_group_by.hash(&mut hasher);
+    config.backlinks_show_taxon.hash(&mut hasher);
+    config.backlinks_show_date.hash(&mut hasher);
+    config.taxon_order.hash(&mut hasher);
+    config.max_backlinks.hash(&mut hasher);
+    config.embed_badge_min.hash(&mut hasher);
+    config.numbered_references.hash(&mut hasher);
+    config.merge_further_reading.hash(&mut hasher);
+    config.auto_numbering.hash(&mut hasher);
+    config.numbering_scope.hash(&mut hasher);
+    config.transitive_contexts.hash(&mut hasher);
+    config.mentions_page.hash(&mut hasher);
+    config.bibliography_page.hash(&mut hasher);
+    config.bibliography_sort.hash(&mut hasher);
+    config.bibliography_style.hash(&mut hasher);
+    config.citation_style.hash(&mut hasher);
+    config.taxon_pages.hash(&mut hasher);
+    config.taxon_pages_only.hash(&mut hasher);
+    config.taxon_pages_order.hash(&mut hasher);
+    config.tag_pages.hash(&mut hasher);
+    config.author_pages.hash(&mut hasher);
+    config.authors.hash(&mut hasher);
+    config.licenses.hash(&mut hasher);
+    config.license.hash(&mut hasher);
+    config.license_page.hash(&mut hasher);
+    config.series_pages.hash(&mut hasher);
+    config.max_related.hash(&mut hasher);
+    config.min_related_score.to_bits().hash(&mut hasher);
+    config.taxa.hash(&mut hasher);
+    config.lang.hash(&mut hasher);
+    config.date_format.hash(&mut hasher);
+    config.word_count.hash(&mut hasher);
+    config.word_count_code.hash(&mut hasher);
+    config.words_per_minute.hash(&mut hasher);
+    config.show_reading_time.hash(&mut hasher);
+    config.social_meta.hash(&mut hasher);
+    config.robots.hash(&mut hasher);
+    std::hash::Hasher::finish(&hasher)
+}
+
 pub fn get_cache_dir() -> String {
     join_path(&root_dir(), CACHE_DIR_NAME)
 }
@@ -171,8 +1660,42 @@ pub fn full_url(path: &str) -> String {
     format!("{}{}", base_url(), path)
 }
 
+/// The root page is mapped to the site root.
 pub fn full_html_url(slug: &str) -> String {
-    full_url(&format!("{}{}", slug, lock_config().page_suffix))
+    if slug == root_slug() {
+        return base_url();
+    }
+    let url_style = lock_config().url_style;
+    match url_style {
+        UrlStyle::Html => full_url(&format!("{}.html", slug)),
+        UrlStyle::Directory => full_url(&format!("{}/", slug)),
+        UrlStyle::Bare => full_url(slug),
+    }
+}
+
+/// Output file of the page `slug`, relative to the output dir, in the [`UrlStyle`].
+pub fn output_html_name(slug: &str) -> String {
+    if slug == root_slug() {
+        return "index.html".to_string();
+    }
+    let url_style = lock_config().url_style;
+    match url_style {
+        UrlStyle::Directory => format!("{}/index.html", slug),
+        UrlStyle::Html | UrlStyle::Bare => format!("{}.html", slug),
+    }
+}
+
+/// The slug of the page written to `html_name`, the inverse of [`output_html_name`].
+pub fn html_name_slug(html_name: &str) -> String {
+    if html_name == "index.html" {
+        return root_slug();
+    }
+    let url_style = lock_config().url_style;
+    let html_name = match url_style {
+        UrlStyle::Directory => html_name.strip_suffix("/index.html").unwrap_or(html_name),
+        UrlStyle::Html | UrlStyle::Bare => html_name,
+    };
+    crate::slug::to_slug(html_name)
 }
 
 /**
@@ -211,21 +1734,35 @@ pub fn auto_create_dir_path(paths: Vec<&str>) -> String {
     }
 
     let parent_dir = filepath.parent().unwrap();
-    if !parent_dir.exists() {
+    if !parent_dir.exists() && !is_dry_run() {
         let _ = create_dir_all(&parent_dir);
     }
 
     filepath.to_str().unwrap().to_string()
 }
 
+/// A buffer file not used by any other thread.
 pub fn buffer_path() -> String {
-    join_path(&get_cache_dir(), BUFFER_FILE_NAME)
+    static BUFFER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let count = BUFFER_COUNTER.fetch_add(1, Ordering::Relaxed);
+    join_path(&get_cache_dir(), &format!("{}-{}", BUFFER_FILE_NAME, count))
 }
 
 pub fn output_path(path: &str) -> String {
     auto_create_dir_path(vec![&output_dir(), path])
 }
 
+/// The [`crate::compiler::callback::Callback`] of the last compilation.
+pub fn callback_path() -> String {
+    join_path(&get_cache_dir(), CALLBACK_FILE_NAME)
+}
+
+/// The pages expired as of the last compilation, see
+/// [`crate::compiler::state::CompileState::reconcile_expired`].
+pub fn expired_path() -> String {
+    join_path(&get_cache_dir(), EXPIRED_FILE_NAME)
+}
+
 pub fn hash_dir() -> String {
     join_path(&get_cache_dir(), HASH_DIR_NAME)
 }
@@ -242,6 +1779,34 @@ pub fn entry_path(path: &str) -> PathBuf {
     auto_create_dir_path(vec![&entry_dir(), path]).into()
 }
 
+pub fn section_dir() -> String {
+    join_path(&get_cache_dir(), SECTION_DIR_NAME)
+}
+
+/// The hash of `name` in hexadecimal, naming its files in the cache
+/// as `name` may be too long for a file name.
+fn hash_name(name: &str) -> String {
+    let mut hasher = std::hash::DefaultHasher::new();
+    name.hash(&mut hasher);
+    format!("{:x}", std::hash::Hasher::finish(&hasher))
+}
+
+/// The compiled page `slug` in the cache, see [`hash_name`].
+pub fn section_path(slug: &str) -> PathBuf {
+    let name = format!("{}.json", hash_name(slug));
+    auto_create_dir_path(vec![&section_dir(), &name]).into()
+}
+
+/// The name of the files of the source file `relative_path` in the cache, see [`hash_name`],
+/// keeping its extension, such as `3a9f0c2d41b7e6f8.md`.
+pub fn source_cache_name(relative_path: &str) -> String {
+    let extension = Path::new(relative_path).extension();
+    match extension.and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{}.{}", hash_name(relative_path), extension),
+        None => hash_name(relative_path),
+    }
+}
+
 /// Return is file modified i.e. is hash updated.
 pub fn is_hash_updated<P: AsRef<Path>>(content: &str, hash_path: P) -> (bool, u64) {
     let mut hasher = std::hash::DefaultHasher::new();
@@ -260,7 +1825,7 @@ pub fn is_hash_updated<P: AsRef<Path>>(content: &str, hash_path: P) -> (bool, u6
 pub fn verify_and_file_hash(relative_path: &str) -> Result<bool, std::io::Error> {
     let root_dir = root_dir();
     let full_path = join_path(&root_dir, relative_path);
-    let hash_path = hash_path(&format!("{}.hash", relative_path));
+    let hash_path = hash_path(&format!("{}.hash", source_cache_name(relative_path)));
 
     let content = std::fs::read_to_string(full_path)?;
     let (is_modified, current_hash) = is_hash_updated(&content, &hash_path);
@@ -270,6 +1835,71 @@ pub fn verify_and_file_hash(relative_path: &str) -> Result<bool, std::io::Error>
     return Ok(is_modified);
 }
 
+/// Checks whether the fingerprint of `path` differs from the stored one.
+/// If so, updates the stored fingerprint.
+pub fn verify_update_fingerprint(path: &str, fingerprint: u64) -> Result<bool, std::io::Error> {
+    let fingerprint_path = hash_path(&format!("{}.fingerprint", path));
+    let history = std::fs::read_to_string(&fingerprint_path)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok());
+    let is_modified = history != Some(fingerprint);
+    if is_modified {
+        std::fs::write(&fingerprint_path, fingerprint.to_string())?;
+    }
+
+    Ok(is_modified)
+}
+
+/// The [`CACHE_VERSION`] along with the options affecting the parsed pages,
+/// as the taxa are displayed when parsing, in the language of the page,
+/// and the lists are split.
+fn cache_version() -> String {
+    let mut hasher = std::hash::DefaultHasher::new();
+    let config = lock_config();
+    config.taxa.hash(&mut hasher);
+    config.lang.hash(&mut hasher);
+    config.list_keys.hash(&mut hasher);
+    config.slug_mode.hash(&mut hasher);
+    config.slug_case.hash(&mut hasher);
+    config.flatten_slugs.hash(&mut hasher);
+    config.reserved_prefix.hash(&mut hasher);
+    config.max_slug_length.hash(&mut hasher);
+    format!("{}-{:x}", CACHE_VERSION, std::hash::Hasher::finish(&hasher))
+}
+
+/// Removes the cache written by another version of kodama or with other taxa,
+/// whose format may differ.
+pub fn verify_cache_version() -> Result<(), std::io::Error> {
+    let cache_dir = get_cache_dir();
+    let version_path = join_path(&cache_dir, VERSION_FILE_NAME);
+    let cache_version = cache_version();
+    if fs::read_to_string(&version_path).is_ok_and(|version| version == cache_version) {
+        return Ok(());
+    }
+
+    for dir in [hash_dir(), entry_dir(), section_dir()] {
+        if Path::new(&dir).exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    create_dir_all(&cache_dir)?;
+    fs::write(version_path, cache_version)
+}
+
+/// The [`SlugMode`] of the last compilation if it differs from the current one,
+/// which is then stored.
+pub fn verify_update_slug_mode() -> Result<Option<String>, std::io::Error> {
+    let slug_mode_path = join_path(&get_cache_dir(), SLUG_MODE_FILE_NAME);
+    let slug_mode = slug_mode().as_str();
+    let history = fs::read_to_string(&slug_mode_path).ok();
+    if history.as_deref() == Some(slug_mode) {
+        return Ok(None);
+    }
+    create_dir_all(get_cache_dir())?;
+    fs::write(slug_mode_path, slug_mode)?;
+    Ok(history)
+}
+
 /// Checks whether the content has been modified by comparing its current hash with the stored hash.
 /// If the content is modified, updates the stored hash to reflect the latest state.
 pub fn verify_update_hash(path: &str, content: &str) -> Result<bool, std::io::Error> {
@@ -303,3 +1933,91 @@ pub fn delete_all_built_files() -> Result<(), std::io::Error> {
     std::fs::remove_dir_all(join_path(&root_dir, &output_dir()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{test_forest, writer::Writer};
+
+    #[test]
+    fn taxa_are_named_in_the_language_of_the_page() {
+        let mut config = test_config(".");
+        let theorem = TaxonEntry {
+            display: Some("Theorem".to_string()),
+            names: BTreeMap::from([("zh".to_string(), "定理".to_string())]),
+            ..TaxonEntry::default()
+        };
+        config.taxa.insert("theorem".to_string(), theorem);
+        let _guard = lock_test_config(config);
+
+        assert_eq!(taxon_display("theorem", Some("zh")).unwrap(), "定理");
+        assert_eq!(taxon_display("theorem", Some("zh-CN")).unwrap(), "定理");
+        assert_eq!(taxon_display("Theorem", Some("fr")).unwrap(), "Theorem");
+        assert_eq!(taxon_display("theorem", None).unwrap(), "Theorem");
+        assert_eq!(taxon_display("定理", Some("en")).unwrap(), "Theorem");
+        assert_eq!(taxon_entry("定理").unwrap().display.unwrap(), "Theorem");
+        assert_eq!(taxon_display("lemma", Some("zh")), None);
+        assert_eq!(taxon_display("gadget", Some("zh")), None);
+    }
+
+    #[test]
+    fn default_lang_names_the_pages_without_lang() {
+        let mut config = test_config(".");
+        let lemma = TaxonEntry {
+            names: BTreeMap::from([("zh".to_string(), "引理".to_string())]),
+            ..TaxonEntry::default()
+        };
+        config.taxa.insert("lemma".to_string(), lemma);
+        config.lang = Some("zh".to_string());
+        let _guard = lock_test_config(config);
+
+        assert_eq!(taxon_display("lemma", None).unwrap(), "引理");
+    }
+
+    #[test]
+    fn url_style_drives_urls_and_output_files() {
+        let cases = [
+            (UrlStyle::Html, "/notes/bar.html", "notes/bar.html"),
+            (UrlStyle::Directory, "/notes/bar/", "notes/bar/index.html"),
+            (UrlStyle::Bare, "/notes/bar", "notes/bar.html"),
+        ];
+        for (url_style, url, html_name) in cases {
+            let mut config = test_config(".");
+            config.base_url = "https://example.org/".to_string();
+            config.url_style = url_style;
+            let _guard = lock_test_config(config);
+
+            assert_eq!(full_html_url("index"), "https://example.org/");
+            assert_eq!(output_html_name("index"), "index.html");
+            assert_eq!(html_name_slug("index.html"), "index");
+
+            assert_eq!(
+                full_html_url("notes/bar"),
+                format!("https://example.org{}", url)
+            );
+            assert_eq!(output_html_name("notes/bar"), html_name);
+            assert_eq!(html_name_slug(html_name), "notes/bar");
+        }
+    }
+
+    #[test]
+    fn url_style_drives_the_links() {
+        let cases = [
+            (UrlStyle::Html, r#"href="/notes/bar.html""#),
+            (UrlStyle::Directory, r#"href="/notes/bar/""#),
+            (UrlStyle::Bare, r#"href="/notes/bar""#),
+        ];
+        for (url_style, href) in cases {
+            let mut config = test_config("kodama-tests");
+            config.url_style = url_style;
+            let index = "---\ntitle: Index\n---\n\nSee [](notes/bar.md).\n";
+            let bar = "---\ntitle: Bar\n---\n\nBack to [](/index.md).\n";
+            let sources = [("index", index), ("notes/bar", bar)];
+            let (forest, _guard) = test_forest(&sources, config);
+
+            let html = |slug: &str| Writer::html_doc(&forest.compiled()[slug], &forest.state).0;
+            assert!(html("index").contains(href), "{:?}", url_style);
+            assert!(html("notes/bar").contains(r#"href="/""#));
+        }
+    }
+}
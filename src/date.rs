@@ -0,0 +1,177 @@
+//! The `date` metadata, parsed so that the pages are ordered by their dates
+//! rather than by their text, and shown in [`crate::config::date_format`].
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+const MONTHS_EN: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The dates already parsed by their text, see [`Date::parse_cached`].
+static PARSED: LazyLock<Mutex<HashMap<String, Option<Date>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A date written `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM` or `YYYY/MM/DD`, ordered in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+
+    /// Hours and minutes, if written.
+    pub time: Option<(u8, u8)>,
+}
+
+impl Date {
+    pub fn parse(s: &str) -> Option<Date> {
+        let s = s.trim();
+        let (date, time) = match s.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None => (s, None),
+        };
+        let parts: Vec<&str> = match (date.contains('-'), time) {
+            (true, _) => date.split('-').collect(),
+            (false, None) => date.split('/').collect(),
+            (false, Some(_)) => return None,
+        };
+        let [year, month, day] = parts[..] else {
+            return None;
+        };
+        let year: u16 = number(year, 4)?;
+        let month: u8 = number(month, 2).filter(|month| (1..=12).contains(month))?;
+        let day: u8 =
+            number(day, 2).filter(|day| (1..=days_in_month(year, month)).contains(day))?;
+        let time = match time {
+            None => None,
+            Some(time) => {
+                let (hours, minutes) = time.split_once(':')?;
+                let hours: u8 = number(hours, 2).filter(|hours| *hours < 24)?;
+                let minutes: u8 = number(minutes, 2).filter(|minutes| *minutes < 60)?;
+                Some((hours, minutes))
+            }
+        };
+        Some(Date {
+            year,
+            month,
+            day,
+            time,
+        })
+    }
+
+    /// The date and time in UTC of `secs` seconds after the Unix epoch.
+    pub fn from_unix(secs: u64) -> Date {
+        let (days, secs) = (secs / 86400, secs % 86400);
+        let time = Some(((secs / 3600) as u8, (secs % 3600 / 60) as u8));
+
+        // the civil date of the day `days` since 1970-01-01, counted in eras of 400 years
+        // from 0000-03-01 so that the leap day is the last day of each year.
+        let days = days + 719468;
+        let (era, day_of_era) = (days / 146097, days % 146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month + 2) / 5 + 1) as u8;
+        let month = if month < 10 { month + 3 } else { month - 9 } as u8;
+        let year = (era * 400 + year_of_era + u64::from(month <= 2)) as u16;
+        Date {
+            year,
+            month,
+            day,
+            time,
+        }
+    }
+
+    /// [`Date::parse`], remembering the dates already parsed.
+    pub fn parse_cached(s: &str) -> Option<Date> {
+        let mut parsed = PARSED.lock().unwrap();
+        *parsed
+            .entry(s.to_string())
+            .or_insert_with(|| Date::parse(s))
+    }
+
+    /// This date in `format`, where `%Y`, `%m`, `%d`, `%H` and `%M` are the numbers
+    /// with leading zeros, `%-m` and `%-d` without them, `%B` the name of the month
+    /// and `%b` its first three letters, in `lang` if it is `zh` and in English otherwise.
+    pub fn format(&self, format: &str, lang: Option<&str>) -> String {
+        let chinese = lang.is_some_and(|lang| lang.split('-').next() == Some("zh"));
+        let month_name = match chinese {
+            true => format!("{}月", self.month),
+            false => MONTHS_EN[self.month as usize - 1].to_string(),
+        };
+        let (hours, minutes) = self.time.unwrap_or((0, 0));
+
+        let mut result = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+            let spec = match chars.next() {
+                Some('-') => chars.next().map(|c| format!("-{}", c)),
+                c => c.map(String::from),
+            };
+            match spec.as_deref() {
+                Some("Y") => result.push_str(&self.year.to_string()),
+                Some("m") => result.push_str(&format!("{:02}", self.month)),
+                Some("-m") => result.push_str(&self.month.to_string()),
+                Some("d") => result.push_str(&format!("{:02}", self.day)),
+                Some("-d") => result.push_str(&self.day.to_string()),
+                Some("H") => result.push_str(&format!("{:02}", hours)),
+                Some("M") => result.push_str(&format!("{:02}", minutes)),
+                Some("B") => result.push_str(&month_name),
+                Some("b") if chinese => result.push_str(&month_name),
+                Some("b") => result.extend(month_name.chars().take(3)),
+                Some("%") => result.push('%'),
+                Some(spec) => {
+                    result.push('%');
+                    result.push_str(spec);
+                }
+                None => result.push('%'),
+            }
+        }
+        result
+    }
+}
+
+impl std::fmt::Display for Date {
+    /// As written `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM`, so that it is parsed back the same.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)?;
+        match self.time {
+            Some((hours, minutes)) => write!(f, "T{:02}:{:02}", hours, minutes),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The number written with `len` digits as `s`.
+fn number<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    let digits = s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+    digits.then(|| s.parse().ok()).flatten()
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
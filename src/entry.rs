@@ -1,16 +1,26 @@
 use crate::{
     compiler::{section::HTMLContent, taxon::Taxon},
-    config, html,
-    html_flake::html_entry_header,
+    config,
+    date::Date,
+    html,
+    html_flake::{
+        html_authors, html_bibliography_entry, html_entry_header, html_list_items,
+        html_reading_time, BibliographyEntry,
+    },
 };
 use serde::{Deserialize, Serialize};
-use std::collections::{hash_map::Keys, HashMap};
+use std::collections::{hash_map::Keys, BTreeMap, HashMap};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HTMLMetaData(pub HashMap<String, HTMLContent>);
+/// The values by key, and the items of the list-valued keys.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HTMLMetaData(pub HashMap<String, HTMLContent>, pub MetaDataLists);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EntryMetaData(pub HashMap<String, String>);
+pub struct EntryMetaData(pub HashMap<String, String>, pub MetaDataLists);
+
+/// The items of each list-valued key, written as a YAML sequence or, for the keys of
+/// [`config::list_keys`], separated by commas. Its value is then the items joined by `, `.
+pub type MetaDataLists = BTreeMap<String, Vec<String>>;
 
 pub const KEY_TITLE: &'static str = "title";
 pub const KEY_SLUG: &'static str = "slug";
@@ -25,6 +35,15 @@ pub const KEY_PAGE_TITLE: &'static str = "page-title";
 /// Controls whether the current page displays backlinks.
 pub const KEY_BACKLINKS: &'static str = "backlinks";
 
+/// `backlinks-suppress: bool | slug, ...`:
+/// Keeps the current page out of the backlinks of all the pages it links,
+/// or only of the listed ones.
+pub const KEY_BACKLINKS_SUPPRESS: &str = "backlinks-suppress";
+
+/// `related: bool`:
+/// Controls whether the current page displays and is listed in related pages.
+pub const KEY_RELATED: &str = "related";
+
 /// `collect: bool`:
 /// Controls whether the current page is a collection page.
 /// A collection page displays metadata of child entries.
@@ -34,7 +53,173 @@ pub const KEY_COLLECT: &'static str = "collect";
 /// Controls whether the current page process as reference.
 pub const KEY_ASREF: &'static str = "asref";
 
-const PRESET_METADATA: [&'static str; 9] = [
+/// `orphan: bool`:
+/// Marks the current page as intentionally standalone,
+/// so that it is not reported as an orphan.
+pub const KEY_ORPHAN: &str = "orphan";
+
+/// `ignore-unused: bool`:
+/// Marks a reference page as kept on purpose, so that it is not reported as unused
+/// when no page cites it.
+pub const KEY_IGNORE_UNUSED: &str = "ignore-unused";
+
+/// `bib-ignore: year, doi`: the [`crate::config::reference_fields`] a reference page
+/// is not reported for missing.
+pub const KEY_BIB_IGNORE: &str = "bib-ignore";
+
+/// `child-taxon: exercise`: the taxon of the pages embedded by the current page
+/// without a taxon of their own, in these embeds only.
+pub const KEY_CHILD_TAXON: &str = "child-taxon";
+
+/// `stub: bool`:
+/// Marks a placeholder page synthesized for a missing slug.
+pub const KEY_STUB: &str = "stub";
+
+/// `draft: bool` and `private: bool`:
+/// Keep the current page out of the build, unless [`crate::config::is_drafts`],
+/// and out of the generated taxon and tag pages in any case,
+/// see [`crate::config::taxon_pages`] and [`crate::config::tag_pages`].
+pub const KEY_DRAFT: &str = "draft";
+pub const KEY_PRIVATE: &str = "private";
+
+/// `unlisted: bool`:
+/// Compile the current page, but keep it out of every listing of pages,
+/// see [`crate::compiler::state::CompileState::is_listed`].
+pub const KEY_UNLISTED: &str = "unlisted";
+
+/// `pinned: bool`:
+/// Put the current page first in the generated listings, marked with a pin,
+/// see [`crate::compiler::state::sort_by_order`].
+pub const KEY_PINNED: &str = "pinned";
+
+/// `featured: bool`:
+/// List the current page in the generated page [`crate::compiler::state::featured_slug`].
+pub const KEY_FEATURED: &str = "featured";
+
+/// `social-meta: bool`:
+/// Controls whether the current page has the tags describing it for the link previews,
+/// see [`crate::config::is_social_meta`].
+pub const KEY_SOCIAL_META: &str = "social-meta";
+
+/// `robots`: a custom list of the directives for the search engines, such as `noindex` and
+/// `nofollow`, replacing [`crate::config::robots`] for the current page.
+pub const KEY_ROBOTS: &str = "robots";
+
+/// `description` and `image`: the summary and the picture shown in the link previews
+/// of the current page, the image being a URL or a path under the base URL.
+pub const KEY_DESCRIPTION: &str = "description";
+pub const KEY_IMAGE: &str = "image";
+
+/// `context: bool`:
+/// Controls whether the current page displays the pages embedding it.
+pub const KEY_CONTEXT: &str = "context";
+
+/// `embed: bool`:
+/// Controls whether the current page can be embedded.
+/// Embeds of a page with `embed: false` are rendered as local links.
+pub const KEY_EMBED: &str = "embed";
+
+/// `link: bool`:
+/// Controls whether the current page is listed in the backlinks
+/// and references of other pages, and in every other listing of pages,
+/// see [`crate::compiler::state::CompileState::is_listed`].
+pub const KEY_LINK: &str = "link";
+
+/// `date`: a custom key, ordering the backlinks, see [`crate::config::BacklinksOrder`].
+/// Dates are compared as strings, so they should be written as `YYYY-MM-DD`.
+pub const KEY_DATE: &str = "date";
+
+/// `updated`: a custom key, the date the page was last changed, filled in from the git
+/// history with [`crate::config::is_git_dates`] if not written.
+pub const KEY_UPDATED: &str = "updated";
+
+/// `expires`: the date after which the current page is archived, or left out of the build,
+/// see [`crate::config::Expired`] and [`MetaData::is_expired`].
+pub const KEY_EXPIRES: &str = "expires";
+
+/// `tags`: a custom list, listing the current page in the generated page of each tag,
+/// see [`crate::config::tag_pages`].
+pub const KEY_TAGS: &str = "tags";
+
+/// `series` and `series-index`: the name of the series the current page is a part of, and
+/// its number in the series, see [`crate::compiler::state::CompileState::series`].
+pub const KEY_SERIES: &str = "series";
+pub const KEY_SERIES_INDEX: &str = "series-index";
+
+/// `order`: a number placing the current page in the generated listings, before the pages
+/// without one, see [`crate::compiler::state::sort_by_order`].
+pub const KEY_ORDER: &str = "order";
+
+/// `license`: the id of the license of the current page, shown in its footer,
+/// see [`crate::config::LicenseEntry`].
+pub const KEY_LICENSE: &str = "license";
+
+/// `aliases`: a custom list of the former slugs of the current page, whose links and
+/// embeds go to it and where a redirect to it is written, see [`crate::compiler::aliases`].
+pub const KEY_ALIASES: &str = "aliases";
+
+/// `author` and `year`: custom keys of a reference page, shown by the citations of it,
+/// see [`crate::html_flake::html_citation`].
+pub const KEY_AUTHOR: &str = "author";
+pub const KEY_YEAR: &str = "year";
+
+/// `authors`: a custom list of the authors of the current page, along with a list-valued
+/// `author`, see [`MetaData::authors`] and [`crate::config::author_pages`].
+pub const KEY_AUTHORS: &str = "authors";
+
+/// `journal`, `doi` and `url`: custom keys of a reference page, shown with its `author`
+/// and `year` in its bibliography entry, see [`MetaData::to_bibliography_entry`].
+pub const KEY_JOURNAL: &str = "journal";
+pub const KEY_DOI: &str = "doi";
+pub const KEY_URL: &str = "url";
+
+/// `bibtex-type`: the BibTeX entry type of a reference page, such as `article`,
+/// see [`crate::compiler::bibtex::BibEntry::kind`].
+pub const KEY_BIBTEX_TYPE: &str = "bibtex-type";
+
+/// `number`: shown as the number of the page wherever it is numbered,
+/// leaving the numbers of the other pages as if it were not numbered,
+/// see [`crate::compiler::numbering::manual_number`].
+pub const KEY_NUMBER: &str = "number";
+
+/// `lang`: the language of the page such as `zh`, naming its taxon in that language,
+/// see [`crate::config::taxon_display`].
+pub const KEY_LANG: &str = "lang";
+
+/// `display-metadata`: the custom keys shown as rows of the header of the current page,
+/// replacing [`crate::config::display_metadata`], or changing it by the items `+key`
+/// and `-key`, see [`MetaData::display_keys`].
+pub const KEY_DISPLAY_METADATA: &str = "display-metadata";
+
+/// `translations`: a custom list of the slugs of the same page in other languages, linked
+/// above it and from its head, see [`crate::compiler::translations`].
+pub const KEY_TRANSLATIONS: &str = "translations";
+
+/// `citation-style: footnote`: how the citations read on the page,
+/// see [`crate::config::CitationStyle`].
+pub const KEY_CITATION_STYLE: &str = "citation-style";
+
+/// `ref-<key>: author: ...; year: ...; title: ...; url: ...`: a reference declared inline,
+/// cited as `[@key]` without a page of its own, see
+/// [`crate::compiler::state::CompileState::inline_references`]. Also `journal` and `doi`.
+pub const KEY_INLINE_REFERENCE_PREFIX: &str = "ref-";
+
+/// Virtual keys start with `:`. They are computed by the compiler
+/// and never displayed, see [`crate::compiler::section::SectionStats`].
+pub const KEY_WORDCOUNT: &str = ":wordcount";
+
+/// Minutes needed to read the `:wordcount` at [`crate::config::words_per_minute`],
+/// shown with [`crate::config::is_show_reading_time`].
+pub const KEY_READINGTIME: &str = ":readingtime";
+
+/// Pages listed in the backlinks of the page. Like `:parent-count`, only set in
+/// [`crate::compiler::state::CompileState::metadata`] once all pages are compiled.
+pub const KEY_BACKLINK_COUNT: &str = ":backlink-count";
+
+/// Pages embedding the page.
+pub const KEY_PARENT_COUNT: &str = ":parent-count";
+
+const PRESET_METADATA: [&str; 38] = [
     KEY_TITLE,
     KEY_SLUG,
     KEY_TAXON,
@@ -42,8 +227,37 @@ const PRESET_METADATA: [&'static str; 9] = [
     KEY_PARENT,
     KEY_PAGE_TITLE,
     KEY_BACKLINKS,
+    KEY_BACKLINKS_SUPPRESS,
+    KEY_RELATED,
     KEY_COLLECT,
     KEY_ASREF,
+    KEY_ORPHAN,
+    KEY_IGNORE_UNUSED,
+    KEY_BIB_IGNORE,
+    KEY_CHILD_TAXON,
+    KEY_STUB,
+    KEY_DRAFT,
+    KEY_PRIVATE,
+    KEY_UNLISTED,
+    KEY_PINNED,
+    KEY_FEATURED,
+    KEY_EXPIRES,
+    KEY_BIBTEX_TYPE,
+    KEY_LANG,
+    KEY_TRANSLATIONS,
+    KEY_DISPLAY_METADATA,
+    KEY_SERIES,
+    KEY_SERIES_INDEX,
+    KEY_ORDER,
+    KEY_LICENSE,
+    KEY_CITATION_STYLE,
+    KEY_NUMBER,
+    KEY_CONTEXT,
+    KEY_SOCIAL_META,
+    KEY_ROBOTS,
+    KEY_IMAGE,
+    KEY_EMBED,
+    KEY_LINK,
 ];
 
 pub trait MetaData<V>
@@ -54,8 +268,120 @@ where
     fn get_str(&self, key: &str) -> Option<&String>;
     fn keys<'a>(&'a self) -> Keys<'a, String, V>;
 
+    /// The items of the list-valued `key`, see [`MetaDataLists`].
+    fn get_list(&self, key: &str) -> Option<&[String]>;
+
     fn is_custom_metadata(s: &str) -> bool {
         !PRESET_METADATA.contains(&s)
+            && !s.starts_with(':')
+            && !s.starts_with(KEY_INLINE_REFERENCE_PREFIX)
+    }
+
+    /// The authors of the page: the items of `author` written as a list, or of `authors`,
+    /// or else the single `author`.
+    fn authors(&self) -> Vec<String> {
+        if let Some(items) = self
+            .get_list(KEY_AUTHOR)
+            .or_else(|| self.get_list(KEY_AUTHORS))
+        {
+            return items.to_vec();
+        }
+        let authors = match (self.get_str(KEY_AUTHORS), self.get_str(KEY_AUTHOR)) {
+            (Some(authors), _) => split_list(&htmlize::unescape(authors)),
+            (None, Some(author)) => vec![htmlize::unescape(author).trim().to_string()],
+            (None, None) => vec![],
+        };
+        authors.into_iter().filter(|a| !a.is_empty()).collect()
+    }
+
+    /// The custom keys shown as rows of the header, in order: those of
+    /// [`config::display_metadata`], replaced by the `display-metadata` of the page, then
+    /// with its items `+key` added and its items `-key` removed. `None` if neither is set,
+    /// so that all custom keys are shown as items.
+    fn display_keys(&self) -> Option<Vec<String>> {
+        let items = match self.get_list(KEY_DISPLAY_METADATA) {
+            Some(items) => Some(items.to_vec()),
+            None => self.get_str(KEY_DISPLAY_METADATA).map(|s| split_list(s)),
+        };
+        let Some(items) = items else {
+            return config::display_metadata();
+        };
+        let is_change = |item: &String| item.starts_with(['+', '-']);
+        let mut keys = match items.iter().all(is_change) {
+            true => config::display_metadata().unwrap_or_default(),
+            false => items
+                .iter()
+                .filter(|item| !is_change(item))
+                .cloned()
+                .collect(),
+        };
+        for item in items.iter().filter(|item| is_change(item)) {
+            let key = item[1..].trim().to_string();
+            match item.starts_with('+') {
+                true if !keys.contains(&key) => keys.push(key),
+                true => {}
+                false => keys.retain(|k| *k != key),
+            }
+        }
+        Some(keys)
+    }
+
+    /// The `license` of the page, unescaped, or else [`config::default_license`].
+    fn license(&self) -> Option<String> {
+        self.get_str(KEY_LICENSE)
+            .map(|license| htmlize::unescape(license).trim().to_string())
+            .filter(|license| !license.is_empty())
+            .or_else(config::default_license)
+    }
+
+    /// The `robots` of the page in lowercase, or else [`config::robots`].
+    fn robots(&self) -> Vec<String> {
+        let items = match self.get_list(KEY_ROBOTS) {
+            Some(items) => items.to_vec(),
+            None => match self.get_str(KEY_ROBOTS) {
+                Some(s) => split_list(s),
+                None => config::robots(),
+            },
+        };
+        items
+            .iter()
+            .map(|item| item.trim().to_lowercase())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// Whether the `robots` of the page keep it out of the search engines.
+    fn is_noindex(&self) -> bool {
+        self.robots()
+            .iter()
+            .any(|item| item == "noindex" || item == "none")
+    }
+
+    /// How the page uses its metadata `key`: `"shown"` in its header, `"read"` by kodama
+    /// without being shown, or `"unused"`, see [`MetaData::display_keys`].
+    fn key_use(&self, key: &str) -> &'static str {
+        let is_custom = Self::is_custom_metadata(key);
+        let shown = match self.display_keys() {
+            Some(display_keys) => {
+                display_keys.iter().any(|k| k == key) || (is_custom && is_known_metadata(key))
+            }
+            None => is_custom,
+        };
+        match (shown, is_known_metadata(key)) {
+            (true, _) => "shown",
+            (false, true) => "read",
+            (false, false) => "unused",
+        }
+    }
+
+    /// The items of `translations`, as written.
+    fn translations(&self) -> Vec<String> {
+        match self.get_list(KEY_TRANSLATIONS) {
+            Some(items) => items.to_vec(),
+            None => self
+                .get_str(KEY_TRANSLATIONS)
+                .map_or(vec![], |s| split_list(s)),
+        }
     }
 
     /// Return all custom metadata keys without [`PRESET_METADATA`].
@@ -105,10 +431,53 @@ where
         return self.get_str(KEY_SLUG);
     }
 
+    fn date(&self) -> Option<&String> {
+        self.get_str(KEY_DATE)
+    }
+
+    /// The `date` parsed, `None` if it is missing or written otherwise.
+    fn parsed_date(&self) -> Option<Date> {
+        self.date().and_then(|date| Date::parse_cached(date))
+    }
+
+    /// The `date` in [`config::date_format`] and the `lang` of the page,
+    /// or as written if it cannot be parsed.
+    fn display_date(&self) -> Option<String> {
+        self.date().map(|date| self.format_date(date))
+    }
+
+    /// `date` in [`config::date_format`] and the `lang` of the page,
+    /// or as written if it cannot be parsed.
+    fn format_date(&self, date: &str) -> String {
+        let (Some(format), Some(parsed)) = (config::date_format(), Date::parse_cached(date)) else {
+            return date.to_string();
+        };
+        let lang = self
+            .get_str(KEY_LANG)
+            .cloned()
+            .or_else(config::default_lang);
+        parsed.format(&format, lang.as_deref())
+    }
+
     fn is_enable_backlinks(&self) -> bool {
         return self.get_bool(&KEY_BACKLINKS).unwrap_or(true);
     }
 
+    /// Whether the current page should not be listed in the backlinks of `target`.
+    fn is_backlink_suppressed(&self, target: &str) -> bool {
+        match self.get_str(KEY_BACKLINKS_SUPPRESS).map(|s| s.trim()) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(slugs) => slugs
+                .split(',')
+                .any(|s| crate::slug::to_slug(s.trim()) == target),
+        }
+    }
+
+    fn is_enable_related(&self) -> bool {
+        self.get_bool(KEY_RELATED).unwrap_or(true)
+    }
+
     fn is_collect(&self) -> bool {
         return self.get_bool(&KEY_COLLECT).unwrap_or(false);
     }
@@ -116,6 +485,101 @@ where
     fn is_asref(&self) -> bool {
         return self.get_bool(&KEY_ASREF).unwrap_or(false);
     }
+
+    fn is_orphan(&self) -> bool {
+        self.get_bool(KEY_ORPHAN).unwrap_or(false)
+    }
+
+    fn is_ignore_unused(&self) -> bool {
+        self.get_bool(KEY_IGNORE_UNUSED).unwrap_or(false)
+    }
+
+    fn is_stub(&self) -> bool {
+        self.get_bool(KEY_STUB).unwrap_or(false)
+    }
+
+    /// Whether [`config::today`] is past the `expires` of the page: a later day, or from
+    /// its time on if it has one.
+    fn is_expired(&self) -> bool {
+        let Some(expires) = self
+            .get_str(KEY_EXPIRES)
+            .and_then(|s| Date::parse_cached(s))
+        else {
+            return false;
+        };
+        let today = config::today();
+        match expires.time {
+            Some(_) => today >= expires,
+            None => {
+                (today.year, today.month, today.day) > (expires.year, expires.month, expires.day)
+            }
+        }
+    }
+
+    fn is_draft(&self) -> bool {
+        self.get_bool(KEY_DRAFT).unwrap_or(false) || self.get_bool(KEY_PRIVATE).unwrap_or(false)
+    }
+
+    /// The `series` of the page, unescaped.
+    fn series(&self) -> Option<String> {
+        self.get_str(KEY_SERIES)
+            .map(|series| htmlize::unescape(series).trim().to_string())
+            .filter(|series| !series.is_empty())
+    }
+
+    /// The `order` of the page, `None` if it is missing or no finite number.
+    fn order(&self) -> Option<f64> {
+        self.get_str(KEY_ORDER)
+            .and_then(|order| order.trim().parse::<f64>().ok())
+            .filter(|order| order.is_finite())
+    }
+
+    fn is_unlisted(&self) -> bool {
+        self.get_bool(KEY_UNLISTED).unwrap_or(false)
+    }
+
+    fn is_pinned(&self) -> bool {
+        self.get_bool(KEY_PINNED).unwrap_or(false)
+    }
+
+    fn is_featured(&self) -> bool {
+        self.get_bool(KEY_FEATURED).unwrap_or(false)
+    }
+
+    fn is_enable_social_meta(&self) -> bool {
+        self.get_bool(KEY_SOCIAL_META).unwrap_or(true)
+    }
+
+    fn is_enable_context(&self) -> bool {
+        self.get_bool(KEY_CONTEXT).unwrap_or(true)
+    }
+
+    fn is_enable_embed(&self) -> bool {
+        self.get_bool(KEY_EMBED).unwrap_or(true)
+    }
+
+    fn is_enable_link(&self) -> bool {
+        self.get_bool(KEY_LINK).unwrap_or(true)
+    }
+
+    /// The bibliography entry of a reference page, `None` if it has none of the
+    /// bibliographic keys, see [`html_bibliography_entry`].
+    fn to_bibliography_entry(&self, href: Option<&str>, number: Option<usize>) -> Option<String> {
+        let keys = [KEY_AUTHOR, KEY_YEAR, KEY_JOURNAL, KEY_DOI, KEY_URL];
+        if keys.iter().all(|key| self.get_str(key).is_none()) {
+            return None;
+        }
+        let entry = BibliographyEntry {
+            author: self.get_str(KEY_AUTHOR).map(String::as_str),
+            title: self.page_title().map(String::as_str),
+            journal: self.get_str(KEY_JOURNAL).map(String::as_str),
+            year: self.get_str(KEY_YEAR).map(String::as_str),
+            doi: self.get_str(KEY_DOI).map(String::as_str),
+            url: self.get_str(KEY_URL).map(String::as_str),
+        };
+        let style = config::bibliography_style();
+        Some(html_bibliography_entry(&entry, href, style, number))
+    }
 }
 
 impl MetaData<HTMLContent> for HTMLMetaData {
@@ -130,6 +594,10 @@ impl MetaData<HTMLContent> for HTMLMetaData {
     fn keys<'a>(&'a self) -> Keys<'a, String, HTMLContent> {
         return self.0.keys();
     }
+
+    fn get_list(&self, key: &str) -> Option<&[String]> {
+        self.1.get(key).map(Vec::as_slice)
+    }
 }
 
 impl MetaData<String> for EntryMetaData {
@@ -144,6 +612,10 @@ impl MetaData<String> for EntryMetaData {
     fn keys<'a>(&'a self) -> Keys<'a, String, String> {
         return self.0.keys();
     }
+
+    fn get_list(&self, key: &str) -> Option<&[String]> {
+        self.1.get(key).map(Vec::as_slice)
+    }
 }
 
 impl HTMLMetaData {
@@ -185,7 +657,64 @@ impl EntryMetaData {
             (html!(span class = {span_class.join(" ")} => {taxon}))
             {title} {" "}
             (html!(a class = "slug", href = {slug_url} => "["{&slug_text}"]"))))
-          (html!(html_entry_header(self.etc()))))
+          (html!(html_entry_header(self.etc_with_date(), &self.display_rows()))))
+    }
+
+    /// The keys of [`MetaData::display_keys`] the page has, each with its value shown as
+    /// by [`EntryMetaData::etc_with_date`].
+    fn display_rows(&self) -> Vec<(String, String)> {
+        let author_pages = config::author_pages();
+        let keys = self.display_keys().unwrap_or_default();
+        keys.into_iter()
+            .filter(|key| self.0.contains_key(key))
+            .map(|key| {
+                let value = self.display_value(&key, author_pages.as_deref());
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// [`MetaData::etc`] with the `date` and the `updated` shown by [`MetaData::format_date`],
+    /// and the items of the lists each in a span, see [`html_list_items`], followed by the
+    /// reading time with [`config::is_show_reading_time`]. The authors are joined by commas,
+    /// each linked to their page with [`config::author_pages`], see [`html_authors`].
+    /// With [`MetaData::display_keys`], these keys are left to [`EntryMetaData::display_rows`],
+    /// and the keys kodama does not know are hidden.
+    fn etc_with_date(&self) -> Vec<String> {
+        let mut etc_keys = self.etc_keys();
+        etc_keys.sort();
+        if let Some(display_keys) = self.display_keys() {
+            etc_keys.retain(|key| is_known_metadata(key) && !display_keys.contains(key));
+        }
+        let reading_time = self
+            .get_str(KEY_READINGTIME)
+            .filter(|_| config::is_show_reading_time())
+            .map(|minutes| html_reading_time(minutes));
+        let author_pages = config::author_pages();
+        etc_keys
+            .into_iter()
+            .map(|key| self.display_value(&key, author_pages.as_deref()))
+            .chain(reading_time)
+            .collect()
+    }
+
+    /// The value of `key` as shown in the header, `author_pages` being
+    /// [`config::author_pages`].
+    fn display_value(&self, key: &str, author_pages: Option<&str>) -> String {
+        let is_authors = key == KEY_AUTHOR || key == KEY_AUTHORS;
+        if is_authors && (self.get_list(key).is_some() || author_pages.is_some()) {
+            let authors = match self.get_list(key) {
+                Some(items) => items.to_vec(),
+                None if key == KEY_AUTHORS => split_list(&htmlize::unescape(&self.0[key])),
+                None => vec![htmlize::unescape(&self.0[key]).trim().to_string()],
+            };
+            return html_authors(&authors, author_pages);
+        }
+        match (self.get_list(key), key == KEY_DATE || key == KEY_UPDATED) {
+            (Some(items), _) => html_list_items(key, items),
+            (None, true) => self.format_date(self.get(key).unwrap()),
+            (None, false) => self.get(key).unwrap().clone(),
+        }
     }
 
     /// hidden suffix `/index` in slug text.
@@ -201,7 +730,129 @@ impl EntryMetaData {
         slug_text.to_string()
     }
 
+    /// Set `key` to `value`, split into items if it is one of [`config::list_keys`].
     pub fn update(&mut self, key: String, value: String) {
+        match config::is_list_key(&key) {
+            true => _ = self.1.insert(key.to_string(), split_list(&value)),
+            false => _ = self.1.remove(&key),
+        }
         let _ = self.0.insert(key, value);
     }
 }
+
+/// Whether kodama knows the metadata `key`: a preset or computed key, one it reads
+/// such as `date` or `author`, a list key, or a field of the references.
+pub fn is_known_metadata(key: &str) -> bool {
+    const READ: [&str; 11] = [
+        KEY_DESCRIPTION,
+        KEY_DATE,
+        KEY_UPDATED,
+        KEY_TAGS,
+        KEY_ALIASES,
+        KEY_AUTHOR,
+        KEY_AUTHORS,
+        KEY_YEAR,
+        KEY_JOURNAL,
+        KEY_DOI,
+        KEY_URL,
+    ];
+    PRESET_METADATA.contains(&key)
+        || READ.contains(&key)
+        || key.starts_with(':')
+        || key.starts_with(KEY_INLINE_REFERENCE_PREFIX)
+        || crate::config::is_list_key(key)
+        || crate::config::reference_fields()
+            .iter()
+            .any(|field| field == key)
+}
+
+/// The items of a list written `a, b, c`, or `[a, b, c]` as a YAML flow sequence,
+/// each of them unquoted.
+pub fn split_list(s: &str) -> Vec<String> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(s);
+    s.split(',')
+        .map(|item| unquote(item.trim()).to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// `s` without the quotes around it, if any.
+pub fn unquote(s: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| s.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn split_list_unquotes_items() {
+        assert_eq!(split_list("rust, compilers"), items(&["rust", "compilers"]));
+        assert_eq!(split_list("[a, 'b', \"c\"]"), items(&["a", "b", "c"]));
+        assert_eq!(split_list(" , "), items(&[]));
+    }
+
+    #[test]
+    fn update_splits_list_keys() {
+        let _guard = config::lock_test_config(config::test_config("."));
+        let mut metadata = EntryMetaData(HashMap::new(), MetaDataLists::new());
+        metadata.update(KEY_TAGS.to_string(), "rust, compilers".to_string());
+        metadata.update("status".to_string(), "a, b".to_string());
+
+        assert_eq!(
+            metadata.get_list(KEY_TAGS),
+            Some(&items(&["rust", "compilers"])[..])
+        );
+        assert_eq!(metadata.get_list("status"), None);
+        assert_eq!(metadata.get_str("status").unwrap(), "a, b");
+    }
+
+    #[test]
+    fn lists_survive_serde_round_trip() {
+        let mut lists = MetaDataLists::new();
+        lists.insert(KEY_AUTHORS.to_string(), items(&["Ann", "Bob"]));
+        let mut values = HashMap::new();
+        let value = HTMLContent::Plain("Ann, Bob".to_string());
+        values.insert(KEY_AUTHORS.to_string(), value);
+        let metadata = HTMLMetaData(values, lists);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        let metadata: HTMLMetaData = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            metadata.get_list(KEY_AUTHORS),
+            Some(&items(&["Ann", "Bob"])[..])
+        );
+        assert_eq!(metadata.get_str(KEY_AUTHORS).unwrap(), "Ann, Bob");
+        assert_eq!(metadata.get_list(KEY_TAGS), None);
+    }
+
+    #[test]
+    fn backlinks_suppress_all_or_listed() {
+        let metadata = |value: &str| {
+            let mut metadata = EntryMetaData(HashMap::new(), MetaDataLists::new());
+            metadata
+                .0
+                .insert(KEY_BACKLINKS_SUPPRESS.to_string(), value.to_string());
+            metadata
+        };
+        assert!(metadata("true").is_backlink_suppressed("a"));
+        assert!(!metadata("false").is_backlink_suppressed("a"));
+        assert!(!EntryMetaData(HashMap::new(), MetaDataLists::new()).is_backlink_suppressed("a"));
+
+        let listed = metadata("a.md, notes/b");
+        assert!(listed.is_backlink_suppressed("a"));
+        assert!(listed.is_backlink_suppressed("notes/b"));
+        assert!(!listed.is_backlink_suppressed("c"));
+    }
+}
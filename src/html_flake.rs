@@ -1,6 +1,7 @@
 use std::ops::Not;
 
 use crate::{
+    compiler::taxon::Taxon,
     config,
     entry::{EntryMetaData, MetaData},
     html,
@@ -24,16 +25,142 @@ pub fn html_article_inner(
         open,
         article_id,
         metadata.data_taxon(),
+        metadata.is_stub(),
     )
 }
 
-pub fn html_footer_section(summary: &str, content: &String) -> String {
+fn html_footer_details(summary: &str, content: &String) -> String {
     let summary = format!("<header><h1>{}</h1></header>", summary);
     let inner_html = format!("{}{}", (html!(summary => {summary})), content);
-    let html_details = format!("<details open>{}</details>", inner_html);
+    format!("<details open>{}</details>", inner_html)
+}
+
+pub fn html_footer_section(summary: &str, content: &String) -> String {
+    let html_details = html_footer_details(summary, content);
     html!(section class="block" => {html_details})
 }
 
+/// [`html_footer_section`] with an `id`, so that it can be linked to.
+pub fn html_footer_section_with_id(id: &str, summary: &str, content: &String) -> String {
+    let html_details = html_footer_details(summary, content);
+    html!(section class="block", id={id} => {html_details})
+}
+
+pub fn html_embed_badge(count: usize) -> String {
+    let places = if count == 1 { "place" } else { "places" };
+    let text = format!("Transcluded in {} {}", count, places);
+    html!(a class = "embed-badge", href = "#contexts" => {text})
+}
+
+pub fn html_draft_banner() -> String {
+    html!(div class = "draft-banner" => "Draft: this page is not published.")
+}
+
+/// The languages of a page in `lang` and of its `translations`, by their `lang`, URL and
+/// title, the latter linked, see [`crate::compiler::translations`].
+pub fn html_translations(lang: &str, translations: &[(String, String, String)]) -> String {
+    let mut items = vec![html!(span class = "current-lang" => {htmlize::escape_text(lang)})];
+    for (lang, url, title) in translations {
+        let lang = htmlize::escape_attribute(lang).to_string();
+        let title = htmlize::escape_attribute(title);
+        items.push(
+            html!(a href = {url}, hreflang = {&lang}, lang = {&lang}, title = {title} => {&lang}),
+        );
+    }
+    html!(div class = "translations" => {items.join(" · ")})
+}
+
+/// The `hreflang` links of a page to itself and to its translations, by their `lang`
+/// and URL, see [`html_translations`].
+pub fn html_alternate_links(alternates: &[(String, String)]) -> String {
+    alternates
+        .iter()
+        .map(|(lang, url)| {
+            let lang = htmlize::escape_attribute(lang);
+            format!(
+                "\n<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">",
+                lang, url
+            )
+        })
+        .collect()
+}
+
+/// The mark of a pinned page in the generated listings.
+pub fn html_pin() -> String {
+    html!(span class = "pin", title = "Pinned" => "📌")
+}
+
+/// The license `name` of a page at the end of its footer, linked at `url` if any.
+pub fn html_license(name: &str, url: Option<&str>) -> String {
+    let name = htmlize::escape_text(name).to_string();
+    let license = match url {
+        Some(url) => html!(a href = {htmlize::escape_attribute(url)}, rel = "license" => {name}),
+        None => name,
+    };
+    html!(div class = "license" => "License: " (license))
+}
+
+/// The tag of the `directives` for the search engines in the head, if any.
+pub fn html_robots(directives: &[String]) -> String {
+    match directives.is_empty() {
+        true => String::new(),
+        false => {
+            let content = htmlize::escape_attribute(directives.join(", "));
+            format!("\n<meta name=\"robots\" content=\"{}\">", content)
+        }
+    }
+}
+
+/// The navigation of a part of the series `name`, linked at `url` if any: "Part N of M" with the
+/// `parts` by their URL and title, the `current` one not linked, and the links to the
+/// previous and the next parts, if any.
+pub fn html_series(
+    name: &str,
+    url: Option<&str>,
+    parts: &[(String, String)],
+    current: usize,
+) -> String {
+    let link = |(url, title): &(String, String)| html!(a href = {url} => {title});
+    let items: String = parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| match i == current {
+            true => html!(li class = "current" => {&part.1}),
+            false => html!(li => (link(part))),
+        })
+        .collect();
+    let name = match url {
+        Some(url) => html!(a href = {url} => {name}),
+        None => name.to_string(),
+    };
+    let heading = format!("Part {} of {} in {}", current + 1, parts.len(), name);
+    let previous = current
+        .checked_sub(1)
+        .and_then(|i| parts.get(i))
+        .map(|part| html!(span class = "series-prev" => "← " (link(part))));
+    let next = parts
+        .get(current + 1)
+        .map(|part| html!(span class = "series-next" => (link(part)) " →"));
+    html!(nav class = "series" =>
+      (html!(p => {heading}))
+      (html!(ol => {items}))
+      (html!(p class = "series-nav" => {previous.unwrap_or_default()} {next.unwrap_or_default()})))
+}
+
+/// Shown on an expired page which is still written, `date` being its `expires`.
+pub fn html_archived_banner(date: &str) -> String {
+    html!(div class = "archived-banner" => "Archived: this page expired on " {date} ".")
+}
+
+pub fn html_backlinks_group(title: &str, items: &str) -> String {
+    let header = html!(h2 class = "backlinks-group" => {title});
+    format!("{}{}", header, items)
+}
+
+pub fn html_backlink_snippet(snippet: &str) -> String {
+    html!(p class = "backlink-snippet" => {snippet})
+}
+
 pub fn html_section(
     summary: &String,
     content: &String,
@@ -41,12 +168,20 @@ pub fn html_section(
     open: bool,
     id: String,
     data_taxon: Option<&String>,
+    stub: bool,
 ) -> String {
     let mut class_name: Vec<&str> = vec!["block"];
     if hide_metadata {
         class_name.push("hide-metadata");
     }
-    let data_taxon = data_taxon.map_or("", |s| s);
+    if stub {
+        class_name.push("stub");
+    }
+    let data_taxon = data_taxon.map(|s| Taxon::class_name(s)).unwrap_or_default();
+    let taxon_class = format!("taxon-{}", data_taxon);
+    if !data_taxon.is_empty() {
+        class_name.push(&taxon_class);
+    }
     let open = open.then(|| "open").unwrap_or("");
     let inner_html = format!("{}{}", (html!(summary => {summary})), content);
     let html_details = format!(
@@ -58,7 +193,9 @@ pub fn html_section(
     html!(section class = {class_name.join(" ")}, data_taxon = {data_taxon} => {html_details})
 }
 
-pub fn html_entry_header(mut etc: Vec<String>) -> String {
+/// The metadata of a page as items, followed by the `rows` of
+/// [`config::display_metadata`] by key, as a definition list.
+pub fn html_entry_header(mut etc: Vec<String>, rows: &[(String, String)]) -> String {
     let mut meta_items: Vec<String> = vec![];
     meta_items.append(&mut etc);
 
@@ -68,7 +205,46 @@ pub fn html_entry_header(mut etc: Vec<String>) -> String {
         .reduce(|s, t| s + &t)
         .unwrap_or(String::new());
 
-    html!(div class="metadata" => (html!(ul => {items})))
+    let rows: String = rows.iter()
+        .map(|(key, value)| {
+            let key = htmlize::escape_text(key);
+            html!(div class = "meta-row", data_key = {&key} => (html!(dt => {&key})) (html!(dd => {value})))
+        })
+        .collect();
+    let rows = match rows.is_empty() {
+        true => String::new(),
+        false => html!(dl class = "meta-rows" => {rows}),
+    };
+    html!(div class="metadata" => (html!(ul => {items})) {rows})
+}
+
+pub fn html_reading_time(minutes: &str) -> String {
+    html!(span class = "reading-time" => "~" {minutes} " min read")
+}
+
+/// The `authors` of a page joined by commas, each shown by its name in `[authors]` and
+/// linked to its page under `prefix`, if any, see [`config::author_pages`].
+pub fn html_authors(authors: &[String], prefix: Option<&str>) -> String {
+    let authors: Vec<String> = authors
+        .iter()
+        .map(|author| {
+            let name = htmlize::escape_text(config::author_name(author)).to_string();
+            let Some(prefix) = prefix else {
+                return html!(span class = "author" => {name});
+            };
+            let url = config::full_html_url(&config::prefixed_slug(prefix, author));
+            html!(a class = "author", href = {url} => {name})
+        })
+        .collect();
+    authors.join(", ")
+}
+
+/// The items of the list-valued metadata `key` each in a span, so that they can be styled
+/// apart, such as the `tags` as chips.
+pub fn html_list_items(key: &str, items: &[String]) -> String {
+    items.iter()
+        .map(|item| html!(span class = "list-item", data_key = {key} => {htmlize::escape_text(item)}))
+        .collect()
 }
 
 pub fn catalog_item(
@@ -127,6 +303,181 @@ pub fn html_link(href: &str, title: &str, text: &str, class_name: &str) -> Strin
       (html!(a href = {href}, title = {title} => {text})))
 }
 
+/// Placed after each citation of the reference page `slug`, and replaced by its number
+/// or removed once the page is written, see `Writer::number_references`.
+pub fn html_reference_marker(slug: &str) -> String {
+    format!("<!--reference:{}-->", slug)
+}
+
+/// A citation of `slug` written as `html`, rewritten in the [`config::CitationStyle`]
+/// of the page once it is written, see `Writer::style_citations`.
+pub fn html_citation_marker(slug: &str, locator: Option<&str>, html: &str) -> String {
+    format!(
+        "<!--citation:{}-->{}<!--/locator-->{}<!--/citation-->",
+        slug,
+        locator.unwrap_or_default(),
+        html
+    )
+}
+
+/// `[3, p. 12]`, linking to the reference numbered `number`.
+pub fn html_numeric_citation(number: usize, locator: &str) -> String {
+    let href = format!("#reference-{}", number);
+    let text = match locator.is_empty() {
+        true => format!("[{}]", number),
+        false => format!("[{}, {}]", number, locator),
+    };
+    html!(a class = "citation", href = {href} => {text})
+}
+
+/// The superscript of the footnote numbered `number`, linking to it.
+pub fn html_footnote_marker(number: usize) -> String {
+    let href = format!("#footnote-{}", number);
+    let id = format!("footnote-marker-{}", number);
+    let link = html!(a href = {href} => {number.to_string()});
+    html!(sup class = "footnote-marker", id = {id} => {link})
+}
+
+/// The footnotes of a page, each of them linking back to its marker.
+pub fn html_footnotes(footnotes: &[String]) -> String {
+    let items: String = footnotes
+        .iter()
+        .enumerate()
+        .map(|(index, footnote)| {
+            let id = format!("footnote-{}", index + 1);
+            let href = format!("#footnote-marker-{}", index + 1);
+            let back = html!(a class = "footnote-back", href = {href} => "↩");
+            html!(li id = {id} => (footnote) (" ") (back))
+        })
+        .collect();
+    html!(section class = "block footnotes" => (html!(ol => {items})))
+}
+
+/// The text of a link `[#](slug)`, replaced by the taxon and number of `slug` once the
+/// page is written, or else by `fallback`, see `Writer::number_cross_references`.
+pub fn html_cross_reference(slug: &str, fallback: &str) -> String {
+    format!("<!--number:{}-->{}<!--/number-->", slug, fallback)
+}
+
+pub fn html_reference_number(number: usize) -> String {
+    let href = format!("#reference-{}", number);
+    let text = format!("[{}]", number);
+    html!(sup class = "reference-number" => (html!(a href = {href} => {text})))
+}
+
+/// A block of the references, linked from the citations numbered `number`.
+pub fn html_numbered_reference(number: usize, block: &str) -> String {
+    format!(
+        r#"<div class="numbered-reference" id="reference-{}" data-number="[{}]">{}</div>"#,
+        number, number, block
+    )
+}
+
+/// The fields of a reference page shown in its bibliography entry, each of them optional.
+pub struct BibliographyEntry<'a> {
+    pub author: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub journal: Option<&'a str>,
+    pub year: Option<&'a str>,
+    pub doi: Option<&'a str>,
+    pub url: Option<&'a str>,
+}
+
+/// `Author (Year). Title. Journal. doi:…` or `[n] Author. Title. Journal, Year. doi:…`
+/// in [`config::BibliographyStyle::Numeric`], leaving out the missing fields.
+/// The title links to `href` if given, else to the `url` of the reference.
+pub fn html_bibliography_entry(
+    entry: &BibliographyEntry,
+    href: Option<&str>,
+    style: config::BibliographyStyle,
+    number: Option<usize>,
+) -> String {
+    let title = entry.title.map(|title| {
+        let title = html!(em => {title});
+        match href.or(entry.url) {
+            Some(href) => html!(a href = {href} => (title)),
+            None => title,
+        }
+    });
+    let parts: [Option<String>; 3] = match style {
+        config::BibliographyStyle::AuthorYear => {
+            let source = match (entry.author, entry.year) {
+                (Some(author), Some(year)) => Some(format!("{} ({})", author, year)),
+                (author, year) => author.or(year).map(str::to_string),
+            };
+            [source, title, entry.journal.map(str::to_string)]
+        }
+        config::BibliographyStyle::Numeric => {
+            let journal: Vec<&str> = entry.journal.into_iter().chain(entry.year).collect();
+            let journal = (!journal.is_empty()).then(|| journal.join(", "));
+            [entry.author.map(str::to_string), title, journal]
+        }
+    };
+    let parts: Vec<String> = parts.into_iter().flatten().collect();
+    let mut text = match parts.is_empty() {
+        true => String::new(),
+        false => format!("{}.", parts.join(". ")),
+    };
+    if let Some(number) = number.filter(|_| style == config::BibliographyStyle::Numeric) {
+        text = format!("[{}] {}", number, text);
+    }
+    if let Some(doi) = entry.doi {
+        let doi = doi.trim_start_matches("https://doi.org/");
+        let link = html!(a href = {format!("https://doi.org/{}", doi)} => ("doi:") {doi});
+        text = format!("{} {}", text, link);
+    }
+    if let Some(url) = entry.url.filter(|_| href.is_some()) {
+        text = format!("{} {}", text, html!(a href = {url} => {url}));
+    }
+    html!(span class = "bibliography-entry" => (text))
+}
+
+/// `(author year, locator)`, falling back to the title of the reference without its author
+/// and year.
+pub fn html_citation(
+    href: &str,
+    title: &str,
+    author: Option<&str>,
+    year: Option<&str>,
+    locator: Option<&str>,
+) -> String {
+    let text = citation_text(title, author, year, locator);
+    html_link(href, title, &text, "local citation")
+}
+
+/// A citation of a reference declared inline, linking its `url` if it has one.
+pub fn html_inline_citation(
+    url: Option<&str>,
+    title: &str,
+    author: Option<&str>,
+    year: Option<&str>,
+    locator: Option<&str>,
+) -> String {
+    let text = citation_text(title, author, year, locator);
+    match url {
+        Some(url) => html_link(url, title, &text, "external citation"),
+        None => html!(span class = "citation", title = {title} => {text}),
+    }
+}
+
+/// `(Author Year, p. 12)`, or `(Title)` without an author and a year.
+fn citation_text(
+    title: &str,
+    author: Option<&str>,
+    year: Option<&str>,
+    locator: Option<&str>,
+) -> String {
+    let source: Vec<&str> = author.into_iter().chain(year).collect();
+    let source = match source.is_empty() {
+        true => title.to_string(),
+        false => source.join(" "),
+    };
+    match locator {
+        Some(locator) => format!("({}, {})", source, locator),
+        None => format!("({})", source),
+    }
+}
+
 pub fn html_header_nav(title: &str, page_title: &str, href: &str) -> String {
     let onclick = format!("window.location.href='{}'", href);
     let link = html!(span onclick={onclick}, title={page_title} => ("« ") (title));
@@ -134,8 +485,11 @@ pub fn html_header_nav(title: &str, page_title: &str, href: &str) -> String {
     html!(header class = "header" => (html!(nav class = "nav" => {nav_inner})))
 }
 
+/// A whole page, `head_meta` being the tags of its head after the title.
 pub fn html_doc(
     page_title: &str,
+    lang: &str,
+    head_meta: &str,
     header_html: &str,
     article_inner: &str,
     footer_html: &str,
@@ -153,11 +507,12 @@ pub fn html_doc(
       "\n\n"
       (toc_html));
 
-    let html = html!(html lang = "en-US" => 
+    let html = html!(html lang = {lang} => 
       (html!(head => r#"
 <meta http-equiv="Content-Type" content="text/html; charset=utf-8">
 <meta name="viewport" content="width=device-width">"#
         (format!("<title>{page_title}</title>")) 
+        (head_meta)
         (html_import_meta())
         (html_css())
         (html_import_fonts())
@@ -166,6 +521,43 @@ pub fn html_doc(
     format!("{}\n{}", doc_type, &html)
 }
 
+/// The Open Graph and Twitter card tags of a page of plain text `title` and `description`,
+/// `kind` being its `og:type`.
+pub fn html_social_meta(
+    title: &str,
+    description: &str,
+    url: &str,
+    kind: &str,
+    image: Option<&str>,
+) -> String {
+    let property = |property: &str, content: &str| {
+        let content = htmlize::escape_attribute(content);
+        format!("\n<meta property=\"{}\" content=\"{}\">", property, content)
+    };
+    let name = |name: &str, content: &str| {
+        let content = htmlize::escape_attribute(content);
+        format!("\n<meta name=\"{}\" content=\"{}\">", name, content)
+    };
+    let card = match image {
+        Some(_) => "summary_large_image",
+        None => "summary",
+    };
+
+    let mut html = String::new();
+    html.push_str(&property("og:title", title));
+    html.push_str(&property("og:description", description));
+    html.push_str(&property("og:url", url));
+    html.push_str(&property("og:type", kind));
+    html.push_str(&name("twitter:card", card));
+    html.push_str(&name("twitter:title", title));
+    html.push_str(&name("twitter:description", description));
+    if let Some(image) = image {
+        html.push_str(&property("og:image", image));
+        html.push_str(&name("twitter:image", image));
+    }
+    html
+}
+
 pub fn html_css() -> String {
     match config::disable_export_css() {
         true => html!(style => (html_main_style()) (html_typst_style())),
@@ -192,6 +584,12 @@ pub fn html_import_math() -> String {
     return config::CUSTOM_MATH_HTML.clone();
 }
 
+pub fn html_redirect(url: &str, title: &str) -> String {
+    config::CUSTOM_REDIRECT_HTML
+        .replace("{url}", &htmlize::escape_attribute(url))
+        .replace("{title}", &htmlize::escape_text(title))
+}
+
 pub fn html_main_style() -> &'static str {
     return include_str!("include/main.css");
 }
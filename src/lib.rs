@@ -0,0 +1,24 @@
+//! Kodama compiles a forest of Markdown and Typst notes into HTML.
+//!
+//! Besides the `kodama` binary, the compiler can be driven from another program:
+//! [`compile_tree`] compiles the sources into a [`CompiledForest`] without writing anything,
+//! and [`write_forest`] writes its HTML to the output directory. The `kodama compile` command
+//! is [`compile_tree_cached`], which reuses the cache of the last compilation, then
+//! [`write_forest`].
+
+pub mod compiler;
+pub mod config;
+pub mod date;
+pub mod entry;
+pub mod html_flake;
+mod html_macro;
+mod process;
+mod recorder;
+pub mod slug;
+mod typst_cli;
+
+pub use compiler::{
+    compile_tree, compile_tree_cached, compile_tree_with_hooks, write_forest, CompileError,
+    CompiledForest,
+};
+pub use config::Config;
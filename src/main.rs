@@ -1,17 +1,13 @@
-mod compiler;
-mod config;
-mod entry;
-mod html_flake;
-mod html_macro;
-mod process;
-mod recorder;
-mod slug;
-mod typst_cli;
-
-use std::fs;
+use std::{collections::BTreeMap, fs};
 
 use clap::Parser;
-use config::{output_path, CompileConfig, FooterMode};
+use kodama::{
+    compiler::{self, defaults::origin_kind, diff::ChangeKind},
+    config::{self, output_path, CompileConfig, FooterMode, UrlStyle},
+    date::Date,
+    entry::MetaData,
+    html_flake, slug,
+};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -28,6 +24,27 @@ enum Command {
 
     /// Clean build files (.cache & publish).
     Clean(CleanCommand),
+
+    /// Print the embed, link and reference graph of the workspace.
+    Graph(GraphCommand),
+
+    /// Compare the pages of the workspace with the HTML in an existing output dir.
+    Diff(DiffCommand),
+
+    /// Print the source pages whose content is embedded in a page, directly or transitively.
+    Sources(SourcesCommand),
+
+    /// Print the metadata of a page, where each value comes from and how it is used.
+    Meta(MetaCommand),
+
+    /// Move a page to another slug, rewriting the links, embeds and citations of it.
+    Mv(MvCommand),
+
+    /// Work with the reference pages as BibTeX.
+    Bib(BibCommand),
+
+    /// Work with the slugs frozen in `slugs.toml`.
+    Slugs(SlugsCommand),
 }
 
 #[derive(clap::Args)]
@@ -44,7 +61,11 @@ struct CompileCommand {
     #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
     root: String,
 
-    /// Disable pretty urls (`/page` to `/page.html`)
+    /// Slug of the page served at the site root [default: index]
+    #[arg(long)]
+    root_slug: Option<String>,
+
+    /// Disable pretty urls (`/page` to `/page.html`), as `url_style = "html"`
     #[arg(short, long, default_value_t = false)]
     disable_pretty_urls: bool,
 
@@ -59,6 +80,78 @@ struct CompileCommand {
     /// Disable exporting the `main.css` file to the output directory.
     #[arg(long)]
     disable_export_css: bool,
+
+    /// Render embeds nested deeper than this as links (`0` to never embed).
+    #[arg(long)]
+    max_embed_depth: Option<usize>,
+
+    /// Only compile these pages (slugs or source paths) and the pages they embed.
+    #[arg(long, num_args = 1..)]
+    only: Vec<String>,
+
+    /// Do not print the pages that are not reachable from any other page.
+    #[arg(long)]
+    hide_orphans: bool,
+
+    /// Keep the lexicographically-first file when several files produce the same slug.
+    #[arg(long)]
+    force: bool,
+
+    /// Reparse, recompile and rewrite every page, ignoring the cache in `.cache`.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Also compile the pages marked `draft`, showing a banner on them.
+    #[arg(long)]
+    drafts: bool,
+
+    /// Expire the pages against this date, written `YYYY-MM-DD`, instead of today.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    now: Option<Date>,
+
+    /// Only write the pages whose sources, references or backlinks changed
+    /// since the last compilation, trusting the other pages to be up to date.
+    #[arg(long)]
+    rerender_affected: bool,
+
+    /// Exit with an error if any warning was reported, e.g. a missing embed.
+    #[arg(long, visible_alias = "strict")]
+    deny_warnings: bool,
+
+    /// Print the time spent in each phase and the N slowest pages.
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    timings: Option<usize>,
+
+    /// Write the time spent on each page to this file as JSON.
+    #[arg(long, value_name = "FILE")]
+    timings_json: Option<String>,
+
+    /// Write the backlinks and parents changed since the last compilation to this file as JSON.
+    #[arg(long, value_name = "FILE")]
+    changed_json: Option<String>,
+
+    /// Write the source pages embedded in each page as an HTML comment.
+    #[arg(long)]
+    emit_sources: bool,
+
+    /// Compile without writing anything, and print what would be built.
+    /// Exit with an error if any diagnostic was reported.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print the metadata of this page and where each value comes from.
+    #[arg(long, value_name = "SLUG")]
+    explain_metadata: Option<String>,
+
+    /// Specify the output format of the diagnostics.
+    #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Human)]
+    diagnostics: DiagnosticsFormat,
+}
+
+#[derive(Clone, clap::ValueEnum)]
+enum DiagnosticsFormat {
+    Human,
+    Json,
 }
 
 #[derive(clap::Args)]
@@ -88,6 +181,140 @@ struct CleanCommand {
     html: bool,
 }
 
+#[derive(clap::Args)]
+struct GraphCommand {
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+
+    /// Output format of the graph.
+    #[arg(short, long, value_enum, default_value_t = GraphFormat::Json)]
+    format: GraphFormat,
+}
+
+#[derive(Clone, clap::ValueEnum)]
+enum GraphFormat {
+    Json,
+}
+
+#[derive(clap::Args)]
+struct DiffCommand {
+    /// Output directory of a previous build to compare with.
+    old_output: String,
+
+    /// Base URL or publish URL (e.g. https://www.example.com/)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.base_url.into())]
+    base: String,
+
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+
+    /// Disable pretty urls (`/page` to `/page.html`), as `url_style = "html"`
+    #[arg(short, long, default_value_t = false)]
+    disable_pretty_urls: bool,
+
+    /// Specify the inline mode for the footer sections
+    #[arg(short, long, default_value_t = FooterMode::Link)]
+    footer_mode: FooterMode,
+
+    /// Compare with a build which did not export the `main.css` file.
+    #[arg(long)]
+    disable_export_css: bool,
+
+    /// Print a unified diff of each changed page.
+    #[arg(long)]
+    full: bool,
+}
+
+#[derive(clap::Args)]
+struct SourcesCommand {
+    /// Slug or source path of the page.
+    slug: String,
+
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+}
+
+#[derive(clap::Args)]
+struct MvCommand {
+    /// Slug or source path of the page.
+    old: String,
+
+    /// The new slug of the page.
+    new: String,
+
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+
+    /// Add the former slug to the `aliases` of the page, so that its URL keeps working.
+    #[arg(long)]
+    alias: bool,
+
+    /// Print the planned rewrites without writing any file.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(clap::Args)]
+struct MetaCommand {
+    /// Slug or source path of the page.
+    slug: String,
+
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+
+    /// Print the metadata as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct BibCommand {
+    #[command(subcommand)]
+    command: BibSubcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum BibSubcommand {
+    /// Print the reference pages as BibTeX entries, their cite keys being their slugs.
+    Export(BibExportCommand),
+}
+
+#[derive(clap::Args)]
+struct SlugsCommand {
+    #[command(subcommand)]
+    command: SlugsSubcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum SlugsSubcommand {
+    /// Write the current slug of each source file into `slugs.toml`, so that it is kept
+    /// whatever the slug settings.
+    Freeze(SlugsFreezeCommand),
+}
+
+#[derive(clap::Args)]
+struct SlugsFreezeCommand {
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+}
+
+#[derive(clap::Args)]
+struct BibExportCommand {
+    /// Configures the project root (for absolute paths)
+    #[arg(short, long, default_value_t = config::DEFAULT_CONFIG.root_dir.into())]
+    root: String,
+
+    /// Only export the references cited by some page.
+    #[arg(long)]
+    used_only: bool,
+}
+
 fn main() {
     let cli = Cli::parse();
     match &cli.command {
@@ -95,28 +322,132 @@ fn main() {
             let root = &compile_command.root;
             let output = &compile_command.output;
 
-            config::mutex_set(
-                &config::CONFIG,
-                CompileConfig::new(
-                    root.to_string(),
-                    output.to_string(),
-                    compile_command.base.to_string(),
-                    compile_command.disable_pretty_urls,
-                    compile_command.short_slug,
-                    compile_command.footer_mode.clone(),
-                    compile_command.disable_export_css,
-                ),
+            let mut compile_config = CompileConfig::new(
+                root.to_string(),
+                output.to_string(),
+                compile_command.base.to_string(),
+                compile_command.disable_pretty_urls,
+                compile_command.short_slug,
+                compile_command.footer_mode.clone(),
+                compile_command.disable_export_css,
+            );
+            apply_config_file(
+                &mut compile_config,
+                read_config_file(root),
+                compile_command.disable_pretty_urls,
             );
+            if let Some(root_slug) = compile_command.root_slug.clone() {
+                compile_config.root_slug = root_slug;
+            }
+            if let Some(max_embed_depth) = compile_command.max_embed_depth {
+                compile_config.max_embed_depth = Some(max_embed_depth);
+            }
+            compile_config.force = compile_command.force;
+            compile_config.no_cache = compile_command.no_cache;
+            compile_config.drafts = compile_command.drafts;
+            compile_config.now = compile_command.now;
+            compile_config.rerender_affected = compile_command.rerender_affected;
+            compile_config.emit_sources = compile_command.emit_sources;
+            compile_config.timings =
+                compile_command.timings.is_some() || compile_command.timings_json.is_some();
+            config::mutex_set(&config::CONFIG, compile_config);
+
+            if compile_command.dry_run {
+                return dry_run(compile_command);
+            }
 
             if !compile_command.disable_export_css {
                 export_css_files()
             }
 
-            match compiler::compile_all(root) {
-                Err(err) => eprintln!("{:?}", err),
-                Ok(_) => (),
+            let result = match compile_command.only.is_empty() {
+                true => compiler::compile_all(root),
+                false => {
+                    let slugs: Vec<String> = compile_command
+                        .only
+                        .iter()
+                        .map(|s| slug::to_slug(s))
+                        .collect();
+                    compiler::compile_only(root, &slugs)
+                }
+            };
+
+            match result {
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+                Ok(state) => {
+                    let orphans = state.orphans();
+                    if !compile_command.hide_orphans && !orphans.is_empty() {
+                        println!("Orphans ({}): {}", orphans.len(), orphans.join(", "));
+                    }
+                    let unused = state.unused_references();
+                    if compile_command.only.is_empty() && !unused.is_empty() {
+                        let count = unused.len();
+                        println!("Unused references ({}): {}", count, unused.join(", "));
+                    }
+                    if !state.stubs.is_empty() {
+                        let stubs = state.stubs.join(", ");
+                        println!("Stubs ({}): {}", state.stubs.len(), stubs);
+                    }
+                    if !state.drifted_slugs.is_empty() {
+                        let count = state.drifted_slugs.len();
+                        let drifted: Vec<String> = state
+                            .drifted_slugs
+                            .iter()
+                            .map(|(frozen, computed)| format!("{} (now {})", frozen, computed))
+                            .collect();
+                        println!("Frozen slugs drifted ({}): {}", count, drifted.join(", "));
+                    }
+                    if !state.newly_expired.is_empty() {
+                        let count = state.newly_expired.len();
+                        let expired = state.newly_expired.join(", ");
+                        println!("Expired since the last build ({}): {}", count, expired);
+                    }
+                    if let Some(slug) = &compile_command.explain_metadata {
+                        explain_metadata(&state, slug, false);
+                    }
+                    state.callback_deltas.iter().for_each(|d| println!("{}", d));
+                    if let Some(path) = &compile_command.changed_json {
+                        let json = serde_json::to_string_pretty(&state.callback_deltas).unwrap();
+                        if let Err(err) = fs::write(path, json) {
+                            eprintln!("{:?}", err);
+                        }
+                    }
+                    if let Some(timings) = &state.timings {
+                        print_timings(compile_command, &timings.report());
+                    }
+
+                    let diagnostics = &state.diagnostics;
+                    match compile_command.diagnostics {
+                        DiagnosticsFormat::Human => {
+                            diagnostics.iter().for_each(|d| eprintln!("{}", d));
+                        }
+                        DiagnosticsFormat::Json => {
+                            eprintln!("{}", serde_json::to_string_pretty(diagnostics).unwrap());
+                        }
+                    }
+
+                    if diagnostics.has_errors()
+                        || (compile_command.deny_warnings && diagnostics.has_warnings())
+                    {
+                        std::process::exit(1);
+                    }
+                }
             }
         }
+        Command::Graph(graph_command) => print_graph(graph_command),
+        Command::Diff(diff_command) => print_diff(diff_command),
+        Command::Sources(sources_command) => print_sources(sources_command),
+        Command::Meta(meta_command) => print_meta(meta_command),
+        Command::Mv(mv_command) => move_page(mv_command),
+        Command::Bib(bib_command) => match &bib_command.command {
+            BibSubcommand::Export(export_command) => print_bibtex(export_command),
+        },
+        Command::Slugs(slugs_command) => match &slugs_command.command {
+            SlugsSubcommand::Freeze(freeze_command) => freeze_slugs(freeze_command),
+        },
         Command::Clean(clean_command) => {
             config::mutex_set(
                 &config::CONFIG,
@@ -160,6 +491,544 @@ fn main() {
     }
 }
 
+fn dry_run(compile_command: &CompileCommand) {
+    let forest = match compiler::compile_dry_run(&compile_command.root) {
+        Ok(forest) => forest,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let state = &forest.state;
+    let root_slug = config::root_slug();
+    let mut rows = vec![[
+        "SLUG".to_string(),
+        "REACHED".to_string(),
+        "EMBEDS".to_string(),
+        "LINKS".to_string(),
+        "REFERENCES".to_string(),
+        "OUTPUT".to_string(),
+    ]];
+    for slug in &forest.slugs {
+        let edges = state.dependency_graph().get(slug);
+        let count = |f: fn(&compiler::graph::PageEdges) -> usize| edges.map_or(0, f).to_string();
+        let reached = match state.unreached.contains(slug) {
+            true => "residued",
+            false => "root",
+        };
+        let output = match *slug != root_slug && slug == "index" {
+            true => "-".to_string(), // shadowed by the root page.
+            false => config::join_path(&config::output_dir(), &config::output_html_name(slug)),
+        };
+        rows.push([
+            slug.to_string(),
+            reached.to_string(),
+            count(|e| e.embeds.len()),
+            count(|e| e.links.len()),
+            count(|e| e.references.len()),
+            output,
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..6)
+        .map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap())
+        .collect();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+
+    forest.diagnostics().iter().for_each(|d| eprintln!("{}", d));
+    if !forest.diagnostics().is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Print each metadata key of the page `slug` with its value, where it comes from:
+/// the page, a `meta.toml`, `kodama.toml`, a fallback or the compiler, and how it is used,
+/// see [`MetaData::key_use`]. As JSON with `json`. Returns whether `slug` is compiled.
+fn explain_metadata(state: &compiler::state::CompileState, slug: &str, json: bool) -> bool {
+    let slug = slug::to_slug(slug);
+    let Some(section) = state.pages.get(&slug) else {
+        eprintln!("Error: [{}] is not a compiled page.", slug);
+        return false;
+    };
+    let origins = state.metadata_origins.get(&slug);
+    let mut keys: Vec<&String> = section.metadata.keys().collect();
+    keys.sort();
+
+    if json {
+        let entries: Vec<serde_json::Value> = keys
+            .into_iter()
+            .map(|key| {
+                let origin = origins.and_then(|origins| origins.get(key));
+                serde_json::json!({
+                    "key": key,
+                    "value": section.metadata.get(key),
+                    "list": section.metadata.get_list(key),
+                    "origin": origin,
+                    "kind": origin_kind(origin.map(String::as_str)),
+                    "use": section.metadata.key_use(key),
+                })
+            })
+            .collect();
+        let json = serde_json::json!({ "slug": slug, "metadata": entries });
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return true;
+    }
+    println!("Metadata of [{}]:", slug);
+    for key in keys {
+        let value = section.metadata.get(key).unwrap();
+        let used = section.metadata.key_use(key);
+        match origins.and_then(|origins| origins.get(key)) {
+            Some(origin) => println!("  {}: {} (from {}, {})", key, value, origin, used),
+            None => println!("  {}: {} (computed, {})", key, value, used),
+        }
+    }
+    true
+}
+
+fn print_meta(meta_command: &MetaCommand) {
+    let root = &meta_command.root;
+    set_analysis_config(root);
+
+    match compiler::compile_state(root) {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok(state) => {
+            if !explain_metadata(&state, &meta_command.slug, meta_command.json) {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn print_timings(compile_command: &CompileCommand, report: &compiler::timings::TimingReport) {
+    if let Some(top) = compile_command.timings {
+        println!("{}", report.display(top));
+    }
+    if let Some(path) = &compile_command.timings_json {
+        let json = serde_json::to_string_pretty(report).unwrap();
+        if let Err(err) = fs::write(path, json) {
+            eprintln!("{:?}", err);
+        }
+    }
+}
+
+/// The prefix of the series pages, unless disabled by `series_pages = false`.
+fn series_pages(enabled: Option<bool>, prefix: Option<&str>) -> Option<String> {
+    let prefix = prefix.unwrap_or(config::DEFAULT_SERIES_PAGES_PREFIX);
+    enabled.unwrap_or(true).then(|| prefix.to_string())
+}
+
+fn parse_date(s: &str) -> Result<Date, String> {
+    Date::parse(s).ok_or_else(|| format!("`{}` is not written `YYYY-MM-DD`", s))
+}
+
+fn read_config_file(root: &str) -> config::ConfigFile {
+    config::ConfigFile::read(root).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
+}
+
+/// Apply the settings of `kodama.toml` to `compile_config`, before those of the command line.
+/// The `url_style` must agree with `--disable-pretty-urls`, see [`url_style`].
+fn apply_config_file(
+    compile_config: &mut CompileConfig<String>,
+    config_file: config::ConfigFile,
+    disable_pretty_urls: bool,
+) {
+    if let Some(root_slug) = config_file.root {
+        compile_config.root_slug = root_slug;
+    }
+    compile_config.max_embed_depth = config_file.max_embed_depth;
+    if let Some(expired) = config_file.expired {
+        compile_config.expired = expired;
+    }
+    compile_config.allow_stubs = config_file.allow_stubs.unwrap_or(false);
+    if let Some(broken_links) = config_file.broken_links {
+        compile_config.broken_links = broken_links;
+    }
+    if let Some(slug_mode) = config_file.slug_mode {
+        compile_config.slug_mode = slug_mode;
+    }
+    if let Some(slug_case) = config_file.slug_case {
+        compile_config.slug_case = slug_case;
+    }
+    compile_config.url_style = url_style(disable_pretty_urls, config_file.url_style);
+    compile_config.flatten_slugs = config_file.flatten_slugs.unwrap_or(false);
+    if let Some(reserved_prefix) = config_file.reserved_prefix {
+        compile_config.reserved_prefix = reserved_prefix;
+    }
+    if let Some(max_slug_length) = config_file.max_slug_length {
+        compile_config.max_slug_length = max_slug_length;
+    }
+    compile_config.post_hooks = config_file.post_hooks.unwrap_or_default();
+    compile_config.taxa = config_file.taxa.unwrap_or_default();
+    let metadata = config_file.metadata.unwrap_or_default();
+    compile_config.metadata_defaults = config::metadata_text(&metadata.defaults);
+    compile_config.metadata_variables = config::metadata_text(&metadata.variables);
+    compile_config.metadata_schema = metadata.schema;
+    if let Some(backlinks_order) = config_file.backlinks_order {
+        compile_config.backlinks_order = backlinks_order;
+    }
+    if let Some(backlinks_group_by) = config_file.backlinks_group_by {
+        compile_config.backlinks_group_by = backlinks_group_by;
+    }
+    compile_config.taxon_order = config_file.taxon_order.unwrap_or_default();
+    compile_config.backlinks_show_taxon = config_file.backlinks_show_taxon.unwrap_or(false);
+    compile_config.backlinks_show_date = config_file.backlinks_show_date.unwrap_or(false);
+    compile_config.max_backlinks = config_file.max_backlinks;
+    compile_config.embed_badge_min = config_file.embed_badge_min;
+    compile_config.numbered_references = config_file.numbered_references.unwrap_or(false);
+    compile_config.merge_further_reading = config_file.merge_further_reading.unwrap_or(false);
+    compile_config.auto_numbering = config_file.auto_numbering.unwrap_or(false);
+    if let Some(numbering_scope) = config_file.numbering_scope {
+        compile_config.numbering_scope = numbering_scope;
+    }
+    compile_config.transitive_contexts = config_file.transitive_contexts.unwrap_or(false);
+    if config_file.mentions.unwrap_or(false) {
+        let slug = config_file.mentions_slug.as_deref();
+        let slug = slug.unwrap_or(config::DEFAULT_MENTIONS_SLUG).to_string();
+        compile_config.mentions_page = Some(slug);
+    }
+    if config_file.bibliography.unwrap_or(false) {
+        let slug = config_file.bibliography_slug.as_deref();
+        let slug = slug
+            .unwrap_or(config::DEFAULT_BIBLIOGRAPHY_SLUG)
+            .to_string();
+        compile_config.bibliography_page = Some(slug);
+    }
+    if config_file.taxon_pages.unwrap_or(false) {
+        let slug = config_file.taxon_pages_slug.as_deref();
+        let slug = slug.unwrap_or(config::DEFAULT_TAXON_PAGES_SLUG).to_string();
+        compile_config.taxon_pages = Some(slug);
+    }
+    compile_config.taxon_pages_only = config_file.taxon_pages_only.unwrap_or_default();
+    if let Some(taxon_pages_order) = config_file.taxon_pages_order {
+        compile_config.taxon_pages_order = taxon_pages_order;
+    }
+    if config_file.tag_pages.unwrap_or(false) {
+        let prefix = config_file.tag_pages_prefix.as_deref();
+        let prefix = prefix
+            .unwrap_or(config::DEFAULT_TAG_PAGES_PREFIX)
+            .to_string();
+        compile_config.tag_pages = Some(prefix);
+    }
+    if config_file.author_pages.unwrap_or(false) {
+        let prefix = config_file.author_pages_prefix.as_deref();
+        let prefix = prefix
+            .unwrap_or(config::DEFAULT_AUTHOR_PAGES_PREFIX)
+            .to_string();
+        compile_config.author_pages = Some(prefix);
+    }
+    compile_config.series_pages = series_pages(
+        config_file.series_pages,
+        config_file.series_pages_prefix.as_deref(),
+    );
+    compile_config.authors = config_file.authors.unwrap_or_default();
+    compile_config.licenses = config_file
+        .licenses
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    compile_config.license = config_file.license;
+    if config_file.license_page.unwrap_or(false) {
+        let slug = config_file.license_page_slug.as_deref();
+        let slug = slug
+            .unwrap_or(config::DEFAULT_LICENSE_PAGE_SLUG)
+            .to_string();
+        compile_config.license_page = Some(slug);
+    }
+    compile_config.bibtex = config_file.bibtex;
+    compile_config.lang = config_file.lang;
+    compile_config.date_format = config_file.date_format;
+    if let Some(word_count) = config_file.word_count {
+        compile_config.word_count = word_count;
+    }
+    compile_config.word_count_code = config_file.word_count_code.unwrap_or(true);
+    if let Some(words_per_minute) = config_file.words_per_minute {
+        compile_config.words_per_minute = words_per_minute.max(1);
+    }
+    compile_config.show_reading_time = config_file.show_reading_time.unwrap_or(false);
+    compile_config.git_dates = config_file.git_dates.unwrap_or(false);
+    compile_config.bibliography_sort = config_file
+        .bibliography_sort
+        .unwrap_or_else(|| config::DEFAULT_BIBLIOGRAPHY_SORT.map(String::from).to_vec());
+    compile_config.reference_fields = config_file
+        .reference_fields
+        .unwrap_or_else(|| config::DEFAULT_REFERENCE_FIELDS.map(String::from).to_vec());
+    if let Some(list_keys) = config_file.list_keys {
+        compile_config.list_keys = list_keys;
+    }
+    if let Some(bibliography_style) = config_file.bibliography_style {
+        compile_config.bibliography_style = bibliography_style;
+    }
+    if let Some(citation_style) = config_file.citation_style {
+        compile_config.citation_style = citation_style;
+    }
+    compile_config.max_related = config_file.max_related;
+    if let Some(min_related_score) = config_file.min_related_score {
+        compile_config.min_related_score = min_related_score;
+    }
+    compile_config.emit_link_index = config_file.emit_link_index.unwrap_or(true);
+    compile_config.emit_page_meta = config_file.emit_page_meta.unwrap_or(false);
+    compile_config.page_meta_html = config_file.page_meta_html.unwrap_or_default();
+    compile_config.social_meta = config_file.social_meta.unwrap_or(true);
+    compile_config.robots = config_file.robots.unwrap_or_default();
+    compile_config.display_metadata = config_file.display_metadata;
+}
+
+/// Configure a compilation which only inspects the workspace.
+fn set_analysis_config(root: &str) {
+    let mut compile_config = CompileConfig::new(
+        root.to_string(),
+        config::DEFAULT_CONFIG.output_dir.into(),
+        config::DEFAULT_CONFIG.base_url.into(),
+        false,
+        config::DEFAULT_CONFIG.short_slug,
+        FooterMode::Link,
+        true,
+    );
+    apply_config_file(&mut compile_config, read_config_file(root), false);
+    config::mutex_set(&config::CONFIG, compile_config);
+}
+
+/// The `url_style` of `kodama.toml`, or that of `--disable-pretty-urls`, which must agree.
+fn url_style(disable_pretty_urls: bool, url_style: Option<UrlStyle>) -> UrlStyle {
+    match url_style {
+        Some(url_style) if disable_pretty_urls && url_style != UrlStyle::Html => {
+            eprintln!(
+                "Error: `--disable-pretty-urls` is `url_style = \"html\"`, but `kodama.toml` has `url_style = \"{}\"`.",
+                url_style.as_str()
+            );
+            std::process::exit(1);
+        }
+        Some(url_style) => url_style,
+        None => config::to_url_style(disable_pretty_urls),
+    }
+}
+
+fn move_page(mv_command: &MvCommand) {
+    let root = &mv_command.root;
+    set_analysis_config(root);
+    let old = slug::to_slug(&mv_command.old);
+    let new = slug::to_slug(&mv_command.new);
+
+    let workspace = compiler::all_source_files(std::path::Path::new(root)).and_then(|workspace| {
+        workspace
+            .diagnostics
+            .iter()
+            .for_each(|d| eprintln!("{}", d));
+        if !workspace.source_paths.contains_key(&old) {
+            return Err(compiler::CompileError::Missing(old.to_string(), None));
+        }
+        if let Some(existed) = workspace.source_paths.get(&new) {
+            let source = workspace.source_paths[&old].to_string();
+            let existed = existed.to_string();
+            return Err(compiler::CompileError::DuplicateSlug(
+                new.to_string(),
+                source,
+                existed,
+            ));
+        }
+        Ok(workspace)
+    });
+    let rename =
+        workspace.and_then(|workspace| compiler::rename::plan_rename(root, &workspace, &old, &new));
+    let rename = rename.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    for (path, rewrites) in &rename.rewrites {
+        let content = fs::read_to_string(config::join_path(root, path)).unwrap_or_default();
+        for rewrite in rewrites {
+            let line = compiler::rename::line_of(&content, rewrite.range.start);
+            println!("{}:{}: {} -> {}", path, line, rewrite.from, rewrite.to);
+        }
+    }
+    let targets = match rename.count() {
+        1 => "1 target".to_string(),
+        count => format!("{} targets", count),
+    };
+    let files = match rename.rewrites.len() {
+        1 => "1 file".to_string(),
+        files => format!("{} files", files),
+    };
+    if mv_command.dry_run {
+        println!(
+            "Would move `{}` to `{}`, rewriting {} in {}.",
+            rename.source, rename.target, targets, files
+        );
+        return;
+    }
+
+    let alias = mv_command.alias.then_some(old.as_str());
+    match compiler::rename::apply_rename(root, &rename, alias) {
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        Ok(is_aliased) => {
+            println!(
+                "Moved `{}` to `{}`, rewriting {} in {}.",
+                rename.source, rename.target, targets, files
+            );
+            if alias.is_some() && !is_aliased {
+                eprintln!(
+                    "Warning: [{}] is not added to the `aliases` of `{}`, add it there.",
+                    old, rename.target
+                );
+            }
+        }
+    }
+}
+
+fn print_graph(graph_command: &GraphCommand) {
+    let root = &graph_command.root;
+    set_analysis_config(root);
+
+    match compiler::compile_state(root) {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok(state) => match graph_command.format {
+            GraphFormat::Json => {
+                let json = serde_json::to_string_pretty(state.dependency_graph()).unwrap();
+                println!("{}", json);
+            }
+        },
+    }
+}
+
+fn print_diff(diff_command: &DiffCommand) {
+    let root = &diff_command.root;
+    let mut compile_config = CompileConfig::new(
+        root.to_string(),
+        config::DEFAULT_CONFIG.output_dir.into(),
+        diff_command.base.to_string(),
+        diff_command.disable_pretty_urls,
+        config::DEFAULT_CONFIG.short_slug,
+        diff_command.footer_mode.clone(),
+        diff_command.disable_export_css,
+    );
+    apply_config_file(
+        &mut compile_config,
+        read_config_file(root),
+        diff_command.disable_pretty_urls,
+    );
+    config::mutex_set(&config::CONFIG, compile_config);
+
+    let changes = match compiler::diff_workspace(root, &diff_command.old_output) {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok((changes, _)) => changes,
+    };
+
+    for change in &changes {
+        match change.is_ripple() {
+            false => println!("{:<8} {}", change.kind, change.slug),
+            true => println!(
+                "{:<8} {} (via {})",
+                change.kind,
+                change.slug,
+                change.causes.join(", ")
+            ),
+        }
+        if diff_command.full {
+            print!("{}", change.unified_diff());
+        }
+    }
+
+    let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+    println!(
+        "{} added, {} removed, {} changed.",
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Changed)
+    );
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn print_sources(sources_command: &SourcesCommand) {
+    let root = &sources_command.root;
+    set_analysis_config(root);
+
+    let slug = slug::to_slug(&sources_command.slug);
+    match compiler::compile_state(root) {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok(state) => match state.pages.get(&slug) {
+            None => {
+                eprintln!("Error: [{}] does not exist.", slug);
+                std::process::exit(1);
+            }
+            Some(section) => section.sources().iter().for_each(|s| println!("{}", s)),
+        },
+    }
+}
+
+fn print_bibtex(export_command: &BibExportCommand) {
+    let root = &export_command.root;
+    set_analysis_config(root);
+
+    match compiler::compile_state(root) {
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        Ok(state) => {
+            let entries = state.bibtex_entries(export_command.used_only);
+            print!("{}", compiler::bibtex::write_bibtex(&entries));
+        }
+    }
+}
+
+fn freeze_slugs(freeze_command: &SlugsFreezeCommand) {
+    let root = &freeze_command.root;
+    set_analysis_config(root);
+    let root_dir = std::path::Path::new(root);
+
+    let workspace = compiler::all_source_files(root_dir).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    workspace
+        .diagnostics
+        .iter()
+        .for_each(|d| eprintln!("{}", d));
+    let slugs: BTreeMap<String, String> = workspace
+        .source_paths
+        .into_iter()
+        .map(|(slug, path)| (path, slug))
+        .collect();
+    if let Err(err) = compiler::frozen::write_frozen_slugs(root_dir, &slugs) {
+        eprintln!("{:?}", err);
+        std::process::exit(1);
+    }
+    println!(
+        "Froze the slugs of {} source file(s) into `{}`.",
+        slugs.len(),
+        config::SLUGS_FILE_NAME
+    );
+}
+
 fn export_css_files() {
     export_css_file(&html_flake::html_main_style(), "main.css");
     export_css_file(&&html_flake::html_typst_style(), "typst.css");
@@ -4,34 +4,55 @@ use std::collections::HashMap;
 use crate::{
     compiler::{
         parser::parse_spanned_markdown,
-        section::{EmbedContent, HTMLContent, LazyContent, LocalLink, SectionOption},
+        section::{Citation, EmbedContent, HTMLContent, LazyContent, LocalLink, SectionOption},
         CompileError,
     },
+    config,
+    entry::{split_list, unquote, HTMLMetaData, MetaData},
     html_flake::html_link,
     recorder::{ParseRecorder, State},
-    slug::to_slug,
+    slug::{self, resolve_link, source_slug, split_anchor, to_slug},
 };
 use pulldown_cmark::{Tag, TagEnd};
+use std::path::Path;
 
 pub struct Embed;
 
+/// The action of a link `[Text](slug#:further)`, see [`LocalLink::further`].
+const FURTHER_READING_ACTION: &str = "further";
+
 impl Processer for Embed {
     fn start(&mut self, tag: &Tag<'_>, recorder: &mut ParseRecorder) {
         match tag {
             Tag::Link {
                 link_type: _,
                 dest_url,
-                title: _,
+                title,
                 id: _,
             } => {
-                let (mut url, action) = url_action(dest_url);
-                if action == State::Embed.strify() {
+                let (url, action) = url_action(dest_url);
+                if let Some(key) = dest_url.strip_prefix('@') {
+                    recorder.enter(State::Cite);
+                    recorder.push(key.to_string()); // [0]
+                    recorder.push(title.to_string()); // [1]: Locator
+                } else if action == State::Embed.strify() {
+                    let url = resolve_link(&url, &recorder.current);
                     recorder.enter(State::Embed);
                     recorder.push(url); // [0]
+                    recorder.push(title.to_string()); // [1]: Metadata overrides
+                } else if action == FURTHER_READING_ACTION && is_local_link(&url) {
+                    let mut url = resolve_link(&url, &recorder.current);
+                    recorder.enter(State::LocalLink);
+                    if url.ends_with(".md") {
+                        url.truncate(url.len() - 3);
+                    }
+                    // the action is kept, so that the link is known as further reading.
+                    recorder.push(format!("{}#:{}", url, action));
                 } else if is_external_link(&url) {
                     recorder.enter(State::ExternalLink);
                     recorder.push(url);
                 } else if is_local_link(&dest_url) {
+                    let mut url = resolve_link(&url, &recorder.current);
                     recorder.enter(State::LocalLink);
 
                     if url.ends_with(".md") {
@@ -52,7 +73,8 @@ impl Processer for Embed {
             let entry_url = recorder.data.get(0).map_or("", |s| s);
             let entry_url = crate::config::relativize(entry_url);
 
-            let embed_text = recorder.data.get(1);
+            let metadata_overrides = parse_metadata_overrides(&recorder.data[1]);
+            let embed_text = recorder.data.get(2);
             let (section_option, inline_title) = parse_embed_text(embed_text);
 
             recorder.exit();
@@ -60,6 +82,7 @@ impl Processer for Embed {
                 url: entry_url,
                 title: inline_title,
                 option: section_option,
+                metadata_overrides,
             }));
         }
 
@@ -68,6 +91,13 @@ impl Processer for Embed {
                 .data
                 .get(0)
                 .map_or(String::new(), |s| s.to_string());
+            let (url, action) = url_action(&url.into());
+            let (url, anchor) = split_anchor(&url);
+            // `[Text](#anchor)` links to the current page.
+            let slug = match url.is_empty() {
+                true => source_slug(Path::new(&recorder.current)).0,
+                false => to_slug(url),
+            };
             let text = match recorder.data.len() > 1 {
                 true => Some(recorder.data[1..].join("")),
                 false => None,
@@ -75,11 +105,20 @@ impl Processer for Embed {
             recorder.exit();
 
             return Some(LazyContent::Local(LocalLink {
-                slug: to_slug(&url),
+                slug,
                 text,
+                further: action == FURTHER_READING_ACTION,
+                anchor: anchor.map(str::to_string),
             }));
         }
 
+        if *tag == TagEnd::Link && recorder.state == State::Cite {
+            let key = recorder.data[0].to_string();
+            let locator = Some(recorder.data[1].to_string()).filter(|s| !s.is_empty());
+            recorder.exit();
+            return Some(LazyContent::Cite(Citation { key, locator }));
+        }
+
         if *tag == TagEnd::Link && recorder.state == State::ExternalLink {
             let url = recorder
                 .data
@@ -105,10 +144,10 @@ impl Processer for Embed {
         &self,
         s: &pulldown_cmark::CowStr<'_>,
         recorder: &mut ParseRecorder,
-        metadata: &mut HashMap<String, HTMLContent>,
+        metadata: &mut HTMLMetaData,
     ) -> Result<(), CompileError> {
         if allow_inline(&recorder.state) {
-            recorder.push(s.to_string()); // [1, 2, ...]: Text
+            recorder.push(s.to_string()); // [1, 2, ...]: Text, or [2, 3, ...] for `State::Embed`
             return Ok(());
         }
 
@@ -144,32 +183,72 @@ fn allow_inline(state: &State) -> bool {
 /// `(I)` `x86_64-pc-windows-msvc` and `(II)` `aarch64-unknown-linux-musl`.
 /// `(I)` automatically splits the input by lines,
 /// while `(II)` receives the entire multi-line string as a whole.
+///
+/// A list is written as a YAML sequence, either `key: [a, b]` or `key:` followed by
+/// the lines `- a` and `- b`, or as `key: a, b` for the keys of [`config::list_keys`].
+/// Its items are kept in [`MetaData::get_list`] and its value is the items joined by `, `.
 pub fn parse_metadata(
     s: &str,
-    metadata: &mut HashMap<String, HTMLContent>,
+    metadata: &mut HTMLMetaData,
     recorder: &mut ParseRecorder,
 ) -> Result<(), CompileError> {
     let lines: Vec<&str> = s.split("\n").collect();
+    let mut entries: Vec<(&str, &str, Option<Vec<String>>)> = vec![];
     for s in lines {
         if s.trim().len() != 0 {
+            // an item of the sequence of the key written without a value just before.
+            if let (Some(item), Some((_, "", items))) =
+                (s.trim().strip_prefix('-'), entries.last_mut())
+            {
+                let item = unquote(item.trim()).to_string();
+                items.get_or_insert_with(Vec::new).push(item);
+                continue;
+            }
             let pos = s
                 .find(':')
                 .expect(&format!("metadata item expect `name: value` in {}", s));
-            let key = s[0..pos].trim();
-            let val = s[pos + 1..].trim();
+            entries.push((s[0..pos].trim(), s[pos + 1..].trim(), None));
+        }
+    }
 
-            let mut val = parse_spanned_markdown(val, &format!("{}:metadata", recorder.current))?;
-            if key == "taxon" {
-                if let HTMLContent::Plain(v) = val {
-                    val = HTMLContent::Plain(display_taxon(&v));
-                }
-            }
-            metadata.insert(key.to_string(), val);
+    let mut taxon = None;
+    for (key, val, items) in entries {
+        let items = items.or_else(|| {
+            (is_flow_sequence(val) || config::is_list_key(key)).then(|| split_list(val))
+        });
+        let val = match &items {
+            Some(items) => items.join(", "),
+            None => val.to_string(),
+        };
+        let val = parse_spanned_markdown(&val, &slug::metadata_slug(&recorder.current))?;
+        if let Some(items) = items {
+            metadata.1.insert(key.to_string(), items);
         }
+        match (key, val) {
+            ("taxon", HTMLContent::Plain(v)) => taxon = Some(v),
+            (key, val) => _ = metadata.0.insert(key.to_string(), val),
+        }
+    }
+
+    // displayed once the `lang` of the page is known, wherever it is written.
+    if let Some(taxon) = taxon {
+        let lang = match metadata.get("lang") {
+            Some(HTMLContent::Plain(lang)) => Some(lang.as_str()),
+            _ => None,
+        };
+        let display = display_taxon(&taxon, lang);
+        metadata
+            .0
+            .insert("taxon".to_string(), HTMLContent::Plain(display));
     }
     Ok(())
 }
 
+/// `[a, b]`, but not a citation `[@key]`.
+fn is_flow_sequence(val: &str) -> bool {
+    val.starts_with('[') && val.ends_with(']') && !val.starts_with("[@")
+}
+
 pub fn parse_embed_text(embed_text: Option<&String>) -> (SectionOption, Option<String>) {
     match embed_text {
         None => (SectionOption::default(), None),
@@ -177,6 +256,7 @@ pub fn parse_embed_text(embed_text: Option<&String>) -> (SectionOption, Option<S
             let mut numbering = false;
             let mut details_open = true;
             let mut catalog = true;
+            let mut force = false;
 
             let mut index = 0;
             let chars = embed_text.chars();
@@ -185,12 +265,13 @@ pub fn parse_embed_text(embed_text: Option<&String>) -> (SectionOption, Option<S
                     '+' => numbering = true,
                     '-' => details_open = false,
                     '.' => catalog = false,
+                    '!' => force = true,
                     _ => break,
                 }
                 index += 1;
             }
 
-            let option = SectionOption::new(numbering, details_open, catalog);
+            let option = SectionOption::new(numbering, details_open, catalog, force);
             let inline_title = &embed_text[index..].trim();
             let inline_title = match !inline_title.is_empty() {
                 true => Some(inline_title.to_string()),
@@ -201,7 +282,31 @@ pub fn parse_embed_text(embed_text: Option<&String>) -> (SectionOption, Option<S
     }
 }
 
-pub fn display_taxon(s: &str) -> String {
+/// Parse the link title `key: value; key: value` of an embed.
+pub fn parse_metadata_overrides(s: &str) -> HashMap<String, String> {
+    let mut overrides: HashMap<String, String> = s
+        .split(';')
+        .filter_map(|item| item.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    display_taxon_override(&mut overrides);
+    overrides
+}
+
+/// Display the `taxon` of the metadata overrides of an embed, in their `lang` if any.
+pub fn display_taxon_override(overrides: &mut HashMap<String, String>) {
+    if let Some(taxon) = overrides.get("taxon").filter(|taxon| !taxon.is_empty()) {
+        let display = display_taxon(taxon, overrides.get("lang").map(String::as_str));
+        overrides.insert("taxon".to_string(), display);
+    }
+}
+
+/// The taxon `s` shown as `Theorem. ` on a page in `lang`, see [`config::taxon_display`].
+pub fn display_taxon(s: &str, lang: Option<&str>) -> String {
+    if let Some(display) = config::taxon_display(s, lang) {
+        return format!("{}. ", display);
+    }
     match s.split_at_checked(1) {
         Some((first, rest)) => format!("{}. ", first.to_uppercase() + rest),
         _ => s.to_string(),
@@ -219,3 +324,68 @@ fn is_external_link(url: &str) -> bool {
 fn is_local_link(url: &str) -> bool {
     !super::typst_image::is_inline_typst(url) && !is_external_link(url) && !url.contains(":")
 }
+
+/// The target of the link to `dest_url` as written, before its `#` if any, if it is an embed,
+/// a further reading or a local link, as told apart when [`Embed`] starts the link.
+pub fn page_target(dest_url: &str) -> Option<&str> {
+    let (url, action) = match dest_url.find("#:") {
+        Some(pos) => (&dest_url[0..pos], &dest_url[pos + 2..]),
+        None => (dest_url, ""),
+    };
+    let is_page = match action {
+        _ if dest_url.starts_with('@') => false,
+        "embed" => true,
+        FURTHER_READING_ACTION => is_local_link(url),
+        _ => !is_external_link(url) && is_local_link(dest_url),
+    };
+    is_page.then(|| url.split('#').next().unwrap_or(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> HTMLMetaData {
+        let _guard = config::lock_test_config(config::test_config("."));
+        let mut metadata = HTMLMetaData::default();
+        let mut recorder = ParseRecorder::new("test.md".to_string());
+        parse_metadata(s, &mut metadata, &mut recorder).unwrap();
+        metadata
+    }
+
+    fn items(metadata: &HTMLMetaData, key: &str) -> Option<Vec<String>> {
+        metadata.get_list(key).map(<[String]>::to_vec)
+    }
+
+    #[test]
+    fn lists_of_each_syntax() {
+        let metadata = parse("tags: rust, compilers\nauthors:\n- Ann\n- 'Bob'\nkeywords: [x, y]");
+
+        let list = |items: &[&str]| Some(items.iter().map(|s| s.to_string()).collect());
+        assert_eq!(items(&metadata, "tags"), list(&["rust", "compilers"]));
+        assert_eq!(items(&metadata, "authors"), list(&["Ann", "Bob"]));
+        assert_eq!(items(&metadata, "keywords"), list(&["x", "y"]));
+        assert_eq!(metadata.get_str("authors").unwrap(), "Ann, Bob");
+    }
+
+    #[test]
+    fn commas_split_only_list_keys() {
+        let metadata = parse("title: Hello, world\nsource: [@key]");
+
+        assert_eq!(items(&metadata, "title"), None);
+        assert_eq!(items(&metadata, "source"), None);
+    }
+
+    #[test]
+    fn metadata_overrides_are_key_value_pairs() {
+        let _guard = config::lock_test_config(config::test_config("."));
+        let overrides = parse_metadata_overrides("taxon: lemma; author: Ann;; date: ; : x");
+
+        let mut keys: Vec<&String> = overrides.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["author", "date", "taxon"]);
+        assert_eq!(overrides["taxon"], "Lemma. ");
+        assert_eq!(overrides["author"], "Ann");
+        assert_eq!(overrides["date"], "");
+    }
+}
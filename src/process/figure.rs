@@ -1,7 +1,8 @@
 use pulldown_cmark::{Tag, TagEnd};
 
 use crate::{
-    compiler::{section::{HTMLContent, LazyContent}, CompileError},
+    compiler::{section::LazyContent, CompileError},
+    entry::HTMLMetaData,
     recorder::{ParseRecorder, State},
 };
 
@@ -40,7 +41,7 @@ impl Processer for Figure {
         &self,
         s: &pulldown_cmark::CowStr<'_>,
         recorder: &mut ParseRecorder,
-        _metadata: &mut std::collections::HashMap<String, HTMLContent>,
+        _metadata: &mut HTMLMetaData,
     ) -> Result<(), CompileError> {
         if recorder.state == State::Figure {
             recorder.push(s.to_string()); // [1]: alt text
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{compiler::{section::{HTMLContent, LazyContent}, CompileError}, recorder::ParseRecorder};
+use crate::{compiler::{section::LazyContent, CompileError}, entry::HTMLMetaData, recorder::ParseRecorder};
 use pulldown_cmark::{CowStr, Tag, TagEnd};
 
 pub trait Processer {
@@ -17,7 +17,7 @@ pub trait Processer {
         &self,
         s: &CowStr<'_>,
         recorder: &mut ParseRecorder,
-        metadata: &mut HashMap<String, HTMLContent>,
+        metadata: &mut HTMLMetaData,
     ) -> Result<(), CompileError> {
         Ok(())
     }
@@ -1,8 +1,9 @@
 use std::fs;
 
 use crate::{
-    compiler::{section::{HTMLContent, LazyContent}, CompileError},
+    compiler::{section::LazyContent, CompileError},
     config::{self, join_path, output_path, parent_dir},
+    entry::HTMLMetaData,
     html_flake::{html_figure, html_figure_code},
     recorder::{ParseRecorder, State},
     slug::adjust_name,
@@ -202,7 +203,7 @@ impl Processer for TypstImage {
         &self,
         s: &pulldown_cmark::CowStr<'_>,
         recorder: &mut ParseRecorder,
-        _metadata: &mut std::collections::HashMap<String, HTMLContent>,
+        _metadata: &mut HTMLMetaData,
     )  -> Result<(), CompileError> {
         if allow_inline(&recorder.state) {
             // [1]: imported / inline typst / span / block
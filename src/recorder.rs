@@ -31,6 +31,7 @@ pub enum State {
     Figure,
     LocalLink,
     ExternalLink,
+    Cite,
 }
 
 impl State {
@@ -48,6 +49,7 @@ impl State {
             State::Figure => "figure",
             State::LocalLink => "local",       // style class name
             State::ExternalLink => "external", // style class name
+            State::Cite => "citation",         // style class name
         }
     }
 }
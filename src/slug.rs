@@ -1,5 +1,7 @@
 use std::{fmt::Display, path::Path, str::FromStr};
 
+use crate::config::{self, SlugCase, SlugMode};
+
 #[derive(Debug)]
 pub enum Ext {
     Markdown,
@@ -34,12 +36,121 @@ pub fn to_hash_id(slug: &str) -> String {
     slug.replace("/", "-")
 }
 
+/// The badge ending the pseudo-slugs of the metadata of the pages, see [`metadata_slug`].
+const METADATA_BADGE: &str = ":metadata";
+
+/// The pseudo-slug `slug:metadata` of the metadata of the page `slug`,
+/// which is not reachable from user content.
+pub fn metadata_slug(slug: &str) -> String {
+    format!("{}{}", slug, METADATA_BADGE)
+}
+
+/// Whether `slug` is a [`metadata_slug`].
+pub fn is_metadata_slug(slug: &str) -> bool {
+    slug.ends_with(METADATA_BADGE)
+}
+
+/// `slug:metadata` to `slug`.
+pub fn clip_metadata_badge(slug: &str) -> String {
+    slug.strip_suffix(METADATA_BADGE)
+        .unwrap_or(slug)
+        .to_string()
+}
+
+/// The name of the page `slug` in title case, hyphens and underscores as spaces,
+/// such as `My First Note` for `notes/my-first-note`, a page `index` named after its
+/// directory.
+pub fn to_title(slug: &str) -> String {
+    let mut segments = slug.rsplit('/');
+    let name = match segments.next() {
+        Some("index") => segments.next().unwrap_or("index"),
+        name => name.unwrap_or(slug),
+    };
+    name.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string());
+            first.unwrap_or_default() + chars.as_str()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Split `note.md#anchor` into `("note.md", Some("anchor"))`.
+pub fn split_anchor(url: &str) -> (&str, Option<&str>) {
+    match url.split_once('#') {
+        Some((url, anchor)) if !anchor.is_empty() => (url, Some(anchor)),
+        Some((url, _)) => (url, None),
+        None => (url, None),
+    }
+}
+
 /// path to slug
 pub fn to_slug(fullname: &str) -> String {
     path_to_slug(Path::new(fullname)).0
 }
 
+/// The slug of the source file `path`, relative to the workspace dir: its [`path_to_slug`],
+/// or only its name with [`config::is_flatten_slugs`].
+pub fn source_slug(path: &Path) -> (String, Option<Ext>) {
+    let (slug, ext) = full_slug(path);
+    let slug = match config::is_flatten_slugs() {
+        true => slug.rsplit('/').next().unwrap_or(&slug).to_string(),
+        false => slug,
+    };
+    (limit_length(&slug), ext)
+}
+
+/// The characters never written in the slug of a source file, as they end or escape
+/// the path of a url, and so are the control characters, see [`rejected_char`].
+pub const REJECTED_CHARS: [char; 3] = ['?', '#', '%'];
+
+/// The first character of `slug` not allowed in the output paths, see [`REJECTED_CHARS`].
+pub fn rejected_char(slug: &str) -> Option<char> {
+    slug.chars()
+        .find(|c| REJECTED_CHARS.contains(c) || c.is_control())
+}
+
+/// The slug of a generated page of `kind`, such as `tags` or `backlinks`, under
+/// [`config::reserved_prefix`]: `-/tags` for the page of the kind itself, `-/tags/rust`
+/// for that of `name` otherwise.
+pub fn synthetic_slug(kind: &str, name: &str) -> String {
+    let prefix = config::reserved_prefix();
+    match name.is_empty() {
+        true => format!("{}{}", prefix, kind),
+        false => format!("{}{}/{}", prefix, kind, name),
+    }
+}
+
+/// Whether `slug` is under [`config::reserved_prefix`], as only the generated pages are.
+pub fn is_reserved_slug(slug: &str) -> bool {
+    let prefix = config::reserved_prefix();
+    !prefix.is_empty() && slug.starts_with(&prefix)
+}
+
+/// The target `url` of a link or an embed written in the source file `current`:
+/// a `./` or `../` url is resolved against the directory of `current`, as the source file
+/// of that [`source_slug`], and the other urls are kept, as relative to the workspace dir.
+pub fn resolve_link(url: &str, current: &str) -> String {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return url.to_string();
+    }
+    let dir = Path::new(current).parent().unwrap_or(Path::new(""));
+    let path = pretty_path(&dir.join(url));
+    match config::is_flatten_slugs() {
+        true => format!("/{}", path.rsplit('/').next().unwrap_or(&path)),
+        false => format!("/{}", path),
+    }
+}
+
 pub fn path_to_slug(path: &Path) -> (String, Option<Ext>) {
+    let (slug, ext) = full_slug(path);
+    (limit_length(&slug), ext)
+}
+
+/// The slug of `path` before [`limit_length`].
+fn full_slug(path: &Path) -> (String, Option<Ext>) {
     let slug = path
         // this works for both windows and unix
         .strip_prefix("./")
@@ -49,7 +160,169 @@ pub fn path_to_slug(path: &Path) -> (String, Option<Ext>) {
         .extension()
         .and_then(|e| e.to_str())
         .and_then(|e| e.parse().ok());
-    (pretty_path(&slug.with_extension("")), ext)
+    (slugify(&pretty_path(&slug.with_extension(""))), ext)
+}
+
+/// `slug` cut to [`config::max_slug_length`] bytes if longer: its last segment is truncated
+/// and followed by `-` and a hash of the whole `slug` in lowercase, so that the slugs cut
+/// alike stay apart, and those differing only in case still differ only in case.
+/// Its directories are kept.
+fn limit_length(slug: &str) -> String {
+    let max = config::max_slug_length();
+    if slug.len() <= max {
+        return slug.to_string();
+    }
+    let hash = format!("{:08x}", fnv1a(slug.to_lowercase().as_bytes()) as u32);
+    let (dir, name) = match slug.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, slug),
+    };
+    let dir_len = dir.map_or(0, |dir| dir.len() + 1);
+    let mut end = max.saturating_sub(dir_len + 1 + hash.len()).min(name.len());
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    let name = name[..end].trim_end_matches('-');
+    match dir {
+        Some(dir) => format!("{}/{}-{}", dir, name, hash),
+        None => format!("{}-{}", name, hash),
+    }
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, the same on every build and platform, so that
+/// the truncated slugs of [`limit_length`] do not change.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `slug` in the [`config::slug_mode`], [`normalize`]d with the whitespace as `-`,
+/// then in lowercase with [`SlugCase::Lower`].
+fn slugify(slug: &str) -> String {
+    let slug = match config::slug_mode() {
+        SlugMode::Path => slug.to_string(),
+        _ => normalize(slug)
+            .split(char::is_whitespace)
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-"),
+    };
+    match config::slug_case() {
+        SlugCase::Preserve => slug,
+        SlugCase::Lower => slug.to_lowercase(),
+    }
+}
+
+/// The text `s` in the [`config::slug_mode`]: as is with [`SlugMode::Path`], with the
+/// combining accents following a Latin letter composed into it with [`SlugMode::Unicode`],
+/// as `e\u{301}` becoming `é`, and then the accented Latin letters as ASCII with
+/// [`SlugMode::Translit`], as `café` becoming `cafe`. The other characters, such as
+/// Chinese, are kept.
+///
+/// The slugs of the pages, of the tags and the anchors of the headings go through it,
+/// so that the links written either way reach them.
+pub fn normalize(s: &str) -> String {
+    match config::slug_mode() {
+        SlugMode::Path => s.to_string(),
+        SlugMode::Unicode => compose(s),
+        SlugMode::Translit => transliterate(&compose(s)),
+    }
+}
+
+/// The combining accents, each with the letters it composes with and the composed letters.
+const COMPOSITIONS: [(char, &str, &str); 8] = [
+    ('\u{300}', "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    ('\u{301}', "AEIOUYaeiouyCcNnSsZz", "ÁÉÍÓÚÝáéíóúýĆćŃńŚśŹź"),
+    ('\u{302}', "AEIOUaeiou", "ÂÊÎÔÛâêîôû"),
+    ('\u{303}', "ANOano", "ÃÑÕãñõ"),
+    ('\u{308}', "AEIOUaeiouy", "ÄËÏÖÜäëïöüÿ"),
+    ('\u{30A}', "AUau", "ÅŮåů"),
+    ('\u{30C}', "CcDdEeNnRrSsTtZz", "ČčĎďĚěŇňŘřŠšŤťŽž"),
+    ('\u{327}', "CcSs", "ÇçŞş"),
+];
+
+/// The ASCII of the accented Latin letters.
+const TRANSLITERATIONS: [(&str, &str); 28] = [
+    ("ÀÁÂÃÄÅĀĂĄ", "A"),
+    ("àáâãäåāăą", "a"),
+    ("ÇĆČ", "C"),
+    ("çćč", "c"),
+    ("ĎĐ", "D"),
+    ("ďđð", "d"),
+    ("ÈÉÊËĒĘĚ", "E"),
+    ("èéêëēęě", "e"),
+    ("ÌÍÎÏĪ", "I"),
+    ("ìíîïīı", "i"),
+    ("Ł", "L"),
+    ("ł", "l"),
+    ("ÑŃŇ", "N"),
+    ("ñńň", "n"),
+    ("ÒÓÔÕÖØŌŐ", "O"),
+    ("òóôõöøōő", "o"),
+    ("Ř", "R"),
+    ("ř", "r"),
+    ("ŚŠŞ", "S"),
+    ("śšş", "s"),
+    ("Ť", "T"),
+    ("ť", "t"),
+    ("ÙÚÛÜŪŮŰ", "U"),
+    ("ùúûüūůű", "u"),
+    ("Ý", "Y"),
+    ("ýÿ", "y"),
+    ("ŹŻŽ", "Z"),
+    ("źżž", "z"),
+];
+
+/// The ligatures and the letters transliterated into several ASCII letters.
+const LIGATURES: [(char, &str); 8] = [
+    ('Æ', "AE"),
+    ('æ', "ae"),
+    ('Œ', "OE"),
+    ('œ', "oe"),
+    ('ß', "ss"),
+    ('Þ', "TH"),
+    ('þ', "th"),
+    ('Ĳ', "IJ"),
+];
+
+fn compose(s: &str) -> String {
+    let mut composed = String::with_capacity(s.len());
+    for c in s.chars() {
+        let letter = composed.chars().last();
+        let composition = COMPOSITIONS.iter().find(|(accent, _, _)| *accent == c);
+        let letter = letter
+            .zip(composition)
+            .and_then(|(letter, (_, letters, accented))| {
+                let index = letters.chars().position(|l| l == letter)?;
+                Some((letter, accented.chars().nth(index)?))
+            });
+        match letter {
+            Some((letter, accented)) => {
+                composed.truncate(composed.len() - letter.len_utf8());
+                composed.push(accented);
+            }
+            None => composed.push(c),
+        }
+    }
+    composed
+}
+
+fn transliterate(s: &str) -> String {
+    let mut ascii = String::with_capacity(s.len());
+    for c in s.chars() {
+        if let Some((_, letter)) = TRANSLITERATIONS
+            .iter()
+            .find(|(letters, _)| letters.contains(c))
+        {
+            ascii.push_str(letter);
+        } else if let Some((_, letters)) = LIGATURES.iter().find(|(ligature, _)| *ligature == c) {
+            ascii.push_str(letters);
+        } else if !('\u{300}'..='\u{36F}').contains(&c) {
+            ascii.push(c);
+        }
+    }
+    ascii
 }
 
 pub fn pretty_path(path: &std::path::Path) -> String {
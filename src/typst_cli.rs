@@ -1,4 +1,4 @@
-use std::{fs, path::Path, process::Command};
+use std::{fs, io::Write, path::Path, process::Command};
 
 use crate::{
     config::{self, verify_and_file_hash},
@@ -6,6 +6,13 @@ use crate::{
 };
 
 pub fn source_to_inline_html(typst_path: &str, html_path: &str) -> Result<String, std::io::Error> {
+    let root_dir = config::root_dir();
+    let full_path = config::join_path(&root_dir, typst_path);
+    // a dry run compiles the file every time, as it writes neither the HTML nor its hash.
+    if config::is_dry_run() {
+        return Ok(html_to_body_content(&source_to_html(&full_path, &root_dir)?));
+    }
+
     if !verify_and_file_hash(typst_path)? && Path::new(html_path).exists() {
         let existed_html = fs::read_to_string(html_path)?;
         let existed_html = html_to_body_content(&existed_html);
@@ -13,8 +20,6 @@ pub fn source_to_inline_html(typst_path: &str, html_path: &str) -> Result<String
         return Ok(existed_html);
     }
 
-    let root_dir = config::root_dir();
-    let full_path = config::join_path(&root_dir, typst_path);
     let html = source_to_html(&full_path, &root_dir)?;
     let html_body = html_to_body_content(&html);
 
@@ -103,18 +108,24 @@ pub fn compile_source(
     root_dir: &str,
     output_format: &str,
 ) -> Result<String, std::io::Error> {
-    let buffer_path = config::buffer_path();
-    fs::write(&buffer_path, src)?;
-
-    let output = Command::new("typst")
-        .arg("c")
-        .arg(format!("-f={}", output_format))
-        .arg(format!("--root={}", root_dir))
-        .arg(&buffer_path)
-        .arg("-")
-        .stdout(std::process::Stdio::piped())
-        .output()?;
-    fs::remove_file(buffer_path)?;
+    let output = match config::is_dry_run() {
+        true => compile_stdin(src, root_dir, output_format)?,
+        false => {
+            let buffer_path = config::buffer_path();
+            fs::write(&buffer_path, src)?;
+
+            let output = Command::new("typst")
+                .arg("c")
+                .arg(format!("-f={}", output_format))
+                .arg(format!("--root={}", root_dir))
+                .arg(&buffer_path)
+                .arg("-")
+                .stdout(std::process::Stdio::piped())
+                .output()?;
+            fs::remove_file(buffer_path)?;
+            output
+        }
+    };
 
     Ok(if output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -130,6 +141,26 @@ pub fn compile_source(
     })
 }
 
+/// Compile `src` given through the standard input, so that a dry run writes no buffer.
+fn compile_stdin(
+    src: &str,
+    root_dir: &str,
+    output_format: &str,
+) -> Result<std::process::Output, std::io::Error> {
+    let mut child = Command::new("typst")
+        .arg("c")
+        .arg(format!("-f={}", output_format))
+        .arg(format!("--root={}", root_dir))
+        .arg("-")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(src.as_bytes())?;
+    child.wait_with_output()
+}
+
 pub fn file_to_html(typst_path: &str, root_dir: &str) -> Result<String, std::io::Error> {
     compile_file(typst_path, root_dir, "html", Some("--features=html"))
         .map(|s| html_to_body_content(&s))
@@ -163,6 +194,10 @@ pub fn compile_file(
 
 /// typst file to svg (`stdout -> disk`)
 pub fn write_svg(typst_path: &str, svg_path: &str) -> Result<(), std::io::Error> {
+    // the page only links to the SVG, which a dry run does not write.
+    if config::is_dry_run() {
+        return Ok(());
+    }
     if !verify_and_file_hash(typst_path)? && Path::new(svg_path).exists() {
         println!("Skip: {}", crate::slug::pretty_path(Path::new(typst_path)));
         return Ok(());
@@ -16,9 +16,18 @@
   html.elem("kodamameta", v, attrs: attrs)
 }
 
-#let embed(url, title, numbering: false, open: true, catalog: true) = {
+#let embed(url, title, numbering: false, open: true, catalog: true, force: false, meta: (:)) = {
   let v = title
-  let attrs = (url: url, numbering: repri(numbering), open: repri(open), catalog: repri(catalog))
+  let attrs = (
+    url: url,
+    numbering: repri(numbering),
+    open: repri(open),
+    catalog: repri(catalog),
+    force: repri(force),
+  )
+  for (key, value) in meta {
+    attrs.insert("meta-" + key, repri(value))
+  }
 
   if type(title) != content {
     v = none